@@ -2,4 +2,4 @@
 import type { AudioType } from "./AudioType";
 import type { ProjectType } from "./ProjectType";
 
-export type AttachmentType = { "Audio": AudioType } | { "Project": ProjectType };
+export type AttachmentType = { "Audio": AudioType } | { "Project": ProjectType } | "LiveProject" | "Midi" | "File";
@@ -1,3 +1,6 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AudioMeta } from "./AudioMeta";
+import type { Loudness } from "./Loudness";
+import type { MusicalAnalysis } from "./MusicalAnalysis";
 
-export type AudioFileInfo = { path: string, filename: string, };
+export type AudioFileInfo = { path: string, filename: string, sha256: string, meta: AudioMeta | null, loudness: Loudness | null, musical: MusicalAnalysis | null, };
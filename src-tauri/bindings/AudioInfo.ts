@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Technical details read from the header of an audio file.
+ */
+export type AudioInfo = { codec: string, sample_rate: number, bit_depth: number | null, channels: number, duration_secs: number | null, };
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AudioInfo } from "./AudioInfo";
+import type { AudioType } from "./AudioType";
+
+/**
+ * Metadata stored for audio files.
+ */
+export type AudioMeta = { audio_type: AudioType, info: AudioInfo | null, };
@@ -1,3 +1,3 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export type AudioType = "Wav" | "Mp3" | "Flac" | "Other";
+export type AudioType = "Wav" | "Mp3" | "Flac" | "Aiff" | "Ogg" | "Opus" | "Other";
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A position in bars and beats, both starting at 1.
+ */
+export type BarBeat = { bar: number, beat: number, };
@@ -1,3 +1,4 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CommentPosition } from "./CommentPosition";
 
-export type Comment = { sender: string, body: string, };
+export type Comment = { sender: string, body: string, position: CommentPosition | null, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BarBeat } from "./BarBeat";
+
+/**
+ * A time or time range in an audio file attached to a note.
+ */
+export type CommentPosition = { file_hash: string, start_secs: number, end_secs: number | null, bar_beat: BarBeat | null, };
@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DuplicateAttachment } from "./DuplicateAttachment";
+import type { SharedNote } from "./SharedNote";
+import type { TempoResult } from "./TempoResult";
+
+/**
+ * Result of sending a note.
+ */
+export type CreatedNote = { "Created": TempoResult<SharedNote> } | { "Duplicates": Array<DuplicateAttachment> };
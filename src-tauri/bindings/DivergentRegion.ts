@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A stretch of time where two null tested files differ.
+ */
+export type DivergentRegion = { start_secs: number, end_secs: number, rms_dbfs: number, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * An attachment of a new note that's identical to one already on a note in the folder.
+ */
+export type DuplicateAttachment = { attachment_index: number, hash: string, channel_ulid: string | null, channel_name: string | null, note_ulid: string, sender: string, };
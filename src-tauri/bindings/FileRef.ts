@@ -1,3 +1,3 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export type FileRef = { rel: string, abs: string, };
+export type FileRef = { rel: string, abs: string, pack: string | null, size: number | null, };
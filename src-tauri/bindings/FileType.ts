@@ -5,4 +5,4 @@ import type { ProjectType } from "./ProjectType";
 /**
  * All file types known by Tempo.
  */
-export type FileType = { "Audio": AudioType } | { "Project": ProjectType } | "MaxForLive" | "Other";
+export type FileType = { "Audio": AudioType } | { "Project": ProjectType } | "Midi" | "MaxForLive" | "Other";
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Loudness and peaks of an audio file, measured per ITU-R BS.1770 and EBU Tech 3342.
+ */
+export type Loudness = { integrated_lufs: number | null, loudness_range_lu: number | null, sample_peak_dbfs: number | null, true_peak_dbtp: number | null, };
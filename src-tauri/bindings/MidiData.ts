@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { MidiTempo } from "./MidiTempo";
+import type { MidiTimeSignature } from "./MidiTimeSignature";
+
+/**
+ * Metadata read from a standard MIDI file.
+ */
+export type MidiData = { format: number, ppq: number | null, track_names: Array<string>, tempos: Array<MidiTempo>, time_signatures: Array<MidiTimeSignature>, length_ticks: bigint, length_secs: number, note_count: bigint, lowest_note: number | null, highest_note: number | null, };
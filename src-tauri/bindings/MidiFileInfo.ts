@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { MidiData } from "./MidiData";
+
+export type MidiFileInfo = { path: string, filename: string, midi: MidiData, };
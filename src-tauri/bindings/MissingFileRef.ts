@@ -1,4 +1,5 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { FileRef } from "./FileRef";
+import type { RelinkCandidate } from "./RelinkCandidate";
 
-export type MissingFileRef = { file: FileRef, err: string, };
+export type MissingFileRef = { file: FileRef, err: string, candidates: Array<RelinkCandidate>, };
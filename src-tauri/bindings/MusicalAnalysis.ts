@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { MusicalKey } from "./MusicalKey";
+
+/**
+ * Estimated tempo and key of an audio file.
+ */
+export type MusicalAnalysis = { bpm: number | null, key: MusicalKey | null, };
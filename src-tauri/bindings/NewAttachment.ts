@@ -1,5 +1,9 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { NewAudioAttachment } from "./NewAudioAttachment";
+import type { NewFileAttachment } from "./NewFileAttachment";
+import type { NewLiveProjectAttachment } from "./NewLiveProjectAttachment";
+import type { NewMidiAttachment } from "./NewMidiAttachment";
 import type { NewProjectAttachment } from "./NewProjectAttachment";
+import type { NewStemsAttachment } from "./NewStemsAttachment";
 
-export type NewAttachment = { "Project": NewProjectAttachment } | { "Audio": NewAudioAttachment };
+export type NewAttachment = { "Project": NewProjectAttachment } | { "Audio": NewAudioAttachment } | { "LiveProject": NewLiveProjectAttachment } | { "Midi": NewMidiAttachment } | { "Stems": NewStemsAttachment } | { "File": NewFileAttachment };
@@ -4,4 +4,4 @@ import type { NewAttachment } from "./NewAttachment";
 /**
  * A request from the frontend to make a new note.
  */
-export type NewNote = { body: string, reply_ulid: string | null, attachment: NewAttachment | null, };
+export type NewNote = { body: string, reply_ulid: string | null, attachments: Array<NewAttachment>, allow_duplicates: boolean, };
@@ -1,3 +1,4 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Relink } from "./Relink";
 
-export type NewProjectAttachment = { title: string, path: string, render: string | null, };
+export type NewProjectAttachment = { title: string, path: string, render: string | null, relinks: Array<Relink>, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DivergentRegion } from "./DivergentRegion";
+
+/**
+ * Result of null testing one audio file against another.
+ */
+export type NullTest = { offset_frames: number, offset_secs: number, difference_rms_dbfs: number | null, difference_peak_dbfs: number | null, regions: Array<DivergentRegion>, difference_file: string | null, };
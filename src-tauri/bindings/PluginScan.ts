@@ -5,4 +5,4 @@ import type { PluginRef } from "./PluginRef";
  * A scan of all plugins inside of a project file and a listing of plugins that clients are missing.
  * Used when adding a project to a folder.
  */
-export type PluginScan = { plugins: Array<PluginRef>, missing: { [key in string]?: Array<number> }, };
+export type PluginScan = { plugins: Array<PluginRef>, missing: { [key in string]?: Array<number> }, project_version: string | null, cannot_open: Array<string>, };
@@ -1,3 +1,3 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export type PluginType = "Au" | "Vst" | "Vst3";
+export type PluginType = "Au" | "Vst" | "Vst3" | "Clap";
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Comment } from "./Comment";
+
+/**
+ * A comment with a position, returned by position.
+ */
+export type PositionedComment = { ulid: string, reply_ulid: string | null, comment: Comment, };
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FileRef } from "./FileRef";
+
+/**
+ * A missing file ref that should point at `path` instead.
+ * Sent by the frontend after a user accepts a `RelinkCandidate`.
+ */
+export type Relink = { file: FileRef, path: string, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A file in a sample library which might be a missing file.
+ */
+export type RelinkCandidate = { path: string, size: number, };
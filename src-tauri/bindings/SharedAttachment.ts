@@ -1,5 +1,9 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { SharedAudioAttachment } from "./SharedAudioAttachment";
+import type { SharedFileAttachment } from "./SharedFileAttachment";
+import type { SharedLiveProjectAttachment } from "./SharedLiveProjectAttachment";
+import type { SharedMidiAttachment } from "./SharedMidiAttachment";
 import type { SharedProjectAttachment } from "./SharedProjectAttachment";
+import type { SharedStemsAttachment } from "./SharedStemsAttachment";
 
-export type SharedAttachment = { "Project": SharedProjectAttachment } | { "Audio": SharedAudioAttachment };
+export type SharedAttachment = { "Project": SharedProjectAttachment } | { "Audio": SharedAudioAttachment } | { "LiveProject": SharedLiveProjectAttachment } | { "Midi": SharedMidiAttachment } | { "Stems": SharedStemsAttachment } | { "File": SharedFileAttachment };
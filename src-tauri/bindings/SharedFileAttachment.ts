@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FileAttachmentInfo } from "./FileAttachmentInfo";
+import type { TempoResult } from "./TempoResult";
+
+export type SharedFileAttachment = { title: string | null, mime: string | null, size: number, file: TempoResult<FileAttachmentInfo>, };
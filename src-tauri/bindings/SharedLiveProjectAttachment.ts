@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AudioFileInfo } from "./AudioFileInfo";
+import type { SharedLiveProjectSet } from "./SharedLiveProjectSet";
+import type { TempoResult } from "./TempoResult";
+
+export type SharedLiveProjectAttachment = { title: string, sets: Array<SharedLiveProjectSet>, render: TempoResult<AudioFileInfo> | null, };
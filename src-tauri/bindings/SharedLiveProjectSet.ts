@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ProjectInfo } from "./ProjectInfo";
+import type { TempoResult } from "./TempoResult";
+
+export type SharedLiveProjectSet = { path: string, project: TempoResult<ProjectInfo>, };
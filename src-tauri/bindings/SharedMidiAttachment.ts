@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { MidiFileInfo } from "./MidiFileInfo";
+import type { TempoResult } from "./TempoResult";
+
+export type SharedMidiAttachment = { title: string | null, file: TempoResult<MidiFileInfo>, };
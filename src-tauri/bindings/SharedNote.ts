@@ -3,6 +3,6 @@ import type { RepliableComment } from "./RepliableComment";
 import type { SharedAttachment } from "./SharedAttachment";
 
 /**
- * Similar to `NoteDoc`, but contains extra information validating note's attachment (if any)
+ * Similar to `NoteDoc`, but contains extra information validating note's attachments
  */
-export type SharedNote = { sender: string, body: string, reply_ulid: string | null, attachment: SharedAttachment | null, comments: { [key in string]?: RepliableComment }, };
+export type SharedNote = { sender: string, body: string, reply_ulid: string | null, attachments: Array<SharedAttachment>, comments: { [key in string]?: RepliableComment }, };
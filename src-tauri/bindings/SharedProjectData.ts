@@ -4,4 +4,4 @@ import type { PluginRef } from "./PluginRef";
 /**
  * Scanned information about a project in a a Tempo Folder
  */
-export type SharedProjectData = { "Ableton": { missing_files: Array<string>, missing_plugins: Array<PluginRef>, } };
+export type SharedProjectData = { "Ableton": { missing_files: Array<string>, missing_plugins: Array<PluginRef>, version: string | null, cannot_open: Array<string>, } } | { "Reaper": { missing_files: Array<string>, missing_plugins: Array<PluginRef>, version: string | null, } } | { "Bitwig": { missing_files: Array<string>, missing_plugins: Array<PluginRef>, relink_files: Array<string>, } } | { "StudioOne": { missing_files: Array<string>, missing_plugins: Array<PluginRef>, } } | { "FlStudio": { missing_files: Array<string>, missing_plugins: Array<PluginRef>, version: string | null, } };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AudioFileInfo } from "./AudioFileInfo";
+import type { SharedStem } from "./SharedStem";
+import type { TempoResult } from "./TempoResult";
+
+export type SharedStemsAttachment = { title: string, stems: Array<SharedStem>, mix: TempoResult<AudioFileInfo> | null, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { PeakLevel } from "./PeakLevel";
+
+/**
+ * Min/max peaks of an audio file at a few resolutions, used to draw waveforms.
+ */
+export type WaveformPeaks = { sample_rate: number, frames: number, levels: Array<PeakLevel>, };
@@ -1,15 +1,25 @@
-use std::path::{Path, PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
 
 use crate::{
-    daw::ableton::copy_ableton_project,
+    daw::{ableton::copy_ableton_live_project, reader::get_data_reader},
     db::SharedDb,
-    file::get_unique_dir,
+    file::{
+        audio::copy_audio_file, copy_file, copy_files_into, get_unique_dir, get_unique_ref_filename,
+    },
     misc::{Result, TempoError},
     shared::{
-        AudioFileInfo, FileErr, ProjectInfo, SharedAttachment, SharedAudioAttachment,
-        SharedProjectAttachment,
+        AudioFileInfo, FileAttachmentInfo, FileErr, MidiFileInfo, ProjectInfo, SharedAttachment,
+        SharedAudioAttachment, SharedFileAttachment, SharedLiveProjectAttachment,
+        SharedLiveProjectSet, SharedMidiAttachment, SharedProjectAttachment, SharedStem,
+        SharedStemsAttachment,
+    },
+    types::{
+        Attachment, AudioAttachment, FileAttachment, FileInfo, FileMeta, LiveProjectAttachment,
+        LiveProjectSet, MidiAttachment, ProjectAttachment, Stem, StemsAttachment,
     },
-    types::{Attachment, AudioAttachment, FileInfo, FileMeta, ProjectAttachment, ProjectData},
 };
 
 impl ProjectAttachment {
@@ -25,40 +35,160 @@ impl ProjectAttachment {
         // aka "live project"
         let project_dir = get_unique_dir(dest_dir, &format!("[tempo] {}", &self.title))?;
 
-        let errs = match project_data {
-            ProjectData::Ableton { refs, plugins: _ } => copy_ableton_project(
-                folder,
-                &self.hash,
-                &project_info.filename,
-                &refs,
-                &project_dir,
-            )?,
-        };
+        let errs = get_data_reader(&project_data).copy_project(
+            folder,
+            &self.hash,
+            &project_info.filename,
+            project_data,
+            &project_dir,
+        )?;
+
+        Ok((project_dir, errs))
+    }
+}
+
+impl LiveProjectAttachment {
+    pub fn copy(&self, folder: &Path, dest_dir: &Path) -> Result<(PathBuf, Vec<FileErr>)> {
+        let project_dir = get_unique_dir(dest_dir, &format!("[tempo] {}", &self.title))?;
+
+        let sets: Vec<(String, String)> = self
+            .sets
+            .iter()
+            .map(|s| (s.path.clone(), s.hash.clone()))
+            .collect();
+
+        let errs = copy_ableton_live_project(folder, &sets, &project_dir)?;
 
         Ok((project_dir, errs))
     }
+}
+
+impl MidiAttachment {
+    /// Copies the MIDI file into `dest_dir` using its original filename.
+    /// Returns the path of the copy.
+    pub fn copy(&self, folder: &Path, dest_dir: &Path) -> Result<PathBuf> {
+        let midi_info = load_file_info(folder, &self.hash, "MIDI file")?;
+
+        match midi_info.meta {
+            FileMeta::Midi(_) => (),
+            t => handle_unexpected_filemeta_variant(t, "MIDI")?,
+        }
+
+        copy_file(folder, &self.hash, dest_dir, &midi_info.filename)
+    }
+}
 
-    // pub fn copy_render(&self, folder: &Path, dest_dir: &Path) -> Result<PathBuf> {
-    //     todo!()
-    // }
+impl FileAttachment {
+    /// Copies the file into `dest_dir` using its original filename.
+    /// Returns the path of the copy.
+    pub fn copy(&self, folder: &Path, dest_dir: &Path) -> Result<PathBuf> {
+        let info = load_file_info(folder, &self.hash, "file")?;
+
+        copy_file(folder, &self.hash, dest_dir, &info.filename)
+    }
 }
 
-// impl AudioAttachment {
-//     pub fn copy(&self, folder: &Path, dest_dir: &Path) -> Result<PathBuf> {
-//         let project_info = load_file_info(folder, &self.hash, "audio")?;
+impl StemsAttachment {
+    /// Copies all stems and the reference mix into a new directory inside of `dest_dir`, using their original filenames.
+    /// Stems whose metadata can't be loaded are returned as errors instead of failing the whole copy.
+    pub fn copy(&self, folder: &Path, dest_dir: &Path) -> Result<(PathBuf, Vec<FileErr>)> {
+        let stems_dir = get_unique_dir(dest_dir, &format!("[tempo] {}", &self.title))?;
+
+        let mix = self
+            .mix_hash
+            .as_ref()
+            .map(|hash| ("reference mix".to_string(), hash));
+        let all = self
+            .stems
+            .iter()
+            .map(|s| (s.name.clone(), &s.hash))
+            .chain(mix);
 
-//         match project_info.meta {
-//             FileMeta::Audio(_) => (),
-//             t => handle_unexpected_filemeta_variant(t, "project")?,
-//         }
+        // { hash : filename in stems dir }
+        let mut files: HashMap<String, String> = HashMap::new();
+        let mut used: HashSet<String> = HashSet::new();
+        let mut errs = vec![];
 
-//         let copy_path = get_unique_filename(dest_dir, &project_info.filename)?;
+        for (name, hash) in all {
+            if files.contains_key(hash) {
+                continue;
+            }
+            match load_file_info(folder, hash, "stem") {
+                Ok(info) => {
+                    let filename = get_unique_ref_filename(&used, info.filename);
+                    used.insert(filename.clone());
+                    files.insert(hash.clone(), filename);
+                }
+                Err(e) => errs.push(FileErr {
+                    filename: name,
+                    err: e.to_string(),
+                }),
+            }
+        }
+
+        errs.extend(copy_files_into(folder, &files, &stems_dir)?);
 
-//         fs::copy(get_file_path(folder, &self.hash), &copy_path)?;
+        Ok((stems_dir, errs))
+    }
+}
 
-//         Ok(copy_path)
-//     }
-// }
+impl AudioAttachment {
+    /// Copies the audio file into `dest_dir` using its original filename.
+    pub fn copy(&self, folder: &Path, dest_dir: &Path) -> Result<PathBuf> {
+        copy_audio_file(folder, &self.hash, dest_dir)
+    }
+}
+
+impl Attachment {
+    /// Hash of this attachment's render, if it has one.
+    pub fn render_hash(&self) -> Option<&String> {
+        match self {
+            Attachment::Project(p) => p.render_hash.as_ref(),
+            Attachment::LiveProject(p) => p.render_hash.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Hashes of the audio files in this attachment, including renders and stems.
+    pub fn audio_hashes(&self) -> Vec<&String> {
+        match self {
+            Attachment::Audio(a) => vec![&a.hash],
+            Attachment::Stems(s) => s
+                .stems
+                .iter()
+                .map(|s| &s.hash)
+                .chain(s.mix_hash.as_ref())
+                .collect(),
+            a => a.render_hash().into_iter().collect(),
+        }
+    }
+
+    /// Hashes of the files that make this attachment a version of something: projects (or the sets of a
+    /// Live Project), renders, audio and stems. Files referenced by projects, MIDI and other files aren't included.
+    pub fn version_hashes(&self) -> Vec<&String> {
+        match self {
+            Attachment::Project(p) => [&p.hash].into_iter().chain(&p.render_hash).collect(),
+            Attachment::LiveProject(p) => p
+                .sets
+                .iter()
+                .map(|s| &s.hash)
+                .chain(&p.render_hash)
+                .collect(),
+            Attachment::Midi(_) | Attachment::File(_) => vec![],
+            a => a.audio_hashes(),
+        }
+    }
+
+    /// Copies this attachment's render into `dest_dir` using its original filename.
+    pub fn copy_render(&self, folder: &Path, dest_dir: &Path) -> Result<PathBuf> {
+        match self.render_hash() {
+            Some(hash) => copy_audio_file(folder, hash, dest_dir),
+            None => Err(TempoError::Note(
+                "Cannot copy render, this attachment has no render".into(),
+            )),
+        }
+    }
+}
 
 fn load_file_info(folder: &Path, hash: &str, expected: &str) -> Result<FileInfo> {
     match FileInfo::load(folder, hash) {
@@ -95,6 +225,55 @@ impl SharedAttachment {
                     file: AudioFileInfo::new(folder, &hash),
                 })
             }
+
+            Attachment::LiveProject(LiveProjectAttachment {
+                title,
+                sets,
+                render_hash,
+            }) => Self::LiveProject(SharedLiveProjectAttachment {
+                title,
+                sets: sets
+                    .into_iter()
+                    .map(|LiveProjectSet { path, hash }| SharedLiveProjectSet {
+                        path,
+                        project: ProjectInfo::new(folder, &hash, db),
+                    })
+                    .collect(),
+                render: render_hash.map(|h| AudioFileInfo::new(folder, &h)),
+            }),
+
+            Attachment::Midi(MidiAttachment { title, hash }) => Self::Midi(SharedMidiAttachment {
+                title,
+                file: MidiFileInfo::new(folder, &hash),
+            }),
+
+            Attachment::File(FileAttachment {
+                title,
+                hash,
+                mime,
+                size,
+            }) => Self::File(SharedFileAttachment {
+                title,
+                mime,
+                size,
+                file: FileAttachmentInfo::new(folder, &hash),
+            }),
+
+            Attachment::Stems(StemsAttachment {
+                title,
+                stems,
+                mix_hash,
+            }) => Self::Stems(SharedStemsAttachment {
+                title,
+                stems: stems
+                    .into_iter()
+                    .map(|Stem { name, hash }| SharedStem {
+                        name,
+                        file: AudioFileInfo::new(folder, &hash),
+                    })
+                    .collect(),
+                mix: mix_hash.map(|h| AudioFileInfo::new(folder, &h)),
+            }),
         }
     }
 }
@@ -2,9 +2,10 @@
 
 use crate::{
     misc::{is_sha256, new_ulid, path_to_str, Result, TempoError},
+    note::{find_duplicate_attachments, Created, StagedAttachment},
     shared::NewNote,
     structure::{expect_valid_folder, get_channel_meta_path, get_note_path, note_exists},
-    types::{ChannelDoc, NoteDoc},
+    types::{Attachment, ChannelDoc, NoteDoc},
     verify::{Verifiable, VerifiableWithInfo},
 };
 use automerge::{ActorId, Automerge};
@@ -287,18 +288,52 @@ impl Verifiable for ChannelDoc {
     }
 }
 
+/// Hydrates a note, using the single attachment of notes from older clients as `attachments`.
+fn hydrate_note(doc: &Automerge) -> Result<NoteDoc> {
+    let mut d: NoteDoc = hydrate(doc)?;
+
+    if d.attachments.is_empty() {
+        d.attachments.extend(d.attachment.take());
+    }
+
+    // kept in sync so older clients can still read the note when it's saved again
+    d.attachment = d.attachments.first().cloned();
+
+    Ok(d)
+}
+
 impl NoteDoc {
     /// Creates and saves a `NoteDoc` to disk.
-    /// Returns ulid and doc.
+    /// Returns ulid and doc, or the attachments already on other notes if duplicates aren't allowed.
     pub fn create(
         folder: &Path,
         username: &str,
         channel_ulid: Option<&str>,
         note: NewNote,
-    ) -> Result<(String, Self)> {
+    ) -> Result<Created<(String, Self)>> {
         expect_valid_folder(folder)?;
         note.verify(folder, channel_ulid)?;
 
+        // nothing is added to the folder until we know the note will be created
+        let staged = note
+            .attachments
+            .into_iter()
+            .map(|a| a.stage(folder, username))
+            .collect::<Result<Vec<StagedAttachment>>>()?;
+
+        if !note.allow_duplicates {
+            let attachments: Vec<&Attachment> = staged.iter().map(|a| &a.attachment).collect();
+            let duplicates = find_duplicate_attachments(folder, username, &attachments)?;
+            if !duplicates.is_empty() {
+                return Ok(Created::Duplicates(duplicates));
+            }
+        }
+
+        let attachments = staged
+            .into_iter()
+            .map(|a| a.commit(folder, username))
+            .collect::<Result<Vec<Attachment>>>()?;
+
         let note_ulid = new_ulid();
         let note_path = get_note_path(folder, channel_ulid, &note_ulid);
 
@@ -306,16 +341,14 @@ impl NoteDoc {
             sender: username.to_owned(),
             body: note.body,
             reply_ulid: note.reply_ulid,
-            attachment: note
-                .attachment
-                .map(|a| a.create(folder, username))
-                .transpose()?,
+            attachment: attachments.first().cloned(),
+            attachments,
             comments: HashMap::new(),
         };
 
         save_new_doc(&doc, &note_path, username)?;
 
-        Ok((note_ulid, doc))
+        Ok(Created::Note((note_ulid, doc)))
     }
 
     pub fn load(
@@ -336,9 +369,7 @@ impl NoteDoc {
 
         let (_, doc) = get_doc(&get_note_path(folder, channel_ulid, note_ulid), actor_id)?;
 
-        let d: NoteDoc = hydrate(&doc)?;
-
-        Ok(d)
+        hydrate_note(&doc)
     }
 
     /// Saves any changes made to this `NoteDoc` back to disk.
@@ -360,3 +391,147 @@ impl NoteDoc {
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::AudioAttachment;
+
+    // how notes were stored before they could have multiple attachments
+    #[derive(Reconcile, Hydrate)]
+    struct OldNoteDoc {
+        sender: String,
+        body: String,
+        reply_ulid: Option<String>,
+        attachment: Option<Attachment>,
+        comments: HashMap<String, crate::types::RepliableComment>,
+    }
+
+    #[test]
+    fn test_hydrate_old_note() {
+        let old = OldNoteDoc {
+            sender: "user".into(),
+            body: "new mix".into(),
+            reply_ulid: None,
+            attachment: Some(Attachment::Audio(AudioAttachment {
+                title: None,
+                hash: "a".repeat(64),
+            })),
+            comments: HashMap::new(),
+        };
+
+        let mut doc = Automerge::new();
+        let mut tx = doc.transaction();
+        reconcile(&mut tx, &old).unwrap();
+        tx.commit();
+
+        let note = hydrate_note(&doc).unwrap();
+        assert_eq!(note.attachments.len(), 1);
+        assert!(matches!(
+            &note.attachments[0],
+            Attachment::Audio(AudioAttachment { hash, .. }) if hash == &"a".repeat(64)
+        ));
+        assert!(note.attachment.is_some());
+    }
+
+    #[test]
+    fn test_old_clients_read_first_attachment() {
+        let audio = |c: &str| {
+            Attachment::Audio(AudioAttachment {
+                title: None,
+                hash: c.repeat(64),
+            })
+        };
+
+        let mut doc = Automerge::new();
+        let mut tx = doc.transaction();
+        reconcile(
+            &mut tx,
+            &NoteDoc {
+                sender: "user".into(),
+                body: "two mixes".into(),
+                reply_ulid: None,
+                attachments: vec![audio("a"), audio("b")],
+                attachment: None,
+                comments: HashMap::new(),
+            },
+        )
+        .unwrap();
+        tx.commit();
+
+        // a note loaded and saved again by a new client
+        let note = hydrate_note(&doc).unwrap();
+        assert_eq!(note.attachments.len(), 2);
+        let mut tx = doc.transaction();
+        reconcile(&mut tx, &note).unwrap();
+        tx.commit();
+
+        let old: OldNoteDoc = hydrate(&doc).unwrap();
+        assert!(matches!(
+            old.attachment,
+            Some(Attachment::Audio(AudioAttachment { hash, .. })) if hash == "a".repeat(64)
+        ));
+
+        // the legacy field isn't added to the attachments again
+        assert_eq!(hydrate_note(&doc).unwrap().attachments.len(), 2);
+    }
+
+    // how comments were stored before they could have a position
+    #[derive(Reconcile)]
+    struct OldComment {
+        sender: String,
+        body: String,
+    }
+
+    #[derive(Reconcile)]
+    struct OldRepliableComment {
+        comment: OldComment,
+        replies: HashMap<String, OldComment>,
+    }
+
+    #[derive(Reconcile)]
+    struct OldCommentNoteDoc {
+        sender: String,
+        body: String,
+        reply_ulid: Option<String>,
+        attachments: Vec<Attachment>,
+        comments: HashMap<String, OldRepliableComment>,
+    }
+
+    #[test]
+    fn test_hydrate_old_comment() {
+        let old = OldCommentNoteDoc {
+            sender: "user".into(),
+            body: "new mix".into(),
+            reply_ulid: None,
+            attachments: vec![],
+            comments: HashMap::from([(
+                "c".into(),
+                OldRepliableComment {
+                    comment: OldComment {
+                        sender: "user".into(),
+                        body: "nice".into(),
+                    },
+                    replies: HashMap::from([(
+                        "r".into(),
+                        OldComment {
+                            sender: "other".into(),
+                            body: "thanks".into(),
+                        },
+                    )]),
+                },
+            )]),
+        };
+
+        let mut doc = Automerge::new();
+        let mut tx = doc.transaction();
+        reconcile(&mut tx, &old).unwrap();
+        tx.commit();
+
+        let note = hydrate_note(&doc).unwrap();
+        let comment = &note.comments["c"];
+        assert_eq!(comment.comment.body, "nice");
+        assert!(comment.comment.position.is_none());
+        assert!(comment.replies["r"].position.is_none());
+    }
+}
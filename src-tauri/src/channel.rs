@@ -1,6 +1,6 @@
 
 use crate::{
-    folder::FolderInner, misc::{Result, TempoError}, note::Note, shared::NewNote, tempo::Tempo, types::ChannelDoc
+    folder::FolderInner, misc::{Result, TempoError}, note::{Created, Note}, shared::NewNote, tempo::Tempo, types::ChannelDoc
 };
 use std::sync::{Arc, RwLock};
 
@@ -83,7 +83,7 @@ impl Channel {
         )
     }
 
-    pub fn create_note(&self, note: NewNote) -> Result<Note> {
+    pub fn create_note(&self, note: NewNote) -> Result<Created<Note>> {
         Note::create(
             self.tempo.clone(),
             self.folder.clone(),
@@ -1,5 +1,7 @@
 use crate::{
+    daw::ableton,
     misc::{self, fatal_error_close_windows, path_to_str},
+    note::Created,
     shared::*,
     structure::validate_folder_structure,
     tempo::Tempo,
@@ -38,10 +40,7 @@ pub async fn need_full_disk(handle: AppHandle) -> Result<bool> {
         Err(e) => {
             fatal_error_close_windows(
                 &handle,
-                &format!(
-                    "error while checking for full disk access: {}",
-                    e
-                ),
+                &format!("error while checking for full disk access: {}", e),
             );
         }
     })
@@ -194,13 +193,18 @@ pub async fn create_note(
     channel_ulid: Option<String>,
     note: NewNote,
     state: St!(),
-) -> Result<TempoResult<SharedNote>> {
+) -> Result<CreatedNote> {
     info!("got msg {:#?}", &note);
-    Ok(state
-        .folder(&folder)?
-        .channel(channel_ulid.as_deref())?
-        .create_note(note)?
-        .get()?)
+    Ok(
+        match state
+            .folder(&folder)?
+            .channel(channel_ulid.as_deref())?
+            .create_note(note)?
+        {
+            Created::Note(n) => CreatedNote::Created(n.get()?),
+            Created::Duplicates(d) => CreatedNote::Duplicates(d),
+        },
+    )
 }
 
 #[tauri::command]
@@ -219,6 +223,23 @@ pub async fn add_comment(
         .get()?)
 }
 
+/// Comments on a note's audio with a position, sorted by position.
+/// Only comments on `file_sha256` are returned if it's given.
+#[tauri::command]
+pub async fn get_comments_by_position(
+    folder: PathBuf,
+    channel_ulid: Option<String>,
+    note_ulid: String,
+    file_sha256: Option<String>,
+    state: St!(),
+) -> Result<Vec<PositionedComment>> {
+    Ok(state
+        .folder(&folder)?
+        .channel(channel_ulid.as_deref())?
+        .note(&note_ulid)?
+        .positioned_comments(file_sha256.as_deref()))
+}
+
 /// Returns path to new copy of project
 /// `dir` is directory where project will be copied into
 #[tauri::command]
@@ -226,6 +247,7 @@ pub async fn copy_project(
     folder: PathBuf,
     channel_ulid: Option<String>,
     note_ulid: String,
+    attachment_index: usize,
     dest_dir: PathBuf,
     state: St!(),
 ) -> Result<(PathBuf, Vec<FileErr>)> {
@@ -233,18 +255,141 @@ pub async fn copy_project(
         .folder(&folder)?
         .channel(channel_ulid.as_deref())?
         .note(&note_ulid)?
-        .attachment()
+        .attachment(attachment_index)?
     {
-        None => err("Cannot copy project from a note containing no project".into()),
-        Some(a) => match a {
-            Attachment::Project(p) => Ok(p.copy(&folder, &dest_dir)?),
-            Attachment::Audio(_) => {
-                err("Cannot copy project from a note with an audio attachment".into())
-            }
-        },
+        Attachment::Project(p) => Ok(p.copy(&folder, &dest_dir)?),
+        Attachment::LiveProject(p) => Ok(p.copy(&folder, &dest_dir)?),
+        Attachment::Audio(_) => err("Cannot copy project from an audio attachment".into()),
+        Attachment::Midi(_) => err("Cannot copy project from a MIDI attachment".into()),
+        Attachment::Stems(_) => err("Cannot copy project from stems".into()),
+        Attachment::File(_) => err("Cannot copy project from a file attachment".into()),
+    }
+}
+
+/// Returns path to new copy of MIDI file
+/// `dest_dir` is directory where the file will be copied into
+#[tauri::command]
+pub async fn copy_midi(
+    folder: PathBuf,
+    channel_ulid: Option<String>,
+    note_ulid: String,
+    attachment_index: usize,
+    dest_dir: PathBuf,
+    state: St!(),
+) -> Result<PathBuf> {
+    match state
+        .folder(&folder)?
+        .channel(channel_ulid.as_deref())?
+        .note(&note_ulid)?
+        .attachment(attachment_index)?
+    {
+        Attachment::Midi(m) => Ok(m.copy(&folder, &dest_dir)?),
+        _ => err("Cannot copy MIDI from an attachment that isn't MIDI".into()),
     }
 }
 
+/// Returns path to new directory containing the stems
+/// `dest_dir` is directory where the stems directory will be created
+#[tauri::command]
+pub async fn copy_stems(
+    folder: PathBuf,
+    channel_ulid: Option<String>,
+    note_ulid: String,
+    attachment_index: usize,
+    dest_dir: PathBuf,
+    state: St!(),
+) -> Result<(PathBuf, Vec<FileErr>)> {
+    match state
+        .folder(&folder)?
+        .channel(channel_ulid.as_deref())?
+        .note(&note_ulid)?
+        .attachment(attachment_index)?
+    {
+        Attachment::Stems(s) => Ok(s.copy(&folder, &dest_dir)?),
+        _ => err("Cannot copy stems from an attachment that isn't a stem pack".into()),
+    }
+}
+
+/// Returns path to new copy of the attached file
+/// `dest_dir` is directory where the file will be copied into
+#[tauri::command]
+pub async fn copy_file_attachment(
+    folder: PathBuf,
+    channel_ulid: Option<String>,
+    note_ulid: String,
+    attachment_index: usize,
+    dest_dir: PathBuf,
+    state: St!(),
+) -> Result<PathBuf> {
+    match state
+        .folder(&folder)?
+        .channel(channel_ulid.as_deref())?
+        .note(&note_ulid)?
+        .attachment(attachment_index)?
+    {
+        Attachment::File(f) => Ok(f.copy(&folder, &dest_dir)?),
+        _ => err("Cannot copy a file from an attachment that isn't a file".into()),
+    }
+}
+
+/// Returns path to new copy of the audio file
+/// `dest_dir` is directory where the file will be copied into
+#[tauri::command]
+pub async fn copy_audio(
+    folder: PathBuf,
+    channel_ulid: Option<String>,
+    note_ulid: String,
+    attachment_index: usize,
+    dest_dir: PathBuf,
+    state: St!(),
+) -> Result<PathBuf> {
+    match state
+        .folder(&folder)?
+        .channel(channel_ulid.as_deref())?
+        .note(&note_ulid)?
+        .attachment(attachment_index)?
+    {
+        Attachment::Audio(a) => Ok(a.copy(&folder, &dest_dir)?),
+        _ => err("Cannot copy audio from an attachment that isn't audio".into()),
+    }
+}
+
+/// Returns path to new copy of the project's render
+/// `dest_dir` is directory where the render will be copied into
+#[tauri::command]
+pub async fn copy_render(
+    folder: PathBuf,
+    channel_ulid: Option<String>,
+    note_ulid: String,
+    attachment_index: usize,
+    dest_dir: PathBuf,
+    state: St!(),
+) -> Result<PathBuf> {
+    Ok(state
+        .folder(&folder)?
+        .channel(channel_ulid.as_deref())?
+        .note(&note_ulid)?
+        .attachment(attachment_index)?
+        .copy_render(&folder, &dest_dir)?)
+}
+
+/// Copies the renders of a note and every note it replies to
+/// Returns path to new directory containing the renders
+#[tauri::command]
+pub async fn export_lineage_renders(
+    folder: PathBuf,
+    channel_ulid: Option<String>,
+    note_ulid: String,
+    dest_dir: PathBuf,
+    state: St!(),
+) -> Result<(PathBuf, Vec<FileErr>)> {
+    Ok(state
+        .folder(&folder)?
+        .channel(channel_ulid.as_deref())?
+        .note(&note_ulid)?
+        .export_lineage_renders(&dest_dir)?)
+}
+
 #[tauri::command]
 pub async fn get_file_info(
     folder: PathBuf,
@@ -256,9 +401,81 @@ pub async fn get_file_info(
     ))
 }
 
+/// Returns min/max peaks of an audio file for drawing its waveform, cached after the first call
+#[tauri::command]
+pub async fn get_waveform_peaks(
+    folder: PathBuf,
+    file_sha256: String,
+    state: St!(),
+) -> Result<WaveformPeaks> {
+    Ok(state.folder(&folder)?.waveform_peaks(&file_sha256)?)
+}
+
+/// Returns the loudness of an audio file, measuring it if it hasn't been measured before
+#[tauri::command]
+pub async fn analyze_loudness(
+    folder: PathBuf,
+    file_sha256: String,
+    state: St!(),
+) -> Result<Loudness> {
+    Ok(state.folder(&folder)?.loudness(&file_sha256)?)
+}
+
+/// Returns the estimated tempo and key of an audio file, analyzing it if it hasn't been analyzed before
+#[tauri::command]
+pub async fn analyze_bpm_key(
+    folder: PathBuf,
+    file_sha256: String,
+    state: St!(),
+) -> Result<MusicalAnalysis> {
+    Ok(state.folder(&folder)?.musical_analysis(&file_sha256)?)
+}
+
+/// Aligns two audio files and measures their difference
+/// `difference_file` is where the difference is written as a WAV file, if it's given
+#[tauri::command]
+pub async fn null_test_audio(
+    folder: PathBuf,
+    a_sha256: String,
+    b_sha256: String,
+    difference_file: Option<PathBuf>,
+    state: St!(),
+) -> Result<NullTest> {
+    Ok(state
+        .folder(&folder)?
+        .null_test(&a_sha256, &b_sha256, difference_file.as_deref())?)
+}
+
+#[tauri::command]
+pub async fn scan_project_file_refs(
+    folder: PathBuf,
+    project: PathBuf,
+    state: St!(),
+) -> Result<ProjectFileRefScan> {
+    Ok(state.folder(&folder)?.scan_project_file_refs(&project)?)
+}
+
+/// Finds the sets in a Live Project, used when attaching a Live Project.
+#[tauri::command]
+pub async fn find_live_project_sets(live_project: PathBuf) -> Result<Vec<PathBuf>> {
+    if !ableton::is_live_project(&live_project) {
+        return err(format!(
+            "{} is not a Live Project, it should contain an Ableton Project Info directory",
+            path_to_str(&live_project)
+        ));
+    }
+    Ok(ableton::find_live_project_sets(&live_project)?)
+}
+
+#[tauri::command]
+pub async fn get_library_roots(state: St!()) -> Result<Vec<PathBuf>> {
+    Ok(state.get_library_roots())
+}
+
+/// Sets the sample library directories which are searched for missing files.
 #[tauri::command]
-pub async fn scan_project_file_refs(project: PathBuf) -> Result<ProjectFileRefScan> {
-    Ok(ProjectFileRefScan::new(&project)?)
+pub async fn set_library_roots(roots: Vec<PathBuf>, state: St!()) -> Result<()> {
+    Ok(state.set_library_roots(roots)?)
 }
 
 #[tauri::command]
@@ -1,4 +1,9 @@
 pub mod ableton;
+pub mod bitwig;
+pub mod fl_studio;
 pub mod macos;
 pub mod project;
 pub mod plugin;
+pub mod reader;
+pub mod reaper;
+pub mod studio_one;
@@ -11,13 +11,24 @@
 
 mod als;
 mod db;
+mod pack;
 mod project;
+mod reader;
+mod version;
 
-pub use als::{verify_project, ProjectFileRefReader, ProjectFileRefWriter, ProjectPluginReader};
+pub use als::{
+    get_project_tempo, get_project_version, verify_project, ProjectFileRefReader,
+    ProjectFileRefWriter, ProjectPluginReader,
+};
 pub use db::{have_plugin_db, scan_plugin_db, ScannedAbletonPlugin};
+pub use pack::{find_pack_file, AbletonPack, AbletonPackFileRef};
 pub use project::{
-    add_ableton_project, copy_ableton_project, scan_filerefs, AbletonProjectPluginScan,
+    copy_ableton_live_project, copy_ableton_project, find_live_project_sets, is_live_project,
+    scan_filerefs, scan_live_project_filerefs, stage_ableton_live_project,
+    stage_ableton_project_with_relinks, AbletonProjectPluginScan,
 };
+pub use reader::AbletonReader;
+pub use version::{can_open_with_any, scan_installed_versions, AbletonVersion};
 
 use serde::{Deserialize, Serialize};
 
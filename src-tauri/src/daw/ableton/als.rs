@@ -30,12 +30,16 @@ use std::{
 };
 
 use crate::{
-    daw::{ableton::AbletonPluginRef, macos::AudioUnitId},
+    daw::{
+        ableton::{AbletonPack, AbletonPluginRef, AbletonVersion},
+        macos::AudioUnitId,
+        project::ProjectTempo,
+    },
     misc::{Result, TempoError},
 };
 
-// TODO create some generalized interface for reading project file info to add other daws later on
-// would be nice as a standalone separate library
+// other daws are read through daw/reader.rs
+// TODO would be nice as a standalone separate library
 
 // TODO maybe serde would be nice for matching filerefs?
 // might avoid the weirdness with match_event_in_fileref
@@ -137,11 +141,22 @@ it seems like Value="3" means it's relative to the project file itself.
 
 it looks like Value="5" means the path is relative to the root of a Live pack?
 and Value="7" seems to mean it's relative to some other core directory of Ableton I think.
+this seems to be Live's Core Library, which ships inside of the Live app bundle.
+
+FileRefs of type 5 also contain LivePackName and LivePackId tags, eg "Drum Essentials" and "www.ableton.com/183".
+see pack.rs for how Tempo handles these.
 
-we only care about setting RelativePathType to the value which will make the relative path relative to the project file. again, i think this is Value="3"
+when we collect files into the Files directory we set RelativePathType to the value which will make the relative path relative to the project file. again, i think this is Value="3"
 
 these values probably differ with different schema versions
 
+-----------------------------------------------------------------------------------------------------
+OriginalFileSize:
+
+FileRefs also contain an OriginalFileSize tag holding the size of the referenced file in bytes.
+Tempo uses this when searching sample libraries for missing files, see file/library.rs.
+there's also an OriginalCrc tag, i'm not sure what Ableton calculates this over so it's ignored for now.
+
 -----------------------------------------------------------------------------------------------------
 PluginInfo:
 
@@ -162,6 +177,27 @@ au:
 
 plugin device ids as found in Ableton's plugin db (plugins table) can be used to construct these format-specific ids
 
+-----------------------------------------------------------------------------------------------------
+Tempo:
+
+the song tempo and time signature are parameters of the mixer of the main track (MasterTrack, MainTrack in Live 12):
+
+<MasterTrack>
+  <DeviceChain>
+    <Mixer>
+      <Tempo>
+        <LomId Value="0" />
+        <Manual Value="120" />
+        ...
+      </Tempo>
+      <TimeSignature>
+        <LomId Value="0" />
+        <Manual Value="201" />
+        ...
+
+the time signature is a single number: numerator - 1 + 99 * log2(denominator), so 201 is 4/4 and 302 is 6/8.
+only the Manual values are read, tempo automation is ignored.
+
  */
 
 type GzXmlReader = quick_xml::reader::Reader<BufReader<GzDecoder<BufReader<File>>>>;
@@ -181,12 +217,42 @@ const COMPONENT_TYPE: &[u8] = b"ComponentType";
 const COMPONENT_SUBTYPE: &[u8] = b"ComponentSubType";
 const COMPONENT_MANUFACTURER: &[u8] = b"ComponentManufacturer";
 
+const MASTER_TRACK: &[u8] = b"MasterTrack";
+const MAIN_TRACK: &[u8] = b"MainTrack";
+const TEMPO: &[u8] = b"Tempo";
+const TIME_SIGNATURE: &[u8] = b"TimeSignature";
+const MANUAL: &[u8] = b"Manual";
+
 /// FileRef extracted from an Ableton project file.
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct AbletonFileRef {
     pub rel: String,
     pub abs: String,
     pub rel_type: String,
+
+    // only present in FileRefs pointing into Live Packs
+    pub pack_name: Option<String>,
+    pub pack_id: Option<String>,
+
+    // OriginalFileSize, size of the referenced file in bytes
+    pub size: Option<u64>,
+}
+
+impl AbletonFileRef {
+    /// Returns the Live Pack or library this FileRef's relative path is relative to, if any.
+    pub fn pack(&self) -> Option<AbletonPack> {
+        match self.rel_type.as_str() {
+            "5" => Some(AbletonPack::Pack {
+                name: self
+                    .pack_name
+                    .clone()
+                    .unwrap_or_else(|| "Unknown Pack".into()),
+                id: self.pack_id.clone(),
+            }),
+            "7" => Some(AbletonPack::CoreLibrary),
+            _ => None,
+        }
+    }
 }
 
 /// Creates a new `GzXMLReader`, used for reading Ableton project files.
@@ -613,6 +679,128 @@ pub fn verify_project(project: &Path) -> Result<()> {
     }
 }
 
+/// Reads the version of Live that saved an Ableton project.
+/// Uses the Creator attribute of the Ableton tag, falls back to MinorVersion.
+/// `None` if neither attribute holds a recognizable version.
+pub fn get_project_version(project: &Path) -> Result<Option<AbletonVersion>> {
+    let mut r = new_gzxml_reader(project)?;
+    let mut buf = Vec::new();
+
+    match_decl(&mut r, &mut buf)?;
+    buf.clear();
+
+    let e = match match_ableton(&mut r, &mut buf)? {
+        Event::Start(e) => e,
+        _ => return Ok(None),
+    };
+
+    let mut creator: Option<AbletonVersion> = None;
+    let mut minor_version: Option<AbletonVersion> = None;
+
+    for a in e.attributes() {
+        let a = map_attr_result(&r, a)?;
+        match a.key.as_ref() {
+            b"Creator" => creator = AbletonVersion::parse(&r.decoder().decode(&a.value)?),
+            b"MinorVersion" => {
+                minor_version = AbletonVersion::parse(&r.decoder().decode(&a.value)?)
+            }
+            _ => (),
+        }
+    }
+
+    Ok(creator.or(minor_version))
+}
+
+/// Decodes Live's single number time signature into `(numerator, denominator)`.
+fn decode_time_signature(value: u32) -> Option<(u8, u8)> {
+    let numerator = (value % 99 + 1) as u8;
+    let denominator = 1u8.checked_shl(value / 99)?;
+    Some((numerator, denominator))
+}
+
+/// Reads the tempo and time signature of the main track of an Ableton project.
+/// `None` if there's no tempo, the time signature defaults to 4/4 if it's missing.
+pub fn get_project_tempo(project: &Path) -> Result<Option<ProjectTempo>> {
+    let (mut r, mut buf) = get_project_reader_and_validate(project)?;
+
+    // number of open tags inside of the main track, 0 if outside of it
+    let mut depth = 0usize;
+    // parameter whose Manual value we're looking for and its depth
+    let mut param: Option<(&[u8], usize)> = None;
+
+    let mut bpm: Option<f64> = None;
+    let mut time_signature: Option<(u8, u8)> = None;
+
+    loop {
+        buf.clear();
+
+        match r.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) => {
+                let name = e.name();
+                if depth == 0 {
+                    if matches!(name.as_ref(), MASTER_TRACK | MAIN_TRACK) {
+                        depth = 1;
+                    }
+                    continue;
+                }
+
+                depth += 1;
+                match name.as_ref() {
+                    TEMPO => param = Some((TEMPO, depth)),
+                    TIME_SIGNATURE => param = Some((TIME_SIGNATURE, depth)),
+                    _ => (),
+                }
+            }
+            Ok(Event::Empty(e)) if depth > 0 && e.name().as_ref() == MANUAL => {
+                let Some((name, param_depth)) = param else {
+                    continue;
+                };
+                if param_depth != depth {
+                    continue;
+                }
+
+                let value = extract_value(&r, e.attributes(), MANUAL)?;
+                match name {
+                    TEMPO => bpm = value.parse().ok(),
+                    _ => time_signature = value.parse().ok().and_then(decode_time_signature),
+                }
+                param = None;
+
+                if bpm.is_some() && time_signature.is_some() {
+                    break;
+                }
+            }
+            Ok(Event::End(_)) if depth > 0 => {
+                if param.is_some_and(|(_, d)| d == depth) {
+                    param = None;
+                }
+
+                depth -= 1;
+                // the tempo is only in the main track
+                if depth == 0 {
+                    break;
+                }
+            }
+            Ok(Event::Eof) => break,
+            Ok(_) => (),
+            Err(e) => {
+                return Err(TempoError::Ableton(format!(
+                    "XML error at byte {}: {e}",
+                    r.buffer_position()
+                )))
+            }
+        }
+    }
+
+    let (numerator, denominator) = time_signature.unwrap_or((4, 4));
+
+    Ok(bpm.filter(|b| *b > 0.0).map(|bpm| ProjectTempo {
+        bpm,
+        numerator,
+        denominator,
+    }))
+}
+
 fn get_project_reader_and_writer_and_validate(
     input: &Path,
     output: &Path,
@@ -708,14 +896,13 @@ fn map_attr_result<'a>(
 /// This does not handle matching the opening or closing FileRef tag, the caller must take care of this.
 ///
 /// The RelativePathType event is also ignored.
+/// LivePackName and LivePackId are extracted but still returned.
 ///
 /// Returns `Some` for all events which are not RelativePath or Path tags.
 fn handle_event_in_fileref<'a>(
     reader: &GzXmlReader,
     event: quick_xml::Result<Event<'a>>,
-    rel: &mut Option<String>,
-    abs: &mut Option<String>,
-    rel_type: &mut Option<String>,
+    fields: &mut FileRefFields,
 ) -> Result<Option<Event<'a>>> {
     let save_value = |tag: &[u8], val: String, opt: &mut Option<String>| {
         if let Some(val) = opt.as_ref() {
@@ -733,6 +920,9 @@ fn handle_event_in_fileref<'a>(
     const RELATIVEPATH: &[u8] = b"RelativePath";
     const PATH: &[u8] = b"Path";
     const RELATIVEPATHTYPE: &[u8] = b"RelativePathType";
+    const LIVEPACKNAME: &[u8] = b"LivePackName";
+    const LIVEPACKID: &[u8] = b"LivePackId";
+    const ORIGINALFILESIZE: &[u8] = b"OriginalFileSize";
 
     match event {
         Ok(Event::Empty(e)) => {
@@ -740,19 +930,44 @@ fn handle_event_in_fileref<'a>(
                 save_value(
                     RELATIVEPATH,
                     extract_value(reader, e.attributes(), RELATIVEPATH)?,
-                    rel,
+                    &mut fields.rel,
                 )?;
                 Ok(None)
             } else if e.name().as_ref() == PATH {
-                save_value(PATH, extract_value(reader, e.attributes(), PATH)?, abs)?;
+                save_value(
+                    PATH,
+                    extract_value(reader, e.attributes(), PATH)?,
+                    &mut fields.abs,
+                )?;
                 Ok(None)
             } else if e.name().as_ref() == RELATIVEPATHTYPE {
                 save_value(
                     RELATIVEPATHTYPE,
                     extract_value(reader, e.attributes(), RELATIVEPATHTYPE)?,
-                    rel_type,
+                    &mut fields.rel_type,
                 )?;
                 Ok(None)
+            } else if e.name().as_ref() == LIVEPACKNAME {
+                save_value(
+                    LIVEPACKNAME,
+                    extract_value(reader, e.attributes(), LIVEPACKNAME)?,
+                    &mut fields.pack_name,
+                )?;
+                Ok(Some(Event::Empty(e)))
+            } else if e.name().as_ref() == LIVEPACKID {
+                save_value(
+                    LIVEPACKID,
+                    extract_value(reader, e.attributes(), LIVEPACKID)?,
+                    &mut fields.pack_id,
+                )?;
+                Ok(Some(Event::Empty(e)))
+            } else if e.name().as_ref() == ORIGINALFILESIZE {
+                save_value(
+                    ORIGINALFILESIZE,
+                    extract_value(reader, e.attributes(), ORIGINALFILESIZE)?,
+                    &mut fields.size,
+                )?;
+                Ok(Some(Event::Empty(e)))
             } else {
                 Ok(Some(Event::Empty(e)))
             }
@@ -762,14 +977,36 @@ fn handle_event_in_fileref<'a>(
     }
 }
 
-fn build_fileref(
+/// Values extracted from the tags inside of a FileRef.
+#[derive(Default)]
+struct FileRefFields {
     rel: Option<String>,
     abs: Option<String>,
     rel_type: Option<String>,
-    reader: &GzXmlReader,
-) -> Result<AbletonFileRef> {
+    pack_name: Option<String>,
+    pack_id: Option<String>,
+    size: Option<String>,
+}
+
+fn build_fileref(fields: FileRefFields, reader: &GzXmlReader) -> Result<AbletonFileRef> {
+    let FileRefFields {
+        rel,
+        abs,
+        rel_type,
+        pack_name,
+        pack_id,
+        size,
+    } = fields;
+
     match (rel, abs, rel_type) {
-        (Some(rel), Some(abs), Some(rel_type)) => Ok(AbletonFileRef { rel, abs, rel_type }),
+        (Some(rel), Some(abs), Some(rel_type)) => Ok(AbletonFileRef {
+            rel,
+            abs,
+            rel_type,
+            pack_name,
+            pack_id,
+            size: size.and_then(|s| s.parse::<u64>().ok()),
+        }),
         (a, b, c) => Err(TempoError::Ableton(format!(
             "Failed to build FileRef at byte {}, expected (rel, abs, rel_type), found ({:#?}, {:#?}, {:#?})",
             reader.buffer_position(), a, b, c
@@ -782,23 +1019,19 @@ fn build_fileref(
 /// This should be called immediately after the opening FileRef tag is matched.
 /// Will set the reader immediately after the closing FileRef tag.
 fn match_fileref_read(reader: &mut GzXmlReader, buf: &mut Vec<u8>) -> Result<AbletonFileRef> {
-    let mut rel: Option<String> = None;
-    let mut abs: Option<String> = None;
-    let mut rel_type: Option<String> = None;
+    let mut fields = FileRefFields::default();
 
     loop {
         buf.clear();
         let event = reader.read_event_into(buf);
-        if let Some(Event::End(ref e)) =
-            handle_event_in_fileref(reader, event, &mut rel, &mut abs, &mut rel_type)?
-        {
+        if let Some(Event::End(ref e)) = handle_event_in_fileref(reader, event, &mut fields)? {
             if e.name().as_ref() == b"FileRef" {
                 break;
             }
         }
     }
 
-    build_fileref(rel, abs, rel_type, reader)
+    build_fileref(fields, reader)
 }
 
 /// Matches in the relative and absolute path of a FileRef.
@@ -815,14 +1048,12 @@ fn match_fileref_write(
     writer: &mut GzXmlWriter,
     buf: &mut Vec<u8>,
 ) -> Result<AbletonFileRef> {
-    let mut rel: Option<String> = None;
-    let mut abs: Option<String> = None;
-    let mut rel_type: Option<String> = None;
+    let mut fields = FileRefFields::default();
 
     loop {
         buf.clear();
         let event = reader.read_event_into(buf);
-        match handle_event_in_fileref(reader, event, &mut rel, &mut abs, &mut rel_type) {
+        match handle_event_in_fileref(reader, event, &mut fields) {
             Err(e) => return Err(e),
             Ok(Some(e)) => {
                 if let Event::End(ref e) = e {
@@ -837,7 +1068,7 @@ fn match_fileref_write(
         }
     }
 
-    build_fileref(rel, abs, rel_type, reader)
+    build_fileref(fields, reader)
 }
 
 /// Matches a VstPluginInfo.
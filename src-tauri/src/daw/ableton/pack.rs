@@ -0,0 +1,276 @@
+// =====================================================================================
+// This file is licensed under either of
+// Apache License, Version 2.0 or MIT license, at your option.
+// =====================================================================================
+// You may obtain a copy of the Apache License, Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0
+// =====================================================================================
+// You may obtain a copy of the MIT License at
+// https://opensource.org/licenses/MIT
+// =====================================================================================
+
+// Live Pack and Core Library file references
+
+/*
+FileRefs with RelativePathType 5 are relative to the root of an installed Live Pack.
+FileRefs with RelativePathType 7 are relative to Live's Core Library, which lives inside of the Live app bundle.
+
+most collaborators have the same packs installed, so we try not to collect these files into the Files directory.
+
+when a project is added to a folder:
+- pack FileRefs are left untouched in the project file
+- the referenced file is still copied into the folder, in case a collaborator doesn't have the pack
+
+when a project is copied out of a folder:
+- if the pack file exists on this machine the FileRef is left alone, Live will resolve it from the user's library
+- otherwise the FileRef is pointed into the Files directory and the file is copied like any other file
+
+we only look for packs in their default install locations for now:
+- Live Packs: ~/Music/Ableton/Factory Packs/[pack name]
+- Core Library: /Applications/Ableton Live [edition].app/Contents/App-Resources/Core Library
+ */
+
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use log::error;
+use serde::{Deserialize, Serialize};
+
+/// A Live Pack or library that a FileRef can be relative to.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Hash)]
+pub enum AbletonPack {
+    Pack {
+        // LivePackName
+        name: String,
+        // LivePackId, eg www.ableton.com/183
+        id: Option<String>,
+    },
+    CoreLibrary,
+}
+
+impl fmt::Display for AbletonPack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AbletonPack::Pack { name, id: _ } => write!(f, "{name}"),
+            AbletonPack::CoreLibrary => write!(f, "Core Library"),
+        }
+    }
+}
+
+/// A file inside of a Live Pack that's referenced by a project in a Tempo folder.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Hash)]
+pub struct AbletonPackFileRef {
+    pub pack: AbletonPack,
+    // path relative to the root of the pack, as found in the FileRef
+    pub rel: String,
+}
+
+/// Looks for a file in a locally installed Live Pack.
+/// Returns path to file if it exists.
+pub fn find_pack_file(pack: &AbletonPack, rel: &str) -> Option<PathBuf> {
+    find_pack_file_in(&get_pack_roots(pack), rel)
+}
+
+/// Returns the first of `roots` that contains `rel`.
+fn find_pack_file_in(roots: &[PathBuf], rel: &str) -> Option<PathBuf> {
+    roots
+        .iter()
+        .map(|root| root.join(rel))
+        .find(|p| p.is_file())
+}
+
+#[cfg(target_os = "macos")]
+fn get_pack_roots(pack: &AbletonPack) -> Vec<PathBuf> {
+    use directories::UserDirs;
+
+    let factory_packs = match UserDirs::new() {
+        Some(d) => Some(
+            d.home_dir()
+                .join("Music")
+                .join("Ableton")
+                .join("Factory Packs"),
+        ),
+        None => {
+            error!("get_pack_roots(): could not open user home directory");
+            None
+        }
+    };
+
+    pack_roots(pack, factory_packs.as_deref(), Path::new("/Applications"))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn get_pack_roots(_pack: &AbletonPack) -> Vec<PathBuf> {
+    vec![]
+}
+
+/// Directories a pack could be installed in.
+/// `factory_packs` is the Factory Packs directory, `applications` is the directory Live is installed in.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn pack_roots(
+    pack: &AbletonPack,
+    factory_packs: Option<&Path>,
+    applications: &Path,
+) -> Vec<PathBuf> {
+    match pack {
+        AbletonPack::Pack { name, id: _ } => {
+            factory_packs.map(|d| d.join(name)).into_iter().collect()
+        }
+        AbletonPack::CoreLibrary => match fs::read_dir(applications) {
+            Ok(ents) => {
+                let mut roots: Vec<PathBuf> = ents
+                    .filter_map(|ent| ent.ok())
+                    .filter(|ent| {
+                        let name = ent.file_name().to_string_lossy().to_string();
+                        name.starts_with("Ableton Live") && name.ends_with(".app")
+                    })
+                    .map(|ent| {
+                        ent.path()
+                            .join("Contents")
+                            .join("App-Resources")
+                            .join("Core Library")
+                    })
+                    .collect();
+                // read_dir order is unspecified, sort so lookups don't depend on it
+                // reversed so eg Live 12 is checked before Live 11
+                roots.sort();
+                roots.reverse();
+                roots
+            }
+            Err(e) => {
+                error!(
+                    "pack_roots(): failed to read {}: {e}",
+                    applications.to_string_lossy()
+                );
+                vec![]
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::{daw::ableton::als::AbletonFileRef, tests::get_temp_dir};
+
+    fn touch(path: &Path) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, b"sample").unwrap();
+    }
+
+    fn fileref(rel: &str, rel_type: &str, pack_name: Option<&str>) -> AbletonFileRef {
+        AbletonFileRef {
+            rel: rel.into(),
+            abs: format!("/somewhere/else/{rel}"),
+            rel_type: rel_type.into(),
+            pack_name: pack_name.map(|n| n.into()),
+            pack_id: None,
+            size: None,
+        }
+    }
+
+    #[test]
+    fn test_fileref_pack() {
+        assert_eq!(
+            fileref("Samples/Kick.wav", "5", Some("Drum Essentials")).pack(),
+            Some(AbletonPack::Pack {
+                name: "Drum Essentials".into(),
+                id: None
+            })
+        );
+        assert_eq!(
+            fileref("Samples/Kick.wav", "5", None).pack(),
+            Some(AbletonPack::Pack {
+                name: "Unknown Pack".into(),
+                id: None
+            })
+        );
+        assert_eq!(
+            fileref("Samples/Kick.wav", "7", None).pack(),
+            Some(AbletonPack::CoreLibrary)
+        );
+        assert_eq!(fileref("Samples/Kick.wav", "3", None).pack(), None);
+    }
+
+    #[test]
+    fn test_find_live_pack_file() {
+        let dir = get_temp_dir("tempo-pack-test");
+        let factory_packs = dir.join("Factory Packs");
+        let applications = dir.join("Applications");
+
+        let rel = "Samples/Drums/Kick 808.wav";
+        touch(&factory_packs.join("Drum Essentials").join(rel));
+
+        let pack = AbletonPack::Pack {
+            name: "Drum Essentials".into(),
+            id: Some("www.ableton.com/183".into()),
+        };
+        let roots = pack_roots(&pack, Some(&factory_packs), &applications);
+        assert_eq!(roots, vec![factory_packs.join("Drum Essentials")]);
+
+        assert_eq!(
+            find_pack_file_in(&roots, rel),
+            Some(factory_packs.join("Drum Essentials").join(rel))
+        );
+        assert_eq!(find_pack_file_in(&roots, "Samples/Drums/Snare.wav"), None);
+
+        // directories are not pack files
+        assert_eq!(find_pack_file_in(&roots, "Samples/Drums"), None);
+
+        // pack that isn't installed
+        let missing = AbletonPack::Pack {
+            name: "Chop and Swing".into(),
+            id: None,
+        };
+        let roots = pack_roots(&missing, Some(&factory_packs), &applications);
+        assert_eq!(find_pack_file_in(&roots, rel), None);
+
+        assert!(pack_roots(&pack, None, &applications).is_empty());
+    }
+
+    #[test]
+    fn test_find_core_library_file() {
+        let dir = get_temp_dir("tempo-pack-test");
+        let applications = dir.join("Applications");
+
+        let core_library = |app: &str| {
+            applications
+                .join(app)
+                .join("Contents")
+                .join("App-Resources")
+                .join("Core Library")
+        };
+
+        let rel = "Samples/Grooves/Swing 16-55.agr";
+        touch(&core_library("Ableton Live 11 Suite.app").join(rel));
+        touch(&core_library("Ableton Live 12 Suite.app").join(rel));
+        touch(&core_library("Ableton Live 12 Suite.app").join("Only In 12.wav"));
+        touch(&core_library("Some Other.app").join("Other.wav"));
+        fs::create_dir_all(applications.join("Ableton Live 12 Notes")).unwrap();
+
+        assert!(pack_roots(&AbletonPack::CoreLibrary, None, &dir.join("Nowhere")).is_empty());
+
+        let roots = pack_roots(&AbletonPack::CoreLibrary, None, &applications);
+        assert_eq!(
+            roots,
+            vec![
+                core_library("Ableton Live 12 Suite.app"),
+                core_library("Ableton Live 11 Suite.app")
+            ]
+        );
+
+        assert_eq!(
+            find_pack_file_in(&roots, rel),
+            Some(core_library("Ableton Live 12 Suite.app").join(rel))
+        );
+        assert_eq!(
+            find_pack_file_in(&roots, "Only In 12.wav"),
+            Some(core_library("Ableton Live 12 Suite.app").join("Only In 12.wav"))
+        );
+        assert_eq!(find_pack_file_in(&roots, "Other.wav"), None);
+    }
+}
@@ -9,19 +9,46 @@ use log::{error, info, warn};
 use crate::{
     daw::{ableton::ProjectFileRefWriter, plugin::PluginType},
     db::{PluginNameVendor, SharedDb},
-    file::{add_file_with_filename, add_referenced_file},
-    misc::{
-        extract_file_extension, get_filename, hash_file, path_to_str, remove_file_extension,
-        Result, TempoError,
-    },
-    shared::{FileErr, FileRef, MissingFileRef, PluginRef, PluginScan, ProjectFileRefScan},
+    file::{copy_files_into, get_unique_ref_filename, stage_referenced_files, StagedProject},
+    misc::{extract_file_extension, get_filename, path_to_str, Result, TempoError},
+    shared::{FileErr, FileRef, MissingFileRef, PluginRef, PluginScan, ProjectFileRefScan, Relink},
     structure::{expect_valid_folder, get_file_path},
-    types::{FileMeta, ProjectData},
+    types::{FileInfo, FileMeta, ProjectData},
 };
 
-use super::{als::AbletonFileRef, AbletonPluginRef, ProjectFileRefReader, ProjectPluginReader};
+use super::{
+    als::AbletonFileRef, can_open_with_any, find_pack_file, get_project_version, AbletonPack,
+    AbletonPackFileRef, AbletonPluginRef, AbletonVersion, ProjectFileRefReader,
+    ProjectPluginReader,
+};
 
 pub fn scan_filerefs(project: &Path) -> Result<ProjectFileRefScan> {
+    let project_dir = project.parent().ok_or(TempoError::Ableton(format!(
+        "Could not find parent directory of project {}",
+        project.to_string_lossy()
+    )))?;
+
+    scan_filerefs_in(project, project_dir)
+}
+
+/// Scans the file refs of all sets in a Live Project.
+pub fn scan_live_project_filerefs(live_project: &Path) -> Result<ProjectFileRefScan> {
+    let mut scan = ProjectFileRefScan {
+        ok: HashSet::new(),
+        missing: HashSet::new(),
+    };
+
+    for set in find_live_project_sets(live_project)? {
+        let set_scan = scan_filerefs_in(&set, live_project)?;
+        scan.ok.extend(set_scan.ok);
+        scan.missing.extend(set_scan.missing);
+    }
+
+    Ok(scan)
+}
+
+/// Scans file refs of a project, relative paths are resolved against `project_dir`.
+fn scan_filerefs_in(project: &Path, project_dir: &Path) -> Result<ProjectFileRefScan> {
     if !project.exists() || !project.is_file() {
         return Err(TempoError::Ableton(format!(
             "Project {} does not exist or is not a file",
@@ -29,22 +56,21 @@ pub fn scan_filerefs(project: &Path) -> Result<ProjectFileRefScan> {
         )));
     }
 
-    let project_dir = project.parent().ok_or(TempoError::Ableton(format!(
-        "Could not find parent directory of project {}",
-        project.to_string_lossy()
-    )))?;
-
     // dont you just love 200 closures
 
     // expand a relative path into an absolute path
     let expand_rel = |rel: &Path| project_dir.join(PathBuf::from(rel));
 
-    // turn relative into absolute
-    let rel = |fr: &AbletonFileRef| expand_rel(&PathBuf::from(&fr.rel));
-
     // get absolute
     let abs = |fr: &AbletonFileRef| PathBuf::from(&fr.abs);
 
+    // turn relative into absolute
+    // pack refs are relative to the root of the pack, look for the pack on this machine instead
+    let rel = |fr: &AbletonFileRef| match fr.pack() {
+        Some(pack) => find_pack_file(&pack, &fr.rel).unwrap_or_else(|| abs(fr)),
+        None => expand_rel(&PathBuf::from(&fr.rel)),
+    };
+
     // whether fileref exists
     let exists = |fr: &AbletonFileRef| -> std::result::Result<(), String> {
         if rel(fr).exists() || abs(fr).exists() {
@@ -85,17 +111,24 @@ pub fn scan_filerefs(project: &Path) -> Result<ProjectFileRefScan> {
 
         found.insert(fr.clone());
 
+        let pack = fr.pack().map(|p| p.to_string());
+
         match check_fr(&fr) {
             Ok(_) => scan.ok.insert(FileRef {
                 rel: fr.rel,
                 abs: fr.abs,
+                pack,
+                size: fr.size,
             }),
             Err(e) => scan.missing.insert(MissingFileRef {
                 file: FileRef {
                     rel: fr.rel,
                     abs: fr.abs,
+                    pack,
+                    size: fr.size,
                 },
                 err: e,
+                candidates: vec![],
             }),
         };
     }
@@ -107,6 +140,11 @@ pub struct AbletonProjectPluginScan {
     refs: Vec<(AbletonPluginRef, Option<PluginNameVendor>)>,
     // { username : idx of missing }
     missing: HashMap<String, Vec<usize>>,
+
+    // version of Live that saved this project
+    version: Option<AbletonVersion>,
+    // users who can't open this version of the project
+    cannot_open: Vec<String>,
 }
 
 impl AbletonProjectPluginScan {
@@ -122,9 +160,39 @@ impl AbletonProjectPluginScan {
             }
         }
 
+        let version = match get_project_version(path) {
+            Ok(v) => v,
+            Err(e) => {
+                error!("AbletonProjectScan::new(): error while reading Live version: {e}");
+                None
+            }
+        };
+
         Ok(Self {
             refs: found_plugs.into_iter().map(|p| (p, None)).collect(),
             missing: HashMap::new(),
+            version,
+            cannot_open: vec![],
+        })
+    }
+
+    /// Scans plugins used by all sets in a Live Project.
+    /// Uses the newest version of Live that saved any of the sets.
+    pub fn new_live_project(live_project: &Path) -> Result<Self> {
+        let mut found_plugs = HashSet::new();
+        let mut version: Option<AbletonVersion> = None;
+
+        for set in find_live_project_sets(live_project)? {
+            let scan = Self::new(&set)?;
+            found_plugs.extend(scan.refs.into_iter().map(|(p, _)| p));
+            version = version.max(scan.version);
+        }
+
+        Ok(Self {
+            refs: found_plugs.into_iter().map(|p| (p, None)).collect(),
+            missing: HashMap::new(),
+            version,
+            cannot_open: vec![],
         })
     }
 
@@ -151,11 +219,26 @@ impl AbletonProjectPluginScan {
 
         self.missing.insert(username.to_owned(), missing);
 
+        if let Some(version) = self.version.as_ref() {
+            if let Some(installed) = db.get_ableton_versions()? {
+                if !can_open_with_any(&installed, version) {
+                    self.cannot_open.push(username.to_owned());
+                }
+            }
+        }
+
         Ok(())
     }
 
     pub fn done(self) -> PluginScan {
-        let Self { refs, missing } = self;
+        let Self {
+            refs,
+            missing,
+            version,
+            mut cannot_open,
+        } = self;
+
+        cannot_open.sort();
 
         let plugins: Vec<PluginRef> = refs
             .into_iter()
@@ -183,11 +266,20 @@ impl AbletonProjectPluginScan {
             })
             .collect();
 
-        PluginScan { plugins, missing }
+        PluginScan {
+            plugins,
+            missing,
+            project_version: version.map(|v| v.to_string()),
+            cannot_open,
+        }
     }
 
-    pub fn done_ableton(self) -> Vec<AbletonPluginRef> {
-        self.refs.into_iter().map(|(p, _)| p).collect()
+    /// Returns the plugins used by the project and the version of Live that saved it.
+    pub fn done_ableton(self) -> (Vec<AbletonPluginRef>, Option<AbletonVersion>) {
+        (
+            self.refs.into_iter().map(|(p, _)| p).collect(),
+            self.version,
+        )
     }
 }
 
@@ -195,48 +287,301 @@ impl AbletonProjectPluginScan {
 /// Creates the Files directory and copies all files referenced in the project.
 /// The project will be copied into `dest`.
 /// The Files directory will be created inside of `dest`.
+///
+/// Files in Live Packs installed on this machine are not copied, see pack.rs.
 pub fn copy_ableton_project(
     folder: &Path,
     project_sha256: &str,
     project_filename: &str,
     refs: &HashMap<String, String>,
+    pack_refs: &HashMap<String, Vec<AbletonPackFileRef>>,
     live_project: &Path,
 ) -> Result<Vec<FileErr>> {
     expect_valid_folder(folder)?;
 
-    fs::copy(
-        get_file_path(folder, project_sha256),
-        live_project.join(project_filename),
-    )?;
+    // hashes of files that the copied project refers to
+    let needed = if pack_refs.is_empty() {
+        fs::copy(
+            get_file_path(folder, project_sha256),
+            live_project.join(project_filename),
+        )?;
+        refs.keys().cloned().collect()
+    } else {
+        copy_ableton_project_file(
+            folder,
+            project_sha256,
+            &live_project.join(project_filename),
+            refs,
+            pack_refs,
+            refs,
+        )?
+    };
+
+    copy_files_dir(folder, live_project, refs, &needed)
+}
+
+/// Copies a Live Project made up of multiple sets **from a Tempo folder** into the given directory.
+/// `sets` is a list of `(path of set relative to the Live Project, hash of set)`.
+/// All sets share one Files directory, sets are adjusted to point at the shared copies of their files.
+pub fn copy_ableton_live_project(
+    folder: &Path,
+    sets: &[(String, String)],
+    live_project: &Path,
+) -> Result<Vec<FileErr>> {
+    expect_valid_folder(folder)?;
+
+    let mut set_data = vec![];
+    for (path, hash) in sets {
+        let info = FileInfo::load(folder, hash)?;
+        match info.meta {
+            FileMeta::Project(ProjectData::Ableton {
+                refs, pack_refs, ..
+            }) => set_data.push((path, hash, refs, pack_refs)),
+            o => {
+                return Err(TempoError::Project(format!(
+                    "Possible corruption: expected Ableton metadata for {path}, found {o:#?}"
+                )))
+            }
+        }
+    }
+
+    // sets may use different filenames for the same file, or the same filename for different files
+    // pick one filename per file for the shared Files dir
+    // { hash : filename in Files dir }
+    let mut files: HashMap<String, String> = HashMap::new();
+    let mut used: HashSet<String> = HashSet::new();
+
+    for (_, _, refs, _) in set_data.iter() {
+        let mut refs: Vec<(&String, &String)> = refs.iter().collect();
+        refs.sort_by(|a, b| a.1.cmp(b.1));
+
+        for (hash, filename) in refs {
+            if !files.contains_key(hash) {
+                let filename = get_unique_ref_filename(&used, filename.clone());
+                used.insert(filename.clone());
+                files.insert(hash.clone(), filename);
+            }
+        }
+    }
+
+    let mut needed: HashSet<String> = HashSet::new();
+
+    for (path, hash, refs, pack_refs) in set_data.iter() {
+        let dest = live_project.join(path);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        needed.extend(copy_ableton_project_file(
+            folder, hash, &dest, refs, pack_refs, &files,
+        )?);
+    }
+
+    copy_files_dir(folder, live_project, &files, &needed)
+}
+
+/// Creates the Files directory of a Live Project and copies the `needed` files in `files` into it.
+/// `files` is `{ hash : filename in Files dir }`
+fn copy_files_dir(
+    folder: &Path,
+    live_project: &Path,
+    files: &HashMap<String, String>,
+    needed: &HashSet<String>,
+) -> Result<Vec<FileErr>> {
+    let files: HashMap<&String, &String> = files
+        .iter()
+        .filter(|(hash, _)| needed.contains(*hash))
+        .collect();
 
     // if this directory does not exist Ableton gets angry
     fs::create_dir_all(live_project.join("Ableton Project Info"))?;
 
-    let files_dir = live_project.join("Files");
+    if files.is_empty() {
+        return Ok(vec![]);
+    }
 
-    let mut failures: Vec<FileErr> = vec![];
+    copy_files_into(folder, files, &live_project.join("Files"))
+}
 
-    if !refs.is_empty() {
-        fs::create_dir_all(&files_dir)?;
-        for (h, filename) in refs.iter() {
-            // println!("{} {}", h, filename);
-            match fs::copy(get_file_path(folder, h), files_dir.join(filename)) {
-                Ok(_) => (),
-                Err(e) => {
-                    failures.push(FileErr {
-                        filename: filename.to_owned(),
-                        err: e.to_string(),
-                    });
+/// Copies a project file out of a folder, adjusting its FileRefs to point into a Files directory.
+/// `refs` and `pack_refs` are from the project's `ProjectData`.
+/// `files` is `{ hash : filename in Files dir }` for the Files directory the copy will use.
+/// Pack refs that can't be found on this machine are pointed into the Files directory.
+/// Returns the hashes of files that the copied project refers to.
+fn copy_ableton_project_file(
+    folder: &Path,
+    project_sha256: &str,
+    dest: &Path,
+    refs: &HashMap<String, String>,
+    pack_refs: &HashMap<String, Vec<AbletonPackFileRef>>,
+    files: &HashMap<String, String>,
+) -> Result<HashSet<String>> {
+    // { filename used by project : hash }
+    let hashes: HashMap<&String, &String> = refs.iter().map(|(h, f)| (f, h)).collect();
+
+    // { pack file ref : hash }
+    let pack_hashes: HashMap<&AbletonPackFileRef, &String> = pack_refs
+        .iter()
+        .flat_map(|(h, rs)| rs.iter().map(move |r| (r, h)))
+        .collect();
+
+    let mut needed: HashSet<String> = HashSet::new();
+
+    ProjectFileRefWriter::new(&get_file_path(folder, project_sha256), dest)?.edit_relative_paths(
+        |fr| match fr.pack() {
+            Some(pack) => {
+                if find_pack_file(&pack, &fr.rel).is_some() {
+                    return Ok(None);
+                }
+
+                let pack_ref = AbletonPackFileRef {
+                    pack,
+                    rel: fr.rel.clone(),
+                };
+
+                match pack_hashes
+                    .get(&pack_ref)
+                    .and_then(|h| files.get(*h).map(|f| (h, f)))
+                {
+                    Some((hash, filename)) => {
+                        info!(
+                            "{} is not installed, using collected copy of {}",
+                            pack_ref.pack, pack_ref.rel
+                        );
+                        needed.insert(hash.to_string());
+                        Ok(Some(format!("Files/{filename}")))
+                    }
+                    None => {
+                        warn!("no collected copy of pack file {:#?}", pack_ref);
+                        Ok(None)
+                    }
                 }
             }
+            None => {
+                let Some(hash) = fr
+                    .rel
+                    .strip_prefix("Files/")
+                    .and_then(|f| hashes.get(&f.to_string()))
+                else {
+                    return Ok(None);
+                };
+
+                needed.insert(hash.to_string());
+
+                match files.get(*hash) {
+                    Some(filename) if fr.rel != format!("Files/{filename}") => {
+                        Ok(Some(format!("Files/{filename}")))
+                    }
+                    _ => Ok(None),
+                }
+            }
+        },
+    )?;
+
+    Ok(needed)
+}
+
+/// Stages an Ableton project to be added to a Tempo folder.
+/// Also used for clips, racks and presets, which are stored the same way as sets.
+/// Missing files are replaced by files chosen by the user, see `ProjectFileRefScan`.
+pub fn stage_ableton_project_with_relinks(
+    folder: &Path,
+    username: &str,
+    project: &Path,
+    relinks: &[Relink],
+) -> Result<StagedProject> {
+    let live_project = project.parent().ok_or(TempoError::Project(format!(
+        "Error: Project path has no parent. This shouldn't happen. Project: {}",
+        path_to_str(project)
+    )))?;
+
+    stage_ableton_project_in(folder, username, project, live_project, relinks)
+}
+
+/// Stages all sets in a Live Project directory to be added to a Tempo folder.
+/// Files shared between sets are only stored once.
+/// Returns a list of `(path of set relative to the Live Project, staged set)`.
+pub fn stage_ableton_live_project(
+    folder: &Path,
+    username: &str,
+    live_project: &Path,
+    relinks: &[Relink],
+) -> Result<Vec<(String, StagedProject)>> {
+    let sets = find_live_project_sets(live_project)?;
+
+    if sets.is_empty() {
+        return Err(TempoError::Project(format!(
+            "Could not find any sets in the Live Project {}",
+            path_to_str(live_project)
+        )));
+    }
+
+    let mut staged = vec![];
+    for set in sets {
+        let path = set
+            .strip_prefix(live_project)
+            .map_err(|e| {
+                TempoError::Project(format!("Failed to get path of set in Live Project: {e}"))
+            })?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<String>>()
+            .join("/");
+
+        info!("stage_ableton_live_project(): staging set {path}");
+
+        let set = stage_ableton_project_in(folder, username, &set, live_project, relinks)?;
+        staged.push((path, set));
+    }
+
+    Ok(staged)
+}
+
+/// Whether a directory appears to be a Live Project.
+pub fn is_live_project(dir: &Path) -> bool {
+    dir.is_dir() && dir.join("Ableton Project Info").is_dir()
+}
+
+/// Finds all sets in a Live Project, sorted by path.
+/// Skips the Backup directory and Ableton Project Info.
+pub fn find_live_project_sets(live_project: &Path) -> Result<Vec<PathBuf>> {
+    let mut sets = vec![];
+    let mut dirs = vec![live_project.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        for ent in fs::read_dir(&dir)? {
+            let ent = ent?;
+            let path = ent.path();
+            let name = ent.file_name().to_string_lossy().to_string();
+
+            if name.starts_with('.') {
+                continue;
+            }
+
+            if path.is_dir() {
+                if name != "Backup" && name != "Ableton Project Info" {
+                    dirs.push(path);
+                }
+            } else if extract_file_extension(&name).1.as_deref() == Some("als") {
+                sets.push(path);
+            }
         }
     }
 
-    Ok(failures)
+    sets.sort();
+
+    Ok(sets)
 }
 
-/// Adds an Ableton project into a Tempo folder.
-pub fn add_ableton_project(folder: &Path, username: &str, project: &Path) -> Result<String> {
+/// Stages an Ableton project to be added to a Tempo folder.
+/// Relative paths in the project are resolved against `live_project`.
+fn stage_ableton_project_in(
+    folder: &Path,
+    username: &str,
+    project: &Path,
+    live_project: &Path,
+    relinks: &[Relink],
+) -> Result<StagedProject> {
     // something feels odd in this but it seems to work
 
     expect_valid_folder(folder)?;
@@ -250,150 +595,175 @@ pub fn add_ableton_project(folder: &Path, username: &str, project: &Path) -> Res
         out.to_string_lossy()
     );
 
+    // { (rel, abs) : file to use instead }
+    let relinks: HashMap<(&str, &str), &Path> = relinks
+        .iter()
+        .map(|r| ((r.file.rel.as_str(), r.file.abs.as_str()), r.path.as_path()))
+        .collect();
+
+    // first find all referenced files, in the order they appear in the project
+    // { file ref : (referenced file, pack the file ref points into) }
+    let mut resolved: HashMap<AbletonFileRef, (Option<PathBuf>, Option<AbletonPack>)> =
+        HashMap::new();
+    let mut files: Vec<PathBuf> = vec![];
+    let mut seen_files: HashSet<PathBuf> = HashSet::new();
+
+    for fr in ProjectFileRefReader::new(&copy)? {
+        let fr = fr?;
+        if resolved.contains_key(&fr) {
+            continue;
+        }
 
-    let writer = ProjectFileRefWriter::new(&copy, &out)?;
+        let (file, pack) = resolve_fileref(&fr, live_project, &relinks);
 
-    // { hash : (filename to use in Files dir, original path) }
-    let mut known: HashMap<String, (String, PathBuf)> = HashMap::new();
+        if let Some(file) = file.as_ref() {
+            if seen_files.insert(file.clone()) {
+                files.push(file.clone());
+            }
+        }
 
-    // used filenames
-    let mut used: HashSet<String> = HashSet::new();
+        resolved.insert(fr, (file, pack));
+    }
 
-    // previously handled file refs
-    let mut found: HashMap<PathBuf, String> = HashMap::new();
+    // copy all files into the staging directory
+    let staged = stage_referenced_files(folder, username, files)?;
+
+    // { hash : pack file refs }
+    let mut pack_refs: HashMap<String, Vec<AbletonPackFileRef>> = HashMap::new();
 
     let create_rel_path = |filename: &str| format!("Files/{filename}");
 
+    let writer = ProjectFileRefWriter::new(&copy, &out)?;
+
     writer.edit_relative_paths(|fr| {
-        let rel = project
-            .parent()
-            .ok_or(TempoError::Project(format!(
-                "Error: Project path has no parent. This shouldn't happen. Project: {}",
-                path_to_str(project)
-            )))?
-            .join(&fr.rel);
-        let abs = PathBuf::from(&fr.abs);
-
-        info!("resolving reference {:#?}", fr);
-        info!("rel: {}, abs: {}", path_to_str(&rel), path_to_str(&abs));
-
-        // try to find location of file
-        let file = {
-            if rel.exists() {
-                if !rel.is_dir() {
-                    Some(rel)
-                } else {
-                    warn!(
-                        "relative path was directory in add_ableton_project(): {}",
-                        path_to_str(&rel)
-                    );
-                    None
-                }
-            } else if abs.exists() {
-                if !abs.is_dir() {
-                    Some(abs)
-                } else {
-                    warn!(
-                        "absolute path was directory in add_ableton_project(): {}",
-                        path_to_str(&abs)
-                    );
-                    None
-                }
-            } else {
-                None
-            }
+        let (file, pack) = match resolved.get(fr) {
+            Some(r) => r.clone(),
+            None => resolve_fileref(fr, live_project, &relinks),
         };
 
-        // just check for duplicate refs here
-        if let Some(file) = file {
-            // check if we've copied this file already
-            if let Some(f) = found.get(&file) {
-                Ok(Some(f.clone()))
-            } else {
-                let hash = hash_file(&file)?;
-                info!("hash: {hash}");
-                if let Some((f, _)) = known.get(&hash) {
-                    Ok(Some(create_rel_path(f)))
-                } else {
-                    // otherwise get a unique filename for this file
-                    let mut filename = get_filename(&file)?;
-                    info!("filename: {filename}");
-                    if used.contains(&filename) {
-                        let (no_ext, ext) = extract_file_extension(&filename);
-                        let mut dup: usize = 1;
-                        while used.contains(&filename) {
-                            filename = format!("{no_ext}-{dup}{}", {
-                                if let Some(e) = ext.as_ref() {
-                                    format!(".{e}")
-                                } else {
-                                    "".into()
-                                }
-                            });
-                            dup += 1;
-                        }
+        match file.as_ref().and_then(|f| staged.hash(f)) {
+            Some(hash) => {
+                if let Some(pack) = pack {
+                    // pack refs are left pointing into the pack
+                    // we still collect the file in case a collaborator doesn't have the pack, see pack.rs
+                    let pack_ref = AbletonPackFileRef {
+                        pack,
+                        rel: fr.rel.clone(),
+                    };
+                    let locations = pack_refs.entry(hash.clone()).or_default();
+                    if !locations.contains(&pack_ref) {
+                        locations.push(pack_ref);
                     }
-                    let files_path = create_rel_path(&filename);
-                    known.insert(hash, (filename.clone(), file.clone()));
-                    used.insert(filename.clone());
-                    found.insert(file, files_path.clone());
-
-                    Ok(Some(files_path))
+                    return Ok(None);
                 }
+
+                Ok(staged.filename(hash).map(create_rel_path))
+            }
+            None => {
+                warn!(
+                    "failed to find FileRef while copying project, skipping: {:#?}",
+                    fr
+                );
+                Ok(None)
             }
-        } else {
-            warn!(
-                "failed to find FileRef while copying project, skipping: {:#?}",
-                fr
-            );
-            Ok(None)
         }
     })?;
 
-    drop(used);
-    drop(found);
-
     // now we've adjusted relative filerefs to point into Files dir
-    // we need to go through and copy all files into shared folder
-
-    let mut file_info_refs: HashMap<String, String> = HashMap::new();
-
-    for (orig_hash, (filename_to_use, file_path)) in known.into_iter() {
-        let added_hash = add_referenced_file(folder, username, &file_path)?;
-
-        if added_hash != orig_hash {
-            return Err(TempoError::Folder(format!(
-                "A file referenced in the project file {} has changed while Tempo was copying it. Please save the project and try sending it again. File that changed: {}",
-                project.to_string_lossy(),
-                file_path.to_string_lossy()
-            )));
-        }
-
-        file_info_refs.insert(added_hash, filename_to_use);
-    }
+    // staged files are moved into place in the shared folder when the project is committed
+    let file_info_refs = staged.refs();
 
-    // scan plugins
-    let plugins = AbletonProjectPluginScan::new(&copy)?.done_ableton();
+    // scan plugins and the Live version, a version that can't be read is logged by the scan
+    let (plugins, version) = AbletonProjectPluginScan::new(&copy)?.done_ableton();
 
-    add_file_with_filename(
+    StagedProject::new(
         folder,
         username,
+        project,
         &out,
-        &get_filename(project)?,
+        staged,
         FileMeta::Project(ProjectData::Ableton {
             refs: file_info_refs,
+            pack_refs,
             plugins,
+            version,
         }),
     )
 }
 
+/// Finds the file a FileRef points to.
+/// Returns `(referenced file, pack the FileRef points into)`.
+/// Files chosen by the user in `relinks` are used if the file can't be found.
+fn resolve_fileref(
+    fr: &AbletonFileRef,
+    live_project: &Path,
+    relinks: &HashMap<(&str, &str), &Path>,
+) -> (Option<PathBuf>, Option<AbletonPack>) {
+    let rel = live_project.join(&fr.rel);
+    let abs = PathBuf::from(&fr.abs);
+
+    let pack = fr.pack();
+
+    // pack refs are relative to the root of the pack
+    let rel = match pack.as_ref() {
+        Some(pack) => find_pack_file(pack, &fr.rel).unwrap_or(rel),
+        None => rel,
+    };
+
+    info!("resolving reference {:#?}", fr);
+    info!("rel: {}, abs: {}", path_to_str(&rel), path_to_str(&abs));
+
+    // try to find location of file
+    let file = {
+        if rel.exists() {
+            if !rel.is_dir() {
+                Some(rel)
+            } else {
+                warn!(
+                    "relative path was directory in add_ableton_project(): {}",
+                    path_to_str(&rel)
+                );
+                None
+            }
+        } else if abs.exists() {
+            if !abs.is_dir() {
+                Some(abs)
+            } else {
+                warn!(
+                    "absolute path was directory in add_ableton_project(): {}",
+                    path_to_str(&abs)
+                );
+                None
+            }
+        } else {
+            None
+        }
+    };
+
+    // fall back to a file the user chose to relink to
+    // relinked files are always collected, even if the ref pointed into a pack
+    match file {
+        Some(file) => (Some(file), pack),
+        None => match relinks.get(&(fr.rel.as_str(), fr.abs.as_str())) {
+            Some(p) if p.is_file() => {
+                info!("relinking to {}", path_to_str(p));
+                (Some(p.to_path_buf()), None)
+            }
+            _ => (None, pack),
+        },
+    }
+}
+
 /// Prepares to add an Ableton project to a Tempo folder.
 /// We create a copy of the project, and create a destination file for our modified version of the project file.
 /// Returns `(path to copy of project, output project path)`
 fn prepare_ableton_project(project: &Path) -> Result<(PathBuf, PathBuf)> {
-    let filename = remove_file_extension(&get_filename(project)?);
+    // clips, racks and presets keep their own extension
+    let (filename, ext) = extract_file_extension(&get_filename(project)?);
+    let ext = ext.unwrap_or("als".into());
 
-    let mut copy_filename = format!("[tempo copy] {filename}.als");
-    let mut out_filename = format!("[tempo output] {filename}.als");
+    let mut copy_filename = format!("[tempo copy] {filename}.{ext}");
+    let mut out_filename = format!("[tempo output] {filename}.{ext}");
 
     let mut dup: usize = 1;
 
@@ -402,8 +772,8 @@ fn prepare_ableton_project(project: &Path) -> Result<(PathBuf, PathBuf)> {
         let out = std::env::temp_dir().join(&out_filename);
 
         if src.exists() || out.exists() {
-            copy_filename = format!("[tempo copy] {filename}-{dup}.als");
-            out_filename = format!("[tempo output] {filename}-{dup}.als");
+            copy_filename = format!("[tempo copy] {filename}-{dup}.{ext}");
+            out_filename = format!("[tempo output] {filename}-{dup}.{ext}");
             dup += 1;
         } else {
             break (src, out);
@@ -423,4 +793,135 @@ fn prepare_ableton_project(project: &Path) -> Result<(PathBuf, PathBuf)> {
     Ok((src, out))
 }
 
-// TODO add tests here again
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{misc::hash_file, structure::create_tempo_folder, tests::get_temp_dir};
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    fn sample_ref(rel: &str, abs: &str) -> String {
+        format!(
+            r#"<SampleRef><FileRef><RelativePathType Value="3" /><RelativePath Value="{rel}" /><Path Value="{abs}" /><Type Value="1" /></FileRef></SampleRef>"#
+        )
+    }
+
+    fn write_set(path: &Path, sample_refs: &[String]) {
+        let xml = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?><Ableton MajorVersion="5" MinorVersion="12.0_12049" Creator="Ableton Live 12.0.5"><LiveSet>{}</LiveSet></Ableton>"#,
+            sample_refs.concat()
+        );
+        let mut gz = GzEncoder::new(fs::File::create(path).unwrap(), Compression::default());
+        gz.write_all(xml.as_bytes()).unwrap();
+        gz.finish().unwrap();
+    }
+
+    #[test]
+    fn test_add_and_copy_project() {
+        let folder = get_temp_dir("ableton_folder");
+        create_tempo_folder(&folder).unwrap();
+
+        let live_project = get_temp_dir("ableton_project");
+        let outside = get_temp_dir("ableton_samples");
+        fs::create_dir_all(live_project.join("Samples")).unwrap();
+        fs::write(live_project.join("Samples/kick.wav"), b"kick").unwrap();
+        fs::write(outside.join("kick.wav"), b"other kick").unwrap();
+
+        // one ref relative to the set, one only found through its absolute path, one missing
+        let set = live_project.join("song.als");
+        write_set(
+            &set,
+            &[
+                sample_ref("Samples/kick.wav", "/nonexistent/kick.wav"),
+                sample_ref("../gone/kick.wav", &path_to_str(&outside.join("kick.wav"))),
+                sample_ref("Samples/snare.wav", "/nonexistent/snare.wav"),
+            ],
+        );
+
+        let hash = stage_ableton_project_with_relinks(&folder, "user", &set, &[])
+            .unwrap()
+            .commit(&folder, "user")
+            .unwrap();
+
+        let FileMeta::Project(ProjectData::Ableton {
+            refs,
+            pack_refs,
+            version,
+            ..
+        }) = FileInfo::load(&folder, &hash).unwrap().meta
+        else {
+            panic!("expected Ableton project data");
+        };
+
+        let kick = hash_file(&live_project.join("Samples/kick.wav")).unwrap();
+        let other_kick = hash_file(&outside.join("kick.wav")).unwrap();
+
+        // both files are collected with unique filenames, the missing one is skipped
+        assert_eq!(refs.len(), 2);
+        let mut filenames: Vec<&String> = refs.values().collect();
+        filenames.sort();
+        assert_eq!(filenames, vec!["kick-1.wav", "kick.wav"]);
+        assert!(refs.contains_key(&kick) && refs.contains_key(&other_kick));
+        assert!(pack_refs.is_empty());
+        assert_eq!(version, Some(AbletonVersion::parse("12.0.5").unwrap()));
+
+        let dest = get_temp_dir("ableton_copy");
+        let errs =
+            copy_ableton_project(&folder, &hash, "song.als", &refs, &pack_refs, &dest).unwrap();
+        assert!(errs.is_empty());
+
+        assert_eq!(
+            fs::read(dest.join("Files").join(&refs[&kick])).unwrap(),
+            b"kick"
+        );
+        assert_eq!(
+            fs::read(dest.join("Files").join(&refs[&other_kick])).unwrap(),
+            b"other kick"
+        );
+
+        // collected refs point into the Files directory, the missing ref is left alone
+        let rels: Vec<String> = ProjectFileRefReader::new(&dest.join("song.als"))
+            .unwrap()
+            .map(|fr| fr.unwrap().rel)
+            .collect();
+        assert_eq!(
+            rels,
+            vec![
+                format!("Files/{}", refs[&kick]),
+                format!("Files/{}", refs[&other_kick]),
+                "Samples/snare.wav".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_live_project_sets() {
+        let live_project = get_temp_dir("live_project");
+
+        fs::create_dir_all(live_project.join("Ableton Project Info")).unwrap();
+        fs::create_dir_all(live_project.join("Backup")).unwrap();
+        fs::create_dir_all(live_project.join("Ideas")).unwrap();
+
+        for set in [
+            "b.als",
+            "a.als",
+            "Backup/a [2024-01-01 120000].als",
+            "Ideas/c.als",
+        ] {
+            fs::File::create(live_project.join(set)).unwrap();
+        }
+        fs::File::create(live_project.join("kick.wav")).unwrap();
+
+        assert!(is_live_project(&live_project));
+        assert!(!is_live_project(&live_project.join("Ideas")));
+
+        assert_eq!(
+            find_live_project_sets(&live_project).unwrap(),
+            vec![
+                live_project.join("Ideas/c.als"),
+                live_project.join("a.als"),
+                live_project.join("b.als"),
+            ]
+        );
+    }
+}
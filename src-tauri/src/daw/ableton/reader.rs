@@ -0,0 +1,190 @@
+// =====================================================================================
+// This file is licensed under either of
+// Apache License, Version 2.0 or MIT license, at your option.
+// =====================================================================================
+// You may obtain a copy of the Apache License, Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0
+// =====================================================================================
+// You may obtain a copy of the MIT License at
+// https://opensource.org/licenses/MIT
+// =====================================================================================
+
+// Ableton implementation of ProjectReader
+
+use std::path::Path;
+
+use log::error;
+
+use crate::{
+    daw::{
+        project::{ProjectTempo, ProjectType},
+        reader::{
+            find_missing_files, find_missing_plugins, other_daw_error, ProjectPluginRef,
+            ProjectPluginScan, ProjectReader,
+        },
+    },
+    db::{get_clients_missing_ableton_version, SharedDb},
+    file::StagedProject,
+    misc::Result,
+    shared::{FileErr, PluginScan, ProjectFileRefScan, Relink, SharedProjectData},
+    types::ProjectData,
+};
+
+use super::{
+    copy_ableton_project, get_project_tempo, is_live_project, scan_filerefs,
+    scan_live_project_filerefs, stage_ableton_project_with_relinks, verify_project,
+    AbletonPluginRef, AbletonProjectPluginScan,
+};
+
+pub struct AbletonReader;
+
+impl ProjectReader for AbletonReader {
+    fn name(&self) -> &'static str {
+        "Ableton"
+    }
+
+    fn project_type(&self, ext: &str) -> Option<ProjectType> {
+        match ext.to_lowercase().as_str() {
+            "als" => Some(ProjectType::Ableton),
+            "alc" => Some(ProjectType::AbletonClip),
+            "adg" => Some(ProjectType::AbletonRack),
+            "adv" => Some(ProjectType::AbletonPreset),
+            _ => None,
+        }
+    }
+
+    fn default_type(&self) -> ProjectType {
+        ProjectType::Ableton
+    }
+
+    fn handles(&self, project_type: &ProjectType) -> bool {
+        matches!(
+            project_type,
+            ProjectType::Ableton
+                | ProjectType::AbletonClip
+                | ProjectType::AbletonRack
+                | ProjectType::AbletonPreset
+        )
+    }
+
+    fn handles_data(&self, data: &ProjectData) -> bool {
+        // clips, racks and presets are stored as ProjectData::Ableton too
+        matches!(data, ProjectData::Ableton { .. })
+    }
+
+    fn verify(&self, project: &Path) -> Result<()> {
+        // clips, racks and presets share the Live Set file format
+        verify_project(project)
+    }
+
+    fn is_project_dir(&self, dir: &Path) -> bool {
+        is_live_project(dir)
+    }
+
+    fn read_tempo(&self, project: &Path) -> Result<Option<ProjectTempo>> {
+        get_project_tempo(project)
+    }
+
+    fn scan_filerefs(&self, path: &Path) -> Result<ProjectFileRefScan> {
+        if is_live_project(path) {
+            scan_live_project_filerefs(path)
+        } else {
+            scan_filerefs(path)
+        }
+    }
+
+    fn scan_plugins(&self, path: &Path) -> Result<Box<dyn ProjectPluginScan>> {
+        if is_live_project(path) {
+            Ok(Box::new(AbletonProjectPluginScan::new_live_project(path)?))
+        } else {
+            Ok(Box::new(AbletonProjectPluginScan::new(path)?))
+        }
+    }
+
+    fn stage_project(
+        &self,
+        folder: &Path,
+        username: &str,
+        project: &Path,
+        relinks: &[Relink],
+    ) -> Result<StagedProject> {
+        stage_ableton_project_with_relinks(folder, username, project, relinks)
+    }
+
+    /// Scans to see if database contains all plugins.
+    /// Checks for presence of all referenced files.
+    fn project_info(
+        &self,
+        folder: &Path,
+        data: ProjectData,
+        db: &SharedDb,
+    ) -> Result<SharedProjectData> {
+        let ProjectData::Ableton {
+            refs,
+            pack_refs: _,
+            plugins,
+            version,
+        } = data
+        else {
+            return Err(other_daw_error(self.name()));
+        };
+
+        let cannot_open = match version.as_ref() {
+            None => vec![],
+            Some(v) => match get_clients_missing_ableton_version(folder, v) {
+                Ok(users) => users,
+                Err(e) => {
+                    error!("AbletonReader::project_info(): error while checking Live versions of users: {e}");
+                    vec![]
+                }
+            },
+        };
+
+        Ok(SharedProjectData::Ableton {
+            missing_files: find_missing_files(folder, refs.into_iter().map(|(h, f)| (h, [f]))),
+            missing_plugins: find_missing_plugins(db, plugins),
+            version: version.map(|v| v.to_string()),
+            cannot_open,
+        })
+    }
+
+    /// The project is copied into `dest`, referenced files are copied into `dest/Files`.
+    fn copy_project(
+        &self,
+        folder: &Path,
+        project_sha256: &str,
+        project_filename: &str,
+        data: ProjectData,
+        dest: &Path,
+    ) -> Result<Vec<FileErr>> {
+        match data {
+            ProjectData::Ableton {
+                refs, pack_refs, ..
+            } => copy_ableton_project(
+                folder,
+                project_sha256,
+                project_filename,
+                &refs,
+                &pack_refs,
+                dest,
+            ),
+            _ => Err(other_daw_error(self.name())),
+        }
+    }
+}
+
+impl ProjectPluginScan for AbletonProjectPluginScan {
+    fn scan_db(&mut self, db: &SharedDb, username: &str) -> Result<()> {
+        AbletonProjectPluginScan::scan_db(self, db, username)
+    }
+
+    fn done(self: Box<Self>) -> PluginScan {
+        AbletonProjectPluginScan::done(*self)
+    }
+}
+
+impl ProjectPluginRef for AbletonPluginRef {
+    fn is_missing(&self, db: &SharedDb) -> Result<bool> {
+        Ok(db.get_ableton_plugin(self)?.is_none())
+    }
+}
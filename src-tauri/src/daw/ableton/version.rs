@@ -0,0 +1,208 @@
+// =====================================================================================
+// This file is licensed under either of
+// Apache License, Version 2.0 or MIT license, at your option.
+// =====================================================================================
+// You may obtain a copy of the Apache License, Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0
+// =====================================================================================
+// You may obtain a copy of the MIT License at
+// https://opensource.org/licenses/MIT
+// =====================================================================================
+
+// Live version detection
+
+/*
+Live can't open sets that were saved by a newer version of Live.
+we keep track of two things:
+
+1. the version of Live that saved a set
+  - read from the Creator attribute of the root Ableton tag, eg Creator="Ableton Live 12.0.5"
+  - older sets without a useful Creator still have MinorVersion, eg MinorVersion="12.0_12049"
+2. the versions of Live a user has installed
+  - Live creates a directory for every version in ~/Library/Preferences/Ableton, eg "Live 12.0.5"
+  - these directories stick around after uninstalling Live, so this can overreport installed versions
+
+we only compare major and minor versions.
+Live seems to be able to open sets from newer patch releases.
+ */
+
+use std::{cmp::Ordering, fmt, fs, path::PathBuf};
+
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+
+use crate::misc::{path_to_str, Result};
+
+/// A version of Live, eg 12.0.5
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq, Hash)]
+pub struct AbletonVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl AbletonVersion {
+    /// Parses a version string like "12.0.5", "Live 11.3.21", "Ableton Live 12.1b3" or "12.0_12049".
+    /// Missing minor and patch versions are treated as 0.
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        let s = s.strip_prefix("Ableton ").unwrap_or(s);
+        let s = s.strip_prefix("Live ").unwrap_or(s);
+
+        // MinorVersion has a build number after an underscore
+        let s = s.split('_').next()?;
+
+        // takes leading digits, "1b3" -> 1
+        let leading = |part: &str| -> Option<u32> {
+            let digits: String = part.chars().take_while(|c| c.is_ascii_digit()).collect();
+            digits.parse::<u32>().ok()
+        };
+
+        let mut parts = s.split('.');
+
+        let major = leading(parts.next()?)?;
+        let minor = parts.next().and_then(leading).unwrap_or(0);
+        let patch = parts.next().and_then(leading).unwrap_or(0);
+
+        Some(Self {
+            major,
+            minor,
+            patch,
+        })
+    }
+
+    /// Whether a set saved with `project` can be opened with this version of Live.
+    pub fn can_open(&self, project: &AbletonVersion) -> bool {
+        (self.major, self.minor) >= (project.major, project.minor)
+    }
+}
+
+impl Ord for AbletonVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch).cmp(&(other.major, other.minor, other.patch))
+    }
+}
+
+impl PartialOrd for AbletonVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for AbletonVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Whether any of the installed versions can open a set saved with `project`.
+/// Returns `true` if no installed versions are known, we can't tell in this case.
+pub fn can_open_with_any(installed: &[AbletonVersion], project: &AbletonVersion) -> bool {
+    installed.is_empty() || installed.iter().any(|v| v.can_open(project))
+}
+
+#[cfg(target_os = "macos")]
+fn get_ableton_prefs_dir() -> Result<PathBuf> {
+    use crate::misc::TempoError;
+    use directories::UserDirs;
+
+    let user_dirs = UserDirs::new().ok_or(TempoError::Other(
+        "Could not open user home directory".into(),
+    ))?;
+
+    Ok(user_dirs
+        .home_dir()
+        .join("Library")
+        .join("Preferences")
+        .join("Ableton"))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn get_ableton_prefs_dir() -> Result<PathBuf> {
+    Err(crate::misc::TempoError::Ableton(
+        "Finding installed versions of Live is only supported on macOS".into(),
+    ))
+}
+
+/// Scans for versions of Live installed on this system.
+/// Most recent version first.
+pub fn scan_installed_versions() -> Result<Vec<AbletonVersion>> {
+    let prefs_dir = get_ableton_prefs_dir()?;
+
+    if !fs::exists(&prefs_dir)? {
+        info!(
+            "scan_installed_versions(): no Ableton preferences found at {}",
+            path_to_str(&prefs_dir)
+        );
+        return Ok(vec![]);
+    }
+
+    let mut versions: Vec<AbletonVersion> = fs::read_dir(&prefs_dir)?
+        .filter_map(|ent| match ent {
+            Ok(ent) => Some(ent),
+            Err(e) => {
+                error!("scan_installed_versions(): error while reading directory entry: {e}");
+                None
+            }
+        })
+        .filter(|ent| ent.path().is_dir())
+        .filter_map(|ent| {
+            let name = ent.file_name().to_string_lossy().to_string();
+            if name.starts_with("Live ") {
+                AbletonVersion::parse(&name)
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    versions.sort();
+    versions.dedup();
+    versions.reverse();
+
+    info!("scan_installed_versions(): found {:#?}", versions);
+
+    Ok(versions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        let v = |major, minor, patch| AbletonVersion {
+            major,
+            minor,
+            patch,
+        };
+
+        assert_eq!(
+            AbletonVersion::parse("Ableton Live 12.0.5"),
+            Some(v(12, 0, 5))
+        );
+        assert_eq!(AbletonVersion::parse("Live 11.3.21"), Some(v(11, 3, 21)));
+        assert_eq!(
+            AbletonVersion::parse("Ableton Live 12.1b3"),
+            Some(v(12, 1, 0))
+        );
+        assert_eq!(AbletonVersion::parse("12.0_12049"), Some(v(12, 0, 0)));
+        assert_eq!(AbletonVersion::parse("Live 11"), Some(v(11, 0, 0)));
+        assert_eq!(AbletonVersion::parse("Preferences"), None);
+    }
+
+    #[test]
+    fn test_can_open() {
+        let live_11 = AbletonVersion::parse("11.3.21").unwrap();
+        let live_12 = AbletonVersion::parse("12.0.5").unwrap();
+        let live_12_patch = AbletonVersion::parse("12.0.10").unwrap();
+
+        assert!(live_12.can_open(&live_11));
+        assert!(!live_11.can_open(&live_12));
+        assert!(live_12.can_open(&live_12_patch));
+
+        assert!(can_open_with_any(&[], &live_12));
+        assert!(can_open_with_any(&[live_11, live_12], &live_12));
+        assert!(!can_open_with_any(&[live_11], &live_12));
+    }
+}
@@ -0,0 +1,43 @@
+// =====================================================================================
+// This file is licensed under either of
+// Apache License, Version 2.0 or MIT license, at your option.
+// =====================================================================================
+// You may obtain a copy of the Apache License, Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0
+// =====================================================================================
+// You may obtain a copy of the MIT License at
+// https://opensource.org/licenses/MIT
+// =====================================================================================
+
+mod bwproject;
+mod project;
+mod reader;
+
+pub use bwproject::verify_project;
+pub use project::{copy_bitwig_project, scan_filerefs, scan_plugins, stage_bitwig_project};
+pub use reader::BitwigReader;
+
+use serde::{Deserialize, Serialize};
+
+use crate::shared::PluginRef;
+
+use super::plugin::PluginType;
+
+/// Represents a plugin found in a Bitwig project file.
+/// Bitwig projects reference plugins by the path of the plugin, the name is taken from the plugin's filename.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, Hash, PartialEq)]
+pub struct BitwigPluginRef {
+    pub plugin_type: PluginType,
+    pub name: String,
+    pub path: String,
+}
+
+impl From<BitwigPluginRef> for PluginRef {
+    fn from(value: BitwigPluginRef) -> Self {
+        Self {
+            plugin_type: value.plugin_type,
+            name: value.name,
+            vendor: "Unknown vendor".into(),
+        }
+    }
+}
@@ -0,0 +1,294 @@
+// =====================================================================================
+// This file is licensed under either of
+// Apache License, Version 2.0 or MIT license, at your option.
+// =====================================================================================
+// You may obtain a copy of the Apache License, Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0
+// =====================================================================================
+// You may obtain a copy of the MIT License at
+// https://opensource.org/licenses/MIT
+// =====================================================================================
+
+// reading Bitwig .bwproject files
+
+/*
+.bwproject files are an undocumented binary format. files start with the magic BtWg.
+
+we don't try to parse the whole structure. strings in the file are stored as a 4 byte big endian length
+followed by UTF-8 bytes, so we look for byte runs that are valid length prefixed strings and only use the ones
+that look like what we're interested in:
+
+- paths ending in an audio or sample extension are referenced media
+- paths ending in a plugin bundle/library extension (.vst3, .vst, .clap, .component, .dll) are plugins
+
+only strings containing a path separator are used. projects also store names, eg clips are named after
+their sample ("kick.wav") and presets after whatever the user typed, which would otherwise be read as
+media or plugins. Bitwig stores media inside of the project directory in a subdirectory (samples/, recordings/),
+so relative media paths always contain a separator.
+
+we never modify project files. we'd have to guess at the structure around the strings to rewrite them,
+and a wrong guess corrupts the project. projects are added to folders as is.
+
+a Bitwig project directory looks like this:
+
+My Song/
+  My Song.bwproject
+  samples/       <- files collected into the project
+  recordings/
+  bounce/
+
+Bitwig resolves relative paths against the project directory.
+media inside of the project directory keeps its relative path, so copies of the project find it.
+media outside of it is copied into samples/ when the project is copied out of a folder.
+the project still points at the original location of that media, so Bitwig asks the user to locate it,
+we tell the user to pick the files in samples/.
+ */
+
+use std::{collections::HashSet, fs, path::Path};
+
+use crate::misc::{extract_file_extension, path_to_str, Result, TempoError};
+
+use crate::daw::plugin::PluginType;
+
+use super::BitwigPluginRef;
+
+const MAGIC: &[u8] = b"BtWg";
+
+// longest string we consider, paths longer than this are very unlikely
+const MAX_STRING_LEN: usize = 4096;
+
+const MEDIA_EXTENSIONS: &[&str] = &[
+    "wav",
+    "aif",
+    "aiff",
+    "flac",
+    "mp3",
+    "ogg",
+    "opus",
+    "mid",
+    "midi",
+    "multisample",
+];
+
+/// A string found in a project file.
+struct BwString {
+    // offset of the length prefix
+    offset: usize,
+    // length of the string in bytes, not including the prefix
+    len: usize,
+}
+
+/// A Bitwig project file which has been read into memory.
+pub struct BwProject {
+    data: Vec<u8>,
+    strings: Vec<BwString>,
+}
+
+impl BwProject {
+    pub fn read(project: &Path) -> Result<Self> {
+        let data = fs::read(project)?;
+        Self::parse(data).ok_or(TempoError::Project(format!(
+            "{} is not a valid Bitwig project",
+            path_to_str(project)
+        )))
+    }
+
+    fn parse(data: Vec<u8>) -> Option<Self> {
+        if !data.starts_with(MAGIC) {
+            return None;
+        }
+
+        let strings = find_strings(&data);
+        Some(Self { data, strings })
+    }
+
+    /// Contents of the project file.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn get(&self, s: &BwString) -> &str {
+        // find_strings() only keeps valid UTF-8
+        std::str::from_utf8(&self.data[s.offset + 4..s.offset + 4 + s.len]).unwrap_or_default()
+    }
+
+    /// Paths of all media referenced by the project, in the order they first appear.
+    pub fn media_paths(&self) -> Vec<String> {
+        let mut seen: HashSet<&str> = HashSet::new();
+        let mut paths = vec![];
+
+        for s in &self.strings {
+            let s = self.get(s);
+            if is_media_path(s) && seen.insert(s) {
+                paths.push(s.to_string());
+            }
+        }
+
+        paths
+    }
+
+    /// All plugins used in the project, duplicates are removed.
+    pub fn plugins(&self) -> Vec<BitwigPluginRef> {
+        let mut seen: HashSet<BitwigPluginRef> = HashSet::new();
+        let mut plugins = vec![];
+
+        for s in &self.strings {
+            if let Some(p) = parse_plugin_path(self.get(s)) {
+                if seen.insert(p.clone()) {
+                    plugins.push(p);
+                }
+            }
+        }
+
+        plugins
+    }
+}
+
+/// Checks whether a file is a Bitwig project.
+pub fn verify_project(project: &Path) -> Result<()> {
+    let mut magic = [0u8; 4];
+    let mut f = fs::File::open(project)?;
+
+    match std::io::Read::read_exact(&mut f, &mut magic) {
+        Ok(()) if magic == MAGIC => Ok(()),
+        _ => Err(TempoError::Project(format!(
+            "{} is not a valid Bitwig project",
+            path_to_str(project)
+        ))),
+    }
+}
+
+/// Finds all length prefixed strings which look like paths, in order.
+/// Strings don't overlap, scanning continues after the end of each string that's found.
+fn find_strings(data: &[u8]) -> Vec<BwString> {
+    let mut strings = vec![];
+    let mut i = MAGIC.len();
+
+    while i + 4 <= data.len() {
+        let len = u32::from_be_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]) as usize;
+
+        if (2..=MAX_STRING_LEN).contains(&len) && i + 4 + len <= data.len() {
+            let bytes = &data[i + 4..i + 4 + len];
+
+            if let Ok(s) = std::str::from_utf8(bytes) {
+                if looks_like_path(s) {
+                    strings.push(BwString { offset: i, len });
+                    i += 4 + len;
+                    continue;
+                }
+            }
+        }
+
+        i += 1;
+    }
+
+    strings
+}
+
+fn looks_like_path(s: &str) -> bool {
+    !s.chars().any(char::is_control) && (s.contains('/') || s.contains('\\'))
+}
+
+fn is_media_path(s: &str) -> bool {
+    matches!(
+        extract_file_extension(s).1.map(|e| e.to_lowercase()),
+        Some(e) if MEDIA_EXTENSIONS.contains(&e.as_str())
+    )
+}
+
+fn parse_plugin_path(s: &str) -> Option<BitwigPluginRef> {
+    // plugin bundles can be referenced by a path inside of the bundle
+    let bundle = s.split(['/', '\\']).find(|c| {
+        extract_file_extension(c)
+            .1
+            .is_some_and(|e| plugin_type(&e).is_some())
+    })?;
+
+    let (name, ext) = extract_file_extension(bundle);
+    let plugin_type = plugin_type(&ext?)?;
+
+    if name.is_empty() {
+        return None;
+    }
+
+    Some(BitwigPluginRef {
+        plugin_type,
+        name,
+        path: s.to_string(),
+    })
+}
+
+fn plugin_type(ext: &str) -> Option<PluginType> {
+    match ext.to_lowercase().as_str() {
+        "vst3" => Some(PluginType::Vst3),
+        "vst" | "dll" => Some(PluginType::Vst),
+        "clap" => Some(PluginType::Clap),
+        "component" => Some(PluginType::Au),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(s: &str) -> Vec<u8> {
+        let mut b = (s.len() as u32).to_be_bytes().to_vec();
+        b.extend_from_slice(s.as_bytes());
+        b
+    }
+
+    fn project() -> Vec<u8> {
+        let mut data = MAGIC.to_vec();
+        data.extend_from_slice(b"0001\x00\x12\x08");
+        data.extend(string("samples/kick.wav"));
+        data.extend_from_slice(b"\x00\x00\x01\x08");
+        data.extend(string("/Users/a/Samples/snare.aif"));
+        data.extend_from_slice(b"\x07\x08");
+        data.extend(string("/Library/Audio/Plug-Ins/VST3/Pro-Q 3.vst3"));
+        data.extend(string("samples/kick.wav"));
+        data.extend_from_slice(b"\x00\x00");
+        data
+    }
+
+    #[test]
+    fn test_media_paths() {
+        let bw = BwProject::parse(project()).unwrap();
+
+        assert_eq!(
+            bw.media_paths(),
+            vec!["samples/kick.wav", "/Users/a/Samples/snare.aif"]
+        );
+
+        assert_eq!(
+            bw.plugins(),
+            vec![BitwigPluginRef {
+                plugin_type: PluginType::Vst3,
+                name: "Pro-Q 3".into(),
+                path: "/Library/Audio/Plug-Ins/VST3/Pro-Q 3.vst3".into(),
+            }]
+        );
+
+        assert!(BwProject::parse(b"<?xml".to_vec()).is_none());
+    }
+
+    #[test]
+    fn test_names_are_not_paths() {
+        let mut data = MAGIC.to_vec();
+        data.extend_from_slice(b"0001\x00\x12\x08");
+        // clip named after its sample
+        data.extend(string("kick.wav"));
+        // preset names
+        data.extend(string("Pro-Q 3.vst3"));
+        data.extend(string("Bass 2.0"));
+        data.extend(string("Lead.clap"));
+        // preset file of a plugin, not the plugin itself
+        data.extend(string("/Users/a/Presets/Pro-Q 3/Warm.vstpreset"));
+        data.extend_from_slice(b"\x00\x00");
+
+        let bw = BwProject::parse(data).unwrap();
+
+        assert!(bw.media_paths().is_empty());
+        assert!(bw.plugins().is_empty());
+    }
+}
@@ -0,0 +1,278 @@
+// =====================================================================================
+// This file is licensed under either of
+// Apache License, Version 2.0 or MIT license, at your option.
+// =====================================================================================
+// You may obtain a copy of the Apache License, Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0
+// =====================================================================================
+// You may obtain a copy of the MIT License at
+// https://opensource.org/licenses/MIT
+// =====================================================================================
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    daw::{
+        plugin::PluginType,
+        reader::{
+            get_project_dir, resolve_media_paths, scan_media_paths, PluginRefScan, ProjectPluginRef,
+        },
+    },
+    db::SharedDb,
+    file::{
+        copy_files_into, get_unique_ref_filename, stage_project_data, stage_referenced_files,
+        StagedProject,
+    },
+    misc::{get_filename, Result},
+    shared::{FileErr, ProjectFileRefScan, Relink},
+    structure::{expect_valid_folder, get_file_path},
+    types::{FileMeta, ProjectData},
+};
+
+use super::{bwproject::BwProject, BitwigPluginRef};
+
+/// Directory in the project directory that media from outside of the project is collected into.
+const SAMPLES_DIR: &str = "samples";
+
+/// Gets the path of a file relative to the project directory, using / as the separator.
+/// `None` if the file isn't inside of the project directory.
+fn get_project_rel_path(file: &Path, project_dir: &Path) -> Option<String> {
+    let rel = file.strip_prefix(project_dir).ok()?;
+    let parts: Vec<String> = rel
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect();
+    Some(parts.join("/"))
+}
+
+/// Files that were collected into the samples directory are found by filename.
+pub fn scan_filerefs(project: &Path) -> Result<ProjectFileRefScan> {
+    let project_dir = get_project_dir(project)?;
+    let bw = BwProject::read(project)?;
+
+    Ok(scan_media_paths(
+        bw.media_paths(),
+        project_dir,
+        &[project_dir.join(SAMPLES_DIR)],
+    ))
+}
+
+/// Stages a Bitwig project to be added to a Tempo folder.
+/// The project file is added as is, see bwproject.rs.
+/// Media inside of the project directory keeps its path, other media is collected into the samples directory.
+/// Missing files are replaced by files chosen by the user, see `ProjectFileRefScan`.
+pub fn stage_bitwig_project(
+    folder: &Path,
+    username: &str,
+    project: &Path,
+    relinks: &[Relink],
+) -> Result<StagedProject> {
+    expect_valid_folder(folder)?;
+
+    let project_dir = get_project_dir(project)?;
+
+    // the whole project is read up front, so changes made while we're copying files don't matter
+    let bw = BwProject::read(project)?;
+
+    let files = resolve_media_paths(
+        bw.media_paths(),
+        project_dir,
+        &[project_dir.join(SAMPLES_DIR)],
+        relinks,
+    )
+    .files;
+
+    // { file : path relative to the project directory in the copied project }
+    let mut layout: HashMap<PathBuf, String> = HashMap::new();
+
+    // filenames used in the samples directory
+    let mut used: HashSet<String> = HashSet::new();
+
+    for file in &files {
+        if let Some(rel) = get_project_rel_path(file, project_dir) {
+            if let Some(name) = rel.strip_prefix(&format!("{SAMPLES_DIR}/")) {
+                used.insert(name.to_string());
+            }
+            layout.insert(file.clone(), rel);
+        }
+    }
+
+    // media outside of the project directory, the copied project still points at its original location
+    let mut relink: Vec<String> = vec![];
+
+    for file in &files {
+        if !layout.contains_key(file) {
+            let filename = get_unique_ref_filename(&used, get_filename(file)?);
+            used.insert(filename.clone());
+            let rel = format!("{SAMPLES_DIR}/{filename}");
+            relink.push(rel.clone());
+            layout.insert(file.clone(), rel);
+        }
+    }
+
+    let staged = stage_referenced_files(folder, username, files.clone())?;
+
+    // { hash : paths relative to the project directory }
+    let mut hash_layout: HashMap<String, Vec<String>> = HashMap::new();
+    for file in &files {
+        if let (Some(hash), Some(rel)) = (staged.hash(file), layout.get(file)) {
+            hash_layout
+                .entry(hash.clone())
+                .or_default()
+                .push(rel.clone());
+        }
+    }
+
+    let refs: HashMap<String, Vec<String>> = staged
+        .refs()
+        .into_keys()
+        .filter_map(|hash| hash_layout.remove(&hash).map(|rels| (hash, rels)))
+        .collect();
+
+    stage_project_data(
+        folder,
+        username,
+        project,
+        bw.data(),
+        staged,
+        FileMeta::Project(ProjectData::Bitwig {
+            refs,
+            relink,
+            plugins: bw.plugins(),
+        }),
+    )
+}
+
+/// Copies a Bitwig project **from a Tempo folder** into `dest`.
+/// Media is copied to its path relative to the project.
+/// Media from outside of the original project directory ends up in the samples directory, Bitwig asks the user to locate it.
+pub fn copy_bitwig_project(
+    folder: &Path,
+    project_sha256: &str,
+    project_filename: &str,
+    refs: &HashMap<String, Vec<String>>,
+    dest: &Path,
+) -> Result<Vec<FileErr>> {
+    expect_valid_folder(folder)?;
+
+    fs::copy(
+        get_file_path(folder, project_sha256),
+        dest.join(project_filename),
+    )?;
+
+    if refs.is_empty() {
+        return Ok(vec![]);
+    }
+
+    copy_files_into(
+        folder,
+        refs.iter()
+            .flat_map(|(hash, rels)| rels.iter().map(move |rel| (hash, rel))),
+        dest,
+    )
+}
+
+pub fn scan_plugins(path: &Path) -> Result<PluginRefScan<BitwigPluginRef>> {
+    // Bitwig versions aren't read from projects yet
+    Ok(PluginRefScan::new(BwProject::read(path)?.plugins(), None))
+}
+
+/// Bitwig plugins are looked up by name, CLAP plugins are never reported as missing.
+impl ProjectPluginRef for BitwigPluginRef {
+    fn is_missing(&self, db: &SharedDb) -> Result<bool> {
+        if self.plugin_type == PluginType::Clap {
+            return Ok(false);
+        }
+
+        Ok(db
+            .get_plugin_by_name(&self.plugin_type, &self.name)?
+            .is_none())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        misc::{hash_file, path_to_str},
+        structure::create_tempo_folder,
+        tests::get_temp_dir,
+        types::FileInfo,
+    };
+
+    fn string(s: &str) -> Vec<u8> {
+        let mut b = (s.len() as u32).to_be_bytes().to_vec();
+        b.extend_from_slice(s.as_bytes());
+        b
+    }
+
+    #[test]
+    fn test_add_and_copy_project() {
+        let folder = get_temp_dir("bitwig_folder");
+        create_tempo_folder(&folder).unwrap();
+
+        let project_dir = get_temp_dir("bitwig_project");
+        let outside = get_temp_dir("bitwig_samples");
+
+        fs::create_dir_all(project_dir.join("samples")).unwrap();
+        fs::create_dir_all(project_dir.join("recordings")).unwrap();
+        fs::write(project_dir.join("samples/kick.wav"), b"kick").unwrap();
+        fs::write(project_dir.join("recordings/take.wav"), b"kick").unwrap();
+        fs::write(outside.join("kick.wav"), b"other kick").unwrap();
+
+        let outside_kick = path_to_str(&outside.join("kick.wav"));
+
+        let mut data = b"BtWg0001\x00\x12".to_vec();
+        data.extend(string("samples/kick.wav"));
+        data.extend_from_slice(b"\x00\x01");
+        data.extend(string("recordings/take.wav"));
+        data.extend(string(&outside_kick));
+        data.extend_from_slice(b"\x00\x00");
+
+        let project = project_dir.join("song.bwproject");
+        fs::write(&project, &data).unwrap();
+
+        let hash = stage_bitwig_project(&folder, "user", &project, &[])
+            .unwrap()
+            .commit(&folder, "user")
+            .unwrap();
+
+        // the project file isn't modified
+        assert_eq!(hash, hash_file(&project).unwrap());
+
+        let FileMeta::Project(ProjectData::Bitwig { refs, relink, .. }) =
+            FileInfo::load(&folder, &hash).unwrap().meta
+        else {
+            panic!("expected Bitwig project data");
+        };
+
+        // identical files inside of the project keep both of their paths
+        let kick = hash_file(&project_dir.join("samples/kick.wav")).unwrap();
+        let mut kick_paths = refs[&kick].clone();
+        kick_paths.sort();
+        assert_eq!(kick_paths, vec!["recordings/take.wav", "samples/kick.wav"]);
+
+        // media from outside of the project gets a unique name in samples
+        assert_eq!(relink, vec!["samples/kick-1.wav"]);
+        assert_eq!(
+            refs[&hash_file(&outside.join("kick.wav")).unwrap()],
+            vec!["samples/kick-1.wav"]
+        );
+
+        let dest = get_temp_dir("bitwig_copy");
+        let errs = copy_bitwig_project(&folder, &hash, "song.bwproject", &refs, &dest).unwrap();
+        assert!(errs.is_empty());
+
+        assert_eq!(fs::read(dest.join("song.bwproject")).unwrap(), data);
+        assert_eq!(fs::read(dest.join("samples/kick.wav")).unwrap(), b"kick");
+        assert_eq!(fs::read(dest.join("recordings/take.wav")).unwrap(), b"kick");
+        assert_eq!(
+            fs::read(dest.join("samples/kick-1.wav")).unwrap(),
+            b"other kick"
+        );
+    }
+}
@@ -0,0 +1,122 @@
+// =====================================================================================
+// This file is licensed under either of
+// Apache License, Version 2.0 or MIT license, at your option.
+// =====================================================================================
+// You may obtain a copy of the Apache License, Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0
+// =====================================================================================
+// You may obtain a copy of the MIT License at
+// https://opensource.org/licenses/MIT
+// =====================================================================================
+
+// Bitwig implementation of ProjectReader
+
+use std::path::Path;
+
+use crate::{
+    daw::{
+        project::ProjectType,
+        reader::{
+            find_missing_files, find_missing_plugins, other_daw_error, ProjectPluginScan,
+            ProjectReader,
+        },
+    },
+    db::SharedDb,
+    file::StagedProject,
+    misc::Result,
+    shared::{FileErr, ProjectFileRefScan, Relink, SharedProjectData},
+    types::ProjectData,
+};
+
+use super::{
+    copy_bitwig_project, scan_filerefs, scan_plugins, stage_bitwig_project, verify_project,
+};
+
+pub struct BitwigReader;
+
+impl ProjectReader for BitwigReader {
+    fn name(&self) -> &'static str {
+        "Bitwig"
+    }
+
+    fn project_type(&self, ext: &str) -> Option<ProjectType> {
+        match ext.to_lowercase().as_str() {
+            "bwproject" => Some(ProjectType::Bitwig),
+            _ => None,
+        }
+    }
+
+    fn default_type(&self) -> ProjectType {
+        ProjectType::Bitwig
+    }
+
+    fn handles(&self, project_type: &ProjectType) -> bool {
+        matches!(project_type, ProjectType::Bitwig)
+    }
+
+    fn handles_data(&self, data: &ProjectData) -> bool {
+        matches!(data, ProjectData::Bitwig { .. })
+    }
+
+    fn verify(&self, project: &Path) -> Result<()> {
+        verify_project(project)
+    }
+
+    fn scan_filerefs(&self, path: &Path) -> Result<ProjectFileRefScan> {
+        scan_filerefs(path)
+    }
+
+    fn scan_plugins(&self, path: &Path) -> Result<Box<dyn ProjectPluginScan>> {
+        Ok(Box::new(scan_plugins(path)?))
+    }
+
+    fn stage_project(
+        &self,
+        folder: &Path,
+        username: &str,
+        project: &Path,
+        relinks: &[Relink],
+    ) -> Result<StagedProject> {
+        stage_bitwig_project(folder, username, project, relinks)
+    }
+
+    fn project_info(
+        &self,
+        folder: &Path,
+        data: ProjectData,
+        db: &SharedDb,
+    ) -> Result<SharedProjectData> {
+        let ProjectData::Bitwig {
+            refs,
+            relink,
+            plugins,
+        } = data
+        else {
+            return Err(other_daw_error(self.name()));
+        };
+
+        Ok(SharedProjectData::Bitwig {
+            missing_files: find_missing_files(folder, refs),
+            missing_plugins: find_missing_plugins(db, plugins),
+            relink_files: relink,
+        })
+    }
+
+    /// The project is copied into `dest`, media is copied to the same paths inside of `dest` as in the original project directory.
+    /// Media from outside of the original project directory is copied into the samples directory.
+    fn copy_project(
+        &self,
+        folder: &Path,
+        project_sha256: &str,
+        project_filename: &str,
+        data: ProjectData,
+        dest: &Path,
+    ) -> Result<Vec<FileErr>> {
+        match data {
+            ProjectData::Bitwig { refs, .. } => {
+                copy_bitwig_project(folder, project_sha256, project_filename, &refs, dest)
+            }
+            _ => Err(other_daw_error(self.name())),
+        }
+    }
+}
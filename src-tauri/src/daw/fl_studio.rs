@@ -0,0 +1,75 @@
+// =====================================================================================
+// This file is licensed under either of
+// Apache License, Version 2.0 or MIT license, at your option.
+// =====================================================================================
+// You may obtain a copy of the Apache License, Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0
+// =====================================================================================
+// You may obtain a copy of the MIT License at
+// https://opensource.org/licenses/MIT
+// =====================================================================================
+
+mod flp;
+mod project;
+mod reader;
+
+pub use flp::verify_project;
+pub use project::{copy_fl_studio_project, scan_filerefs, scan_plugins, stage_fl_studio_project};
+pub use reader::FlStudioReader;
+
+use serde::{Deserialize, Serialize};
+
+use crate::shared::PluginRef;
+
+use super::{ableton::AbletonPluginRef, plugin::PluginType};
+
+/// Whether a plugin is loaded on a channel or in a mixer slot.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, Hash, PartialEq)]
+pub enum FlPluginKind {
+    Generator,
+    Effect,
+}
+
+/// Represents a third party plugin found in an FL Studio project.
+/// Plugins are read from the data of FL Studio's plugin wrapper, see the notes in flp.rs.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, Hash, PartialEq)]
+pub struct FlPluginRef {
+    pub kind: FlPluginKind,
+    pub plugin_type: PluginType,
+    pub name: String,
+    pub vendor: Option<String>,
+
+    // VST2 unique id
+    pub id: Option<u32>,
+
+    // VST3 class id split into 4 fields, in the same way as Ableton's Vst3PluginInfo
+    pub fields: Option<[i32; 4]>,
+}
+
+impl FlPluginRef {
+    /// Plugins in the shared db are identified by the ids that Live uses.
+    /// `None` if the project doesn't store an id for this plugin.
+    pub fn as_ableton(&self) -> Option<AbletonPluginRef> {
+        match self.plugin_type {
+            PluginType::Vst => self.id.map(|id| AbletonPluginRef::Vst {
+                id,
+                name: Some(self.name.clone()),
+            }),
+            PluginType::Vst3 => self.fields.map(|fields| AbletonPluginRef::Vst3 {
+                fields,
+                name: Some(self.name.clone()),
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl From<FlPluginRef> for PluginRef {
+    fn from(value: FlPluginRef) -> Self {
+        Self {
+            plugin_type: value.plugin_type,
+            name: value.name,
+            vendor: value.vendor.unwrap_or("Unknown vendor".into()),
+        }
+    }
+}
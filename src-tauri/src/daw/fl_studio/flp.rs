@@ -0,0 +1,577 @@
+// =====================================================================================
+// This file is licensed under either of
+// Apache License, Version 2.0 or MIT license, at your option.
+// =====================================================================================
+// You may obtain a copy of the Apache License, Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0
+// =====================================================================================
+// You may obtain a copy of the MIT License at
+// https://opensource.org/licenses/MIT
+// =====================================================================================
+
+// reading and rewriting FL Studio .flp files
+
+/*
+.flp files are made of two chunks:
+
+FLhd  u32 length (6)  u16 format  u16 channel count  u16 ppq
+FLdt  u32 length      events...
+
+the data chunk is a flat list of events. the id of an event decides the size of its data:
+
+0..=63     1 byte
+64..=127   2 bytes
+128..=191  4 bytes
+192..=255  variable, a 7 bit little endian varint length followed by the data
+
+the events we're interested in:
+
+64   new channel, starts the events of a channel
+98   mixer slot index, ends the events of the plugin in an effect slot
+196  sample path of a channel (text)
+199  version of FL Studio that saved the project (text, always ascii)
+201  internal name of a plugin, eg "Fruity Wrapper" for third party plugins (text)
+203  display name of a plugin (text)
+213  plugin data, for the Fruity Wrapper this holds the name, vendor, path and ids of the wrapped plugin
+
+text is utf-16le since FL Studio 11.5 and ascii before that, both are null terminated.
+
+the events of a plugin start with 201. plugins in effect slots are followed by 98, plugins of channels
+(generators) aren't, so a plugin that's ended by anything other than 98 is a generator.
+
+Fruity Wrapper data starts with a u32 type, types 8 and 10 are followed by a list of records:
+u32 id, u64 length, data
+
+50 plugin info, 51 VST2 id (4 bytes), 52 VST3 class id (16 bytes), 53 state, 54 name, 55 path, 56 vendor
+
+FL Studio's own plugins ship with FL Studio, we only report plugins loaded through the wrapper.
+
+sample paths are absolute, paths starting with a %...% placeholder (eg %FLStudioFactoryData%) point into FL Studio's
+install and are left alone. FL Studio also looks for samples relative to the project, which is what copy-out relies on.
+
+see https://github.com/demberto/PyFLP for more on the format.
+ */
+
+use std::{collections::HashSet, fs, path::Path};
+
+use crate::{
+    daw::plugin::PluginType,
+    misc::{extract_file_extension, path_to_str, Result, TempoError},
+};
+
+use super::{FlPluginKind, FlPluginRef};
+
+const HEADER_MAGIC: &[u8] = b"FLhd";
+const DATA_MAGIC: &[u8] = b"FLdt";
+
+const EVENT_NEW_CHANNEL: u8 = 64;
+const EVENT_SLOT_INDEX: u8 = 98;
+const EVENT_SAMPLE_PATH: u8 = 196;
+const EVENT_VERSION: u8 = 199;
+const EVENT_INTERNAL_NAME: u8 = 201;
+const EVENT_PLUGIN_NAME: u8 = 203;
+const EVENT_PLUGIN_DATA: u8 = 213;
+
+const WRAPPER: &str = "Fruity Wrapper";
+
+const WRAPPER_VST2_ID: u32 = 51;
+const WRAPPER_VST3_ID: u32 = 52;
+const WRAPPER_NAME: u32 = 54;
+const WRAPPER_PATH: u32 = 55;
+const WRAPPER_VENDOR: u32 = 56;
+
+/// An event in the data chunk.
+struct FlEvent {
+    id: u8,
+    // offset of the id byte
+    offset: usize,
+    // offset and length of the data, not including the varint length of variable size events
+    start: usize,
+    len: usize,
+}
+
+/// An FL Studio project file which has been read into memory.
+pub struct FlpProject {
+    data: Vec<u8>,
+    events: Vec<FlEvent>,
+    // offset of the data chunk's length
+    data_len_offset: usize,
+    // whether text events are utf-16
+    utf16: bool,
+}
+
+/// A plugin whose events are being read.
+struct PendingPlugin {
+    internal_name: String,
+    name: Option<String>,
+    wrapped: Option<WrappedPlugin>,
+}
+
+#[derive(Default)]
+struct WrappedPlugin {
+    name: Option<String>,
+    vendor: Option<String>,
+    path: Option<String>,
+    id: Option<u32>,
+    fields: Option<[i32; 4]>,
+}
+
+impl FlpProject {
+    pub fn read(project: &Path) -> Result<Self> {
+        let data = fs::read(project)?;
+        Self::parse(data).ok_or(TempoError::Project(format!(
+            "{} is not a valid FL Studio project",
+            path_to_str(project)
+        )))
+    }
+
+    fn parse(data: Vec<u8>) -> Option<Self> {
+        if !data.starts_with(HEADER_MAGIC) {
+            return None;
+        }
+
+        let header_len = u32_at(&data, 4)? as usize;
+        let data_magic = 8 + header_len;
+        if data.get(data_magic..data_magic + 4)? != DATA_MAGIC {
+            return None;
+        }
+
+        let data_len_offset = data_magic + 4;
+        let events_start = data_len_offset + 4;
+        let events_end = data
+            .len()
+            .min(events_start + u32_at(&data, data_len_offset)? as usize);
+
+        let events = read_events(&data, events_start, events_end)?;
+
+        let mut project = Self {
+            data,
+            events,
+            data_len_offset,
+            utf16: false,
+        };
+        project.utf16 = project.version().is_some_and(|v| uses_utf16(&v));
+
+        Some(project)
+    }
+
+    fn bytes(&self, e: &FlEvent) -> &[u8] {
+        &self.data[e.start..e.start + e.len]
+    }
+
+    fn text(&self, e: &FlEvent) -> String {
+        decode_text(self.bytes(e), self.utf16)
+    }
+
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// Version of FL Studio that saved the project, eg "20.8.4.2576".
+    pub fn version(&self) -> Option<String> {
+        self.events
+            .iter()
+            .find(|e| e.id == EVENT_VERSION)
+            .map(|e| decode_text(self.bytes(e), false))
+            .filter(|v| !v.is_empty())
+    }
+
+    /// Paths of all samples referenced by the project, in the order they first appear.
+    pub fn sample_paths(&self) -> Vec<String> {
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut paths = vec![];
+
+        for e in self.events.iter().filter(|e| e.id == EVENT_SAMPLE_PATH) {
+            let path = self.text(e);
+            if is_sample_path(&path) && seen.insert(path.clone()) {
+                paths.push(path);
+            }
+        }
+
+        paths
+    }
+
+    /// All third party plugins used in the project, duplicates are removed.
+    pub fn plugins(&self) -> Vec<FlPluginRef> {
+        let mut seen: HashSet<FlPluginRef> = HashSet::new();
+        let mut plugins = vec![];
+        let mut pending: Option<PendingPlugin> = None;
+
+        let mut finish = |p: Option<PendingPlugin>, kind: FlPluginKind| {
+            if let Some(p) = p.and_then(|p| p.into_ref(kind)) {
+                if seen.insert(p.clone()) {
+                    plugins.push(p);
+                }
+            }
+        };
+
+        for e in &self.events {
+            match e.id {
+                EVENT_INTERNAL_NAME => {
+                    finish(pending.take(), FlPluginKind::Generator);
+                    pending = Some(PendingPlugin {
+                        internal_name: self.text(e),
+                        name: None,
+                        wrapped: None,
+                    });
+                }
+                EVENT_NEW_CHANNEL => finish(pending.take(), FlPluginKind::Generator),
+                EVENT_SLOT_INDEX => finish(pending.take(), FlPluginKind::Effect),
+                EVENT_PLUGIN_NAME => {
+                    if let Some(p) = pending.as_mut() {
+                        p.name = Some(self.text(e)).filter(|n| !n.is_empty());
+                    }
+                }
+                EVENT_PLUGIN_DATA => {
+                    if let Some(p) = pending.as_mut().filter(|p| p.internal_name == WRAPPER) {
+                        p.wrapped = parse_wrapper(self.bytes(e));
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        finish(pending.take(), FlPluginKind::Generator);
+
+        plugins
+    }
+
+    /// Rewrites all sample paths in the project.
+    /// `edit` is called with each referenced path and returns the path to use instead, `None` leaves it as is.
+    pub fn rewrite_sample_paths(&self, mut edit: impl FnMut(&str) -> Option<String>) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.data.len());
+        let mut pos = 0;
+
+        for e in self.events.iter().filter(|e| e.id == EVENT_SAMPLE_PATH) {
+            let path = self.text(e);
+            if !is_sample_path(&path) {
+                continue;
+            }
+
+            if let Some(new_path) = edit(&path) {
+                let text = encode_text(&new_path, self.utf16);
+                out.extend_from_slice(&self.data[pos..e.offset]);
+                out.push(e.id);
+                write_varint(&mut out, text.len());
+                out.extend_from_slice(&text);
+                pos = e.start + e.len;
+            }
+        }
+
+        out.extend_from_slice(&self.data[pos..]);
+
+        // events changed size, fix the length of the data chunk
+        let events_len = (out.len() - self.data_len_offset - 4) as u32;
+        out[self.data_len_offset..self.data_len_offset + 4]
+            .copy_from_slice(&events_len.to_le_bytes());
+
+        out
+    }
+}
+
+impl PendingPlugin {
+    fn into_ref(self, kind: FlPluginKind) -> Option<FlPluginRef> {
+        let wrapped = self.wrapped?;
+
+        let plugin_type = match wrapped
+            .path
+            .as_deref()
+            .and_then(|p| extract_file_extension(p).1)
+            .map(|e| e.to_lowercase())
+            .as_deref()
+        {
+            Some("vst3") => PluginType::Vst3,
+            Some("component") => PluginType::Au,
+            Some("clap") => PluginType::Clap,
+            Some(_) => PluginType::Vst,
+            None if wrapped.fields.is_some() => PluginType::Vst3,
+            None => PluginType::Vst,
+        };
+
+        let name = wrapped.name.or(self.name)?;
+
+        Some(FlPluginRef {
+            kind,
+            plugin_type: plugin_type.clone(),
+            name,
+            vendor: wrapped.vendor,
+            id: wrapped.id.filter(|_| plugin_type == PluginType::Vst),
+            fields: wrapped.fields.filter(|_| plugin_type == PluginType::Vst3),
+        })
+    }
+}
+
+/// Checks whether a file is an FL Studio project.
+pub fn verify_project(project: &Path) -> Result<()> {
+    let mut magic = [0u8; 4];
+    let mut f = fs::File::open(project)?;
+
+    match std::io::Read::read_exact(&mut f, &mut magic) {
+        Ok(()) if magic == HEADER_MAGIC => Ok(()),
+        _ => Err(TempoError::Project(format!(
+            "{} is not a valid FL Studio project",
+            path_to_str(project)
+        ))),
+    }
+}
+
+fn u32_at(data: &[u8], pos: usize) -> Option<u32> {
+    data.get(pos..pos + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn read_events(data: &[u8], mut pos: usize, end: usize) -> Option<Vec<FlEvent>> {
+    let mut events = vec![];
+
+    while pos < end {
+        let offset = pos;
+        let id = data[pos];
+        pos += 1;
+
+        let (start, len) = match id {
+            0..=63 => (pos, 1),
+            64..=127 => (pos, 2),
+            128..=191 => (pos, 4),
+            _ => {
+                let (len, varint_len) = read_varint(data.get(pos..end)?)?;
+                (pos + varint_len, len)
+            }
+        };
+
+        if start + len > end {
+            return None;
+        }
+
+        events.push(FlEvent {
+            id,
+            offset,
+            start,
+            len,
+        });
+        pos = start + len;
+    }
+
+    Some(events)
+}
+
+/// Returns the value and the number of bytes it took up.
+fn read_varint(data: &[u8]) -> Option<(usize, usize)> {
+    let mut value = 0usize;
+
+    for (i, b) in data.iter().enumerate().take(5) {
+        value |= ((b & 0x7f) as usize) << (7 * i);
+        if b & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+
+    None
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let b = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(b);
+            return;
+        }
+        out.push(b | 0x80);
+    }
+}
+
+fn uses_utf16(version: &str) -> bool {
+    let mut parts = version.split('.').map(|p| p.parse::<u32>().unwrap_or(0));
+    let major = parts.next().unwrap_or(0);
+    let minor = parts.next().unwrap_or(0);
+    major > 11 || (major == 11 && minor >= 5)
+}
+
+fn decode_text(bytes: &[u8], utf16: bool) -> String {
+    if utf16 {
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+            .trim_end_matches('\0')
+            .to_string()
+    } else {
+        String::from_utf8_lossy(bytes)
+            .trim_end_matches('\0')
+            .to_string()
+    }
+}
+
+fn encode_text(s: &str, utf16: bool) -> Vec<u8> {
+    if utf16 {
+        s.encode_utf16()
+            .chain(std::iter::once(0))
+            .flat_map(|u| u.to_le_bytes())
+            .collect()
+    } else {
+        s.bytes().chain(std::iter::once(0)).collect()
+    }
+}
+
+fn is_sample_path(path: &str) -> bool {
+    !path.is_empty() && !path.starts_with('%')
+}
+
+fn parse_wrapper(data: &[u8]) -> Option<WrappedPlugin> {
+    if !matches!(u32_at(data, 0)?, 8 | 10) {
+        return None;
+    }
+
+    let mut wrapped = WrappedPlugin::default();
+    let mut pos = 4;
+
+    while pos + 12 <= data.len() {
+        let id = u32_at(data, pos)?;
+        let len = u64::from_le_bytes(data[pos + 4..pos + 12].try_into().ok()?) as usize;
+        let value = data.get(pos + 12..(pos + 12).checked_add(len)?)?;
+        pos += 12 + len;
+
+        let text = || Some(decode_text(value, false)).filter(|s| !s.is_empty());
+
+        match id {
+            WRAPPER_VST2_ID if len == 4 => {
+                wrapped.id = Some(u32::from_be_bytes(value.try_into().ok()?))
+            }
+            WRAPPER_VST3_ID if len == 16 => {
+                let mut fields = [0i32; 4];
+                for (i, f) in fields.iter_mut().enumerate() {
+                    *f = u32::from_be_bytes(value[i * 4..i * 4 + 4].try_into().ok()?) as i32;
+                }
+                wrapped.fields = Some(fields);
+            }
+            WRAPPER_NAME => wrapped.name = text(),
+            WRAPPER_PATH => wrapped.path = text(),
+            WRAPPER_VENDOR => wrapped.vendor = text(),
+            _ => (),
+        }
+    }
+
+    Some(wrapped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(out: &mut Vec<u8>, id: u8, data: &[u8]) {
+        out.push(id);
+        if id >= 192 {
+            write_varint(out, data.len());
+        }
+        out.extend_from_slice(data);
+    }
+
+    fn record(out: &mut Vec<u8>, id: u32, data: &[u8]) {
+        out.extend_from_slice(&id.to_le_bytes());
+        out.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        out.extend_from_slice(data);
+    }
+
+    fn wrapper(name: &str, path: &str, vst2_id: Option<&[u8; 4]>) -> Vec<u8> {
+        let mut data = 10u32.to_le_bytes().to_vec();
+        if let Some(id) = vst2_id {
+            record(&mut data, WRAPPER_VST2_ID, id);
+        }
+        record(&mut data, WRAPPER_NAME, name.as_bytes());
+        record(&mut data, WRAPPER_PATH, path.as_bytes());
+        record(&mut data, WRAPPER_VENDOR, b"Xfer Records");
+        data
+    }
+
+    fn project() -> Vec<u8> {
+        let text = |s: &str| encode_text(s, true);
+
+        let mut events = vec![];
+        event(&mut events, EVENT_VERSION, b"21.2.3.4004\0");
+        event(&mut events, EVENT_NEW_CHANNEL, &[0, 0]);
+        event(&mut events, 21, &[0]);
+        event(
+            &mut events,
+            EVENT_SAMPLE_PATH,
+            &text("C:\\Samples\\Kick 01.wav"),
+        );
+        event(&mut events, EVENT_NEW_CHANNEL, &[1, 0]);
+        event(&mut events, EVENT_INTERNAL_NAME, &text(WRAPPER));
+        event(&mut events, EVENT_PLUGIN_NAME, &text("Serum"));
+        event(
+            &mut events,
+            EVENT_PLUGIN_DATA,
+            &wrapper("Serum", "C:\\VstPlugins\\Serum_x64.dll", Some(b"XfsX")),
+        );
+        event(&mut events, EVENT_NEW_CHANNEL, &[2, 0]);
+        event(
+            &mut events,
+            EVENT_SAMPLE_PATH,
+            &text("%FLStudioFactoryData%\\Data\\Patches\\Packs\\Drums\\Clap.wav"),
+        );
+        event(&mut events, EVENT_INTERNAL_NAME, &text("Fruity Limiter"));
+        event(&mut events, EVENT_SLOT_INDEX, &[0, 0]);
+        event(&mut events, EVENT_INTERNAL_NAME, &text(WRAPPER));
+        event(
+            &mut events,
+            EVENT_PLUGIN_DATA,
+            &wrapper("OTT", "C:\\VST3\\OTT.vst3", None),
+        );
+        event(&mut events, EVENT_SLOT_INDEX, &[1, 0]);
+
+        let mut data = HEADER_MAGIC.to_vec();
+        data.extend_from_slice(&6u32.to_le_bytes());
+        data.extend_from_slice(&[0, 0, 3, 0, 96, 0]);
+        data.extend_from_slice(DATA_MAGIC);
+        data.extend_from_slice(&(events.len() as u32).to_le_bytes());
+        data.extend(events);
+        data
+    }
+
+    #[test]
+    fn test_flp() {
+        let flp = FlpProject::parse(project()).unwrap();
+
+        assert_eq!(flp.version().as_deref(), Some("21.2.3.4004"));
+        assert_eq!(flp.sample_paths(), vec!["C:\\Samples\\Kick 01.wav"]);
+        assert_eq!(
+            flp.plugins(),
+            vec![
+                FlPluginRef {
+                    kind: FlPluginKind::Generator,
+                    plugin_type: PluginType::Vst,
+                    name: "Serum".into(),
+                    vendor: Some("Xfer Records".into()),
+                    id: Some(u32::from_be_bytes(*b"XfsX")),
+                    fields: None,
+                },
+                FlPluginRef {
+                    kind: FlPluginKind::Effect,
+                    plugin_type: PluginType::Vst3,
+                    name: "OTT".into(),
+                    vendor: Some("Xfer Records".into()),
+                    id: None,
+                    fields: None,
+                },
+            ]
+        );
+
+        assert!(FlpProject::parse(b"FLhd".to_vec()).is_none());
+    }
+
+    #[test]
+    fn test_rewrite() {
+        let flp = FlpProject::parse(project()).unwrap();
+
+        let out = flp.rewrite_sample_paths(|_| Some(format!("Data/{}", "k".repeat(200))));
+        let rewritten = FlpProject::parse(out).unwrap();
+
+        assert_eq!(
+            rewritten.sample_paths(),
+            vec![format!("Data/{}", "k".repeat(200))]
+        );
+        assert_eq!(rewritten.plugins(), flp.plugins());
+        assert_eq!(rewritten.events.len(), flp.events.len());
+    }
+}
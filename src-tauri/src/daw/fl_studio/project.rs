@@ -0,0 +1,168 @@
+// =====================================================================================
+// This file is licensed under either of
+// Apache License, Version 2.0 or MIT license, at your option.
+// =====================================================================================
+// You may obtain a copy of the Apache License, Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0
+// =====================================================================================
+// You may obtain a copy of the MIT License at
+// https://opensource.org/licenses/MIT
+// =====================================================================================
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use log::info;
+
+use crate::{
+    daw::{
+        plugin::PluginType,
+        reader::{
+            get_project_dir, resolve_media_paths, scan_media_paths, PluginRefScan, ProjectPluginRef,
+        },
+    },
+    db::SharedDb,
+    file::{copy_files_into, stage_project_data, stage_referenced_files, StagedProject},
+    misc::{path_to_str, Result},
+    shared::{FileErr, ProjectFileRefScan, Relink},
+    structure::{expect_valid_folder, get_file_path},
+    types::{FileMeta, ProjectData},
+};
+
+use super::{flp::FlpProject, FlPluginRef};
+
+/// Directory next to the project that samples are copied into.
+const DATA_DIR: &str = "Data";
+
+/// Like FL Studio, samples that were moved next to the project or into its data directory are found by filename.
+fn search_dirs(project_dir: &Path) -> [PathBuf; 2] {
+    [project_dir.to_path_buf(), project_dir.join(DATA_DIR)]
+}
+
+pub fn scan_filerefs(project: &Path) -> Result<ProjectFileRefScan> {
+    let project_dir = get_project_dir(project)?;
+    let flp = FlpProject::read(project)?;
+
+    Ok(scan_media_paths(
+        flp.sample_paths(),
+        project_dir,
+        &search_dirs(project_dir),
+    ))
+}
+
+/// Stages an FL Studio project to be added to a Tempo folder.
+/// Referenced samples are added to the folder, the project is stored as is. Sample paths are rewritten on copy-out.
+/// Missing files are replaced by files chosen by the user, see `ProjectFileRefScan`.
+pub fn stage_fl_studio_project(
+    folder: &Path,
+    username: &str,
+    project: &Path,
+    relinks: &[Relink],
+) -> Result<StagedProject> {
+    expect_valid_folder(folder)?;
+
+    let project_dir = get_project_dir(project)?;
+
+    // the whole project is read up front, so changes made while we're copying files don't matter
+    let flp = FlpProject::read(project)?;
+
+    let media = resolve_media_paths(
+        flp.sample_paths(),
+        project_dir,
+        &search_dirs(project_dir),
+        relinks,
+    );
+
+    let staged = stage_referenced_files(folder, username, media.files)?;
+
+    // { sample path in project : hash }
+    let samples: HashMap<String, String> = media
+        .paths
+        .iter()
+        .filter_map(|(path, file)| Some((path.clone(), staged.hash(file)?.clone())))
+        .collect();
+
+    let refs = staged.refs();
+
+    let plugins = flp.plugins();
+    let version = flp.version();
+
+    // write the project we read samples from, in case the project changed while we were copying files
+    stage_project_data(
+        folder,
+        username,
+        project,
+        &flp.into_data(),
+        staged,
+        FileMeta::Project(ProjectData::FlStudio {
+            samples: samples
+                .into_iter()
+                .filter(|(_, hash)| refs.contains_key(hash))
+                .collect(),
+            refs,
+            plugins,
+            version,
+        }),
+    )
+}
+
+/// Copies an FL Studio project **from a Tempo folder** into `dest`.
+/// Samples are copied into the data directory inside of `dest` and the project's sample paths are rewritten to point there.
+/// `samples` is `{ sample path in project : hash }`, `refs` is `{ hash : filename in data directory }`.
+pub fn copy_fl_studio_project(
+    folder: &Path,
+    project_sha256: &str,
+    project_filename: &str,
+    refs: &HashMap<String, String>,
+    samples: &HashMap<String, String>,
+    dest: &Path,
+) -> Result<Vec<FileErr>> {
+    expect_valid_folder(folder)?;
+
+    let flp = FlpProject::read(&get_file_path(folder, project_sha256))?;
+
+    let out_data = flp.rewrite_sample_paths(|path| {
+        let filename = refs.get(samples.get(path)?)?;
+        Some(path_to_str(&Path::new(DATA_DIR).join(filename)).to_string())
+    });
+    fs::write(dest.join(project_filename), out_data)?;
+
+    info!(
+        "wrote output project: {}",
+        path_to_str(&dest.join(project_filename))
+    );
+
+    if refs.is_empty() {
+        return Ok(vec![]);
+    }
+
+    copy_files_into(folder, refs, &dest.join(DATA_DIR))
+}
+
+pub fn scan_plugins(path: &Path) -> Result<PluginRefScan<FlPluginRef>> {
+    let flp = FlpProject::read(path)?;
+    Ok(PluginRefScan::new(flp.plugins(), flp.version()))
+}
+
+/// Plugins are looked up by their ids, falling back to their names when the project doesn't store an id.
+/// CLAP plugins are never reported as missing.
+impl ProjectPluginRef for FlPluginRef {
+    fn is_missing(&self, db: &SharedDb) -> Result<bool> {
+        if self.plugin_type == PluginType::Clap {
+            return Ok(false);
+        }
+
+        if let Some(p) = self.as_ableton() {
+            if db.get_ableton_plugin(&p)?.is_some() {
+                return Ok(false);
+            }
+        }
+
+        Ok(db
+            .get_plugin_by_name(&self.plugin_type, &self.name)?
+            .is_none())
+    }
+}
@@ -0,0 +1,127 @@
+// =====================================================================================
+// This file is licensed under either of
+// Apache License, Version 2.0 or MIT license, at your option.
+// =====================================================================================
+// You may obtain a copy of the Apache License, Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0
+// =====================================================================================
+// You may obtain a copy of the MIT License at
+// https://opensource.org/licenses/MIT
+// =====================================================================================
+
+// FL Studio implementation of ProjectReader
+
+use std::path::Path;
+
+use crate::{
+    daw::{
+        project::ProjectType,
+        reader::{
+            find_missing_files, find_missing_plugins, other_daw_error, ProjectPluginScan,
+            ProjectReader,
+        },
+    },
+    db::SharedDb,
+    file::StagedProject,
+    misc::Result,
+    shared::{FileErr, ProjectFileRefScan, Relink, SharedProjectData},
+    types::ProjectData,
+};
+
+use super::{
+    copy_fl_studio_project, scan_filerefs, scan_plugins, stage_fl_studio_project, verify_project,
+};
+
+pub struct FlStudioReader;
+
+impl ProjectReader for FlStudioReader {
+    fn name(&self) -> &'static str {
+        "FL Studio"
+    }
+
+    fn project_type(&self, ext: &str) -> Option<ProjectType> {
+        match ext.to_lowercase().as_str() {
+            "flp" => Some(ProjectType::FlStudio),
+            _ => None,
+        }
+    }
+
+    fn default_type(&self) -> ProjectType {
+        ProjectType::FlStudio
+    }
+
+    fn handles(&self, project_type: &ProjectType) -> bool {
+        matches!(project_type, ProjectType::FlStudio)
+    }
+
+    fn handles_data(&self, data: &ProjectData) -> bool {
+        matches!(data, ProjectData::FlStudio { .. })
+    }
+
+    fn verify(&self, project: &Path) -> Result<()> {
+        verify_project(project)
+    }
+
+    fn scan_filerefs(&self, path: &Path) -> Result<ProjectFileRefScan> {
+        scan_filerefs(path)
+    }
+
+    fn scan_plugins(&self, path: &Path) -> Result<Box<dyn ProjectPluginScan>> {
+        Ok(Box::new(scan_plugins(path)?))
+    }
+
+    fn stage_project(
+        &self,
+        folder: &Path,
+        username: &str,
+        project: &Path,
+        relinks: &[Relink],
+    ) -> Result<StagedProject> {
+        stage_fl_studio_project(folder, username, project, relinks)
+    }
+
+    fn project_info(
+        &self,
+        folder: &Path,
+        data: ProjectData,
+        db: &SharedDb,
+    ) -> Result<SharedProjectData> {
+        let ProjectData::FlStudio {
+            refs,
+            plugins,
+            version,
+            ..
+        } = data
+        else {
+            return Err(other_daw_error(self.name()));
+        };
+
+        Ok(SharedProjectData::FlStudio {
+            missing_files: find_missing_files(folder, refs.into_iter().map(|(h, f)| (h, [f]))),
+            missing_plugins: find_missing_plugins(db, plugins),
+            version,
+        })
+    }
+
+    /// The project is copied into `dest`, samples are copied into `dest/Data`.
+    fn copy_project(
+        &self,
+        folder: &Path,
+        project_sha256: &str,
+        project_filename: &str,
+        data: ProjectData,
+        dest: &Path,
+    ) -> Result<Vec<FileErr>> {
+        match data {
+            ProjectData::FlStudio { refs, samples, .. } => copy_fl_studio_project(
+                folder,
+                project_sha256,
+                project_filename,
+                &refs,
+                &samples,
+                dest,
+            ),
+            _ => Err(other_daw_error(self.name())),
+        }
+    }
+}
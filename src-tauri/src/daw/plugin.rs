@@ -1,11 +1,13 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use super::{ableton::{self, AbletonProjectPluginScan}, project::ProjectType};
+use super::reader::get_path_reader;
 use autosurgeon::{Hydrate, Reconcile};
+use log::error;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    misc::{path_to_str, Result, TempoError},
+    file::library::{LibraryIndex, SharedFiles},
+    misc::Result,
     shared::ProjectFileRefScan,
 };
 
@@ -16,36 +18,42 @@ pub enum PluginType {
     Au,
     Vst,
     Vst3,
+    Clap,
 }
 
-pub enum ProjectPluginScan {
-    Ableton(AbletonProjectPluginScan),
-}
+impl ProjectFileRefScan {
+    /// Scans file refs of a project that will be added to `folder`.
+    /// Missing files are searched for in `library_roots`, see library.rs.
+    pub fn new(
+        path: &Path,
+        folder: &Path,
+        library_roots: &[PathBuf],
+        cache_dir: &Path,
+    ) -> Result<Self> {
+        let scan = Self::new_without_relinks(path)?;
 
-impl ProjectPluginScan {
-    pub fn new(path: &Path) -> Result<Self> {
-        match ProjectType::get(path)? {
-            None => Err(TempoError::Project(format!(
-                "Attempted to scan plugins for {} which appears not to be a project file",
-                path_to_str(path)
-            ))),
-            Some(t) => match t {
-                ProjectType::Ableton => Ok(Self::Ableton(AbletonProjectPluginScan::new(path)?)),
-            },
+        if scan.missing.is_empty() || library_roots.is_empty() {
+            return Ok(scan);
         }
-    }
-}
 
-impl ProjectFileRefScan {
-    pub fn new(path: &Path) -> Result<Self> {
-        match ProjectType::get(path)? {
-            None => Err(TempoError::Project(format!(
-                "Attempted to scan file refs for {} which appears to not be a project file",
-                path_to_str(path)
-            ))),
-            Some(t) => match t {
-                ProjectType::Ableton => Ok(ableton::scan_filerefs(path)?),
-            },
+        let shared = SharedFiles::load(folder, cache_dir).unwrap_or_else(|e| {
+            error!(
+                "ProjectFileRefScan::new(): failed to read shared files, not matching by hash: {e}"
+            );
+            SharedFiles::default()
+        });
+
+        let mut index = LibraryIndex::load(cache_dir, library_roots);
+        let scan = scan.find_relinks(&mut index, &shared);
+
+        if let Err(e) = index.save(cache_dir) {
+            error!("ProjectFileRefScan::new(): failed to cache library index: {e}");
         }
+
+        Ok(scan)
+    }
+
+    fn new_without_relinks(path: &Path) -> Result<Self> {
+        get_path_reader(path)?.scan_filerefs(path)
     }
 }
@@ -4,13 +4,54 @@ use autosurgeon::{Hydrate, Reconcile};
 use log::warn;
 use serde::{Deserialize, Serialize};
 
-use crate::misc::{extract_file_extension, get_filename, Result, TempoError};
+use crate::{
+    misc::{extract_file_extension, get_filename, Result, TempoError},
+    types::BarBeat,
+};
 
-use super::ableton;
+use super::reader::get_extension_reader;
 
 #[derive(Debug, Clone, Reconcile, Hydrate, Serialize, Deserialize, Eq, PartialEq, ts_rs::TS)]
 pub enum ProjectType {
+    // Live Set, .als
     Ableton,
+    // Live Clip, .alc
+    AbletonClip,
+    // Rack preset, .adg
+    AbletonRack,
+    // Device preset, .adv
+    AbletonPreset,
+    // REAPER project, .rpp
+    Reaper,
+    // Bitwig Studio project, .bwproject
+    Bitwig,
+    // Studio One song, .song
+    StudioOne,
+    // FL Studio project, .flp
+    FlStudio,
+}
+
+/// Tempo and time signature at the start of a project.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectTempo {
+    pub bpm: f64,
+    pub numerator: u8,
+    pub denominator: u8,
+}
+
+impl ProjectTempo {
+    /// Bar and beat at a time in a render of the project.
+    /// Assumes the tempo doesn't change and the render starts at the start of the first bar.
+    pub fn bar_beat(&self, secs: f64) -> BarBeat {
+        // in beats of the time signature's denominator, eg eighth notes in 6/8
+        let beats = secs * self.bpm / 60.0 * self.denominator as f64 / 4.0;
+        let numerator = self.numerator.max(1) as f64;
+
+        BarBeat {
+            bar: (beats / numerator).floor() as u32 + 1,
+            beat: beats % numerator + 1.0,
+        }
+    }
 }
 
 impl ProjectType {
@@ -19,21 +60,28 @@ impl ProjectType {
         let (filename, ext) = extract_file_extension(&full_filename);
 
         match ext.as_deref() {
-            Some("als") => {
-                if let Err(e) = ableton::verify_project(path) {
-                    Err(TempoError::Ableton(format!("An error occurred while verifying whether {full_filename} is a valid Ableton project: {e}")))
-                } else {
-                    Ok(Some(ProjectType::Ableton))
-                }
-            }
             None => {
                 warn!("get_project_type(): scanned {filename}, appears to have no filename");
                 Ok(None)
             }
-            Some(e) => {
-                warn!("get_project_type(): scanned {filename}.{e}, {e} is not a known project file type");
-                Ok(None)
-            }
+            Some(e) => match get_extension_reader(e) {
+                Some((reader, t)) => {
+                    if let Err(e) = reader.verify(path) {
+                        Err(TempoError::Project(format!("An error occurred while verifying whether {full_filename} is a valid {} file: {e}", reader.name())))
+                    } else {
+                        Ok(Some(t))
+                    }
+                }
+                None => {
+                    warn!("get_project_type(): scanned {filename}.{e}, {e} is not a known project file type");
+                    Ok(None)
+                }
+            },
         }
     }
-}
\ No newline at end of file
+
+    /// Gets the project type of a file from its extension, without verifying the file.
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        get_extension_reader(ext).map(|(_, t)| t)
+    }
+}
@@ -0,0 +1,440 @@
+// =====================================================================================
+// This file is licensed under either of
+// Apache License, Version 2.0 or MIT license, at your option.
+// =====================================================================================
+// You may obtain a copy of the Apache License, Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0
+// =====================================================================================
+// You may obtain a copy of the MIT License at
+// https://opensource.org/licenses/MIT
+// =====================================================================================
+
+// generic interface for reading projects of different daws
+
+/*
+each daw Tempo supports implements ProjectReader, the rest of Tempo goes through the registry below
+instead of matching on ProjectType/ProjectData.
+
+a reader is responsible for:
+- detecting its project files (by extension, then verifying the file) and project directories
+- reading file refs and plugins used by a project
+- staging a project to be added to a folder, this rewrites file refs so they point into the directory the project is copied out to
+- summarizing stored ProjectData for the frontend
+- copying a project back out of a folder, the reader decides the layout of the copied project
+
+to add a daw:
+- add its variants to ProjectType and ProjectData (and SharedProjectData if it has its own summary)
+- implement ProjectReader
+- add it to READERS
+
+readers only supply the format specific parts, the rest is shared:
+- plugins implement ProjectPluginRef, PluginRefScan and find_missing_plugins() check them against users' dbs
+- find_missing_files() checks which referenced files haven't synced yet
+- daws which reference media by path use scan_media_paths() and resolve_media_paths(),
+  a reader only says which directories moved files are looked for in
+- rewritten projects are staged with file::stage_project_data(), nothing is added to the folder until the
+  StagedProject is committed. notes check their attachments for duplicates in between
+ */
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use log::{error, warn};
+
+use crate::{
+    db::SharedDb,
+    file::StagedProject,
+    misc::{path_to_str, Result, TempoError},
+    shared::{
+        FileErr, FileRef, MissingFileRef, PluginRef, PluginScan, ProjectFileRefScan, Relink,
+        SharedProjectData,
+    },
+    structure::file_exists,
+    types::ProjectData,
+};
+
+use super::{
+    ableton::AbletonReader,
+    bitwig::BitwigReader,
+    fl_studio::FlStudioReader,
+    project::{ProjectTempo, ProjectType},
+    reaper::ReaperReader,
+    studio_one::StudioOneReader,
+};
+
+/// Reads and writes projects of a daw.
+pub trait ProjectReader: Sync {
+    /// Name of the daw, used in messages shown to users.
+    fn name(&self) -> &'static str;
+
+    /// Gets the project type of a file from its extension, without verifying the file.
+    fn project_type(&self, ext: &str) -> Option<ProjectType>;
+
+    /// Project type used when the type of a stored project can't be told from its filename.
+    fn default_type(&self) -> ProjectType;
+
+    /// Whether this reader handles the given project type.
+    fn handles(&self, project_type: &ProjectType) -> bool;
+
+    /// Whether this reader handles the given stored project data.
+    fn handles_data(&self, data: &ProjectData) -> bool;
+
+    /// Checks whether a file is a valid project file.
+    fn verify(&self, project: &Path) -> Result<()>;
+
+    /// Whether `dir` is a directory containing projects that can be scanned as a whole.
+    fn is_project_dir(&self, _dir: &Path) -> bool {
+        false
+    }
+
+    /// Reads the tempo and time signature a project starts with.
+    /// `None` if there isn't one or projects of this daw can't be read for it.
+    fn read_tempo(&self, _project: &Path) -> Result<Option<ProjectTempo>> {
+        Ok(None)
+    }
+
+    /// Scans the file refs of a project file or project directory.
+    fn scan_filerefs(&self, path: &Path) -> Result<ProjectFileRefScan>;
+
+    /// Scans the plugins used by a project file or project directory.
+    fn scan_plugins(&self, path: &Path) -> Result<Box<dyn ProjectPluginScan>>;
+
+    /// Stages a project file and all files it references, to be added to a folder when committed.
+    /// Missing files are replaced according to `relinks`.
+    fn stage_project(
+        &self,
+        folder: &Path,
+        username: &str,
+        project: &Path,
+        relinks: &[Relink],
+    ) -> Result<StagedProject>;
+
+    /// Summarizes a project stored in a folder.
+    fn project_info(
+        &self,
+        folder: &Path,
+        data: ProjectData,
+        db: &SharedDb,
+    ) -> Result<SharedProjectData>;
+
+    /// Copies a project out of a folder into `dest`, along with all files it references.
+    fn copy_project(
+        &self,
+        folder: &Path,
+        project_sha256: &str,
+        project_filename: &str,
+        data: ProjectData,
+        dest: &Path,
+    ) -> Result<Vec<FileErr>>;
+}
+
+/// Compares plugins used by a project against plugins in users' dbs.
+pub trait ProjectPluginScan {
+    /// Checks which plugins are missing for a user.
+    fn scan_db(&mut self, db: &SharedDb, username: &str) -> Result<()>;
+
+    fn done(self: Box<Self>) -> PluginScan;
+}
+
+/// Error for project data that was given to the reader of another daw.
+/// This doesn't happen when going through the registry.
+pub fn other_daw_error(name: &str) -> TempoError {
+    TempoError::Project(format!(
+        "Tried to use project data of another daw as {name} project data"
+    ))
+}
+
+/// A plugin used by a project, which can be looked up in a user's plugin db.
+pub trait ProjectPluginRef: Debug + Into<PluginRef> {
+    /// Whether the plugin is missing from a user's db.
+    fn is_missing(&self, db: &SharedDb) -> Result<bool>;
+}
+
+/// Finds plugins which are missing from a user's db.
+/// Plugins that fail to be looked up are treated as missing.
+pub fn find_missing_plugins<P: ProjectPluginRef>(db: &SharedDb, plugins: Vec<P>) -> Vec<PluginRef> {
+    plugins
+        .into_iter()
+        .filter(|p| match p.is_missing(db) {
+            Ok(missing) => missing,
+            Err(e) => {
+                error!("find_missing_plugins(): error while reading plugin: {:#?}, error: {e}, treating as missing", p);
+                true
+            }
+        })
+        .map(Into::into)
+        .collect()
+}
+
+/// Finds referenced files which aren't in a folder, they might still be syncing.
+/// `refs` is `{ hash : names of the file shown to users }`.
+/// Files that fail to be checked are treated as missing.
+pub fn find_missing_files<N>(
+    folder: &Path,
+    refs: impl IntoIterator<Item = (String, N)>,
+) -> Vec<String>
+where
+    N: IntoIterator<Item = String>,
+{
+    let mut missing_files = vec![];
+    for (hash, names) in refs {
+        let exists = match file_exists(folder, &hash) {
+            Ok(e) => e,
+            Err(e) => {
+                error!("find_missing_files(): error while trying to read file {hash}, treating as missing: {e}");
+                false
+            }
+        };
+        if !exists {
+            missing_files.extend(names)
+        }
+    }
+    missing_files
+}
+
+/// Plugin scan of a project whose plugins are looked up one at a time, see `ProjectPluginRef`.
+pub struct PluginRefScan<P> {
+    refs: Vec<P>,
+    // { username : idx of missing }
+    missing: HashMap<String, Vec<usize>>,
+
+    // version of the daw that saved the project
+    version: Option<String>,
+}
+
+impl<P: ProjectPluginRef> PluginRefScan<P> {
+    pub fn new(refs: Vec<P>, version: Option<String>) -> Self {
+        Self {
+            refs,
+            missing: HashMap::new(),
+            version,
+        }
+    }
+}
+
+impl<P: ProjectPluginRef> ProjectPluginScan for PluginRefScan<P> {
+    fn scan_db(&mut self, db: &SharedDb, username: &str) -> Result<()> {
+        let mut missing: Vec<usize> = vec![];
+
+        for (idx, p) in self.refs.iter().enumerate() {
+            match p.is_missing(db) {
+                Ok(false) => (),
+                Ok(true) => missing.push(idx),
+                Err(e) => {
+                    error!("PluginRefScan::scan_db(): error while scanning plugin {:#?} for user {username}: {e}, treating as missing", p);
+                    missing.push(idx);
+                }
+            }
+        }
+
+        self.missing.insert(username.to_owned(), missing);
+
+        Ok(())
+    }
+
+    fn done(self: Box<Self>) -> PluginScan {
+        PluginScan {
+            plugins: self.refs.into_iter().map(Into::into).collect(),
+            missing: self.missing,
+            project_version: self.version,
+            // only Live versions of users are known
+            cannot_open: vec![],
+        }
+    }
+}
+
+/// Gets the directory a project file is in, relative media paths are resolved against it.
+pub fn get_project_dir(project: &Path) -> Result<&Path> {
+    project.parent().ok_or(TempoError::Project(format!(
+        "Error: Project path has no parent. This shouldn't happen. Project: {}",
+        path_to_str(project)
+    )))
+}
+
+/// Turns a media path from a project into a `FileRef`.
+/// Relative paths are kept in `rel`, `abs` is always absolute.
+pub fn media_fileref(path: &str, project_dir: &Path) -> FileRef {
+    let p = Path::new(path);
+
+    let (rel, abs) = if p.is_absolute() {
+        (String::new(), path.to_string())
+    } else {
+        (
+            path.to_string(),
+            path_to_str(&project_dir.join(p)).to_string(),
+        )
+    };
+
+    FileRef {
+        rel,
+        abs,
+        pack: None,
+        size: None,
+    }
+}
+
+/// Finds the file a media path points to.
+/// Like the daws themselves, files that were moved into one of `search_dirs` are found by filename.
+pub fn resolve_media(fr: &FileRef, search_dirs: &[PathBuf]) -> Option<PathBuf> {
+    let abs = PathBuf::from(&fr.abs);
+    if abs.is_file() {
+        return Some(abs);
+    }
+
+    // paths saved on Windows use \\ as the separator
+    let filename = fr.abs.rsplit(['/', '\\']).next()?;
+    if filename.is_empty() {
+        return None;
+    }
+
+    search_dirs
+        .iter()
+        .map(|dir| dir.join(filename))
+        .find(|p| p.is_file())
+}
+
+/// Scans the media paths of a project, see `resolve_media()`.
+pub fn scan_media_paths(
+    paths: impl IntoIterator<Item = String>,
+    project_dir: &Path,
+    search_dirs: &[PathBuf],
+) -> ProjectFileRefScan {
+    let mut scan = ProjectFileRefScan {
+        ok: HashSet::new(),
+        missing: HashSet::new(),
+    };
+
+    for path in paths {
+        let fr = media_fileref(&path, project_dir);
+
+        match resolve_media(&fr, search_dirs) {
+            Some(file) => match fs::File::open(&file) {
+                Ok(_) => {
+                    scan.ok.insert(fr);
+                }
+                Err(_) => {
+                    scan.missing.insert(MissingFileRef {
+                        file: fr,
+                        err: "Could not read file, check file permissions".into(),
+                        candidates: vec![],
+                    });
+                }
+            },
+            None => {
+                scan.missing.insert(MissingFileRef {
+                    file: fr,
+                    err: "File does not exist".into(),
+                    candidates: vec![],
+                });
+            }
+        }
+    }
+
+    scan
+}
+
+/// Media paths of a project which were resolved to files.
+pub struct ResolvedMedia {
+    // { path in project : referenced file }
+    pub paths: HashMap<String, PathBuf>,
+    // referenced files without duplicates, in the order they first appear
+    pub files: Vec<PathBuf>,
+}
+
+/// Resolves the media paths of a project that's about to be added to a folder, see `resolve_media()`.
+/// Missing files are replaced by files chosen by the user, see `ProjectFileRefScan`.
+/// Files that can't be found are skipped.
+pub fn resolve_media_paths(
+    paths: impl IntoIterator<Item = String>,
+    project_dir: &Path,
+    search_dirs: &[PathBuf],
+    relinks: &[Relink],
+) -> ResolvedMedia {
+    // { (rel, abs) : file to use instead }
+    let relinks: HashMap<(&str, &str), &Path> = relinks
+        .iter()
+        .map(|r| ((r.file.rel.as_str(), r.file.abs.as_str()), r.path.as_path()))
+        .collect();
+
+    let mut media = ResolvedMedia {
+        paths: HashMap::new(),
+        files: vec![],
+    };
+    let mut seen_files: HashSet<PathBuf> = HashSet::new();
+
+    for path in paths {
+        let fr = media_fileref(&path, project_dir);
+
+        let file = resolve_media(&fr, search_dirs).or_else(|| {
+            relinks
+                .get(&(fr.rel.as_str(), fr.abs.as_str()))
+                .filter(|p| p.is_file())
+                .map(|p| p.to_path_buf())
+        });
+
+        match file {
+            Some(file) => {
+                if seen_files.insert(file.clone()) {
+                    media.files.push(file.clone());
+                }
+                media.paths.insert(path, file);
+            }
+            None => warn!("resolve_media_paths(): failed to find {path}, skipping"),
+        }
+    }
+
+    media
+}
+
+/// All daws supported by Tempo.
+static READERS: &[&dyn ProjectReader] = &[
+    &AbletonReader,
+    &ReaperReader,
+    &BitwigReader,
+    &StudioOneReader,
+    &FlStudioReader,
+];
+
+/// Finds the reader for a project type.
+pub fn get_reader(project_type: &ProjectType) -> &'static dyn ProjectReader {
+    READERS
+        .iter()
+        .find(|r| r.handles(project_type))
+        .copied()
+        .expect("get_reader(): every project type should have a reader")
+}
+
+/// Finds the reader for stored project data.
+pub fn get_data_reader(data: &ProjectData) -> &'static dyn ProjectReader {
+    READERS
+        .iter()
+        .find(|r| r.handles_data(data))
+        .copied()
+        .expect("get_data_reader(): all project data should have a reader")
+}
+
+/// Finds the reader and project type for a file extension.
+pub fn get_extension_reader(ext: &str) -> Option<(&'static dyn ProjectReader, ProjectType)> {
+    READERS
+        .iter()
+        .find_map(|r| r.project_type(ext).map(|t| (*r, t)))
+}
+
+/// Finds the reader for a project file or project directory.
+pub fn get_path_reader(path: &Path) -> Result<&'static dyn ProjectReader> {
+    if let Some(r) = READERS.iter().find(|r| r.is_project_dir(path)) {
+        return Ok(*r);
+    }
+
+    match ProjectType::get(path)? {
+        Some(t) => Ok(get_reader(&t)),
+        None => Err(TempoError::Project(format!(
+            "{} appears not to be a project file",
+            path_to_str(path)
+        ))),
+    }
+}
@@ -0,0 +1,99 @@
+// =====================================================================================
+// This file is licensed under either of
+// Apache License, Version 2.0 or MIT license, at your option.
+// =====================================================================================
+// You may obtain a copy of the Apache License, Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0
+// =====================================================================================
+// You may obtain a copy of the MIT License at
+// https://opensource.org/licenses/MIT
+// =====================================================================================
+
+mod project;
+mod reader;
+mod rpp;
+
+pub use project::{copy_reaper_project, scan_filerefs, scan_plugins, stage_reaper_project};
+pub use reader::ReaperReader;
+pub use rpp::verify_project;
+
+use serde::{Deserialize, Serialize};
+
+use crate::shared::PluginRef;
+
+use super::{ableton::AbletonPluginRef, macos::AudioUnitId, plugin::PluginType};
+
+/// Represents a plugin found in a REAPER project file.
+/// Names and vendors are parsed from the plugin's display name, eg "VST3: Pro-Q 3 (FabFilter)".
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, Hash, PartialEq)]
+pub enum ReaperPluginRef {
+    // <VST "VST: ..." file.vst 0 "" 1919247729<...> ""
+    Vst {
+        id: Option<u32>,
+        name: String,
+        vendor: Option<String>,
+    },
+
+    // <VST "VST3: ..." file.vst3 0 "" 1234{class id as hex} ""
+    Vst3 {
+        // class id split into 4 fields, in the same way as Ableton's Vst3PluginInfo
+        fields: Option<[i32; 4]>,
+        name: String,
+        vendor: Option<String>,
+    },
+
+    // <AU "AU: ..." "Vendor: Name" "" type subtype manufacturer
+    Au {
+        id: Option<AudioUnitId>,
+        name: String,
+        vendor: Option<String>,
+    },
+
+    // <CLAP "CLAP: ..." com.vendor.plugin ""
+    Clap {
+        id: String,
+        name: String,
+        vendor: Option<String>,
+    },
+}
+
+impl ReaperPluginRef {
+    /// Plugins in the shared db are identified by the ids that Live uses.
+    /// REAPER stores the same ids, so we can look up REAPER plugins in the shared db too.
+    /// `None` for plugins that can't be looked up, the shared db doesn't contain CLAP plugins.
+    pub fn as_ableton(&self) -> Option<AbletonPluginRef> {
+        match self {
+            Self::Vst { id, name, .. } => id.map(|id| AbletonPluginRef::Vst {
+                id,
+                name: Some(name.clone()),
+            }),
+            Self::Vst3 { fields, name, .. } => fields.map(|fields| AbletonPluginRef::Vst3 {
+                fields,
+                name: Some(name.clone()),
+            }),
+            Self::Au { id, name, vendor } => id.as_ref().map(|id| AbletonPluginRef::Au {
+                id: id.clone(),
+                name: Some(name.clone()),
+                manufacturer: vendor.clone(),
+            }),
+            Self::Clap { .. } => None,
+        }
+    }
+}
+
+impl From<ReaperPluginRef> for PluginRef {
+    fn from(value: ReaperPluginRef) -> Self {
+        let (plugin_type, name, vendor) = match value {
+            ReaperPluginRef::Vst { name, vendor, .. } => (PluginType::Vst, name, vendor),
+            ReaperPluginRef::Vst3 { name, vendor, .. } => (PluginType::Vst3, name, vendor),
+            ReaperPluginRef::Au { name, vendor, .. } => (PluginType::Au, name, vendor),
+            ReaperPluginRef::Clap { name, vendor, .. } => (PluginType::Clap, name, vendor),
+        };
+
+        Self {
+            plugin_type,
+            name,
+            vendor: vendor.unwrap_or("Unknown vendor".into()),
+        }
+    }
+}
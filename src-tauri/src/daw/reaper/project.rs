@@ -0,0 +1,125 @@
+// =====================================================================================
+// This file is licensed under either of
+// Apache License, Version 2.0 or MIT license, at your option.
+// =====================================================================================
+// You may obtain a copy of the Apache License, Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0
+// =====================================================================================
+// You may obtain a copy of the MIT License at
+// https://opensource.org/licenses/MIT
+// =====================================================================================
+
+use std::{collections::HashMap, fs, path::Path};
+
+use crate::{
+    daw::reader::{
+        get_project_dir, resolve_media_paths, scan_media_paths, PluginRefScan, ProjectPluginRef,
+    },
+    db::SharedDb,
+    file::{copy_files_into, stage_project_data, stage_referenced_files, StagedProject},
+    misc::Result,
+    shared::{FileErr, ProjectFileRefScan, Relink},
+    structure::{expect_valid_folder, get_file_path},
+    types::{FileMeta, ProjectData},
+};
+
+use super::{rpp::RppProject, ReaperPluginRef};
+
+/// Directory next to the project that media is copied into.
+const MEDIA_DIR: &str = "Media";
+
+/// Like REAPER, files that were moved next to the project are found by filename.
+pub fn scan_filerefs(project: &Path) -> Result<ProjectFileRefScan> {
+    let project_dir = get_project_dir(project)?;
+    let rpp = RppProject::read(project)?;
+
+    Ok(scan_media_paths(
+        rpp.media_paths(),
+        project_dir,
+        &[project_dir.to_path_buf()],
+    ))
+}
+
+/// Stages a REAPER project to be added to a Tempo folder.
+/// Referenced media is added to the folder and FILE lines are rewritten to point into the Media directory.
+/// Missing files are replaced by files chosen by the user, see `ProjectFileRefScan`.
+pub fn stage_reaper_project(
+    folder: &Path,
+    username: &str,
+    project: &Path,
+    relinks: &[Relink],
+) -> Result<StagedProject> {
+    expect_valid_folder(folder)?;
+
+    let project_dir = get_project_dir(project)?;
+
+    // the whole project is read up front, so changes made while we're copying files don't matter
+    let rpp = RppProject::read(project)?;
+
+    let media = resolve_media_paths(
+        rpp.media_paths(),
+        project_dir,
+        &[project_dir.to_path_buf()],
+        relinks,
+    );
+
+    let staged = stage_referenced_files(folder, username, media.files)?;
+
+    let out_text = rpp.rewrite_media_paths(|path| {
+        let hash = staged.hash(media.paths.get(path)?)?;
+        staged.filename(hash).map(|f| format!("{MEDIA_DIR}/{f}"))
+    });
+
+    let refs = staged.refs();
+
+    stage_project_data(
+        folder,
+        username,
+        project,
+        out_text.as_bytes(),
+        staged,
+        FileMeta::Project(ProjectData::Reaper {
+            refs,
+            plugins: rpp.plugins(),
+            version: rpp.version(),
+        }),
+    )
+}
+
+/// Copies a REAPER project **from a Tempo folder** into `dest`.
+/// Media is copied into the Media directory inside of `dest`, the project already points there.
+pub fn copy_reaper_project(
+    folder: &Path,
+    project_sha256: &str,
+    project_filename: &str,
+    refs: &HashMap<String, String>,
+    dest: &Path,
+) -> Result<Vec<FileErr>> {
+    expect_valid_folder(folder)?;
+
+    fs::copy(
+        get_file_path(folder, project_sha256),
+        dest.join(project_filename),
+    )?;
+
+    if refs.is_empty() {
+        return Ok(vec![]);
+    }
+
+    copy_files_into(folder, refs, &dest.join(MEDIA_DIR))
+}
+
+pub fn scan_plugins(path: &Path) -> Result<PluginRefScan<ReaperPluginRef>> {
+    let rpp = RppProject::read(path)?;
+    Ok(PluginRefScan::new(rpp.plugins(), rpp.version()))
+}
+
+/// Plugins that can't be looked up in the db are never reported as missing.
+impl ProjectPluginRef for ReaperPluginRef {
+    fn is_missing(&self, db: &SharedDb) -> Result<bool> {
+        match self.as_ableton() {
+            Some(p) => Ok(db.get_ableton_plugin(&p)?.is_none()),
+            None => Ok(false),
+        }
+    }
+}
@@ -0,0 +1,126 @@
+// =====================================================================================
+// This file is licensed under either of
+// Apache License, Version 2.0 or MIT license, at your option.
+// =====================================================================================
+// You may obtain a copy of the Apache License, Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0
+// =====================================================================================
+// You may obtain a copy of the MIT License at
+// https://opensource.org/licenses/MIT
+// =====================================================================================
+
+// REAPER implementation of ProjectReader
+
+use std::path::Path;
+
+use crate::{
+    daw::{
+        project::{ProjectTempo, ProjectType},
+        reader::{
+            find_missing_files, find_missing_plugins, other_daw_error, ProjectPluginScan,
+            ProjectReader,
+        },
+    },
+    db::SharedDb,
+    file::StagedProject,
+    misc::Result,
+    shared::{FileErr, ProjectFileRefScan, Relink, SharedProjectData},
+    types::ProjectData,
+};
+
+use super::{
+    copy_reaper_project, rpp::RppProject, scan_filerefs, scan_plugins, stage_reaper_project,
+    verify_project,
+};
+
+pub struct ReaperReader;
+
+impl ProjectReader for ReaperReader {
+    fn name(&self) -> &'static str {
+        "REAPER"
+    }
+
+    fn project_type(&self, ext: &str) -> Option<ProjectType> {
+        match ext.to_lowercase().as_str() {
+            "rpp" => Some(ProjectType::Reaper),
+            _ => None,
+        }
+    }
+
+    fn default_type(&self) -> ProjectType {
+        ProjectType::Reaper
+    }
+
+    fn handles(&self, project_type: &ProjectType) -> bool {
+        matches!(project_type, ProjectType::Reaper)
+    }
+
+    fn handles_data(&self, data: &ProjectData) -> bool {
+        matches!(data, ProjectData::Reaper { .. })
+    }
+
+    fn verify(&self, project: &Path) -> Result<()> {
+        verify_project(project)
+    }
+
+    fn read_tempo(&self, project: &Path) -> Result<Option<ProjectTempo>> {
+        Ok(RppProject::read(project)?.tempo())
+    }
+
+    fn scan_filerefs(&self, path: &Path) -> Result<ProjectFileRefScan> {
+        scan_filerefs(path)
+    }
+
+    fn scan_plugins(&self, path: &Path) -> Result<Box<dyn ProjectPluginScan>> {
+        Ok(Box::new(scan_plugins(path)?))
+    }
+
+    fn stage_project(
+        &self,
+        folder: &Path,
+        username: &str,
+        project: &Path,
+        relinks: &[Relink],
+    ) -> Result<StagedProject> {
+        stage_reaper_project(folder, username, project, relinks)
+    }
+
+    fn project_info(
+        &self,
+        folder: &Path,
+        data: ProjectData,
+        db: &SharedDb,
+    ) -> Result<SharedProjectData> {
+        let ProjectData::Reaper {
+            refs,
+            plugins,
+            version,
+        } = data
+        else {
+            return Err(other_daw_error(self.name()));
+        };
+
+        Ok(SharedProjectData::Reaper {
+            missing_files: find_missing_files(folder, refs.into_iter().map(|(h, f)| (h, [f]))),
+            missing_plugins: find_missing_plugins(db, plugins),
+            version,
+        })
+    }
+
+    /// The project is copied into `dest`, media is copied into `dest/Media`.
+    fn copy_project(
+        &self,
+        folder: &Path,
+        project_sha256: &str,
+        project_filename: &str,
+        data: ProjectData,
+        dest: &Path,
+    ) -> Result<Vec<FileErr>> {
+        match data {
+            ProjectData::Reaper { refs, .. } => {
+                copy_reaper_project(folder, project_sha256, project_filename, &refs, dest)
+            }
+            _ => Err(other_daw_error(self.name())),
+        }
+    }
+}
@@ -0,0 +1,431 @@
+// =====================================================================================
+// This file is licensed under either of
+// Apache License, Version 2.0 or MIT license, at your option.
+// =====================================================================================
+// You may obtain a copy of the Apache License, Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0
+// =====================================================================================
+// You may obtain a copy of the MIT License at
+// https://opensource.org/licenses/MIT
+// =====================================================================================
+
+// reading and rewriting REAPER .rpp files
+
+/*
+.rpp files are plain text. each line is a list of tokens separated by whitespace.
+lines starting with < open a block, a line containing only > closes it.
+
+<REAPER_PROJECT 0.1 "7.14/macOS-arm64" 1712345678
+  <TRACK {GUID}
+    NAME Drums
+    <FXCHAIN
+      <VST "VST3: Pro-Q 3 (FabFilter)" "FabFilter Pro-Q 3.vst3" 0 "" 1926627401{72C4DB717A4D459AB97E51745D84B39D} ""
+        base64 plugin state...
+      >
+    >
+    <ITEM
+      <SOURCE WAVE
+        FILE "Audio/kick.wav"
+      >
+    >
+  >
+>
+
+tokens containing whitespace are quoted with ", ' or `. REAPER picks a quote character that doesn't appear in the token.
+REAPER doesn't escape quotes, so a token can't contain all three quote characters.
+
+media is referenced by FILE lines. paths are either absolute or relative to the directory containing the project.
+when a project is added to a folder, FILE lines are rewritten to point into the Media directory next to the project.
+
+plugins are stored as <VST, <AU and <CLAP blocks inside of <FXCHAIN blocks.
+the first token after the block name is the display name of the plugin, eg "AU: AUDelay (Apple)".
+VST2 ids are stored as a number followed by <...>, VST3 ids are stored as a number followed by the class id in {}.
+AU ids are stored as the last three tokens: type, subtype, manufacturer.
+ */
+
+use std::{collections::HashSet, fs, path::Path};
+
+use crate::{
+    daw::{macos::AudioUnitId, project::ProjectTempo},
+    misc::{path_to_str, Result, TempoError},
+};
+
+use super::ReaperPluginRef;
+
+/// A REAPER project file which has been read into memory.
+pub struct RppProject {
+    text: String,
+}
+
+impl RppProject {
+    pub fn read(project: &Path) -> Result<Self> {
+        let text = fs::read_to_string(project)?;
+        Self::parse(text).map_err(|e| {
+            TempoError::Project(format!(
+                "{} is not a valid REAPER project: {e}",
+                path_to_str(project)
+            ))
+        })
+    }
+
+    fn parse(text: String) -> std::result::Result<Self, &'static str> {
+        let first = text.lines().find(|l| !l.trim().is_empty());
+        match first.and_then(|l| next_token(l.trim_start())) {
+            Some((t, _)) if t == "<REAPER_PROJECT" => Ok(Self { text }),
+            _ => Err("missing REAPER_PROJECT block"),
+        }
+    }
+
+    /// Version of REAPER that saved the project, eg "7.14".
+    pub fn version(&self) -> Option<String> {
+        let first = self.text.lines().find(|l| !l.trim().is_empty())?;
+        let tokens = tokenize(first);
+
+        // <REAPER_PROJECT 0.1 "7.14/macOS-arm64" 1712345678
+        let version = tokens.get(2)?;
+        let version = version.split('/').next().unwrap_or(version);
+
+        if version.is_empty() {
+            None
+        } else {
+            Some(version.to_string())
+        }
+    }
+
+    /// Tempo and time signature of the project, from its TEMPO line.
+    pub fn tempo(&self) -> Option<ProjectTempo> {
+        // TEMPO 120 4 4
+        let tokens = self
+            .text
+            .lines()
+            .map(tokenize)
+            .find(|t| t.first().is_some_and(|t| t == "TEMPO"))?;
+
+        let bpm: f64 = tokens.get(1)?.parse().ok().filter(|b| *b > 0.0)?;
+
+        Some(ProjectTempo {
+            bpm,
+            numerator: tokens.get(2).and_then(|t| t.parse().ok()).unwrap_or(4),
+            denominator: tokens.get(3).and_then(|t| t.parse().ok()).unwrap_or(4),
+        })
+    }
+
+    /// Paths of all media referenced by the project, in the order they first appear.
+    pub fn media_paths(&self) -> Vec<String> {
+        let mut seen: HashSet<&str> = HashSet::new();
+        let mut paths = vec![];
+
+        for line in self.text.lines() {
+            if let Some((path, _)) = parse_file_line(line) {
+                if seen.insert(path) {
+                    paths.push(path.to_string());
+                }
+            }
+        }
+
+        paths
+    }
+
+    /// Rewrites all FILE lines in the project.
+    /// `edit` is called with each referenced path and returns the path to use instead, `None` leaves the line as is.
+    pub fn rewrite_media_paths(&self, mut edit: impl FnMut(&str) -> Option<String>) -> String {
+        let mut out = String::with_capacity(self.text.len());
+
+        for line in self.text.split_inclusive('\n') {
+            let (content, ending) = split_line_ending(line);
+
+            match parse_file_line(content) {
+                Some((path, rest)) => match edit(path) {
+                    Some(new_path) => {
+                        let indent = &content[..content.len() - content.trim_start().len()];
+                        out.push_str(indent);
+                        out.push_str("FILE ");
+                        out.push_str(&quote(&new_path));
+                        out.push_str(rest);
+                        out.push_str(ending);
+                    }
+                    None => out.push_str(line),
+                },
+                None => out.push_str(line),
+            }
+        }
+
+        out
+    }
+
+    /// All plugins used in the project, duplicates are removed.
+    pub fn plugins(&self) -> Vec<ReaperPluginRef> {
+        let mut seen: HashSet<ReaperPluginRef> = HashSet::new();
+        let mut plugins = vec![];
+
+        for line in self.text.lines() {
+            if let Some(p) = parse_plugin_line(line) {
+                if seen.insert(p.clone()) {
+                    plugins.push(p);
+                }
+            }
+        }
+
+        plugins
+    }
+}
+
+/// Checks whether a file is a REAPER project.
+pub fn verify_project(project: &Path) -> Result<()> {
+    RppProject::read(project).map(|_| ())
+}
+
+/// Returns `(path, rest of line after the path)` for FILE lines.
+fn parse_file_line(line: &str) -> Option<(&str, &str)> {
+    let (keyword, rest) = next_token_raw(line.trim_start())?;
+    if keyword != "FILE" {
+        return None;
+    }
+
+    let (path, rest) = next_token_raw(rest)?;
+    Some((unquote(path), rest))
+}
+
+fn parse_plugin_line(line: &str) -> Option<ReaperPluginRef> {
+    let tokens = tokenize(line);
+    let kind = tokens.first()?.as_str();
+
+    if !matches!(kind, "<VST" | "<AU" | "<CLAP") {
+        return None;
+    }
+
+    let display = tokens.get(1)?;
+    let (prefix, name, vendor) = parse_display_name(display);
+
+    match (kind, prefix.as_deref()) {
+        ("<VST", Some("VST3" | "VST3i")) => Some(ReaperPluginRef::Vst3 {
+            fields: tokens.iter().skip(2).find_map(|t| parse_vst3_fields(t)),
+            name,
+            vendor,
+        }),
+        ("<VST", _) => Some(ReaperPluginRef::Vst {
+            id: tokens.iter().skip(2).find_map(|t| parse_vst_id(t)),
+            name,
+            vendor,
+        }),
+        ("<AU", _) => Some(ReaperPluginRef::Au {
+            id: parse_au_id(&tokens),
+            name,
+            vendor,
+        }),
+        ("<CLAP", _) => Some(ReaperPluginRef::Clap {
+            id: tokens.get(2).cloned().unwrap_or_default(),
+            name,
+            vendor,
+        }),
+        _ => None,
+    }
+}
+
+/// Splits a display name like "VST3i: Serum (Xfer Records)" into `(prefix, name, vendor)`.
+fn parse_display_name(display: &str) -> (Option<String>, String, Option<String>) {
+    let (prefix, rest) = match display.split_once(": ") {
+        Some((p, r)) if !p.contains(' ') => (Some(p.to_string()), r),
+        _ => (None, display),
+    };
+
+    let rest = rest.trim();
+
+    match rest.strip_suffix(')').and_then(|r| r.rsplit_once(" (")) {
+        Some((name, vendor)) => (prefix, name.trim().to_string(), Some(vendor.to_string())),
+        None => (prefix, rest.to_string(), None),
+    }
+}
+
+// 1919247729<56535472656571>
+fn parse_vst_id(token: &str) -> Option<u32> {
+    let (id, _) = token.split_once('<')?;
+    id.parse::<i64>().ok().map(|id| id as u32)
+}
+
+// 1926627401{72C4DB717A4D459AB97E51745D84B39D}
+fn parse_vst3_fields(token: &str) -> Option<[i32; 4]> {
+    let (_, class_id) = token.split_once('{')?;
+    let class_id = class_id.strip_suffix('}')?;
+
+    if class_id.len() != 32 {
+        return None;
+    }
+
+    let mut fields = [0i32; 4];
+    for (i, f) in fields.iter_mut().enumerate() {
+        *f = u32::from_str_radix(class_id.get(i * 8..i * 8 + 8)?, 16).ok()? as i32;
+    }
+    Some(fields)
+}
+
+// <AU "AU: AUDelay (Apple)" "Apple: AUDelay" "" 1635083896 1684368505 1634758764
+fn parse_au_id(tokens: &[String]) -> Option<AudioUnitId> {
+    let ids: Vec<u32> = tokens
+        .iter()
+        .skip(2)
+        .filter_map(|t| t.parse::<i64>().ok().map(|n| n as u32))
+        .collect();
+
+    match ids[..] {
+        [.., au_type, au_subtype, manufacturer] => Some(AudioUnitId {
+            au_type,
+            au_subtype,
+            manufacturer,
+        }),
+        _ => None,
+    }
+}
+
+fn split_line_ending(line: &str) -> (&str, &str) {
+    let content = line.trim_end_matches(['\r', '\n']);
+    (content, &line[content.len()..])
+}
+
+/// Splits all tokens in a line, quotes are removed.
+fn tokenize(line: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut rest = line;
+
+    while let Some((token, r)) = next_token(rest) {
+        tokens.push(token);
+        rest = r;
+    }
+
+    tokens
+}
+
+fn next_token(s: &str) -> Option<(String, &str)> {
+    next_token_raw(s).map(|(t, r)| (unquote(t).to_string(), r))
+}
+
+/// Returns `(token including quotes, rest of string)`.
+fn next_token_raw(s: &str) -> Option<(&str, &str)> {
+    let s = s.trim_start();
+    let first = s.chars().next()?;
+
+    let end = if matches!(first, '"' | '\'' | '`') {
+        // unterminated quotes run to the end of the line
+        s[1..].find(first).map(|i| i + 2).unwrap_or(s.len())
+    } else {
+        s.find(char::is_whitespace).unwrap_or(s.len())
+    };
+
+    Some((&s[..end], &s[end..]))
+}
+
+fn unquote(token: &str) -> &str {
+    for q in ['"', '\'', '`'] {
+        if let Some(t) = token.strip_prefix(q) {
+            return t.strip_suffix(q).unwrap_or(t);
+        }
+    }
+    token
+}
+
+/// Quotes a token the way REAPER does.
+fn quote(token: &str) -> String {
+    let q = ['"', '\'', '`']
+        .into_iter()
+        .find(|q| !token.contains(*q))
+        .unwrap_or('"');
+    format!("{q}{token}{q}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROJECT: &str = "<REAPER_PROJECT 0.1 \"7.14/macOS-arm64\" 1712345678\r\n  <TRACK\r\n    <FXCHAIN\r\n      <VST \"VST: ReaEQ (Cockos)\" reaeq.vst.dylib 0 \"\" 1919247729<56535472656571> \"\"\r\n      >\r\n      <VST \"VST3: Pro-Q 3 (FabFilter)\" \"FabFilter Pro-Q 3.vst3\" 0 \"\" 1926627401{72C4DB717A4D459AB97E51745D84B39D} \"\"\r\n      >\r\n      <AU \"AU: AUDelay (Apple)\" \"Apple: AUDelay\" \"\" 1635083896 1684368505 1634758764\r\n      >\r\n      <CLAP \"CLAPi: Surge XT (Surge Synth Team)\" org.surge-synth-team.surge-xt \"\"\r\n      >\r\n    >\r\n    <ITEM\r\n      <SOURCE WAVE\r\n        FILE \"Audio/my kick.wav\" 1\r\n      >\r\n    >\r\n    <ITEM\r\n      <SOURCE WAVE\r\n        FILE /Users/a/Samples/snare.wav\r\n      >\r\n    >\r\n  >\r\n>\r\n";
+
+    #[test]
+    fn test_media_paths() {
+        let rpp = RppProject::parse(PROJECT.into()).unwrap();
+
+        assert_eq!(rpp.version().as_deref(), Some("7.14"));
+        assert_eq!(
+            rpp.media_paths(),
+            vec!["Audio/my kick.wav", "/Users/a/Samples/snare.wav"]
+        );
+
+        let out =
+            rpp.rewrite_media_paths(|p| p.ends_with("kick.wav").then(|| "Media/kick.wav".into()));
+        assert!(out.contains("        FILE \"Media/kick.wav\" 1\r\n"));
+        assert!(out.contains("        FILE /Users/a/Samples/snare.wav\r\n"));
+        assert_eq!(
+            out.len(),
+            PROJECT.len() - "Audio/my kick.wav".len() + "Media/kick.wav".len()
+        );
+
+        assert!(RppProject::parse("<Ableton>".into()).is_err());
+    }
+
+    #[test]
+    fn test_tempo() {
+        let rpp = RppProject::parse(
+            "<REAPER_PROJECT 0.1 \"7.14/macOS-arm64\" 1712345678\r\n  TEMPO 90 6 8\r\n>\r\n".into(),
+        )
+        .unwrap();
+        let tempo = rpp.tempo().unwrap();
+        assert_eq!(
+            (tempo.bpm, tempo.numerator, tempo.denominator),
+            (90.0, 6, 8)
+        );
+
+        // 3 eighth notes in at 90 bpm
+        let bb = tempo.bar_beat(1.0);
+        assert_eq!((bb.bar, bb.beat), (1, 4.0));
+        // second bar
+        let bb = tempo.bar_beat(2.0);
+        assert_eq!((bb.bar, bb.beat), (2, 1.0));
+
+        assert!(RppProject::parse(PROJECT.into()).unwrap().tempo().is_none());
+    }
+
+    #[test]
+    fn test_plugins() {
+        let plugins = RppProject::parse(PROJECT.into()).unwrap().plugins();
+
+        assert_eq!(
+            plugins,
+            vec![
+                ReaperPluginRef::Vst {
+                    id: Some(1919247729),
+                    name: "ReaEQ".into(),
+                    vendor: Some("Cockos".into())
+                },
+                ReaperPluginRef::Vst3 {
+                    fields: Some([
+                        0x72C4DB71u32 as i32,
+                        0x7A4D459Au32 as i32,
+                        0xB97E5174u32 as i32,
+                        0x5D84B39Du32 as i32
+                    ]),
+                    name: "Pro-Q 3".into(),
+                    vendor: Some("FabFilter".into())
+                },
+                ReaperPluginRef::Au {
+                    id: Some(AudioUnitId {
+                        au_type: 1635083896,
+                        au_subtype: 1684368505,
+                        manufacturer: 1634758764
+                    }),
+                    name: "AUDelay".into(),
+                    vendor: Some("Apple".into())
+                },
+                ReaperPluginRef::Clap {
+                    id: "org.surge-synth-team.surge-xt".into(),
+                    name: "Surge XT".into(),
+                    vendor: Some("Surge Synth Team".into())
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("Media/kick.wav"), "\"Media/kick.wav\"");
+        assert_eq!(quote("Media/12\" kick.wav"), "'Media/12\" kick.wav'");
+        assert_eq!(unquote("`a \"b' c`"), "a \"b' c");
+    }
+}
@@ -0,0 +1,106 @@
+// =====================================================================================
+// This file is licensed under either of
+// Apache License, Version 2.0 or MIT license, at your option.
+// =====================================================================================
+// You may obtain a copy of the Apache License, Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0
+// =====================================================================================
+// You may obtain a copy of the MIT License at
+// https://opensource.org/licenses/MIT
+// =====================================================================================
+
+mod archive;
+mod project;
+mod reader;
+mod song;
+
+pub use project::{copy_studio_one_project, scan_filerefs, scan_plugins, stage_studio_one_project};
+pub use reader::StudioOneReader;
+pub use song::verify_project;
+
+use serde::{Deserialize, Serialize};
+
+use crate::shared::PluginRef;
+
+use super::{ableton::AbletonPluginRef, macos::AudioUnitId, plugin::PluginType};
+
+/// Represents a plugin found in a Studio One song.
+/// Plugins are identified by the class id in the song's device documents, see the notes in song.rs.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, Hash, PartialEq)]
+pub enum StudioOnePluginRef {
+    // classID="{56535453-<id>-...}"
+    Vst {
+        id: Option<u32>,
+        name: String,
+        vendor: Option<String>,
+    },
+
+    // classID="{72C4DB71-7A4D-459A-B97E-51745D84B39D}"
+    Vst3 {
+        // class id split into 4 fields, in the same way as Ableton's Vst3PluginInfo
+        fields: Option<[i32; 4]>,
+        name: String,
+        vendor: Option<String>,
+    },
+
+    // classID="aufx:dely:appl"
+    Au {
+        id: Option<AudioUnitId>,
+        name: String,
+        vendor: Option<String>,
+    },
+}
+
+impl StudioOnePluginRef {
+    pub fn plugin_type(&self) -> PluginType {
+        match self {
+            Self::Vst { .. } => PluginType::Vst,
+            Self::Vst3 { .. } => PluginType::Vst3,
+            Self::Au { .. } => PluginType::Au,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Vst { name, .. } | Self::Vst3 { name, .. } | Self::Au { name, .. } => name,
+        }
+    }
+
+    /// Plugins in the shared db are identified by the ids that Live uses.
+    /// Studio One stores the same ids, so we can look up Studio One plugins in the shared db too.
+    /// `None` if the id couldn't be read from the song.
+    pub fn as_ableton(&self) -> Option<AbletonPluginRef> {
+        match self {
+            Self::Vst { id, name, .. } => id.map(|id| AbletonPluginRef::Vst {
+                id,
+                name: Some(name.clone()),
+            }),
+            Self::Vst3 { fields, name, .. } => fields.map(|fields| AbletonPluginRef::Vst3 {
+                fields,
+                name: Some(name.clone()),
+            }),
+            Self::Au { id, name, vendor } => id.as_ref().map(|id| AbletonPluginRef::Au {
+                id: id.clone(),
+                name: Some(name.clone()),
+                manufacturer: vendor.clone(),
+            }),
+        }
+    }
+}
+
+impl From<StudioOnePluginRef> for PluginRef {
+    fn from(value: StudioOnePluginRef) -> Self {
+        let plugin_type = value.plugin_type();
+        let (name, vendor) = match value {
+            StudioOnePluginRef::Vst { name, vendor, .. }
+            | StudioOnePluginRef::Vst3 { name, vendor, .. }
+            | StudioOnePluginRef::Au { name, vendor, .. } => (name, vendor),
+        };
+
+        Self {
+            plugin_type,
+            name,
+            vendor: vendor.unwrap_or("Unknown vendor".into()),
+        }
+    }
+}
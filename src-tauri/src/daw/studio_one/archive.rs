@@ -0,0 +1,205 @@
+// =====================================================================================
+// This file is licensed under either of
+// Apache License, Version 2.0 or MIT license, at your option.
+// =====================================================================================
+// You may obtain a copy of the Apache License, Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0
+// =====================================================================================
+// You may obtain a copy of the MIT License at
+// https://opensource.org/licenses/MIT
+// =====================================================================================
+
+// zip reading and rewriting for Studio One songs
+
+/*
+Studio One songs are plain zip archives, they're read and written with the zip crate.
+
+we only read the entries we look at into memory. when a song is rewritten only the changed entries are compressed again,
+every other entry is copied byte for byte, keeping its compression, timestamps and zip64 fields.
+ */
+
+use std::{
+    collections::HashMap,
+    io::{Cursor, Read, Write},
+};
+
+use zip::{result::ZipError, write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::misc::{Result, TempoError};
+
+const LOCAL_HEADER_SIG: &[u8] = b"PK\x03\x04";
+
+/// An entry in a zip archive, `data` is uncompressed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZipEntry {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+fn invalid(e: ZipError) -> TempoError {
+    TempoError::Project(format!("Invalid zip archive: {e}"))
+}
+
+/// Whether `data` starts like a zip archive.
+pub fn is_zip(data: &[u8]) -> bool {
+    data.starts_with(LOCAL_HEADER_SIG)
+}
+
+/// Reads the entries of a zip archive for which `filter` returns true, in the order of the central directory.
+/// Directories are skipped.
+pub fn read_zip(data: &[u8], filter: impl Fn(&str) -> bool) -> Result<Vec<ZipEntry>> {
+    let mut archive = ZipArchive::new(Cursor::new(data)).map_err(invalid)?;
+    let mut entries = vec![];
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).map_err(invalid)?;
+        if file.is_dir() || !filter(file.name()) {
+            continue;
+        }
+
+        let mut data = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut data)?;
+
+        entries.push(ZipEntry {
+            name: file.name().to_string(),
+            data,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Copies a zip archive, replacing the contents of the entries in `replace` (`{ name : uncompressed data }`).
+/// Replaced entries are compressed with deflate and keep their timestamps, all other entries are copied as they are.
+pub fn rewrite_zip(data: &[u8], replace: &HashMap<&str, Vec<u8>>) -> Result<Vec<u8>> {
+    let mut archive = ZipArchive::new(Cursor::new(data)).map_err(invalid)?;
+    let mut writer = ZipWriter::new(Cursor::new(Vec::with_capacity(data.len())));
+
+    for i in 0..archive.len() {
+        let file = archive.by_index_raw(i).map_err(invalid)?;
+
+        match replace.get(file.name()) {
+            Some(new_data) => {
+                let options: SimpleFileOptions = file
+                    .options()
+                    .compression_method(CompressionMethod::Deflated)
+                    .large_file(new_data.len() as u64 > u32::MAX as u64);
+                let name = file.name().to_string();
+                drop(file);
+
+                writer.start_file(name, options).map_err(invalid)?;
+                writer.write_all(new_data)?;
+            }
+            None => writer.raw_copy_file(file).map_err(invalid)?,
+        }
+    }
+
+    Ok(writer.finish().map_err(invalid)?.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zip::DateTime;
+
+    fn song() -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(vec![]));
+        let time = DateTime::from_date_and_time(2024, 5, 17, 13, 37, 0).unwrap();
+
+        writer
+            .start_file(
+                "metainfo.xml",
+                SimpleFileOptions::default()
+                    .compression_method(CompressionMethod::Stored)
+                    .last_modified_time(time),
+            )
+            .unwrap();
+        writer
+            .write_all(b"<?xml version=\"1.0\"?><MetaInformation/>")
+            .unwrap();
+
+        writer
+            .add_directory("Song/", SimpleFileOptions::default())
+            .unwrap();
+
+        writer
+            .start_file(
+                "Song/mediapool.xml",
+                SimpleFileOptions::default().last_modified_time(time),
+            )
+            .unwrap();
+        writer
+            .write_all("<MediaPool>ü</MediaPool>".repeat(100).as_bytes())
+            .unwrap();
+
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_read_zip() {
+        let zip = song();
+        assert!(is_zip(&zip));
+
+        assert_eq!(
+            read_zip(&zip, |_| true).unwrap(),
+            vec![
+                ZipEntry {
+                    name: "metainfo.xml".into(),
+                    data: b"<?xml version=\"1.0\"?><MetaInformation/>".to_vec(),
+                },
+                ZipEntry {
+                    name: "Song/mediapool.xml".into(),
+                    data: "<MediaPool>ü</MediaPool>".repeat(100).into_bytes(),
+                },
+            ]
+        );
+
+        assert_eq!(read_zip(&zip, |n| n == "metainfo.xml").unwrap().len(), 1);
+
+        assert!(read_zip(b"not a zip", |_| true).is_err());
+    }
+
+    #[test]
+    fn test_rewrite_zip() {
+        let zip = song();
+
+        let out = rewrite_zip(
+            &zip,
+            &HashMap::from([("Song/mediapool.xml", b"<MediaPool/>".to_vec())]),
+        )
+        .unwrap();
+
+        let mut before = ZipArchive::new(Cursor::new(zip.as_slice())).unwrap();
+        let mut after = ZipArchive::new(Cursor::new(out.as_slice())).unwrap();
+        assert_eq!(after.len(), 3);
+
+        // unchanged entries are copied as they are
+        for name in ["metainfo.xml", "Song/"] {
+            let mut a = vec![];
+            let mut b = vec![];
+            let i = before.index_for_name(name).unwrap();
+            before.by_index_raw(i).unwrap().read_to_end(&mut a).unwrap();
+            let i = after.index_for_name(name).unwrap();
+            after.by_index_raw(i).unwrap().read_to_end(&mut b).unwrap();
+            assert_eq!(a, b);
+        }
+        let meta = after.by_name("metainfo.xml").unwrap();
+        assert_eq!(meta.compression(), CompressionMethod::Stored);
+        assert_eq!(
+            meta.last_modified(),
+            before.by_name("metainfo.xml").unwrap().last_modified()
+        );
+        drop(meta);
+
+        // rewritten entries keep their timestamps
+        let pool_time = before
+            .by_name("Song/mediapool.xml")
+            .unwrap()
+            .last_modified();
+        let mut pool = after.by_name("Song/mediapool.xml").unwrap();
+        assert_eq!(pool.last_modified(), pool_time);
+        let mut data = vec![];
+        pool.read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"<MediaPool/>");
+    }
+}
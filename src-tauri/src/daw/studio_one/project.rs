@@ -0,0 +1,134 @@
+// =====================================================================================
+// This file is licensed under either of
+// Apache License, Version 2.0 or MIT license, at your option.
+// =====================================================================================
+// You may obtain a copy of the Apache License, Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0
+// =====================================================================================
+// You may obtain a copy of the MIT License at
+// https://opensource.org/licenses/MIT
+// =====================================================================================
+
+use std::{collections::HashMap, fs, path::Path};
+
+use crate::{
+    daw::reader::{
+        get_project_dir, resolve_media_paths, scan_media_paths, PluginRefScan, ProjectPluginRef,
+    },
+    db::SharedDb,
+    file::{copy_files_into, stage_project_data, stage_referenced_files, StagedProject},
+    misc::Result,
+    shared::{FileErr, ProjectFileRefScan, Relink},
+    structure::{expect_valid_folder, get_file_path},
+    types::{FileMeta, ProjectData},
+};
+
+use super::{
+    song::{media_dir_url, url_to_path, Song},
+    StudioOnePluginRef,
+};
+
+/// Directory in the song folder that media is copied into.
+const MEDIA_DIR: &str = "Media";
+
+/// Paths of the media pool urls of a song.
+fn media_paths(song: &Song) -> Result<Vec<String>> {
+    Ok(song.media_urls()?.iter().map(|u| url_to_path(u)).collect())
+}
+
+/// Like Studio One, files that were moved into the song's Media directory are found by filename.
+pub fn scan_filerefs(project: &Path) -> Result<ProjectFileRefScan> {
+    let project_dir = get_project_dir(project)?;
+    let song = Song::read(project)?;
+
+    Ok(scan_media_paths(
+        media_paths(&song)?,
+        project_dir,
+        &[project_dir.join(MEDIA_DIR)],
+    ))
+}
+
+/// Stages a Studio One song to be added to a Tempo folder.
+/// Referenced media is added to the folder and media pool urls are rewritten to point into the Media directory.
+/// Missing files are replaced by files chosen by the user, see `ProjectFileRefScan`.
+pub fn stage_studio_one_project(
+    folder: &Path,
+    username: &str,
+    project: &Path,
+    relinks: &[Relink],
+) -> Result<StagedProject> {
+    expect_valid_folder(folder)?;
+
+    let project_dir = get_project_dir(project)?;
+
+    // the whole project is read up front, so changes made while we're copying files don't matter
+    let song = Song::read(project)?;
+
+    let media = resolve_media_paths(
+        media_paths(&song)?,
+        project_dir,
+        &[project_dir.join(MEDIA_DIR)],
+        relinks,
+    );
+
+    let staged = stage_referenced_files(folder, username, media.files)?;
+
+    let out_data = song.rewrite_media_urls(|url| {
+        let hash = staged.hash(media.paths.get(&url_to_path(url))?)?;
+        staged.filename(hash).map(|f| media_dir_url(MEDIA_DIR, f))
+    })?;
+
+    let refs = staged.refs();
+
+    stage_project_data(
+        folder,
+        username,
+        project,
+        &out_data,
+        staged,
+        FileMeta::Project(ProjectData::StudioOne {
+            refs,
+            plugins: song.plugins()?,
+        }),
+    )
+}
+
+/// Copies a Studio One song **from a Tempo folder** into the song folder `dest`.
+/// Media is copied into the Media directory inside of `dest`, the song already points there.
+pub fn copy_studio_one_project(
+    folder: &Path,
+    project_sha256: &str,
+    project_filename: &str,
+    refs: &HashMap<String, String>,
+    dest: &Path,
+) -> Result<Vec<FileErr>> {
+    expect_valid_folder(folder)?;
+
+    fs::copy(
+        get_file_path(folder, project_sha256),
+        dest.join(project_filename),
+    )?;
+
+    if refs.is_empty() {
+        return Ok(vec![]);
+    }
+
+    copy_files_into(folder, refs, &dest.join(MEDIA_DIR))
+}
+
+pub fn scan_plugins(path: &Path) -> Result<PluginRefScan<StudioOnePluginRef>> {
+    // Studio One versions aren't read from songs yet
+    Ok(PluginRefScan::new(Song::read(path)?.plugins()?, None))
+}
+
+/// Plugins are looked up by their ids, falling back to their names when the id couldn't be read.
+impl ProjectPluginRef for StudioOnePluginRef {
+    fn is_missing(&self, db: &SharedDb) -> Result<bool> {
+        match self.as_ableton() {
+            Some(p) => Ok(db.get_ableton_plugin(&p)?.is_none()),
+            None => Ok(db
+                .get_plugin_by_name(&self.plugin_type(), self.name())?
+                .is_none()),
+        }
+    }
+}
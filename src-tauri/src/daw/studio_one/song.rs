@@ -0,0 +1,500 @@
+// =====================================================================================
+// This file is licensed under either of
+// Apache License, Version 2.0 or MIT license, at your option.
+// =====================================================================================
+// You may obtain a copy of the Apache License, Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0
+// =====================================================================================
+// You may obtain a copy of the MIT License at
+// https://opensource.org/licenses/MIT
+// =====================================================================================
+
+// reading and rewriting Studio One .song files
+
+/*
+.song files are zip archives of xml documents:
+
+metainfo.xml
+Song/song.xml
+Song/mediapool.xml          <- all media used by the song
+Devices/audiomixer.xml      <- insert effects
+Devices/audiosynthfolder.xml  <- instruments
+...
+
+media in the media pool is referenced by a url:
+
+<AudioClip mediaType="Audio" ...>
+  <Url x:id="url" url="file:///Users/a/Songs/My%20Song/Media/kick.wav"/>
+</AudioClip>
+
+urls are normally absolute file urls. when a song is moved Studio One looks for missing media in the Media folder next
+to the song, and relative urls are resolved against the song folder, so we rewrite urls to Media/<filename>.
+
+plugins are stored in the device documents with a classInfo element:
+
+<Attributes x:id="classInfo" classID="{72C4DB71-7A4D-459A-B97E-51745D84B39D}" name="Pro-Q 3" category="AudioEffect"
+  subCategory="Fx|EQ" vendor="FabFilter"/>
+
+the class id tells us what kind of plugin it is:
+- VST2 plugins are given the class id of the VST3 SDK's VST2 wrapper: "VST" followed by 'S' or 'E', then the
+  4 byte VST2 unique id and the start of the lowercased plugin name
+- other GUID class ids are VST3 class ids
+- Audio Units are identified by their type, subtype and manufacturer codes, eg "aufx:dely:appl"
+
+Studio One's built in devices also have classInfo elements, they're skipped by their vendor/subCategory.
+ */
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+};
+
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader, Writer,
+};
+
+use crate::{
+    daw::macos::AudioUnitId,
+    misc::{path_to_str, Result, TempoError},
+};
+
+use super::{
+    archive::{is_zip, read_zip, rewrite_zip, ZipEntry},
+    StudioOnePluginRef,
+};
+
+const SONG: &str = "Song/song.xml";
+const MEDIA_POOL: &str = "Song/mediapool.xml";
+const DEVICES_DIR: &str = "Devices/";
+
+// class ids of VST2 plugins start with "VSTS" or "VSTE"
+const VST2_PREFIXES: &[&str] = &["56535453", "56535445"];
+
+/// A Studio One song which has been read into memory.
+pub struct Song {
+    data: Vec<u8>,
+    // the song, media pool and devices, other entries aren't read
+    entries: Vec<ZipEntry>,
+}
+
+impl Song {
+    pub fn read(song: &Path) -> Result<Self> {
+        let data = fs::read(song)?;
+        Self::parse(data).map_err(|e| {
+            TempoError::Project(format!(
+                "{} is not a valid Studio One song: {e}",
+                path_to_str(song)
+            ))
+        })
+    }
+
+    fn parse(data: Vec<u8>) -> Result<Self> {
+        if !is_zip(&data) {
+            return Err(TempoError::Project("not a zip archive".into()));
+        }
+
+        let entries = read_zip(&data, |name| {
+            name == SONG || name == MEDIA_POOL || is_device(name)
+        })?;
+
+        if !entries.iter().any(|e| e.name == SONG) {
+            return Err(TempoError::Project(format!("{SONG} is missing")));
+        }
+
+        Ok(Self { data, entries })
+    }
+
+    fn media_pool(&self) -> Option<&ZipEntry> {
+        self.entries.iter().find(|e| e.name == MEDIA_POOL)
+    }
+
+    /// Urls of all media in the media pool, in the order they first appear.
+    pub fn media_urls(&self) -> Result<Vec<String>> {
+        let Some(pool) = self.media_pool() else {
+            return Ok(vec![]);
+        };
+
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut urls = vec![];
+
+        for_each_element(&pool.data, |e| {
+            if let Some(url) = media_url(e)? {
+                if seen.insert(url.clone()) {
+                    urls.push(url);
+                }
+            }
+            Ok(())
+        })?;
+
+        Ok(urls)
+    }
+
+    /// All plugins used in the song, duplicates are removed.
+    pub fn plugins(&self) -> Result<Vec<StudioOnePluginRef>> {
+        let mut seen: HashSet<StudioOnePluginRef> = HashSet::new();
+        let mut plugins = vec![];
+
+        for entry in &self.entries {
+            if !is_device(&entry.name) {
+                continue;
+            }
+
+            for_each_element(&entry.data, |e| {
+                if let Some(p) = parse_class_info(e)? {
+                    if seen.insert(p.clone()) {
+                        plugins.push(p);
+                    }
+                }
+                Ok(())
+            })?;
+        }
+
+        Ok(plugins)
+    }
+
+    /// Rewrites all media urls in the media pool and returns the new song.
+    /// `edit` is called with each url and returns the url to use instead, `None` leaves it as is.
+    pub fn rewrite_media_urls(
+        &self,
+        mut edit: impl FnMut(&str) -> Option<String>,
+    ) -> Result<Vec<u8>> {
+        let mut replace: HashMap<&str, Vec<u8>> = HashMap::new();
+
+        if let Some(entry) = self.media_pool() {
+            let mut reader = Reader::from_reader(entry.data.as_slice());
+            let mut writer = Writer::new(Vec::with_capacity(entry.data.len()));
+            let mut buf = vec![];
+
+            loop {
+                buf.clear();
+                match reader.read_event_into(&mut buf)? {
+                    Event::Eof => break,
+                    Event::Start(e) => {
+                        writer.write_event(Event::Start(rewrite_url(&e, &mut edit)?))?
+                    }
+                    Event::Empty(e) => {
+                        writer.write_event(Event::Empty(rewrite_url(&e, &mut edit)?))?
+                    }
+                    e => writer.write_event(e)?,
+                }
+            }
+
+            replace.insert(MEDIA_POOL, writer.into_inner());
+        }
+
+        rewrite_zip(&self.data, &replace)
+    }
+}
+
+fn is_device(name: &str) -> bool {
+    name.starts_with(DEVICES_DIR) && name.ends_with(".xml")
+}
+
+/// Checks whether a file is a Studio One song.
+pub fn verify_project(song: &Path) -> Result<()> {
+    Song::read(song).map(|_| ())
+}
+
+/// Turns a media url into a path, relative urls stay relative.
+pub fn url_to_path(url: &str) -> String {
+    let path = match url.strip_prefix("file://") {
+        // file:///C:/... on Windows
+        Some(p) if p.get(2..3) == Some(":") || p.get(3..4) == Some(":") => {
+            p.trim_start_matches('/')
+        }
+        Some(p) => p,
+        None => url,
+    };
+
+    percent_decode(path)
+}
+
+/// Relative url of a file in the Media folder.
+pub fn media_dir_url(media_dir: &str, filename: &str) -> String {
+    format!("{media_dir}/{}", percent_encode(filename))
+}
+
+fn for_each_element(xml: &[u8], mut f: impl FnMut(&BytesStart) -> Result<()>) -> Result<()> {
+    let mut reader = Reader::from_reader(xml);
+    let mut buf = vec![];
+
+    loop {
+        buf.clear();
+        match reader.read_event_into(&mut buf)? {
+            Event::Eof => return Ok(()),
+            Event::Start(e) | Event::Empty(e) => f(&e)?,
+            _ => (),
+        }
+    }
+}
+
+fn get_attr(e: &BytesStart, name: &[u8]) -> Result<Option<String>> {
+    for a in e.attributes() {
+        let a = a.map_err(quick_xml::Error::from)?;
+        if a.key.as_ref() == name {
+            return Ok(Some(a.unescape_value()?.to_string()));
+        }
+    }
+    Ok(None)
+}
+
+/// The url of a `<Url>` element, `None` for other elements.
+fn media_url(e: &BytesStart) -> Result<Option<String>> {
+    if e.name().as_ref() != b"Url" {
+        return Ok(None);
+    }
+    Ok(get_attr(e, b"url")?.filter(|u| !u.is_empty() && !u.ends_with('/')))
+}
+
+fn rewrite_url(
+    e: &BytesStart,
+    edit: &mut impl FnMut(&str) -> Option<String>,
+) -> Result<BytesStart<'static>> {
+    let new_url = match media_url(e)? {
+        Some(url) => edit(&url),
+        None => None,
+    };
+
+    let Some(new_url) = new_url else {
+        return Ok(e.to_owned());
+    };
+
+    let name = String::from_utf8_lossy(e.name().as_ref()).to_string();
+    let mut out = BytesStart::new(name);
+
+    for a in e.attributes() {
+        let a = a.map_err(quick_xml::Error::from)?;
+        let key = String::from_utf8_lossy(a.key.as_ref()).to_string();
+        if key == "url" {
+            out.push_attribute((key.as_str(), new_url.as_str()));
+        } else {
+            out.push_attribute((key.as_str(), a.unescape_value()?.as_ref()));
+        }
+    }
+
+    Ok(out)
+}
+
+fn parse_class_info(e: &BytesStart) -> Result<Option<StudioOnePluginRef>> {
+    if get_attr(e, b"x:id")?.as_deref() != Some("classInfo") {
+        return Ok(None);
+    }
+
+    let (Some(class_id), Some(name)) = (get_attr(e, b"classID")?, get_attr(e, b"name")?) else {
+        return Ok(None);
+    };
+
+    let vendor = get_attr(e, b"vendor")?.filter(|v| !v.is_empty());
+    let sub_category = get_attr(e, b"subCategory")?.unwrap_or_default();
+
+    // built in devices
+    if vendor.as_deref() == Some("PreSonus") || sub_category.contains("(Native)") {
+        return Ok(None);
+    }
+
+    Ok(parse_class_id(&class_id, name, vendor))
+}
+
+fn parse_class_id(
+    class_id: &str,
+    name: String,
+    vendor: Option<String>,
+) -> Option<StudioOnePluginRef> {
+    let hex: String = class_id
+        .trim_matches(['{', '}'])
+        .chars()
+        .filter(|c| *c != '-')
+        .collect();
+
+    if hex.len() == 32 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        let hex = hex.to_uppercase();
+
+        if VST2_PREFIXES.iter().any(|p| hex.starts_with(p)) {
+            return Some(StudioOnePluginRef::Vst {
+                id: u32::from_str_radix(&hex[8..16], 16).ok(),
+                name,
+                vendor,
+            });
+        }
+
+        let mut fields = [0i32; 4];
+        for (i, f) in fields.iter_mut().enumerate() {
+            *f = u32::from_str_radix(&hex[i * 8..i * 8 + 8], 16).ok()? as i32;
+        }
+
+        return Some(StudioOnePluginRef::Vst3 {
+            fields: Some(fields),
+            name,
+            vendor,
+        });
+    }
+
+    // aufx:dely:appl
+    let codes: Vec<u32> = class_id
+        .split([':', '/', ' ', '-'])
+        .filter(|c| c.len() == 4 && c.is_ascii())
+        .map(|c| u32::from_be_bytes(c.as_bytes().try_into().unwrap_or_default()))
+        .collect();
+
+    match codes[..] {
+        [au_type, au_subtype, manufacturer] => Some(StudioOnePluginRef::Au {
+            id: Some(AudioUnitId {
+                au_type,
+                au_subtype,
+                manufacturer,
+            }),
+            name,
+            vendor,
+        }),
+        _ => None,
+    }
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(b) = s
+                .get(i + 1..i + 3)
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+            {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&out).to_string()
+}
+
+fn percent_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{b:02X}"));
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    const MEDIA_POOL_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<MediaPool>
+  <Attributes x:id="rootFolder" name="Root">
+    <AudioClip mediaType="Audio" useCount="1">
+      <Url x:id="url" url="file:///Users/a/Samples/Snare%20Top.wav"/>
+    </AudioClip>
+    <AudioClip mediaType="Audio" useCount="2">
+      <Url x:id="url" url="file:///C:/Songs/kick.wav"/>
+    </AudioClip>
+  </Attributes>
+</MediaPool>"#;
+
+    const MIXER_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<AudioMixer>
+  <Attributes x:id="classInfo" classID="{72C4DB71-7A4D-459A-B97E-51745D84B39D}" name="Pro-Q 3" vendor="FabFilter"/>
+  <Attributes x:id="classInfo" classID="{56535453-5A33-5250-7365-7272756D0000}" name="Serum"/>
+  <Attributes x:id="classInfo" classID="aufx:dely:appl" name="AUDelay" vendor="Apple"/>
+  <Attributes x:id="classInfo" classID="{D1AD6F8C-D49A-4F6A-B1F0-9B1E4C0A6C59}" name="Pro EQ" vendor="PreSonus"/>
+</AudioMixer>"#;
+
+    fn song() -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(vec![]));
+        for (name, data) in [
+            (SONG, "<Song/>"),
+            (MEDIA_POOL, MEDIA_POOL_XML),
+            ("Devices/audiomixer.xml", MIXER_XML),
+        ] {
+            writer
+                .start_file(name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(data.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_song() {
+        let song = Song::parse(song()).unwrap();
+
+        let urls = song.media_urls().unwrap();
+        assert_eq!(
+            urls,
+            vec![
+                "file:///Users/a/Samples/Snare%20Top.wav",
+                "file:///C:/Songs/kick.wav"
+            ]
+        );
+        assert_eq!(url_to_path(&urls[0]), "/Users/a/Samples/Snare Top.wav");
+        assert_eq!(url_to_path(&urls[1]), "C:/Songs/kick.wav");
+
+        assert_eq!(
+            song.plugins().unwrap(),
+            vec![
+                StudioOnePluginRef::Vst3 {
+                    fields: Some([
+                        0x72C4DB71u32 as i32,
+                        0x7A4D459Au32 as i32,
+                        0xB97E5174u32 as i32,
+                        0x5D84B39Du32 as i32
+                    ]),
+                    name: "Pro-Q 3".into(),
+                    vendor: Some("FabFilter".into()),
+                },
+                StudioOnePluginRef::Vst {
+                    id: Some(0x5A335250),
+                    name: "Serum".into(),
+                    vendor: None,
+                },
+                StudioOnePluginRef::Au {
+                    id: Some(AudioUnitId {
+                        au_type: u32::from_be_bytes(*b"aufx"),
+                        au_subtype: u32::from_be_bytes(*b"dely"),
+                        manufacturer: u32::from_be_bytes(*b"appl"),
+                    }),
+                    name: "AUDelay".into(),
+                    vendor: Some("Apple".into()),
+                },
+            ]
+        );
+
+        assert!(Song::parse(b"<Song/>".to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_rewrite() {
+        let song = Song::parse(song()).unwrap();
+
+        let out = song
+            .rewrite_media_urls(|url| {
+                url.contains("Snare")
+                    .then(|| media_dir_url("Media", "Snare Top.wav"))
+            })
+            .unwrap();
+        let rewritten = Song::parse(out).unwrap();
+
+        assert_eq!(
+            rewritten.media_urls().unwrap(),
+            vec!["Media/Snare%20Top.wav", "file:///C:/Songs/kick.wav"]
+        );
+        assert_eq!(url_to_path("Media/Snare%20Top.wav"), "Media/Snare Top.wav");
+        assert_eq!(rewritten.plugins().unwrap(), song.plugins().unwrap());
+    }
+}
@@ -5,8 +5,9 @@ use std::{
 
 use crate::{
     daw::{
-        ableton::{self, AbletonPluginRef, ScannedAbletonPlugin},
+        ableton::{self, AbletonPluginRef, AbletonVersion, ScannedAbletonPlugin},
         macos::{self, AudioUnitId},
+        plugin::PluginType,
     },
     misc::{path_to_str, Result, TempoError},
     structure::{get_client_dir_path, get_client_shared_db_path, iter_clients},
@@ -22,7 +23,7 @@ mod schema {
 
     /// Creates the tables for the shared info db.
     /// This is stored as `shared.sqlite` in the data directory and is copied into Tempo folders.
-    /// For now this database only contains info about plugins.
+    /// For now this database only contains info about plugins and installed versions of Live.
     pub fn setup_shared_schema(con: &Connection) -> Result<()> {
         con.execute_batch(
             r#"
@@ -72,6 +73,17 @@ mod schema {
                 UNIQUE(type, subtype, manufacturer, name, vendor)
             );
 
+            -- versions of Live installed by this client
+            -- databases copied into folders by older versions of Tempo won't have this table
+
+            CREATE TABLE IF NOT EXISTS ableton_versions (
+                major INTEGER NOT NULL,
+                minor INTEGER NOT NULL,
+                patch INTEGER NOT NULL,
+
+                UNIQUE(major, minor, patch)
+            );
+
             -- we need to avoid creation of any temporary files
             PRAGMA journal_mode = DELETE;
             PRAGMA temp_store = MEMORY;
@@ -128,6 +140,7 @@ pub struct SharedDb {
 pub fn scan_plugins(db: &Path, client_ulid: &str) -> Result<()> {
     let ableton_plugins = ableton::scan_plugin_db()?;
     let audio_units = macos::scan_audio_units()?;
+    let ableton_versions = ableton::scan_installed_versions()?;
 
     if db.exists() {
         info!("removing old db at {}", path_to_str(db));
@@ -207,6 +220,24 @@ pub fn scan_plugins(db: &Path, client_ulid: &str) -> Result<()> {
         }
     }
 
+    if !ableton_versions.is_empty() {
+        let mut ableton_version_stmt = con.prepare(
+            "INSERT OR IGNORE INTO ableton_versions (major, minor, patch) VALUES (?1, ?2, ?3)",
+        )?;
+
+        for AbletonVersion {
+            major,
+            minor,
+            patch,
+        } in ableton_versions.into_iter()
+        {
+            match ableton_version_stmt.execute(params![major, minor, patch]) {
+                Ok(_) => (),
+                Err(e) => error!("error while inserting ableton version row: {e}"),
+            }
+        }
+    }
+
     Ok(())
 }
 
@@ -235,6 +266,46 @@ impl SharedDb {
             .query_row("SELECT ulid FROM info", [], |row| row.get(0))?)
     }
 
+    /// Returns the versions of Live this client has installed, most recent first.
+    /// `None` if this database was created by a version of Tempo which didn't scan for Live versions.
+    pub fn get_ableton_versions(&self) -> Result<Option<Vec<AbletonVersion>>> {
+        let has_table: bool = self.con.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'ableton_versions')",
+            [],
+            |row| row.get(0),
+        )?;
+
+        if !has_table {
+            return Ok(None);
+        }
+
+        let mut stmt = self.con.prepare(
+            "SELECT major, minor, patch FROM ableton_versions ORDER BY major DESC, minor DESC, patch DESC",
+        )?;
+
+        let versions = stmt
+            .query_map([], |row| {
+                Ok(AbletonVersion {
+                    major: row.get(0)?,
+                    minor: row.get(1)?,
+                    patch: row.get(2)?,
+                })
+            })?
+            .filter_map(|res| match res {
+                Ok(v) => Some(v),
+                Err(e) => {
+                    error!(
+                        "get_ableton_versions(): error while reading version in {}: {e}",
+                        path_to_str(&self.db)
+                    );
+                    None
+                }
+            })
+            .collect();
+
+        Ok(Some(versions))
+    }
+
     /// Copies the `SharedDb` into the `clients` folder of a Tempo folder.
     /// This database will be named `info.sqlite` within the client's folder.
     /// No verification is done to see if there's an existing database.
@@ -262,6 +333,33 @@ impl SharedDb {
         }
     }
 
+    /// Looks up a plugin by name, for daws that don't store plugin ids we can match.
+    /// Names are compared case insensitively. CLAP plugins aren't in the db yet, so they're never found.
+    pub fn get_plugin_by_name(
+        &self,
+        plugin_type: &PluginType,
+        name: &str,
+    ) -> Result<Option<PluginNameVendor>> {
+        let table = match plugin_type {
+            PluginType::Vst => "ableton_vst",
+            PluginType::Vst3 => "ableton_vst3",
+            PluginType::Au => "audio_units",
+            PluginType::Clap => return Ok(None),
+        };
+
+        let mut stmt = self.con.prepare(&format!(
+            "SELECT name, vendor FROM {table} WHERE name = ?1 COLLATE NOCASE LIMIT 1"
+        ))?;
+        let mut rows = stmt.query_map(params![name], |row| {
+            Ok(PluginNameVendor {
+                name: row.get(0)?,
+                vendor: row.get(1)?,
+            })
+        })?;
+
+        Ok(rows.next().transpose()?)
+    }
+
     /// `name` optimally should be provided, it's possible that vsts have the same id
     /// if multiple rows are found, `name` will be used to try to identify the desired plugin
     fn get_ableton_vst(&self, id: u32, _name: Option<&str>) -> Result<Option<PluginNameVendor>> {
@@ -418,6 +516,34 @@ pub fn iter_shared_db(folder: &Path) -> Result<impl Iterator<Item = (String, Sha
     }))
 }
 
+/// Returns the usernames of clients in a folder who don't have a version of Live installed that can open a set saved with `version`.
+/// Clients whose databases don't list any versions of Live are skipped, since we can't tell.
+pub fn get_clients_missing_ableton_version(
+    folder: &Path,
+    version: &AbletonVersion,
+) -> Result<Vec<String>> {
+    let mut missing: Vec<String> = vec![];
+
+    for (username, db) in iter_shared_db(folder)? {
+        match db.get_ableton_versions() {
+            Ok(Some(installed)) => {
+                if !ableton::can_open_with_any(&installed, version) {
+                    missing.push(username);
+                }
+            }
+            Ok(None) => (),
+            Err(e) => error!(
+                "get_clients_missing_ableton_version(): failed to read versions for {username} in {}: {e}",
+                path_to_str(folder)
+            ),
+        }
+    }
+
+    missing.sort();
+
+    Ok(missing)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{misc::new_ulid, tests::get_temp_dir};
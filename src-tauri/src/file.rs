@@ -1,24 +1,34 @@
 pub mod audio;
+pub mod library;
+pub mod midi;
 
 use std::{
+    collections::{HashMap, HashSet},
     fs,
-    path::{Path, PathBuf},
+    io::{self, Read, Write},
+    path::{Component, Path, PathBuf},
 };
 
-use log::{error, info};
+use log::{error, info, warn};
+use sha2::{Digest, Sha256};
 
 use crate::{
-    daw::{ableton::add_ableton_project, project::ProjectType},
+    daw::{ableton::is_live_project, project::ProjectType, reader::get_reader},
     misc::{
-        extract_file_extension, get_filename, get_unix_timestamp, hash_file, path_to_str, Result,
+        extract_file_extension, get_filename, get_unix_timestamp, new_ulid, path_to_str, Result,
         TempoError,
     },
-    shared::AttachmentType,
-    structure::{expect_valid_folder, get_file_dir_path, get_file_meta_path, get_file_path},
-    types::{AudioType, FileInfo, FileMeta, FileType},
+    pool::map_bounded,
+    shared::{AttachmentType, FileErr, Relink},
+    structure::{
+        expect_valid_folder, get_client_staging_path, get_file_dir_path, get_file_meta_path,
+        get_file_path,
+    },
+    types::{AudioMeta, AudioType, FileInfo, FileMeta, FileType, MidiData},
 };
 
-use audio::{is_ableton_drmed_aif, is_max_for_live_patch};
+use audio::{is_ableton_drmed_aif, is_ableton_drmed_aif_header, is_max_for_live_patch};
+use midi::{is_midi_file, is_midi_header};
 
 impl FileType {
     pub fn get(path: &Path) -> Result<Self> {
@@ -33,6 +43,11 @@ impl FileType {
             return Ok(FileType::Project(project_type));
         }
 
+        // infer also reports MIDI files as audio
+        if is_midi_file(path)? {
+            return Ok(FileType::Midi);
+        }
+
         if let Some(audio_type) = AudioType::get(path)? {
             return Ok(FileType::Audio(audio_type));
         }
@@ -51,9 +66,13 @@ impl FileType {
 
 impl AttachmentType {
     pub fn get(path: &Path) -> Result<Self> {
+        if is_live_project(path) {
+            return Ok(Self::LiveProject);
+        }
+
         if !path.is_file() {
             return Err(TempoError::File(format!(
-                "Please attach a file. {} is not a file",
+                "Please attach a file or Live Project. {} is not a file",
                 path_to_str(path)
             )));
         }
@@ -62,6 +81,10 @@ impl AttachmentType {
             return Ok(Self::Project(project_type));
         }
 
+        if is_midi_file(path)? {
+            return Ok(Self::Midi);
+        }
+
         if let Some(audio_type) = AudioType::get(path)? {
             return Ok(Self::Audio(audio_type));
         }
@@ -72,10 +95,7 @@ impl AttachmentType {
             Err(e) => error!("FileType::get_attachment(): error while checking for ableton drm: {e}")
         }
 
-        Err(TempoError::File(format!(
-            "Unknown or unsupported file attachment: {}",
-            path_to_str(path)
-        )))
+        Ok(Self::File)
     }
 }
 
@@ -95,14 +115,28 @@ impl FileInfo {
 
 /// For when users directly add files to folders.
 pub fn add_file(folder: &Path, username: &str, file: &Path) -> Result<String> {
+    stage_new_file(folder, username, file)?.commit(folder, username)
+}
+
+/// Stages a file for `add_file()`.
+pub fn stage_new_file(folder: &Path, username: &str, file: &Path) -> Result<Staged> {
     Ok(match FileType::get(file)? {
-        FileType::Project(t) => match t {
-            ProjectType::Ableton => add_ableton_project(folder, username, file)?,
-        },
-        FileType::Audio(audio_type) => {
-            add_file_with_meta(folder, username, file, FileMeta::Audio(audio_type))?
+        FileType::Project(t) => {
+            Staged::Project(get_reader(&t).stage_project(folder, username, file, &[])?)
         }
-        FileType::MaxForLive => add_file_with_meta(folder, username, file, FileMeta::MaxForLive)?,
+        FileType::Audio(audio_type) => stage_file_with_meta(
+            folder,
+            username,
+            file,
+            FileMeta::Audio(AudioMeta::read(file, audio_type)),
+        )?,
+        FileType::Midi => stage_file_with_meta(
+            folder,
+            username,
+            file,
+            FileMeta::Midi(MidiData::read(file)?),
+        )?,
+        FileType::MaxForLive => stage_file_with_meta(folder, username, file, FileMeta::MaxForLive)?,
         FileType::Other => {
             return Err(TempoError::File(
                 "Tried to add unknown/unsupported file type".into(),
@@ -111,61 +145,419 @@ pub fn add_file(folder: &Path, username: &str, file: &Path) -> Result<String> {
     })
 }
 
+/// Stages a project to be added to a shared folder.
+/// Missing files referenced by the project are replaced according to `relinks`.
+pub fn stage_project_file(
+    folder: &Path,
+    username: &str,
+    file: &Path,
+    relinks: &[Relink],
+) -> Result<StagedProject> {
+    match ProjectType::get(file)? {
+        Some(t) => get_reader(&t).stage_project(folder, username, file, relinks),
+        None => Err(TempoError::File(format!(
+            "Tried to add {} as a project, but it is not a project file",
+            path_to_str(file)
+        ))),
+    }
+}
+
+/// Stages an audio file to be added to a shared folder, errors if the file isn't audio.
+pub fn stage_audio_file(folder: &Path, username: &str, file: &Path) -> Result<Staged> {
+    match FileType::get(file)? {
+        FileType::Audio(audio_type) => stage_file_with_meta(
+            folder,
+            username,
+            file,
+            FileMeta::Audio(AudioMeta::read(file, audio_type)),
+        ),
+        t => Err(TempoError::File(format!(
+            "Expected {} to be an audio file, found {:#?} instead",
+            path_to_str(file),
+            t
+        ))),
+    }
+}
+
+// largest file which can be sent as a generic file attachment
+pub const MAX_FILE_ATTACHMENT_SIZE: u64 = 200 * 1024 * 1024;
+
+/// Finds the MIME type of a file from its header.
+/// `None` if `infer` doesn't recognize the file.
+pub fn get_mime_type(path: &Path) -> Result<Option<String>> {
+    Ok(infer::get_from_path(path)
+        .map_err(|e| TempoError::File(format!("Failed to read file: {e}")))?
+        .map(|t| t.mime_type().to_string()))
+}
+
 /// Allows copying of files of unknown types.
 pub fn add_referenced_file(folder: &Path, username: &str, file: &Path) -> Result<String> {
+    stage_referenced_file(folder, username, file)?.commit(folder, username)
+}
+
+/// Stages a file for `add_referenced_file()`.
+pub fn stage_referenced_file(folder: &Path, username: &str, file: &Path) -> Result<Staged> {
     Ok(match FileType::get(file)? {
-        FileType::Project(t) => match t {
-            ProjectType::Ableton => add_ableton_project(folder, username, file)?,
-        },
-        FileType::Audio(audio_type) => {
-            add_file_with_meta(folder, username, file, FileMeta::Audio(audio_type))?
-        }
-        FileType::MaxForLive => add_file_with_meta(folder, username, file, FileMeta::MaxForLive)?,
-        FileType::Other => add_file_with_meta(folder, username, file, FileMeta::Other)?,
+        FileType::Project(t) => {
+            Staged::Project(get_reader(&t).stage_project(folder, username, file, &[])?)
+        }
+        FileType::Audio(audio_type) => stage_file_with_meta(
+            folder,
+            username,
+            file,
+            FileMeta::Audio(AudioMeta::read(file, audio_type)),
+        )?,
+        FileType::Midi => stage_file_with_meta(folder, username, file, referenced_midi_meta(file))?,
+        FileType::MaxForLive => stage_file_with_meta(folder, username, file, FileMeta::MaxForLive)?,
+        FileType::Other => stage_file_with_meta(folder, username, file, FileMeta::Other)?,
     })
 }
 
-/// Adds a file to a shared folder.
-/// Returns the hash of the added file.
-pub fn add_file_with_filename(
+/// Metadata for a MIDI file referenced by a project.
+/// Files that can't be parsed are still copied, they just won't have MIDI metadata.
+fn referenced_midi_meta(file: &Path) -> FileMeta {
+    match MidiData::read(file) {
+        Ok(m) => FileMeta::Midi(m),
+        Err(e) => {
+            warn!(
+                "referenced_midi_meta(): failed to read {}, adding as an unknown file: {e}",
+                path_to_str(file)
+            );
+            FileMeta::Other
+        }
+    }
+}
+
+/// Stages a project whose contents were rewritten, eg to point at media in the folder.
+/// The project keeps the filename of `project`.
+pub fn stage_project_data(
     folder: &Path,
     username: &str,
-    file: &Path,
-    filename: &str,
+    project: &Path,
+    data: &[u8],
+    refs: StagedRefs,
     file_meta: FileMeta,
-) -> Result<String> {
-    info!("adding file {filename}");
+) -> Result<StagedProject> {
+    let filename = get_filename(project)?;
 
-    if !file.exists() || !file.is_file() {
-        return Err(TempoError::File(format!(
-            "{} does not exist or is not a file",
+    let out = get_unique_filename(&std::env::temp_dir(), &format!("[tempo output] {filename}"))?;
+    fs::write(&out, data)?;
+
+    info!("wrote output project: {}", path_to_str(&out));
+
+    let res = StagedProject::new(folder, username, project, &out, refs, file_meta);
+
+    if let Err(e) = fs::remove_file(&out) {
+        error!(
+            "stage_project_data(): failed to remove {}: {e}",
+            path_to_str(&out)
+        );
+    }
+
+    res
+}
+
+// number of bytes at the start of a staged file kept around for detecting its type
+const STAGED_HEADER_LEN: usize = 8192;
+
+/// A file which has been copied into a folder's staging directory but hasn't been added to the folder yet.
+/// The staged copy is removed when this is dropped without being committed.
+#[derive(Debug)]
+pub struct StagedFile {
+    pub sha256: String,
+    path: PathBuf,
+    // first bytes of the file
+    header: Vec<u8>,
+}
+
+/// Copies a file into the staging directory of a folder, hashing it while it's copied.
+/// `file` is only read once.
+pub fn stage_file(folder: &Path, username: &str, file: &Path) -> Result<StagedFile> {
+    let staging_dir = get_client_staging_path(folder, username);
+    fs::create_dir_all(&staging_dir)?;
+
+    let path = staging_dir.join(new_ulid());
+
+    let mut src = fs::File::open(file).map_err(|e| {
+        TempoError::File(format!(
+            "Failed to open file {} to copy it into folder, error: {e}",
             path_to_str(file)
-        )));
+        ))
+    })?;
+    let mut dest = fs::File::create(&path)?;
+
+    let mut hasher = Sha256::new();
+    let mut header: Vec<u8> = Vec::with_capacity(STAGED_HEADER_LEN);
+    let mut buffer = vec![0u8; 64 * 1024];
+
+    let mut copy = || -> Result<()> {
+        loop {
+            let count = src.read(&mut buffer)?;
+            if count == 0 {
+                break;
+            }
+
+            let chunk = &buffer[..count];
+            hasher.update(chunk);
+            if header.len() < STAGED_HEADER_LEN {
+                let take = (STAGED_HEADER_LEN - header.len()).min(count);
+                header.extend_from_slice(&chunk[..take]);
+            }
+            dest.write_all(chunk)?;
+        }
+        dest.flush()?;
+        Ok(())
+    };
+
+    if let Err(e) = copy() {
+        if let Err(e) = fs::remove_file(&path) {
+            error!("stage_file(): failed to remove staged file after failed copy: {e}");
+        }
+        return Err(e);
     }
 
-    expect_valid_folder(folder)?;
+    Ok(StagedFile {
+        sha256: format!("{:x}", hasher.finalize()),
+        path,
+        header,
+    })
+}
 
-    let file_sha256 = hash_file(file)?;
-    let file_dir_path = get_file_dir_path(folder, &file_sha256);
-    let file_path = get_file_path(folder, &file_sha256);
-    let file_meta_path = get_file_meta_path(folder, &file_sha256);
+impl StagedFile {
+    /// Gets metadata for a staged file referenced by a project, using its filename and header.
+    /// `None` for project files, these need to be parsed and added with `add_referenced_file()`.
+    pub fn referenced_file_meta(&self, filename: &str) -> Result<Option<FileMeta>> {
+        let (_, ext) = extract_file_extension(filename);
+        if ProjectType::from_extension(ext.as_deref().unwrap_or("")).is_some() {
+            return Ok(None);
+        }
 
-    if !file_dir_path.exists() {
-        fs::create_dir_all(&file_dir_path)?;
-        fs::copy(file, &file_path)?;
+        if is_midi_header(&self.header) {
+            return Ok(Some(referenced_midi_meta(&self.path)));
+        }
 
-        save_new_file_info(
-            &FileInfo {
-                filename: filename.to_string(),
-                added_by: username.into(),
-                timestamp: get_unix_timestamp()?,
-                meta: file_meta,
-            },
-            &file_meta_path,
-        )?;
+        if let Some(audio_type) = AudioType::from_header(&self.header) {
+            return Ok(Some(FileMeta::Audio(AudioMeta::read(
+                &self.path, audio_type,
+            ))));
+        }
+
+        if is_ableton_drmed_aif_header(filename, &self.header) {
+            return Ok(Some(FileMeta::Audio(AudioMeta {
+                audio_type: AudioType::Other,
+                info: None,
+            })));
+        }
+
+        if is_max_for_live_patch(Path::new(filename))? {
+            return Ok(Some(FileMeta::MaxForLive));
+        }
+
+        Ok(Some(FileMeta::Other))
+    }
+
+    /// Moves the staged file into place under its hash and saves its `FileInfo`.
+    /// If the folder already contains this file the staged copy is removed.
+    /// Returns the hash of the file.
+    pub fn commit(
+        self,
+        folder: &Path,
+        username: &str,
+        filename: &str,
+        file_meta: FileMeta,
+    ) -> Result<String> {
+        let file_dir_path = get_file_dir_path(folder, &self.sha256);
+
+        if !file_dir_path.exists() {
+            fs::create_dir_all(&file_dir_path)?;
+            fs::rename(&self.path, get_file_path(folder, &self.sha256))?;
+
+            save_new_file_info(
+                &FileInfo {
+                    filename: filename.to_string(),
+                    added_by: username.into(),
+                    timestamp: get_unix_timestamp()?,
+                    meta: file_meta,
+                },
+                &get_file_meta_path(folder, &self.sha256),
+            )?;
+        }
+
+        Ok(self.sha256.clone())
+    }
+}
+
+impl Drop for StagedFile {
+    fn drop(&mut self) {
+        if self.path.exists() {
+            if let Err(e) = fs::remove_file(&self.path) {
+                error!(
+                    "failed to remove staged file {}: {e}",
+                    path_to_str(&self.path)
+                );
+            }
+        }
+    }
+}
+
+/// Files referenced by a project which have been copied into the staging directory.
+pub struct StagedRefs {
+    // { path : hash }
+    found: HashMap<PathBuf, String>,
+    // { hash : (filename to use in the copied project's files directory, staged copy, original path) }
+    known: HashMap<String, (String, StagedFile, PathBuf)>,
+}
+
+/// Stages all files referenced by a project in parallel, errors are reported in the order of `files`.
+/// Identical files at different paths share one copy and filename.
+/// Filenames are assigned in the order of `files`.
+pub fn stage_referenced_files(
+    folder: &Path,
+    username: &str,
+    files: Vec<PathBuf>,
+) -> Result<StagedRefs> {
+    let staged = map_bounded(&files, |file| stage_file(folder, username, file));
+
+    let mut found: HashMap<PathBuf, String> = HashMap::new();
+    let mut known: HashMap<String, (String, StagedFile, PathBuf)> = HashMap::new();
+
+    // used filenames
+    let mut used: HashSet<String> = HashSet::new();
+
+    for (file, staged) in files.into_iter().zip(staged) {
+        let staged = staged?;
+        let hash = staged.sha256.clone();
+        info!("hash: {hash}");
+
+        // the extra staged copy of an identical file is dropped
+        if !known.contains_key(&hash) {
+            let filename = get_unique_ref_filename(&used, get_filename(&file)?);
+            info!("filename: {filename}");
+            used.insert(filename.clone());
+            known.insert(hash.clone(), (filename, staged, file.clone()));
+        }
+
+        found.insert(file, hash);
+    }
+
+    Ok(StagedRefs { found, known })
+}
+
+impl StagedRefs {
+    /// Hash of a staged file.
+    pub fn hash(&self, file: &Path) -> Option<&String> {
+        self.found.get(file)
+    }
+
+    /// Filename assigned to a staged file.
+    pub fn filename(&self, hash: &str) -> Option<&str> {
+        self.known.get(hash).map(|(f, _, _)| f.as_str())
+    }
+
+    /// Filenames of all staged files.
+    /// Returns `{ hash : filename }`.
+    pub fn refs(&self) -> HashMap<String, String> {
+        self.known
+            .iter()
+            .map(|(hash, (filename, _, _))| (hash.clone(), filename.clone()))
+            .collect()
+    }
+
+    /// Moves all staged files into place in the folder.
+    /// Referenced project files are parsed and added with `add_referenced_file()`.
+    pub fn commit(self, folder: &Path, username: &str, project: &Path) -> Result<()> {
+        for (orig_hash, (filename_to_use, staged, file_path)) in self.known.into_iter() {
+            let added_hash = match staged.referenced_file_meta(&filename_to_use)? {
+                Some(meta) => staged.commit(folder, username, &get_filename(&file_path)?, meta)?,
+                None => {
+                    drop(staged);
+                    add_referenced_file(folder, username, &file_path)?
+                }
+            };
+
+            if added_hash != orig_hash {
+                return Err(TempoError::Folder(format!(
+                    "A file referenced in the project file {} has changed while Tempo was copying it. Please save the project and try sending it again. File that changed: {}",
+                    project.to_string_lossy(),
+                    file_path.to_string_lossy()
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A project which has been rewritten and staged along with the files it references.
+/// Nothing is added to the folder until it's committed, the staged copies are removed if it's dropped.
+pub struct StagedProject {
+    pub sha256: String,
+    project: PathBuf,
+    file: StagedFile,
+    refs: StagedRefs,
+    meta: FileMeta,
+}
+
+impl StagedProject {
+    /// Stages the rewritten project `out`, it keeps the filename of `project`.
+    pub fn new(
+        folder: &Path,
+        username: &str,
+        project: &Path,
+        out: &Path,
+        refs: StagedRefs,
+        file_meta: FileMeta,
+    ) -> Result<Self> {
+        let file = stage_file(folder, username, out)?;
+        Ok(StagedProject {
+            sha256: file.sha256.clone(),
+            project: project.to_path_buf(),
+            file,
+            refs,
+            meta: file_meta,
+        })
+    }
+
+    /// Adds the referenced files and then the project to the folder.
+    /// Returns the hash of the project.
+    pub fn commit(self, folder: &Path, username: &str) -> Result<String> {
+        self.refs.commit(folder, username, &self.project)?;
+        self.file
+            .commit(folder, username, &get_filename(&self.project)?, self.meta)
     }
+}
 
-    Ok(file_sha256)
+/// A file or project which has been staged but not added to a folder yet.
+pub enum Staged {
+    File {
+        file: StagedFile,
+        filename: String,
+        meta: FileMeta,
+    },
+    Project(StagedProject),
+}
+
+impl Staged {
+    /// Hash the file will have in the folder.
+    pub fn sha256(&self) -> &String {
+        match self {
+            Staged::File { file, .. } => &file.sha256,
+            Staged::Project(p) => &p.sha256,
+        }
+    }
+
+    /// Adds the staged file to the folder.
+    /// Returns its hash.
+    pub fn commit(self, folder: &Path, username: &str) -> Result<String> {
+        match self {
+            Staged::File {
+                file,
+                filename,
+                meta,
+            } => file.commit(folder, username, &filename, meta),
+            Staged::Project(p) => p.commit(folder, username),
+        }
+    }
 }
 
 /// Adds a file to a shared folder. Uses the filename of the provided file.
@@ -176,8 +568,33 @@ pub fn add_file_with_meta(
     file: &Path,
     file_meta: FileMeta,
 ) -> Result<String> {
+    stage_file_with_meta(folder, username, file, file_meta)?.commit(folder, username)
+}
+
+/// Stages a file for `add_file_with_meta()`.
+pub fn stage_file_with_meta(
+    folder: &Path,
+    username: &str,
+    file: &Path,
+    file_meta: FileMeta,
+) -> Result<Staged> {
     let filename = get_filename(file)?;
-    add_file_with_filename(folder, username, file, &filename, file_meta)
+    info!("staging file {filename}");
+
+    if !file.exists() || !file.is_file() {
+        return Err(TempoError::File(format!(
+            "{} does not exist or is not a file",
+            path_to_str(file)
+        )));
+    }
+
+    expect_valid_folder(folder)?;
+
+    Ok(Staged::File {
+        file: stage_file(folder, username, file)?,
+        filename,
+        meta: file_meta,
+    })
 }
 
 fn save_new_file_info(info: &FileInfo, path: &Path) -> Result<()> {
@@ -203,7 +620,10 @@ pub fn get_unique_filename(dir: &Path, filename: &str) -> Result<PathBuf> {
 
         let mut count = 1usize;
         loop {
-            curr = dir.join(format!("{base}-{count}{}", ext.as_deref().unwrap_or("")));
+            curr = dir.join(format!(
+                "{base}-{count}{}",
+                ext.as_ref().map(|e| format!(".{e}")).unwrap_or_default()
+            ));
 
             if !curr.exists() {
                 break Ok(curr);
@@ -214,6 +634,28 @@ pub fn get_unique_filename(dir: &Path, filename: &str) -> Result<PathBuf> {
     }
 }
 
+/// Returns a filename for a referenced file that isn't in `used`.
+pub fn get_unique_ref_filename(used: &HashSet<String>, filename: String) -> String {
+    if !used.contains(&filename) {
+        return filename;
+    }
+
+    let (no_ext, ext) = extract_file_extension(&filename);
+    let mut dup: usize = 1;
+    let mut unique = filename;
+    while used.contains(&unique) {
+        unique = format!("{no_ext}-{dup}{}", {
+            if let Some(e) = ext.as_ref() {
+                format!(".{e}")
+            } else {
+                "".into()
+            }
+        });
+        dup += 1;
+    }
+    unique
+}
+
 pub fn get_unique_dir(parent_dir: &Path, dir_name: &str) -> Result<PathBuf> {
     let mut curr = parent_dir.join(dir_name);
 
@@ -235,10 +677,61 @@ pub fn get_unique_dir(parent_dir: &Path, dir_name: &str) -> Result<PathBuf> {
     }
 }
 
-// will be used for copying attachments
+/// Whether a filename stays inside of the directory it's joined onto.
+/// Filenames come from other users' metadata, so `..`, roots and prefixes are rejected.
+fn is_safe_rel_filename(filename: &str) -> bool {
+    Path::new(filename).components().all(|c| {
+        !matches!(
+            c,
+            Component::ParentDir | Component::RootDir | Component::Prefix(_)
+        )
+    })
+}
+
+/// Copies files out of a folder into `dir`, creating `dir` if needed.
+/// `files` is `{ hash : filename to copy to }`, filenames are relative to `dir`.
+/// Filenames which would end up outside of `dir` aren't copied and are reported as failures.
+/// Files are copied in parallel, failures are returned sorted by filename.
+pub fn copy_files_into<'a>(
+    folder: &Path,
+    files: impl IntoIterator<Item = (&'a String, &'a String)>,
+    dir: &Path,
+) -> Result<Vec<FileErr>> {
+    fs::create_dir_all(dir)?;
+
+    // sorted so failures are reported in a stable order
+    let mut files: Vec<(&String, &String)> = files.into_iter().collect();
+    files.sort_by(|a, b| a.1.cmp(b.1));
+
+    let results = map_bounded(&files, |(h, filename)| {
+        if !is_safe_rel_filename(filename) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Filename points outside of the project directory",
+            ));
+        }
+
+        // filenames can include subdirectories
+        let dest = dir.join(filename);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(get_file_path(folder, h), dest)
+    });
+
+    Ok(files
+        .iter()
+        .zip(results)
+        .filter_map(|((_, filename), r)| {
+            r.err().map(|e| FileErr {
+                filename: filename.to_string(),
+                err: e.to_string(),
+            })
+        })
+        .collect())
+}
 
 /// Copies a single file out of a Tempo folder.
-#[allow(dead_code)]
 pub fn copy_file(folder: &Path, hash: &str, dest_dir: &Path, filename: &str) -> Result<PathBuf> {
     let dest = get_unique_filename(dest_dir, filename)?;
     let src = get_file_path(folder, hash);
@@ -253,3 +746,160 @@ pub fn copy_file(folder: &Path, hash: &str, dest_dir: &Path, filename: &str) ->
 
     Ok(dest)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        misc::hash_file, structure::create_tempo_folder, tests::get_temp_dir, types::AudioInfo,
+    };
+    use serde::Deserialize;
+
+    #[test]
+    fn test_stage_file() {
+        let folder = get_temp_dir("stage_folder");
+        create_tempo_folder(&folder).unwrap();
+
+        let src = get_temp_dir("stage_src").join("kick.wav");
+        fs::write(&src, vec![7u8; STAGED_HEADER_LEN * 3 + 5]).unwrap();
+
+        let staged = stage_file(&folder, "user", &src).unwrap();
+        assert_eq!(staged.sha256, hash_file(&src).unwrap());
+        assert_eq!(staged.header.len(), STAGED_HEADER_LEN);
+
+        let hash = staged
+            .commit(&folder, "user", "kick.wav", FileMeta::Other)
+            .unwrap();
+        assert_eq!(
+            fs::read(get_file_path(&folder, &hash)).unwrap(),
+            fs::read(&src).unwrap()
+        );
+        assert_eq!(FileInfo::load(&folder, &hash).unwrap().filename, "kick.wav");
+
+        // staging the same file again just drops the staged copy
+        let staged = stage_file(&folder, "user", &src).unwrap();
+        let staged_path = staged.path.clone();
+        staged
+            .commit(&folder, "user", "kick.wav", FileMeta::Other)
+            .unwrap();
+        assert!(!staged_path.exists());
+        assert_eq!(
+            fs::read_dir(get_client_staging_path(&folder, "user"))
+                .unwrap()
+                .count(),
+            0
+        );
+    }
+    #[test]
+    fn test_copy_file() {
+        let folder = get_temp_dir("copy_file_folder");
+        create_tempo_folder(&folder).unwrap();
+
+        let src = get_temp_dir("copy_file_src").join("mix.wav");
+        fs::write(&src, b"mix").unwrap();
+        let hash = add_file_with_meta(&folder, "user", &src, FileMeta::Other).unwrap();
+
+        let dest = get_temp_dir("copy_file_dest");
+        let first = copy_file(&folder, &hash, &dest, "mix.wav").unwrap();
+        let second = copy_file(&folder, &hash, &dest, "mix.wav").unwrap();
+
+        assert_eq!(first, dest.join("mix.wav"));
+        assert_eq!(second, dest.join("mix-1.wav"));
+        assert_eq!(fs::read(second).unwrap(), b"mix");
+    }
+
+    #[test]
+    fn test_copy_files_into_rejects_unsafe_filenames() {
+        let folder = get_temp_dir("copy_into_folder");
+        create_tempo_folder(&folder).unwrap();
+
+        let src = get_temp_dir("copy_into_src").join("kick.wav");
+        fs::write(&src, b"kick").unwrap();
+        let hash = add_file_with_meta(&folder, "user", &src, FileMeta::Other).unwrap();
+
+        let base = get_temp_dir("copy_into_dest");
+        let dest = base.join("project");
+        let outside = get_temp_dir("copy_into_outside").join("kick.wav");
+
+        let filenames: Vec<String> = vec![
+            "Samples/kick.wav".into(),
+            "../kick.wav".into(),
+            "Samples/../../kick.wav".into(),
+            path_to_str(&outside).to_string(),
+        ];
+
+        let errs = copy_files_into(&folder, filenames.iter().map(|f| (&hash, f)), &dest).unwrap();
+
+        assert_eq!(fs::read(dest.join("Samples/kick.wav")).unwrap(), b"kick");
+
+        let mut rejected: Vec<&str> = errs.iter().map(|e| e.filename.as_str()).collect();
+        rejected.sort();
+        let mut expected: Vec<&str> = filenames[1..].iter().map(|f| f.as_str()).collect();
+        expected.sort();
+        assert_eq!(rejected, expected);
+
+        assert!(!base.join("kick.wav").exists());
+        assert!(!outside.exists());
+    }
+
+    #[test]
+    fn test_file_info_readable_by_old_clients() {
+        // how older versions of Tempo read file info
+        #[derive(Deserialize)]
+        #[allow(dead_code)]
+        enum OldAudioType {
+            Wav,
+            Mp3,
+            Flac,
+            Other,
+        }
+
+        #[derive(Deserialize)]
+        enum OldFileMeta {
+            Audio(OldAudioType),
+        }
+
+        #[derive(Deserialize)]
+        struct OldFileInfo {
+            meta: OldFileMeta,
+        }
+
+        let audio = AudioMeta {
+            audio_type: AudioType::Aiff,
+            info: Some(AudioInfo {
+                codec: "PCM".into(),
+                sample_rate: 48000,
+                bit_depth: Some(24),
+                channels: 2,
+                duration_secs: Some(1.5),
+            }),
+        };
+        let info = FileInfo {
+            filename: "take.aif".into(),
+            added_by: "user".into(),
+            timestamp: 0,
+            meta: FileMeta::Audio(audio.clone()),
+        };
+        let json = serde_json::to_string(&info).unwrap();
+
+        let old: OldFileInfo = serde_json::from_str(&json).unwrap();
+        assert!(matches!(old.meta, OldFileMeta::Audio(OldAudioType::Other)));
+
+        let FileMeta::Audio(meta) = serde_json::from_str::<FileInfo>(&json).unwrap().meta else {
+            panic!("expected audio");
+        };
+        assert_eq!(meta, audio);
+
+        // written by older versions
+        let FileMeta::Audio(meta) = serde_json::from_str::<FileInfo>(
+            r#"{"filename":"mix.wav","added_by":"user","timestamp":0,"meta":{"Audio":"Wav"}}"#,
+        )
+        .unwrap()
+        .meta
+        else {
+            panic!("expected audio");
+        };
+        assert_eq!(meta.audio_type, AudioType::Wav);
+        assert!(meta.info.is_none());
+    }
+}
@@ -1,51 +1,110 @@
-use std::path::Path;
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
 
 use log::warn;
 
 use crate::{
+    file::copy_file,
     misc::{extract_file_extension, get_filename, path_to_str, Result, TempoError},
-    types::AudioType,
+    types::{AudioMeta, AudioType, FileInfo, FileMeta},
 };
 
+mod decode;
+mod fft;
+pub mod header;
+pub mod loudness;
+pub mod musical;
+pub mod null_test;
+pub mod pcm;
+pub mod peaks;
+
+// number of bytes read from the start of a file to detect its format
+const DETECT_LEN: u64 = 8192;
+
 impl AudioType {
     pub fn get(path: &Path) -> Result<Option<Self>> {
-        let mime = infer::get_from_path(path)
-            .map_err(|e| TempoError::File(format!("Failed to read file: {e}")))?;
+        let mut header = vec![];
+        fs::File::open(path)?
+            .take(DETECT_LEN)
+            .read_to_end(&mut header)?;
+
+        let audio_type = Self::from_header(&header);
+        if audio_type.is_none() {
+            warn!(
+                "get_audio_type(): could not find audio in {}",
+                path_to_str(path)
+            );
+        }
+
+        Ok(audio_type)
+    }
+
+    /// Detects audio from the first bytes of a file.
+    pub fn from_header(header: &[u8]) -> Option<Self> {
+        if let Some(t) = header::detect(header) {
+            return Some(t);
+        }
+
+        // formats we can't read headers of, eg AAC and M4A
+        match infer::get(header) {
+            Some(t) if t.mime_type().starts_with("audio") => Some(AudioType::Other),
+            _ => None,
+        }
+    }
+}
 
-        match mime {
-            None => {
+impl AudioMeta {
+    /// Reads the header of an audio file of a known type.
+    /// Files with headers we can't read are still added, just without `info`.
+    pub fn read(path: &Path, audio_type: AudioType) -> Self {
+        let info = match fs::File::open(path) {
+            Ok(mut f) => header::read_audio_info(&mut f, &audio_type),
+            Err(e) => {
                 warn!(
-                    "get_audio_type(): could not find mime of {}",
+                    "AudioMeta::read(): failed to open {}: {e}",
                     path_to_str(path)
                 );
-                Ok(None)
-            }
-            Some(t) => {
-                if t.mime_type().starts_with("audio") {
-                    Ok(Some(AudioType::Other))
-                } else {
-                    warn!(
-                        "get_audio_type(): found non-audio {t} for {}",
-                        path_to_str(path)
-                    );
-                    Ok(None)
-                }
+                None
             }
+        };
+
+        if info.is_none() && audio_type != AudioType::Other {
+            warn!(
+                "AudioMeta::read(): failed to read {:?} header of {}",
+                audio_type,
+                path_to_str(path)
+            );
         }
+
+        AudioMeta { audio_type, info }
     }
 }
 
 pub fn is_ableton_drmed_aif(path: &Path) -> Result<bool> {
-    let (_, ext) = extract_file_extension(&get_filename(path)?);
-
-    if let Some(ext) = ext {
-        if ext.as_str() == "aif" {
-            // TODO actually read header
-            return Ok(true);
-        }
+    if !has_aif_extension(&get_filename(path)?) {
+        return Ok(false);
     }
 
-    Ok(false)
+    let mut header = vec![];
+    fs::File::open(path)?
+        .take(DETECT_LEN)
+        .read_to_end(&mut header)?;
+
+    Ok(header::is_encrypted_aiff(&header))
+}
+
+/// Same as `is_ableton_drmed_aif` for a file that's already been opened.
+pub fn is_ableton_drmed_aif_header(filename: &str, header: &[u8]) -> bool {
+    has_aif_extension(filename) && header::is_encrypted_aiff(header)
+}
+
+fn has_aif_extension(filename: &str) -> bool {
+    let (_, ext) = extract_file_extension(filename);
+
+    ext.is_some_and(|ext| matches!(ext.to_lowercase().as_str(), "aif" | "aiff"))
 }
 
 pub fn is_max_for_live_patch(path: &Path) -> Result<bool> {
@@ -60,16 +119,18 @@ pub fn is_max_for_live_patch(path: &Path) -> Result<bool> {
     Ok(false)
 }
 
-// pub fn copy_audio_file(folder: &Path, dest: &Path, hash: &str) -> Result<(PathBuf, FileErr)> {
-//     match FileInfo::load(folder, hash) {
-//         Ok(i) => match i.meta {
-//             FileMeta::Audio(_) => {
-
-//             },
-//             t => {
-//                 Err(TempoError::File("Failed to copy audio file, found non-audio metadata: "))
-//             }
-//         },
-//         Err(e) => Err(TempoError::File(format!("Failed to load file metadata for audio file: {e}")))
-//     }
-// }
+/// Copies an audio file out of a folder into `dest_dir` using its original filename.
+/// Returns the path of the copy.
+pub fn copy_audio_file(folder: &Path, hash: &str, dest_dir: &Path) -> Result<PathBuf> {
+    let info = FileInfo::load(folder, hash).map_err(|e| {
+        TempoError::File(format!("Failed to load file metadata for audio file: {e}"))
+    })?;
+
+    match info.meta {
+        FileMeta::Audio(_) => copy_file(folder, hash, dest_dir, &info.filename),
+        t => Err(TempoError::File(format!(
+            "Failed to copy audio file, found non-audio metadata: {:#?}",
+            t
+        ))),
+    }
+}
@@ -0,0 +1,176 @@
+// decoding compressed audio into samples with symphonia
+
+/*
+used by PcmReader for everything it can't read directly: MP3, FLAC, Ogg Vorbis, AAC/ALAC in MP4/M4A,
+and WAV/AIFF with encodings like µ-law or A-law.
+
+symphonia decodes a packet at a time, packets hold a varying number of frames depending on the codec.
+decoded samples are kept until they've been read, so callers can read any number of frames at once.
+
+the first track with a known codec is decoded, other tracks are skipped.
+packets that fail to decode are skipped too, like players do. a damaged MP3 frame shouldn't stop an analysis.
+
+Opus isn't supported by symphonia, those files can't be decoded.
+ */
+
+use std::{fs, io::ErrorKind, path::Path};
+
+use symphonia::core::{
+    audio::SampleBuffer,
+    codecs::{Decoder, DecoderOptions, CODEC_TYPE_NULL},
+    errors::Error,
+    formats::{FormatOptions, FormatReader},
+    io::MediaSourceStream,
+    meta::MetadataOptions,
+    probe::Hint,
+};
+
+use crate::misc::{extract_file_extension, get_filename, Result, TempoError};
+
+fn audio_err(e: Error) -> TempoError {
+    match e {
+        Error::Unsupported(what) => {
+            TempoError::Audio(format!("Can't decode this file, {what} isn't supported"))
+        }
+        e => TempoError::Audio(format!("Failed to decode audio: {e}")),
+    }
+}
+
+/// An audio file being decoded by symphonia.
+pub struct Decoded {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+
+    pub sample_rate: u32,
+    pub channels: u16,
+    // 0 if the container doesn't say
+    pub frames: u64,
+
+    // decoded interleaved samples which haven't been read yet
+    pending: Vec<f32>,
+    pos: usize,
+    done: bool,
+}
+
+impl Decoded {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = fs::File::open(path)?;
+        let mss = MediaSourceStream::new(Box::new(file), Default::default());
+
+        let mut hint = Hint::new();
+        if let Some(ext) = extract_file_extension(&get_filename(path)?).1 {
+            hint.with_extension(&ext);
+        }
+
+        let probed = symphonia::default::get_probe()
+            .format(
+                &hint,
+                mss,
+                &FormatOptions::default(),
+                &MetadataOptions::default(),
+            )
+            .map_err(audio_err)?;
+        let format = probed.format;
+
+        let track = format
+            .tracks()
+            .iter()
+            .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or(TempoError::Audio(
+                "Can't decode this file, it has no audio tracks".into(),
+            ))?;
+
+        let decoder = symphonia::default::get_codecs()
+            .make(&track.codec_params, &DecoderOptions::default())
+            .map_err(audio_err)?;
+
+        let params = &track.codec_params;
+        let mut decoded = Decoded {
+            track_id: track.id,
+            sample_rate: params.sample_rate.unwrap_or(0),
+            channels: params.channels.map(|c| c.count() as u16).unwrap_or(0),
+            frames: params.n_frames.unwrap_or(0),
+            format,
+            decoder,
+            pending: vec![],
+            pos: 0,
+            done: false,
+        };
+
+        // some containers only say in the first packet
+        if decoded.sample_rate == 0 || decoded.channels == 0 {
+            decoded.decode_next()?;
+        }
+
+        Ok(decoded)
+    }
+
+    /// Decodes the next packet of the track into `pending`.
+    /// Sets `done` at the end of the file.
+    fn decode_next(&mut self) -> Result<()> {
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(p) => p,
+                Err(Error::IoError(e)) if e.kind() == ErrorKind::UnexpectedEof => {
+                    self.done = true;
+                    return Ok(());
+                }
+                // chained Ogg streams, we only read the first one
+                Err(Error::ResetRequired) => {
+                    self.done = true;
+                    return Ok(());
+                }
+                Err(e) => return Err(audio_err(e)),
+            };
+
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            match self.decoder.decode(&packet) {
+                Ok(buf) => {
+                    let spec = *buf.spec();
+                    if self.sample_rate == 0 || self.channels == 0 {
+                        self.sample_rate = spec.rate;
+                        self.channels = spec.channels.count() as u16;
+                    }
+
+                    let mut samples = SampleBuffer::<f32>::new(buf.capacity() as u64, spec);
+                    samples.copy_interleaved_ref(buf);
+
+                    self.pending.clear();
+                    self.pending.extend_from_slice(samples.samples());
+                    self.pos = 0;
+                    return Ok(());
+                }
+                Err(Error::DecodeError(_)) => continue,
+                Err(e) => return Err(audio_err(e)),
+            }
+        }
+    }
+
+    /// Reads up to `max_frames` frames, appending interleaved samples to `out`.
+    /// Returns the number of frames read, 0 at the end of the file.
+    pub fn read(&mut self, out: &mut Vec<f32>, max_frames: usize) -> Result<usize> {
+        let channels = self.channels as usize;
+        let wanted = max_frames * channels;
+        let start = out.len();
+
+        while out.len() - start < wanted {
+            if self.pos >= self.pending.len() {
+                if self.done {
+                    break;
+                }
+                self.decode_next()?;
+                continue;
+            }
+
+            let n = (wanted - (out.len() - start)).min(self.pending.len() - self.pos);
+            out.extend_from_slice(&self.pending[self.pos..self.pos + n]);
+            self.pos += n;
+        }
+
+        Ok((out.len() - start) / channels)
+    }
+}
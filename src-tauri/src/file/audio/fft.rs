@@ -0,0 +1,50 @@
+// fast fourier transform, used by tempo/key estimation and null testing
+
+use std::f64::consts::PI;
+
+/// In place radix-2 FFT, `re` and `im` must have the same power of two length.
+pub(super) fn fft(re: &mut [f64], im: &mut [f64]) {
+    let n = re.len();
+
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f64;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (w_re, w_im) = ((angle * k as f64).cos(), (angle * k as f64).sin());
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// In place inverse FFT, scaled so `ifft(fft(x)) == x`.
+pub(super) fn ifft(re: &mut [f64], im: &mut [f64]) {
+    im.iter_mut().for_each(|v| *v = -*v);
+    fft(re, im);
+
+    let n = re.len() as f64;
+    re.iter_mut().for_each(|v| *v /= n);
+    im.iter_mut().for_each(|v| *v = -*v / n);
+}
@@ -0,0 +1,781 @@
+// detecting audio formats and reading technical details from their headers
+
+/*
+only headers are read here, nothing is decoded.
+
+WAV     "RIFF" size "WAVE", then little endian chunks: id, u32 size, data (padded to an even length)
+        "fmt " has the format tag, channels, sample rate and bits per sample, "data" holds the samples.
+        RF64/BW64 files store the real data size in a "ds64" chunk and set the "data" size to 0xffffffff.
+AIFF    "FORM" size "AIFF"/"AIFC", then big endian chunks like WAV.
+        "COMM" has channels, frame count, sample size and the sample rate as an 80 bit float,
+        AIFC adds a compression type after it.
+        samples from some Ableton Live Packs are encrypted but keep the .aif extension. they either aren't a FORM
+        file at all or are an AIFC with a compression type that isn't a real codec, so nothing but Live can play them.
+FLAC    "fLaC" then metadata blocks, the first is always STREAMINFO which has everything we need.
+MP3     an optional ID3v2 tag, then frames. each frame starts with a 4 byte header (sync, version, layer, bitrate,
+        sample rate, padding, channel mode). VBR files have a Xing/Info or VBRI header in their first frame with the
+        frame count, otherwise the duration is estimated from the bitrate.
+Ogg     pages starting with "OggS", the first packet of a stream identifies the codec (Vorbis, Opus or FLAC).
+        the granule position of the last page is the length of the stream in samples.
+
+see
+http://soundfile.sapp.org/doc/WaveFormat/
+https://www.mmsp.ece.mcgill.ca/Documents/AudioFormats/AIFF/Docs/AIFF-1.3.pdf
+https://xiph.org/flac/format.html
+http://www.mp3-tech.org/programmer/frame_header.html
+https://www.rfc-editor.org/rfc/rfc3533 (Ogg), https://www.rfc-editor.org/rfc/rfc7845 (Opus in Ogg)
+ */
+
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::types::{AudioInfo, AudioType};
+
+// how far past the start of the audio (or an ID3 tag) to look for the first MP3 frame
+const MP3_SEARCH_LEN: usize = 64 * 1024;
+
+// how far from the end of an Ogg file to look for the last page
+const OGG_TAIL_LEN: u64 = 64 * 1024;
+
+// Opus always decodes at 48kHz, granule positions are in 48kHz samples
+const OPUS_RATE: f64 = 48_000.0;
+
+pub(super) fn le_u16(b: &[u8], pos: usize) -> Option<u16> {
+    b.get(pos..pos + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+pub(super) fn le_u32(b: &[u8], pos: usize) -> Option<u32> {
+    b.get(pos..pos + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+pub(super) fn le_u64(b: &[u8], pos: usize) -> Option<u64> {
+    b.get(pos..pos + 8)
+        .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+}
+
+pub(super) fn be_u16(b: &[u8], pos: usize) -> Option<u16> {
+    b.get(pos..pos + 2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+}
+
+pub(super) fn be_u32(b: &[u8], pos: usize) -> Option<u32> {
+    b.get(pos..pos + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// Converts an 80 bit IEEE 754 extended float (used by AIFF for sample rates).
+pub(super) fn extended_to_f64(b: &[u8]) -> Option<f64> {
+    let b = b.get(..10)?;
+    let exponent = (((b[0] & 0x7f) as i32) << 8) | b[1] as i32;
+    let mantissa = u64::from_be_bytes(b[2..10].try_into().unwrap());
+
+    if exponent == 0 && mantissa == 0 {
+        return Some(0.0);
+    }
+
+    let value = mantissa as f64 * 2f64.powi(exponent - 16383 - 63);
+    Some(if b[0] & 0x80 != 0 { -value } else { value })
+}
+
+pub(super) fn read_vec<R: Read>(r: &mut R, len: usize) -> Option<Vec<u8>> {
+    let mut buf = vec![0u8; len];
+    r.read_exact(&mut buf).ok()?;
+    Some(buf)
+}
+
+/// Reads up to `len` bytes, stopping early at the end of the file.
+fn read_up_to<R: Read>(r: &mut R, len: usize) -> Option<Vec<u8>> {
+    let mut buf = vec![];
+    r.take(len as u64).read_to_end(&mut buf).ok()?;
+    Some(buf)
+}
+
+/// Length of the ID3v2 tag at the start of `data`, 0 if there isn't one.
+fn id3_len(data: &[u8]) -> usize {
+    if data.len() < 10 || !data.starts_with(b"ID3") {
+        return 0;
+    }
+
+    // sizes are "syncsafe", 7 bits per byte
+    let size = data[6..10]
+        .iter()
+        .fold(0usize, |acc, b| (acc << 7) | (b & 0x7f) as usize);
+    let footer = if data[5] & 0x10 != 0 { 10 } else { 0 };
+
+    10 + size + footer
+}
+
+// AIFC compression types that are actual codecs, anything else can't be played
+const AIFC_COMPRESSIONS: &[&[u8; 4]] = &[
+    b"NONE", b"twos", b"sowt", b"raw ", b"in24", b"in32", b"fl32", b"FL32", b"fl64", b"FL64",
+    b"ulaw", b"ULAW", b"alaw", b"ALAW", b"ima4", b"MAC3", b"MAC6", b"Qclp", b"QDMC", b"QDM2",
+];
+
+/// Compression type in the COMM chunk of an AIFF or AIFC header, `NONE` for plain AIFF.
+/// `None` if `header` isn't AIFF or the COMM chunk isn't within it.
+pub fn aiff_compression(header: &[u8]) -> Option<[u8; 4]> {
+    let is_aifc = match header.get(0..12)? {
+        [b'F', b'O', b'R', b'M', _, _, _, _, rest @ ..] if rest == b"AIFF" => false,
+        [b'F', b'O', b'R', b'M', _, _, _, _, rest @ ..] if rest == b"AIFC" => true,
+        _ => return None,
+    };
+
+    let mut pos = 12;
+    loop {
+        let size = be_u32(header, pos + 4)? as usize;
+
+        if header.get(pos..pos + 4)? == b"COMM" {
+            if !is_aifc {
+                return Some(*b"NONE");
+            }
+            // channels, frames, sample size and rate come first
+            return header.get(pos + 26..pos + 30)?.try_into().ok();
+        }
+
+        pos = pos.checked_add(8 + size + (size & 1))?;
+    }
+}
+
+/// Whether the header of a file with an AIFF extension looks like an encrypted Ableton sample.
+/// Plain AIFF files and AIFCs with a known compression type are never encrypted,
+/// files where the COMM chunk is out of reach of `header` are given the benefit of the doubt.
+pub fn is_encrypted_aiff(header: &[u8]) -> bool {
+    let is_form = header.len() >= 12
+        && &header[0..4] == b"FORM"
+        && matches!(&header[8..12], b"AIFF" | b"AIFC");
+    if !is_form {
+        return true;
+    }
+
+    match aiff_compression(header) {
+        Some(c) => !AIFC_COMPRESSIONS.contains(&&c),
+        None => false,
+    }
+}
+
+/// Detects the format of an audio file from its first bytes.
+/// `None` if the format isn't recognized, the file might still be audio.
+pub fn detect(header: &[u8]) -> Option<AudioType> {
+    if header.len() >= 12 {
+        match (&header[0..4], &header[8..12]) {
+            (b"RIFF" | b"RF64" | b"BW64", b"WAVE") => return Some(AudioType::Wav),
+            (b"FORM", b"AIFF" | b"AIFC") if !is_encrypted_aiff(header) => {
+                return Some(AudioType::Aiff)
+            }
+            _ => (),
+        }
+    }
+
+    if header.starts_with(b"OggS") {
+        return match ogg_first_packet(header) {
+            Some(p) if p.starts_with(b"OpusHead") => Some(AudioType::Opus),
+            Some(p) if p.starts_with(b"\x01vorbis") || p.starts_with(b"\x7fFLAC") => {
+                Some(AudioType::Ogg)
+            }
+            // Ogg can also hold video
+            _ => None,
+        };
+    }
+
+    let start = id3_len(header);
+    if start > 0 && start >= header.len() {
+        // the tag is longer than what we were given, ID3v2 is almost always used by MP3s
+        return Some(AudioType::Mp3);
+    }
+
+    let data = &header[start..];
+    if data.starts_with(b"fLaC") {
+        return Some(AudioType::Flac);
+    }
+    if find_mp3_frame(data, start > 0).is_some() {
+        return Some(AudioType::Mp3);
+    }
+
+    None
+}
+
+/// Reads the technical details of an audio file of a known type.
+/// `None` if the header couldn't be parsed or the format doesn't have one we can read.
+pub fn read_audio_info<R: Read + Seek>(r: &mut R, audio_type: &AudioType) -> Option<AudioInfo> {
+    r.seek(SeekFrom::Start(0)).ok()?;
+
+    match audio_type {
+        AudioType::Wav => read_wav(r),
+        AudioType::Aiff => read_aiff(r),
+        AudioType::Flac => read_flac(r),
+        AudioType::Mp3 => read_mp3(r),
+        AudioType::Ogg | AudioType::Opus => read_ogg(r),
+        AudioType::Other => None,
+    }
+}
+
+fn wav_codec(tag: u16) -> String {
+    match tag {
+        1 => "PCM".into(),
+        3 => "IEEE float".into(),
+        6 => "A-law".into(),
+        7 => "µ-law".into(),
+        0x55 => "MPEG Layer III".into(),
+        t => format!("WAV format {t:#06x}"),
+    }
+}
+
+fn read_wav<R: Read + Seek>(r: &mut R) -> Option<AudioInfo> {
+    let riff = read_vec(r, 12)?;
+    let is_rf64 = &riff[0..4] != b"RIFF";
+
+    let mut fmt: Option<Vec<u8>> = None;
+    let mut ds64_data_size: Option<u64> = None;
+    let mut data_size: Option<u64> = None;
+
+    while fmt.is_none() || data_size.is_none() {
+        let Some(chunk) = read_vec(r, 8) else {
+            break;
+        };
+        let size = le_u32(&chunk, 4)? as u64;
+
+        match &chunk[0..4] {
+            b"fmt " => {
+                fmt = Some(read_vec(r, size.min(64) as usize)?);
+                r.seek(SeekFrom::Current((size - size.min(64) + (size & 1)) as i64))
+                    .ok()?;
+            }
+            b"ds64" => {
+                let body = read_vec(r, size.min(28) as usize)?;
+                ds64_data_size = le_u64(&body, 8);
+                r.seek(SeekFrom::Current((size - size.min(28) + (size & 1)) as i64))
+                    .ok()?;
+            }
+            b"data" => {
+                data_size = Some(match (is_rf64, ds64_data_size) {
+                    (true, Some(s)) if size == 0xffff_ffff => s,
+                    _ => size,
+                });
+                if fmt.is_some() {
+                    break;
+                }
+                r.seek(SeekFrom::Current((size + (size & 1)) as i64)).ok()?;
+            }
+            _ => {
+                r.seek(SeekFrom::Current((size + (size & 1)) as i64)).ok()?;
+            }
+        }
+    }
+
+    let fmt = fmt?;
+    let mut tag = le_u16(&fmt, 0)?;
+    let channels = le_u16(&fmt, 2)?;
+    let sample_rate = le_u32(&fmt, 4)?;
+    let block_align = le_u16(&fmt, 12)?;
+    let bits = le_u16(&fmt, 14)?;
+
+    // WAVE_FORMAT_EXTENSIBLE, the real format is at the start of the subformat GUID
+    if tag == 0xfffe {
+        tag = le_u16(&fmt, 24).unwrap_or(tag);
+    }
+
+    Some(AudioInfo {
+        codec: wav_codec(tag),
+        sample_rate,
+        bit_depth: matches!(tag, 1 | 3).then_some(bits),
+        channels,
+        duration_secs: data_size
+            .filter(|_| block_align > 0 && sample_rate > 0)
+            .map(|s| (s / block_align as u64) as f64 / sample_rate as f64),
+    })
+}
+
+fn read_aiff<R: Read + Seek>(r: &mut R) -> Option<AudioInfo> {
+    let form = read_vec(r, 12)?;
+    let is_aifc = &form[8..12] == b"AIFC";
+
+    loop {
+        let chunk = read_vec(r, 8)?;
+        let size = be_u32(&chunk, 4)? as u64;
+
+        if &chunk[0..4] != b"COMM" {
+            r.seek(SeekFrom::Current((size + (size & 1)) as i64)).ok()?;
+            continue;
+        }
+
+        let comm = read_vec(r, size.min(64) as usize)?;
+        let channels = be_u16(&comm, 0)?;
+        let frames = be_u32(&comm, 2)?;
+        let sample_size = be_u16(&comm, 6)?;
+        let sample_rate = extended_to_f64(comm.get(8..18)?)?;
+
+        let compression = if is_aifc {
+            comm.get(18..22)?.to_vec()
+        } else {
+            b"NONE".to_vec()
+        };
+        let (codec, bit_depth) = match compression.as_slice() {
+            b"NONE" | b"twos" => ("PCM".to_string(), Some(sample_size)),
+            b"sowt" => ("PCM (little endian)".to_string(), Some(sample_size)),
+            b"fl32" | b"FL32" => ("IEEE float".to_string(), Some(32)),
+            b"fl64" | b"FL64" => ("IEEE float".to_string(), Some(64)),
+            b"ulaw" | b"ULAW" => ("µ-law".to_string(), None),
+            b"alaw" | b"ALAW" => ("A-law".to_string(), None),
+            c => (String::from_utf8_lossy(c).trim().to_string(), None),
+        };
+
+        return Some(AudioInfo {
+            codec,
+            sample_rate: sample_rate.round() as u32,
+            bit_depth,
+            channels,
+            duration_secs: (sample_rate > 0.0).then(|| frames as f64 / sample_rate),
+        });
+    }
+}
+
+/// Parses the body of a FLAC STREAMINFO block.
+fn parse_streaminfo(b: &[u8]) -> Option<AudioInfo> {
+    let b = b.get(..18)?;
+    let sample_rate = ((b[10] as u32) << 12) | ((b[11] as u32) << 4) | (b[12] >> 4) as u32;
+    let channels = ((b[12] >> 1) & 0x7) as u16 + 1;
+    let bits = ((((b[12] & 1) << 4) | (b[13] >> 4)) + 1) as u16;
+    let total_samples = (((b[13] & 0xf) as u64) << 32) | be_u32(b, 14)? as u64;
+
+    Some(AudioInfo {
+        codec: "FLAC".into(),
+        sample_rate,
+        bit_depth: Some(bits),
+        channels,
+        // 0 means unknown
+        duration_secs: (total_samples > 0 && sample_rate > 0)
+            .then(|| total_samples as f64 / sample_rate as f64),
+    })
+}
+
+fn read_flac<R: Read + Seek>(r: &mut R) -> Option<AudioInfo> {
+    let start = read_vec(r, 10)?;
+    r.seek(SeekFrom::Start(id3_len(&start) as u64)).ok()?;
+
+    if read_vec(r, 4)? != b"fLaC" {
+        return None;
+    }
+
+    // STREAMINFO is always the first block
+    let block = read_vec(r, 4)?;
+    if block[0] & 0x7f != 0 {
+        return None;
+    }
+
+    parse_streaminfo(&read_vec(r, 34)?)
+}
+
+#[derive(Debug, PartialEq)]
+struct Mp3Frame {
+    // 1, 2, or 25 for MPEG 2.5
+    version: u8,
+    layer: u8,
+    bitrate: u32,
+    sample_rate: u32,
+    padding: bool,
+    channels: u16,
+}
+
+const MP3_BITRATES_V1: [[u32; 14]; 3] = [
+    [
+        32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448,
+    ],
+    [
+        32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384,
+    ],
+    [
+        32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+    ],
+];
+
+const MP3_BITRATES_V2: [[u32; 14]; 2] = [
+    [
+        32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256,
+    ],
+    [8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+];
+
+impl Mp3Frame {
+    fn parse(b: &[u8]) -> Option<Self> {
+        let b = b.get(..4)?;
+        if b[0] != 0xff || b[1] & 0xe0 != 0xe0 {
+            return None;
+        }
+
+        let version = match (b[1] >> 3) & 3 {
+            0 => 25,
+            2 => 2,
+            3 => 1,
+            _ => return None,
+        };
+        let layer = match (b[1] >> 1) & 3 {
+            1 => 3,
+            2 => 2,
+            3 => 1,
+            _ => return None,
+        };
+
+        // 0 is "free" bitrate, 15 is invalid
+        let bitrate_index = (b[2] >> 4) as usize;
+        if bitrate_index == 0 || bitrate_index == 15 {
+            return None;
+        }
+        let bitrate = if version == 1 {
+            MP3_BITRATES_V1[layer as usize - 1][bitrate_index - 1]
+        } else {
+            MP3_BITRATES_V2[(layer != 1) as usize][bitrate_index - 1]
+        };
+
+        let rates = match version {
+            1 => [44100, 48000, 32000],
+            2 => [22050, 24000, 16000],
+            _ => [11025, 12000, 8000],
+        };
+        let sample_rate = *rates.get(((b[2] >> 2) & 3) as usize)?;
+
+        Some(Mp3Frame {
+            version,
+            layer,
+            bitrate,
+            sample_rate,
+            padding: (b[2] >> 1) & 1 == 1,
+            channels: if b[3] >> 6 == 3 { 1 } else { 2 },
+        })
+    }
+
+    fn samples_per_frame(&self) -> u32 {
+        match (self.layer, self.version) {
+            (1, _) => 384,
+            (2, _) | (3, 1) => 1152,
+            _ => 576,
+        }
+    }
+
+    fn len(&self) -> usize {
+        let bytes = if self.layer == 1 {
+            (12 * self.bitrate * 1000 / self.sample_rate + self.padding as u32) * 4
+        } else {
+            self.samples_per_frame() / 8 * self.bitrate * 1000 / self.sample_rate
+                + self.padding as u32
+        };
+        bytes as usize
+    }
+
+    /// Offset of a Xing/Info header from the start of the frame.
+    fn side_info_end(&self) -> usize {
+        4 + match (self.version, self.channels) {
+            (1, 1) => 17,
+            (1, _) => 32,
+            (_, 1) => 9,
+            _ => 17,
+        }
+    }
+}
+
+/// Finds the first MP3 frame in `data`, returns its offset and header.
+/// Without a preceding ID3 tag the frame has to be at the very start, and has to be followed by another frame,
+/// so other files aren't mistaken for MP3s.
+fn find_mp3_frame(data: &[u8], after_id3: bool) -> Option<(usize, Mp3Frame)> {
+    let search_len = if after_id3 {
+        data.len().min(MP3_SEARCH_LEN)
+    } else {
+        1
+    };
+
+    (0..search_len).find_map(|i| {
+        let frame = Mp3Frame::parse(&data[i..])?;
+        let next = i + frame.len();
+        let next_ok = match Mp3Frame::parse(data.get(next..).unwrap_or(&[])) {
+            Some(n) => n.version == frame.version && n.layer == frame.layer,
+            None => after_id3 && next >= data.len(),
+        };
+        next_ok.then_some((i, frame))
+    })
+}
+
+fn read_mp3<R: Read + Seek>(r: &mut R) -> Option<AudioInfo> {
+    let file_len = r.seek(SeekFrom::End(0)).ok()?;
+    r.seek(SeekFrom::Start(0)).ok()?;
+
+    let tag_len = id3_len(&read_up_to(r, 10)?) as u64;
+    r.seek(SeekFrom::Start(tag_len)).ok()?;
+
+    let data = read_up_to(r, MP3_SEARCH_LEN + 4096)?;
+    let (offset, frame) = find_mp3_frame(&data, tag_len > 0)?;
+    let frame_data = &data[offset..];
+
+    let xing = frame.side_info_end();
+    let frame_count = match frame_data.get(xing..xing + 4) {
+        Some(b"Xing" | b"Info") if be_u32(frame_data, xing + 4)? & 1 != 0 => {
+            be_u32(frame_data, xing + 8)
+        }
+        _ => match frame_data.get(36..40) {
+            Some(b"VBRI") => be_u32(frame_data, 36 + 14),
+            _ => None,
+        },
+    };
+
+    let duration_secs = match frame_count {
+        Some(frames) => frames as f64 * frame.samples_per_frame() as f64 / frame.sample_rate as f64,
+        // constant bitrate
+        None => (file_len - tag_len - offset as u64) as f64 * 8.0 / (frame.bitrate as f64 * 1000.0),
+    };
+
+    Some(AudioInfo {
+        codec: format!(
+            "MPEG-{} Layer {}",
+            if frame.version == 25 {
+                "2.5".to_string()
+            } else {
+                frame.version.to_string()
+            },
+            ["I", "II", "III"][frame.layer as usize - 1]
+        ),
+        sample_rate: frame.sample_rate,
+        bit_depth: None,
+        channels: frame.channels,
+        duration_secs: Some(duration_secs),
+    })
+}
+
+/// The first packet of the first page of an Ogg stream, possibly cut short.
+fn ogg_first_packet(data: &[u8]) -> Option<&[u8]> {
+    let segments = *data.get(26)? as usize;
+    let table = data.get(27..27 + segments)?;
+
+    // a packet ends at the first segment shorter than 255 bytes
+    let len: usize = table
+        .iter()
+        .position(|s| *s < 255)
+        .map(|i| table[..=i].iter().map(|s| *s as usize).sum())
+        .unwrap_or_else(|| table.iter().map(|s| *s as usize).sum());
+
+    let start = 27 + segments;
+    data.get(start..(start + len).min(data.len()))
+}
+
+/// Granule position of the last page of the stream with `serial` in `tail`.
+fn ogg_last_granule(tail: &[u8], serial: u32) -> Option<u64> {
+    (0..tail.len().saturating_sub(27)).rev().find_map(|i| {
+        if &tail[i..i + 4] != b"OggS" || le_u32(tail, i + 14)? != serial {
+            return None;
+        }
+        // -1 means no packet ends on this page
+        le_u64(tail, i + 6).filter(|g| *g != u64::MAX)
+    })
+}
+
+fn read_ogg<R: Read + Seek>(r: &mut R) -> Option<AudioInfo> {
+    let file_len = r.seek(SeekFrom::End(0)).ok()?;
+    r.seek(SeekFrom::Start(0)).ok()?;
+
+    let head = read_up_to(r, 64 * 1024)?;
+    if !head.starts_with(b"OggS") {
+        return None;
+    }
+    let serial = le_u32(&head, 14)?;
+    let packet = ogg_first_packet(&head)?;
+
+    r.seek(SeekFrom::Start(file_len.saturating_sub(OGG_TAIL_LEN)))
+        .ok()?;
+    let granule = ogg_last_granule(&read_up_to(r, OGG_TAIL_LEN as usize)?, serial);
+
+    if packet.starts_with(b"\x01vorbis") {
+        let sample_rate = le_u32(packet, 12)?;
+        Some(AudioInfo {
+            codec: "Vorbis".into(),
+            sample_rate,
+            bit_depth: None,
+            channels: *packet.get(11)? as u16,
+            duration_secs: granule
+                .filter(|_| sample_rate > 0)
+                .map(|g| g as f64 / sample_rate as f64),
+        })
+    } else if packet.starts_with(b"OpusHead") {
+        let pre_skip = le_u16(packet, 10)? as u64;
+        let input_rate = le_u32(packet, 12)?;
+        Some(AudioInfo {
+            codec: "Opus".into(),
+            // the rate of the original audio, 0 if unknown
+            sample_rate: if input_rate > 0 {
+                input_rate
+            } else {
+                OPUS_RATE as u32
+            },
+            bit_depth: None,
+            channels: *packet.get(9)? as u16,
+            duration_secs: granule.map(|g| g.saturating_sub(pre_skip) as f64 / OPUS_RATE),
+        })
+    } else if packet.starts_with(b"\x7fFLAC") && packet.get(9..13)? == b"fLaC" {
+        let mut info = parse_streaminfo(packet.get(17..)?)?;
+        if info.duration_secs.is_none() && info.sample_rate > 0 {
+            info.duration_secs = granule.map(|g| g as f64 / info.sample_rate as f64);
+        }
+        Some(info)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn info(data: Vec<u8>) -> (Option<AudioType>, Option<AudioInfo>) {
+        let t = detect(&data);
+        let i = t
+            .as_ref()
+            .and_then(|t| read_audio_info(&mut Cursor::new(data), t));
+        (t, i)
+    }
+
+    #[test]
+    fn test_wav_and_aiff() {
+        // 24 bit stereo 48kHz, one second
+        let mut wav = b"RIFF\0\0\0\0WAVE".to_vec();
+        wav.extend_from_slice(b"fmt \x10\0\0\0");
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&48000u32.to_le_bytes());
+        wav.extend_from_slice(&(48000u32 * 6).to_le_bytes());
+        wav.extend_from_slice(&6u16.to_le_bytes());
+        wav.extend_from_slice(&24u16.to_le_bytes());
+        wav.extend_from_slice(b"LIST\x03\0\0\0abc\0");
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&(48000u32 * 6).to_le_bytes());
+
+        let (t, i) = info(wav);
+        assert_eq!(t, Some(AudioType::Wav));
+        assert_eq!(
+            i,
+            Some(AudioInfo {
+                codec: "PCM".into(),
+                sample_rate: 48000,
+                bit_depth: Some(24),
+                channels: 2,
+                duration_secs: Some(1.0),
+            })
+        );
+
+        // 16 bit mono 44.1kHz, two seconds
+        let mut aiff = b"FORM\0\0\0\0AIFF".to_vec();
+        aiff.extend_from_slice(b"COMM\0\0\0\x12");
+        aiff.extend_from_slice(&1u16.to_be_bytes());
+        aiff.extend_from_slice(&88200u32.to_be_bytes());
+        aiff.extend_from_slice(&16u16.to_be_bytes());
+        aiff.extend_from_slice(&[0x40, 0x0e, 0xac, 0x44, 0, 0, 0, 0, 0, 0]);
+
+        let (t, i) = info(aiff);
+        assert_eq!(t, Some(AudioType::Aiff));
+        let i = i.unwrap();
+        assert_eq!(i.sample_rate, 44100);
+        assert_eq!(i.bit_depth, Some(16));
+        assert_eq!(i.channels, 1);
+        assert_eq!(i.duration_secs, Some(2.0));
+    }
+
+    fn aifc(compression: &[u8; 4]) -> Vec<u8> {
+        let mut aifc = b"FORM\0\0\0\0AIFC".to_vec();
+        aifc.extend_from_slice(b"FVER\0\0\0\x04\xa2\x80\x51\x40");
+        aifc.extend_from_slice(b"COMM\0\0\0\x18");
+        aifc.extend_from_slice(&2u16.to_be_bytes());
+        aifc.extend_from_slice(&44100u32.to_be_bytes());
+        aifc.extend_from_slice(&16u16.to_be_bytes());
+        aifc.extend_from_slice(&[0x40, 0x0e, 0xac, 0x44, 0, 0, 0, 0, 0, 0]);
+        aifc.extend_from_slice(compression);
+        aifc.extend_from_slice(b"\0\0");
+        aifc
+    }
+
+    #[test]
+    fn test_encrypted_aiff() {
+        let sowt = aifc(b"sowt");
+        assert_eq!(aiff_compression(&sowt), Some(*b"sowt"));
+        assert!(!is_encrypted_aiff(&sowt));
+        assert_eq!(info(sowt).0, Some(AudioType::Aiff));
+
+        let mut aiff = b"FORM\0\0\0\0AIFF".to_vec();
+        aiff.extend_from_slice(b"COMM\0\0\0\x12");
+        aiff.extend_from_slice(&[0; 18]);
+        assert_eq!(aiff_compression(&aiff), Some(*b"NONE"));
+        assert!(!is_encrypted_aiff(&aiff));
+
+        // unknown compression
+        let encrypted = aifc(b"\x8f\x13\xe2\x07");
+        assert!(is_encrypted_aiff(&encrypted));
+        assert_eq!(detect(&encrypted), None);
+
+        // not a FORM file at all
+        assert!(is_encrypted_aiff(&[
+            0x5a, 0x13, 0xc7, 0x01, 0x9e, 0x44, 0, 0, 0x10, 0x20, 0x30, 0x40
+        ]));
+
+        // COMM chunk past the end of the header
+        let mut truncated = b"FORM\0\0\0\0AIFC".to_vec();
+        truncated.extend_from_slice(b"APPL\0\x01\0\0");
+        truncated.extend_from_slice(&[0; 64]);
+        assert_eq!(aiff_compression(&truncated), None);
+        assert!(!is_encrypted_aiff(&truncated));
+    }
+
+    #[test]
+    fn test_flac_mp3_and_ogg() {
+        // 44.1kHz, stereo, 16 bit, 441000 samples
+        let mut flac = b"fLaC\x00\x00\x00\x22".to_vec();
+        let mut streaminfo = vec![0u8; 34];
+        streaminfo[10..14].copy_from_slice(&[0x0a, 0xc4, 0x42, 0xf0]);
+        streaminfo[14..18].copy_from_slice(&441000u32.to_be_bytes());
+        flac.extend(streaminfo);
+
+        let (t, i) = info(flac);
+        assert_eq!(t, Some(AudioType::Flac));
+        let i = i.unwrap();
+        assert_eq!(
+            (i.sample_rate, i.channels, i.bit_depth),
+            (44100, 2, Some(16))
+        );
+        assert_eq!(i.duration_secs, Some(10.0));
+
+        // two 128kbps 44.1kHz MPEG-1 Layer III frames behind an empty ID3 tag
+        let mut mp3 = b"ID3\x04\0\0\0\0\0\0".to_vec();
+        for _ in 0..2 {
+            let mut frame = vec![0u8; 417];
+            frame[..4].copy_from_slice(&[0xff, 0xfb, 0x90, 0x00]);
+            mp3.extend(frame);
+        }
+
+        let (t, i) = info(mp3);
+        assert_eq!(t, Some(AudioType::Mp3));
+        let i = i.unwrap();
+        assert_eq!(i.codec, "MPEG-1 Layer III");
+        assert_eq!((i.sample_rate, i.channels, i.bit_depth), (44100, 2, None));
+
+        // random bytes aren't an MP3
+        assert_eq!(detect(&[0xff, 0xfb, 0x90, 0x00, 1, 2, 3]), None);
+
+        // Opus, 2 channels, 312 samples of pre-skip, 1 second long
+        let mut opus = b"OggS\0\x02".to_vec();
+        opus.extend_from_slice(&0u64.to_le_bytes());
+        opus.extend_from_slice(&7u32.to_le_bytes());
+        opus.extend_from_slice(&[0; 8]);
+        opus.extend_from_slice(&[1, 19]);
+        opus.extend_from_slice(b"OpusHead\x01\x02");
+        opus.extend_from_slice(&312u16.to_le_bytes());
+        opus.extend_from_slice(&44100u32.to_le_bytes());
+        opus.extend_from_slice(&[0, 0, 0]);
+        opus.extend_from_slice(b"OggS\0\x04");
+        opus.extend_from_slice(&(48312u64).to_le_bytes());
+        opus.extend_from_slice(&7u32.to_le_bytes());
+        opus.extend_from_slice(&[0; 8]);
+        opus.extend_from_slice(&[1, 0]);
+
+        let (t, i) = info(opus);
+        assert_eq!(t, Some(AudioType::Opus));
+        let i = i.unwrap();
+        assert_eq!(i.codec, "Opus");
+        assert_eq!((i.sample_rate, i.channels), (44100, 2));
+        assert_eq!(i.duration_secs, Some(1.0));
+    }
+}
@@ -0,0 +1,427 @@
+// loudness and peak measurement of audio files
+
+/*
+loudness is measured as described in ITU-R BS.1770-4, loudness range as in EBU Tech 3342:
+
+- every channel is K-weighted: a high shelf (+4 dB above ~1.5 kHz, roughly the effect of the head)
+  followed by a high pass (the RLB curve). coefficients are derived for any sample rate using the analog
+  prototype's parameters, at 48 kHz they match the ones given in the standard.
+- the mean square of the weighted signal is taken over 400 ms blocks overlapping by 75%, channels are summed with
+  weights (1.0, surround channels 1.41, LFE excluded). loudness of a block is -0.691 + 10 log10(sum).
+- integrated loudness gates out blocks below -70 LUFS, then blocks more than 10 LU below the loudness
+  of what's left, and takes the loudness of the average of the remaining blocks.
+- loudness range uses 3 second blocks, gates at -70 LUFS and 20 LU below, and is the difference between the
+  95th and 10th percentile of the remaining blocks.
+- true peak is the peak of the signal oversampled 4 times (2 times at 96 kHz and above, not at all at 192 kHz).
+  the standard only gives an example interpolation filter, we use a windowed sinc of similar length.
+
+we keep the mean square of every 100 ms (the hop between blocks), both block lengths are averages of these.
+
+files in a folder never change, so the first client to measure a file saves the result next to its FileInfo
+and everyone else reads it from there. results from an older VERSION are measured again, results from a newer
+one are left alone so clients on different versions don't keep overwriting each other.
+
+see
+https://www.itu.int/rec/R-REC-BS.1770
+https://tech.ebu.ch/publications/tech3342
+ */
+
+use std::{f64::consts::PI, fs, path::Path};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use super::pcm::PcmReader;
+use crate::{
+    misc::{path_to_str, Result},
+    shared::Loudness,
+    structure::{get_file_loudness_path, get_file_path},
+};
+
+// bump when the analysis changes so saved results are redone
+const VERSION: u32 = 1;
+
+const ABSOLUTE_GATE: f64 = -70.0;
+const RELATIVE_GATE: f64 = -10.0;
+const LRA_RELATIVE_GATE: f64 = -20.0;
+
+// in 100 ms steps
+const MOMENTARY_STEPS: usize = 4;
+const SHORT_TERM_STEPS: usize = 30;
+
+const TRUE_PEAK_TAPS_PER_PHASE: usize = 16;
+
+#[derive(Serialize, Deserialize)]
+struct StoredLoudness {
+    version: u32,
+    loudness: Loudness,
+}
+
+fn load_stored(folder: &Path, file_sha256: &str) -> Option<StoredLoudness> {
+    let data = fs::read(get_file_loudness_path(folder, file_sha256)).ok()?;
+    serde_json::from_slice(&data).ok()
+}
+
+fn to_db(v: f64) -> Option<f64> {
+    (v > 0.0).then(|| 20.0 * v.log10())
+}
+
+fn block_loudness(mean_square: f64) -> f64 {
+    -0.691 + 10.0 * mean_square.log10()
+}
+
+/// Second order IIR filter (transposed direct form II).
+#[derive(Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[0] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+/// The two stages of the K-weighting filter for a sample rate.
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let fs = sample_rate as f64;
+
+    // high shelf
+    let f0 = 1681.974450955533;
+    let gain_db = 3.999843853973347;
+    let q = 0.7071752369554196;
+    let k = (PI * f0 / fs).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b: [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        z: [0.0; 2],
+    };
+
+    // high pass
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (PI * f0 / fs).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad {
+        b: [1.0, -2.0, 1.0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        z: [0.0; 2],
+    };
+
+    [shelf, high_pass]
+}
+
+/// Weight of a channel in the loudness sum, assumes the usual 5.1 order (L R C LFE Ls Rs).
+fn channel_weight(channel: usize, channels: usize) -> f64 {
+    match (channels, channel) {
+        (6, 3) => 0.0,
+        (5, 3 | 4) | (6, 4 | 5) => 1.41,
+        _ => 1.0,
+    }
+}
+
+/// Interpolation filter for true peak measurement, `factor` phases of `TRUE_PEAK_TAPS_PER_PHASE` taps.
+fn oversampling_filter(factor: usize) -> Vec<Vec<f64>> {
+    let len = factor * TRUE_PEAK_TAPS_PER_PHASE;
+    let center = (len - 1) as f64 / 2.0;
+
+    let taps: Vec<f64> = (0..len)
+        .map(|n| {
+            let t = (n as f64 - center) / factor as f64;
+            let sinc = if t == 0.0 {
+                1.0
+            } else {
+                (PI * t).sin() / (PI * t)
+            };
+            // Hann window
+            let w = 0.5 - 0.5 * (2.0 * PI * (n as f64 + 0.5) / len as f64).cos();
+            sinc * w
+        })
+        .collect();
+
+    (0..factor)
+        .map(|phase| taps.iter().skip(phase).step_by(factor).copied().collect())
+        .collect()
+}
+
+/// Measures loudness of interleaved samples fed to it in blocks.
+pub struct LoudnessMeter {
+    channels: usize,
+    weights: Vec<f64>,
+    filters: Vec<[Biquad; 2]>,
+
+    step_len: usize,
+    step_pos: usize,
+    step_sum: f64,
+    // weighted mean square of every complete 100 ms step
+    steps: Vec<f64>,
+
+    sample_peak: f64,
+
+    phases: Vec<Vec<f64>>,
+    // the last few samples of every channel, newest first
+    history: Vec<Vec<f64>>,
+    true_peak: f64,
+}
+
+impl LoudnessMeter {
+    pub fn new(sample_rate: u32, channels: u16) -> Self {
+        let channels = channels as usize;
+        let factor = match sample_rate {
+            0..96_000 => 4,
+            96_000..192_000 => 2,
+            _ => 1,
+        };
+
+        LoudnessMeter {
+            channels,
+            weights: (0..channels).map(|c| channel_weight(c, channels)).collect(),
+            filters: vec![k_weighting(sample_rate); channels],
+            step_len: (sample_rate as usize / 10).max(1),
+            step_pos: 0,
+            step_sum: 0.0,
+            steps: vec![],
+            sample_peak: 0.0,
+            phases: oversampling_filter(factor),
+            history: vec![vec![0.0; TRUE_PEAK_TAPS_PER_PHASE]; channels],
+            true_peak: 0.0,
+        }
+    }
+
+    pub fn push(&mut self, samples: &[f32]) {
+        for frame in samples.chunks_exact(self.channels) {
+            for (c, s) in frame.iter().enumerate() {
+                let x = *s as f64;
+
+                let [shelf, high_pass] = &mut self.filters[c];
+                let y = high_pass.process(shelf.process(x));
+                self.step_sum += self.weights[c] * y * y;
+
+                self.sample_peak = self.sample_peak.max(x.abs());
+
+                let history = &mut self.history[c];
+                history.rotate_right(1);
+                history[0] = x;
+                for phase in self.phases.iter() {
+                    let v: f64 = phase.iter().zip(history.iter()).map(|(h, x)| h * x).sum();
+                    self.true_peak = self.true_peak.max(v.abs());
+                }
+            }
+
+            self.step_pos += 1;
+            if self.step_pos == self.step_len {
+                self.steps.push(self.step_sum / self.step_len as f64);
+                self.step_pos = 0;
+                self.step_sum = 0.0;
+            }
+        }
+    }
+
+    /// Mean squares of blocks `len` steps long, one per step.
+    fn blocks(&self, len: usize) -> Vec<f64> {
+        self.steps
+            .windows(len)
+            .map(|w| w.iter().sum::<f64>() / len as f64)
+            .collect()
+    }
+
+    /// Blocks louder than the absolute gate and `relative_gate` LU below their average.
+    fn gated(blocks: Vec<f64>, relative_gate: f64) -> Vec<f64> {
+        let blocks: Vec<f64> = blocks
+            .into_iter()
+            .filter(|b| *b > 0.0 && block_loudness(*b) > ABSOLUTE_GATE)
+            .collect();
+        if blocks.is_empty() {
+            return blocks;
+        }
+
+        let threshold =
+            block_loudness(blocks.iter().sum::<f64>() / blocks.len() as f64) + relative_gate;
+
+        blocks
+            .into_iter()
+            .filter(|b| block_loudness(*b) > threshold)
+            .collect()
+    }
+
+    pub fn finish(self) -> Loudness {
+        let integrated = Self::gated(self.blocks(MOMENTARY_STEPS), RELATIVE_GATE);
+        let integrated_lufs = (!integrated.is_empty())
+            .then(|| block_loudness(integrated.iter().sum::<f64>() / integrated.len() as f64));
+
+        let mut short_term: Vec<f64> =
+            Self::gated(self.blocks(SHORT_TERM_STEPS), LRA_RELATIVE_GATE)
+                .into_iter()
+                .map(block_loudness)
+                .collect();
+        short_term.sort_by(f64::total_cmp);
+        let percentile = |p: f64| short_term[((short_term.len() - 1) as f64 * p).round() as usize];
+        let loudness_range_lu =
+            (!short_term.is_empty()).then(|| percentile(0.95) - percentile(0.10));
+
+        Loudness {
+            integrated_lufs,
+            loudness_range_lu,
+            sample_peak_dbfs: to_db(self.sample_peak),
+            true_peak_dbtp: to_db(self.true_peak.max(self.sample_peak)),
+        }
+    }
+}
+
+impl Loudness {
+    /// Gets the loudness of an audio file in a folder, measuring it if no one has yet.
+    pub fn get(folder: &Path, file_sha256: &str) -> Result<Self> {
+        let stored = load_stored(folder, file_sha256);
+        if let Some(s) = stored.as_ref().filter(|s| s.version == VERSION) {
+            return Ok(s.loudness.clone());
+        }
+
+        let loudness = Self::measure(&get_file_path(folder, file_sha256))?;
+
+        if stored.is_some_and(|s| s.version > VERSION) {
+            return Ok(loudness);
+        }
+
+        let path = get_file_loudness_path(folder, file_sha256);
+        let stored = StoredLoudness {
+            version: VERSION,
+            loudness: loudness.clone(),
+        };
+        let res = serde_json::to_vec_pretty(&stored)
+            .map_err(|e| e.to_string())
+            .and_then(|data| fs::write(&path, data).map_err(|e| e.to_string()));
+        if let Err(e) = res {
+            warn!(
+                "Loudness::get(): failed to save loudness at {}: {e}",
+                path_to_str(&path)
+            );
+        }
+
+        Ok(loudness)
+    }
+
+    /// Loudness of a file in a folder if it's been measured before.
+    pub fn load(folder: &Path, file_sha256: &str) -> Option<Self> {
+        load_stored(folder, file_sha256)
+            .filter(|s| s.version == VERSION)
+            .map(|s| s.loudness)
+    }
+
+    /// Decodes an audio file and measures its loudness.
+    pub fn measure(file: &Path) -> Result<Self> {
+        let mut reader = PcmReader::open(file)?;
+        let mut meter = LoudnessMeter::new(reader.sample_rate, reader.channels);
+        reader.read_all(|block| meter.push(block))?;
+        Ok(meter.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{file::add_file, structure::create_tempo_folder, tests::get_temp_dir};
+
+    fn sine(meter: &mut LoudnessMeter, freq: f64, db: f64, phase: f64, secs: f64) {
+        let amplitude = 10f64.powf(db / 20.0);
+        let samples: Vec<f32> = (0..(48000.0 * secs) as usize)
+            .flat_map(|i| {
+                let s = (amplitude * (2.0 * PI * freq * i as f64 / 48000.0 + phase).sin()) as f32;
+                [s, s]
+            })
+            .collect();
+        meter.push(&samples);
+    }
+
+    #[test]
+    fn test_loudness() {
+        // EBU Tech 3341: a stereo 1 kHz sine at -23 dBFS measures -23 LUFS
+        let mut meter = LoudnessMeter::new(48000, 2);
+        sine(&mut meter, 1000.0, -23.0, 0.0, 5.0);
+        let l = meter.finish();
+        assert!((l.integrated_lufs.unwrap() + 23.0).abs() < 0.1);
+        assert!(l.loudness_range_lu.unwrap() < 0.1);
+        assert!((l.sample_peak_dbfs.unwrap() + 23.0).abs() < 0.01);
+
+        // EBU Tech 3342: 20 dB steps give a loudness range of 10 LU
+        let mut meter = LoudnessMeter::new(48000, 2);
+        sine(&mut meter, 1000.0, -20.0, 0.0, 10.0);
+        sine(&mut meter, 1000.0, -30.0, 0.0, 10.0);
+        assert!((meter.finish().loudness_range_lu.unwrap() - 10.0).abs() < 1.0);
+
+        // samples of a sine at a quarter of the sample rate shifted by 45° all miss the peak by 3 dB
+        let mut meter = LoudnessMeter::new(48000, 2);
+        sine(&mut meter, 12000.0, -6.0, PI / 4.0, 0.5);
+        let l = meter.finish();
+        assert!((l.sample_peak_dbfs.unwrap() + 9.01).abs() < 0.05);
+        assert!((l.true_peak_dbtp.unwrap() + 6.0).abs() < 0.3);
+
+        let mut meter = LoudnessMeter::new(44100, 1);
+        meter.push(&[0.0; 44100]);
+        let l = meter.finish();
+        assert_eq!(l.integrated_lufs, None);
+        assert_eq!(l.true_peak_dbtp, None);
+    }
+
+    #[test]
+    fn test_loudness_saved_in_folder() {
+        let dir = get_temp_dir("loudness");
+        let folder = dir.join("folder");
+        fs::create_dir_all(&folder).unwrap();
+        create_tempo_folder(&folder).unwrap();
+
+        // a second of mono 16 bit 1 kHz sine at 48 kHz
+        let samples: Vec<i16> = (0..48000)
+            .map(|i| (16384.0 * (2.0 * PI * 1000.0 * i as f64 / 48000.0).sin()) as i16)
+            .collect();
+        let mut wav = b"RIFF".to_vec();
+        wav.extend_from_slice(&(36 + samples.len() as u32 * 2).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt \x10\0\0\0");
+        wav.extend_from_slice(&[1, 0, 1, 0]);
+        wav.extend_from_slice(&48000u32.to_le_bytes());
+        wav.extend_from_slice(&96000u32.to_le_bytes());
+        wav.extend_from_slice(&[2, 0, 16, 0]);
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&(samples.len() as u32 * 2).to_le_bytes());
+        for s in samples {
+            wav.extend_from_slice(&s.to_le_bytes());
+        }
+        let file = dir.join("sine.wav");
+        fs::write(&file, wav).unwrap();
+
+        let hash = add_file(&folder, "user", &file).unwrap();
+        assert_eq!(Loudness::load(&folder, &hash), None);
+
+        let measured = Loudness::get(&folder, &hash).unwrap();
+        assert!(get_file_loudness_path(&folder, &hash).exists());
+        let saved = Loudness::load(&folder, &hash).unwrap();
+        assert!((saved.integrated_lufs.unwrap() - measured.integrated_lufs.unwrap()).abs() < 1e-9);
+
+        // other clients read the saved result instead of measuring again
+        fs::remove_file(get_file_path(&folder, &hash)).unwrap();
+        assert_eq!(Loudness::get(&folder, &hash).unwrap(), saved);
+
+        // results from newer versions are kept
+        let newer = StoredLoudness {
+            version: VERSION + 1,
+            loudness: saved,
+        };
+        let path = get_file_loudness_path(&folder, &hash);
+        fs::write(&path, serde_json::to_vec(&newer).unwrap()).unwrap();
+        assert_eq!(Loudness::load(&folder, &hash), None);
+        fs::write(get_file_path(&folder, &hash), fs::read(&file).unwrap()).unwrap();
+        Loudness::get(&folder, &hash).unwrap();
+        assert_eq!(load_stored(&folder, &hash).unwrap().version, VERSION + 1);
+    }
+}
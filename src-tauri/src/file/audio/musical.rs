@@ -0,0 +1,453 @@
+// tempo and key estimation of audio files
+
+/*
+both estimates are made from a mono downmix of the whole file.
+
+tempo:
+- the onset strength of every 10 ms hop is how much the log energy of the (first differenced, so mostly
+  high frequency) signal rises from the hop before, drums and other transients make big rises.
+- the autocorrelation of the onset strength peaks at lags that are multiples of the beat period.
+- every tempo between MIN_BPM and MAX_BPM in 0.1 steps is scored by the autocorrelation at its period
+  and a few multiples of it, weighted towards 120 bpm (a log-normal prior like librosa's) so that we pick
+  the tempo people would tap along to rather than double or half of it.
+
+key:
+- the downmix is decimated to around 11 kHz and split into overlapping Hann windowed frames. the magnitude
+  of every FFT bin between 55 Hz and 2 kHz is added to the pitch class closest to its frequency (a chromagram).
+- the chroma summed over the file is correlated with the Krumhansl-Kessler key profiles rotated to every
+  tonic, the best of the 24 correlations is the key.
+
+both are rough, loops and songs with a steady beat and a clear tonal center work best.
+results are saved next to the file's FileInfo and versioned like loudness.
+
+see
+https://librosa.org/doc/main/generated/librosa.feature.tempo.html
+http://rnhart.net/articles/key-finding/
+ */
+
+use std::{f64::consts::PI, fs, path::Path};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use super::{fft::fft, pcm::PcmReader};
+use crate::{
+    misc::{path_to_str, Result},
+    shared::{KeyMode, MusicalAnalysis, MusicalKey},
+    structure::{get_file_musical_path, get_file_path},
+};
+
+// bump when the analysis changes so saved results are redone
+const VERSION: u32 = 1;
+
+const HOPS_PER_SEC: f64 = 100.0;
+const MIN_BPM: f64 = 60.0;
+const MAX_BPM: f64 = 200.0;
+const PRIOR_BPM: f64 = 120.0;
+// multiples of the beat period that are scored
+const BEAT_MULTIPLES: usize = 4;
+// tempo isn't estimated for less than this many seconds of audio
+const MIN_TEMPO_SECS: f64 = 4.0;
+
+const KEY_SAMPLE_RATE: u32 = 11025;
+const KEY_FRAME: usize = 4096;
+const KEY_HOP: usize = KEY_FRAME / 2;
+const MIN_KEY_FREQ: f64 = 55.0;
+const MAX_KEY_FREQ: f64 = 2000.0;
+
+// Krumhansl-Kessler profiles, starting at the tonic
+const MAJOR_PROFILE: [f64; 12] = [
+    6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+];
+const MINOR_PROFILE: [f64; 12] = [
+    6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
+];
+
+#[derive(Serialize, Deserialize)]
+struct StoredAnalysis {
+    version: u32,
+    analysis: MusicalAnalysis,
+}
+
+fn load_stored(folder: &Path, file_sha256: &str) -> Option<StoredAnalysis> {
+    let data = fs::read(get_file_musical_path(folder, file_sha256)).ok()?;
+    serde_json::from_slice(&data).ok()
+}
+
+fn pearson(a: &[f64], b: &[f64]) -> f64 {
+    let n = a.len() as f64;
+    let (ma, mb) = (a.iter().sum::<f64>() / n, b.iter().sum::<f64>() / n);
+    let cov: f64 = a.iter().zip(b).map(|(x, y)| (x - ma) * (y - mb)).sum();
+    let va: f64 = a.iter().map(|x| (x - ma).powi(2)).sum();
+    let vb: f64 = b.iter().map(|y| (y - mb).powi(2)).sum();
+    if va == 0.0 || vb == 0.0 {
+        0.0
+    } else {
+        cov / (va * vb).sqrt()
+    }
+}
+
+/// Estimates tempo and key of interleaved samples fed to it in blocks.
+pub struct MusicalAnalyzer {
+    channels: usize,
+
+    hop_len: usize,
+    // actual hops per second, the hop length is rounded
+    hop_rate: f64,
+    hop_pos: usize,
+    hop_energy: f64,
+    prev_sample: f64,
+    prev_log_energy: Option<f64>,
+    // onset strength of every complete hop
+    onsets: Vec<f64>,
+
+    decimation: usize,
+    decimated_pos: usize,
+    decimated_sum: f64,
+    // decimated samples that haven't made a full frame yet
+    frame: Vec<f64>,
+    window: Vec<f64>,
+    // pitch class of every FFT bin, None if it's outside the analyzed range
+    bin_pitch_classes: Vec<Option<usize>>,
+    chroma: [f64; 12],
+}
+
+impl MusicalAnalyzer {
+    pub fn new(sample_rate: u32, channels: u16) -> Self {
+        let decimation = (sample_rate / KEY_SAMPLE_RATE).max(1) as usize;
+        let key_rate = sample_rate as f64 / decimation as f64;
+        let hop_len = (sample_rate as f64 / HOPS_PER_SEC).round().max(1.0) as usize;
+
+        MusicalAnalyzer {
+            channels: channels as usize,
+            hop_len,
+            hop_rate: sample_rate as f64 / hop_len as f64,
+            hop_pos: 0,
+            hop_energy: 0.0,
+            prev_sample: 0.0,
+            prev_log_energy: None,
+            onsets: vec![],
+            decimation,
+            decimated_pos: 0,
+            decimated_sum: 0.0,
+            frame: Vec::with_capacity(KEY_FRAME),
+            window: (0..KEY_FRAME)
+                .map(|n| 0.5 - 0.5 * (2.0 * PI * n as f64 / KEY_FRAME as f64).cos())
+                .collect(),
+            bin_pitch_classes: (0..KEY_FRAME / 2)
+                .map(|k| {
+                    let freq = k as f64 * key_rate / KEY_FRAME as f64;
+                    (MIN_KEY_FREQ..=MAX_KEY_FREQ).contains(&freq).then(|| {
+                        // midi note, C is pitch class 0
+                        let note = 69.0 + 12.0 * (freq / 440.0).log2();
+                        note.round() as usize % 12
+                    })
+                })
+                .collect(),
+            chroma: [0.0; 12],
+        }
+    }
+
+    pub fn push(&mut self, samples: &[f32]) {
+        for frame in samples.chunks_exact(self.channels) {
+            let x = frame.iter().map(|s| *s as f64).sum::<f64>() / self.channels as f64;
+
+            let diff = x - self.prev_sample;
+            self.prev_sample = x;
+            self.hop_energy += diff * diff;
+            self.hop_pos += 1;
+            if self.hop_pos == self.hop_len {
+                self.push_hop();
+            }
+
+            self.decimated_sum += x;
+            self.decimated_pos += 1;
+            if self.decimated_pos == self.decimation {
+                self.frame.push(self.decimated_sum / self.decimation as f64);
+                self.decimated_pos = 0;
+                self.decimated_sum = 0.0;
+                if self.frame.len() == KEY_FRAME {
+                    self.push_frame();
+                }
+            }
+        }
+    }
+
+    fn push_hop(&mut self) {
+        let log_energy = (1.0 + 1000.0 * self.hop_energy / self.hop_len as f64).ln();
+        if let Some(prev) = self.prev_log_energy {
+            self.onsets.push((log_energy - prev).max(0.0));
+        }
+        self.prev_log_energy = Some(log_energy);
+        self.hop_pos = 0;
+        self.hop_energy = 0.0;
+    }
+
+    fn push_frame(&mut self) {
+        let mut re: Vec<f64> = self
+            .frame
+            .iter()
+            .zip(self.window.iter())
+            .map(|(x, w)| x * w)
+            .collect();
+        let mut im = vec![0.0; KEY_FRAME];
+        fft(&mut re, &mut im);
+
+        for (k, pc) in self.bin_pitch_classes.iter().enumerate() {
+            if let Some(pc) = pc {
+                self.chroma[*pc] += (re[k] * re[k] + im[k] * im[k]).sqrt();
+            }
+        }
+
+        self.frame.drain(..KEY_HOP);
+    }
+
+    fn bpm(&self) -> Option<f64> {
+        if (self.onsets.len() as f64) < MIN_TEMPO_SECS * self.hop_rate {
+            return None;
+        }
+
+        let mean = self.onsets.iter().sum::<f64>() / self.onsets.len() as f64;
+        let onsets: Vec<f64> = self.onsets.iter().map(|o| o - mean).collect();
+
+        let max_lag = ((60.0 / MIN_BPM * self.hop_rate) as usize + 1) * BEAT_MULTIPLES;
+        let ac: Vec<f64> = (0..=max_lag.min(onsets.len() - 1))
+            .map(|lag| {
+                onsets
+                    .iter()
+                    .zip(onsets[lag..].iter())
+                    .map(|(a, b)| a * b)
+                    .sum::<f64>()
+            })
+            .collect();
+        if ac[0] <= 0.0 {
+            return None;
+        }
+
+        // linearly interpolated, normalized autocorrelation at a fractional lag
+        let ac_at = |lag: f64| {
+            let i = lag.floor() as usize;
+            let t = lag - i as f64;
+            match (ac.get(i), ac.get(i + 1)) {
+                (Some(a), Some(b)) => Some((a * (1.0 - t) + b * t) / ac[0]),
+                _ => None,
+            }
+        };
+
+        let mut best: Option<(f64, f64)> = None;
+        let steps = ((MAX_BPM - MIN_BPM) * 10.0).round() as usize;
+        for step in 0..=steps {
+            let bpm = MIN_BPM + step as f64 / 10.0;
+            let period = 60.0 / bpm * self.hop_rate;
+
+            let score: f64 = (1..=BEAT_MULTIPLES)
+                .map_while(|m| ac_at(period * m as f64))
+                .sum();
+            let prior = (-0.5 * (bpm / PRIOR_BPM).log2().powi(2)).exp();
+            let score = score * prior;
+
+            if best.is_none_or(|(_, s)| score > s) {
+                best = Some((bpm, score));
+            }
+        }
+
+        best.filter(|(_, s)| *s > 0.0).map(|(bpm, _)| bpm)
+    }
+
+    fn key(&self) -> Option<MusicalKey> {
+        if self.chroma.iter().all(|c| *c == 0.0) {
+            return None;
+        }
+
+        let mut best: Option<(MusicalKey, f64)> = None;
+        for tonic in 0..12 {
+            // chroma starting at the tonic
+            let rotated: Vec<f64> = (0..12).map(|i| self.chroma[(tonic + i) % 12]).collect();
+            for (mode, profile) in [
+                (KeyMode::Major, &MAJOR_PROFILE),
+                (KeyMode::Minor, &MINOR_PROFILE),
+            ] {
+                let r = pearson(&rotated, profile);
+                if best.as_ref().is_none_or(|(_, b)| r > *b) {
+                    best = Some((
+                        MusicalKey {
+                            tonic: tonic as u8,
+                            mode,
+                        },
+                        r,
+                    ));
+                }
+            }
+        }
+
+        best.filter(|(_, r)| *r > 0.0).map(|(k, _)| k)
+    }
+
+    pub fn finish(self) -> MusicalAnalysis {
+        MusicalAnalysis {
+            bpm: self.bpm(),
+            key: self.key(),
+        }
+    }
+}
+
+impl MusicalAnalysis {
+    /// Gets the tempo and key of an audio file in a folder, estimating them if no one has yet.
+    pub fn get(folder: &Path, file_sha256: &str) -> Result<Self> {
+        let stored = load_stored(folder, file_sha256);
+        if let Some(s) = stored.as_ref().filter(|s| s.version == VERSION) {
+            return Ok(s.analysis.clone());
+        }
+
+        let analysis = Self::analyze(&get_file_path(folder, file_sha256))?;
+
+        if stored.is_some_and(|s| s.version > VERSION) {
+            return Ok(analysis);
+        }
+
+        let path = get_file_musical_path(folder, file_sha256);
+        let stored = StoredAnalysis {
+            version: VERSION,
+            analysis: analysis.clone(),
+        };
+        let res = serde_json::to_vec_pretty(&stored)
+            .map_err(|e| e.to_string())
+            .and_then(|data| fs::write(&path, data).map_err(|e| e.to_string()));
+        if let Err(e) = res {
+            warn!(
+                "MusicalAnalysis::get(): failed to save analysis at {}: {e}",
+                path_to_str(&path)
+            );
+        }
+
+        Ok(analysis)
+    }
+
+    /// Tempo and key of a file in a folder if they've been estimated before.
+    pub fn load(folder: &Path, file_sha256: &str) -> Option<Self> {
+        load_stored(folder, file_sha256)
+            .filter(|s| s.version == VERSION)
+            .map(|s| s.analysis)
+    }
+
+    /// Decodes an audio file and estimates its tempo and key.
+    pub fn analyze(file: &Path) -> Result<Self> {
+        let mut reader = PcmReader::open(file)?;
+        let mut analyzer = MusicalAnalyzer::new(reader.sample_rate, reader.channels);
+        reader.read_all(|block| analyzer.push(block))?;
+        Ok(analyzer.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{file::add_file, structure::create_tempo_folder, tests::get_temp_dir};
+
+    const RATE: u32 = 22050;
+
+    fn tone(freqs: &[f64], secs: f64) -> Vec<f32> {
+        (0..(RATE as f64 * secs) as usize)
+            .map(|i| {
+                let t = i as f64 / RATE as f64;
+                (freqs.iter().map(|f| (2.0 * PI * f * t).sin()).sum::<f64>() * 0.2) as f32
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_bpm() {
+        // short noise bursts on every beat at 128 bpm, 20 seconds
+        let period = 60.0 / 128.0 * RATE as f64;
+        let mut seed = 1u32;
+        let samples: Vec<f32> = (0..RATE as usize * 20)
+            .map(|i| {
+                seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+                let noise = (seed >> 8) as f32 / (1 << 24) as f32 - 0.5;
+                if (i as f64 % period) < 400.0 {
+                    noise
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+
+        let mut analyzer = MusicalAnalyzer::new(RATE, 1);
+        analyzer.push(&samples);
+        let bpm = analyzer.finish().bpm.unwrap();
+        assert!((bpm - 128.0).abs() < 0.5, "{bpm}");
+
+        let mut analyzer = MusicalAnalyzer::new(RATE, 1);
+        analyzer.push(&[0.0; RATE as usize]);
+        let a = analyzer.finish();
+        assert_eq!(a.bpm, None);
+        assert_eq!(a.key, None);
+    }
+
+    #[test]
+    fn test_key() {
+        let note = |midi: f64| 440.0 * 2f64.powf((midi - 69.0) / 12.0);
+        let chord = |notes: &[f64], secs: f64| {
+            tone(&notes.iter().map(|n| note(*n)).collect::<Vec<_>>(), secs)
+        };
+
+        // I IV V I in C major
+        let mut samples = chord(&[60.0, 64.0, 67.0], 2.0);
+        samples.extend(chord(&[60.0, 65.0, 69.0], 2.0));
+        samples.extend(chord(&[59.0, 62.0, 67.0], 2.0));
+        samples.extend(chord(&[60.0, 64.0, 67.0], 2.0));
+        let mut analyzer = MusicalAnalyzer::new(RATE, 1);
+        analyzer.push(&samples);
+        let key = analyzer.finish().key.unwrap();
+        assert_eq!((key.tonic, key.mode), (0, KeyMode::Major));
+
+        // i iv V i in A minor
+        let mut samples = chord(&[57.0, 60.0, 64.0], 2.0);
+        samples.extend(chord(&[57.0, 62.0, 65.0], 2.0));
+        samples.extend(chord(&[56.0, 59.0, 64.0], 2.0));
+        samples.extend(chord(&[57.0, 60.0, 64.0], 2.0));
+        let mut analyzer = MusicalAnalyzer::new(RATE, 1);
+        analyzer.push(&samples);
+        let key = analyzer.finish().key.unwrap();
+        assert_eq!((key.tonic, key.mode), (9, KeyMode::Minor));
+    }
+
+    #[test]
+    fn test_analysis_saved_in_folder() {
+        let dir = get_temp_dir("musical");
+        let folder = dir.join("folder");
+        fs::create_dir_all(&folder).unwrap();
+        create_tempo_folder(&folder).unwrap();
+
+        // 4 seconds of an A minor chord as a mono 16 bit WAV
+        let samples = tone(&[220.0, 261.63, 329.63], 4.0);
+        let mut wav = b"RIFF".to_vec();
+        wav.extend_from_slice(&(36 + samples.len() as u32 * 2).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt \x10\0\0\0");
+        wav.extend_from_slice(&[1, 0, 1, 0]);
+        wav.extend_from_slice(&RATE.to_le_bytes());
+        wav.extend_from_slice(&(RATE * 2).to_le_bytes());
+        wav.extend_from_slice(&[2, 0, 16, 0]);
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&(samples.len() as u32 * 2).to_le_bytes());
+        for s in samples {
+            wav.extend_from_slice(&((s * 32767.0) as i16).to_le_bytes());
+        }
+        let file = dir.join("chord.wav");
+        fs::write(&file, wav).unwrap();
+
+        let hash = add_file(&folder, "user", &file).unwrap();
+        assert_eq!(MusicalAnalysis::load(&folder, &hash), None);
+
+        let analysis = MusicalAnalysis::get(&folder, &hash).unwrap();
+        assert!(get_file_musical_path(&folder, &hash).exists());
+        assert_eq!(
+            MusicalAnalysis::load(&folder, &hash),
+            Some(analysis.clone())
+        );
+
+        // other clients read the saved result instead of analyzing again
+        fs::remove_file(get_file_path(&folder, &hash)).unwrap();
+        assert_eq!(MusicalAnalysis::get(&folder, &hash).unwrap(), analysis);
+    }
+}
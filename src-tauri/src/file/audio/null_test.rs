@@ -0,0 +1,438 @@
+// null testing two audio files, to check whether a new render actually changed anything
+
+/*
+renders of the same project are often shifted by a few samples (plugin latency, a different start marker etc.),
+so the files are aligned before subtracting one from the other:
+
+- the first ALIGN_SECS of both files are mixed down to mono and cross-correlated (with an FFT),
+  the lag with the highest correlation within MAX_OFFSET_SECS is the offset between them.
+- the file that starts later has that many frames skipped, then the files are subtracted sample by sample.
+  when one file ends the other is compared against silence.
+- the RMS of the difference is measured over the whole file and over 100 ms windows,
+  consecutive windows louder than DIVERGENCE_DBFS make up the regions where the files diverge.
+
+a perfect null (bit identical after alignment) has no difference level at all.
+the difference can be written to a 32 bit float WAV file so it can be listened to.
+ */
+
+use std::{
+    fs,
+    io::{BufWriter, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use super::{
+    fft::{fft, ifft},
+    pcm::PcmReader,
+};
+use crate::{
+    misc::{path_to_str, Result, TempoError},
+    shared::{DivergentRegion, NullTest},
+};
+
+const ALIGN_SECS: u32 = 10;
+const MAX_OFFSET_SECS: u32 = 1;
+
+const BLOCK_FRAMES: usize = 4096;
+const WINDOWS_PER_SEC: u32 = 10;
+// quieter than 16 bit dither is loud, so different dither doesn't count as a change
+const DIVERGENCE_DBFS: f64 = -60.0;
+
+fn to_db(v: f64) -> Option<f64> {
+    (v > 0.0).then(|| 20.0 * v.log10())
+}
+
+/// Samples of a file, starting with the ones already read for alignment.
+struct Source<R> {
+    reader: PcmReader<R>,
+    head: Vec<f32>,
+    head_pos: usize,
+    block: Vec<f32>,
+}
+
+impl<R: Read + Seek> Source<R> {
+    /// Reads `max_frames` frames into `out`, fewer only at the end of the file.
+    fn read(&mut self, out: &mut Vec<f32>, max_frames: usize) -> Result<usize> {
+        let channels = self.reader.channels as usize;
+        out.clear();
+
+        let from_head = (max_frames * channels).min(self.head.len() - self.head_pos);
+        out.extend_from_slice(&self.head[self.head_pos..self.head_pos + from_head]);
+        self.head_pos += from_head;
+
+        while out.len() < max_frames * channels {
+            let left = max_frames - out.len() / channels;
+            if self.reader.read(&mut self.block, left)? == 0 {
+                break;
+            }
+            out.extend_from_slice(&self.block);
+        }
+
+        Ok(out.len() / channels)
+    }
+
+    fn skip(&mut self, frames: usize) -> Result<()> {
+        let mut skipped = 0;
+        let mut buf = vec![];
+        while skipped < frames {
+            match self.read(&mut buf, (frames - skipped).min(BLOCK_FRAMES))? {
+                0 => break,
+                n => skipped += n,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads up to `frames` frames from the start of a file, returns them interleaved and mixed down to mono.
+fn read_head<R: Read + Seek>(
+    reader: &mut PcmReader<R>,
+    frames: usize,
+) -> Result<(Vec<f32>, Vec<f64>)> {
+    let channels = reader.channels as usize;
+    let mut head = vec![];
+    let mut block = vec![];
+
+    while head.len() < frames * channels {
+        let left = frames - head.len() / channels;
+        if reader.read(&mut block, left.min(BLOCK_FRAMES))? == 0 {
+            break;
+        }
+        head.extend_from_slice(&block);
+    }
+
+    let mono = head
+        .chunks_exact(channels)
+        .map(|f| f.iter().map(|s| *s as f64).sum::<f64>() / channels as f64)
+        .collect();
+
+    Ok((head, mono))
+}
+
+/// Frames `a` is delayed relative to `b`, negative if `b` is delayed.
+fn find_offset(a: &[f64], b: &[f64], max_offset: usize) -> i64 {
+    if a.is_empty() || b.is_empty() {
+        return 0;
+    }
+
+    // zero padded so the correlation doesn't wrap around
+    let n = (a.len() + b.len()).next_power_of_two();
+    let (mut a_re, mut a_im) = (a.to_vec(), vec![0.0; n]);
+    let (mut b_re, mut b_im) = (b.to_vec(), vec![0.0; n]);
+    a_re.resize(n, 0.0);
+    b_re.resize(n, 0.0);
+    fft(&mut a_re, &mut a_im);
+    fft(&mut b_re, &mut b_im);
+
+    // a times the conjugate of b
+    let (mut re, mut im): (Vec<f64>, Vec<f64>) = (0..n)
+        .map(|k| {
+            (
+                a_re[k] * b_re[k] + a_im[k] * b_im[k],
+                a_im[k] * b_re[k] - a_re[k] * b_im[k],
+            )
+        })
+        .unzip();
+    ifft(&mut re, &mut im);
+
+    let lags = (0..=max_offset.min(a.len() - 1) as i64)
+        .chain((1..=max_offset.min(b.len() - 1) as i64).map(|l| -l));
+
+    let mut best = (0, 0.0);
+    for lag in lags {
+        let c = re[lag.rem_euclid(n as i64) as usize];
+        if c > best.1 {
+            best = (lag, c);
+        }
+    }
+    best.0
+}
+
+/// Finds the regions where the difference is loud, a window at a time.
+struct Windows {
+    sample_rate: u32,
+    len: usize,
+    channels: usize,
+    // frame in file a after the current window
+    frame: u64,
+    pos: usize,
+    // sum of squares of the current window
+    sq: f64,
+    regions: Vec<DivergentRegion>,
+}
+
+impl Windows {
+    /// Adds the sum of squares of the difference of a frame.
+    fn push(&mut self, sq: f64) {
+        self.sq += sq;
+        self.pos += 1;
+        self.frame += 1;
+        if self.pos == self.len {
+            self.flush();
+        }
+    }
+
+    /// Ends the current window, adding it to the regions if it's loud enough.
+    fn flush(&mut self) {
+        if self.pos == 0 {
+            return;
+        }
+
+        let secs = |frame: u64| frame as f64 / self.sample_rate as f64;
+        let rms = (self.sq / (self.pos * self.channels) as f64).sqrt();
+
+        if let Some(db) = to_db(rms).filter(|db| *db > DIVERGENCE_DBFS) {
+            let (start_secs, end_secs) = (secs(self.frame - self.pos as u64), secs(self.frame));
+            match self.regions.last_mut() {
+                Some(r) if r.end_secs == start_secs => {
+                    r.end_secs = end_secs;
+                    r.rms_dbfs = r.rms_dbfs.max(db);
+                }
+                _ => self.regions.push(DivergentRegion {
+                    start_secs,
+                    end_secs,
+                    rms_dbfs: db,
+                }),
+            }
+        }
+
+        self.sq = 0.0;
+        self.pos = 0;
+    }
+}
+
+/// Writes interleaved samples to a 32 bit float WAV file.
+struct WavWriter<W: Write + Seek> {
+    w: W,
+    data_len: u32,
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    fn new(mut w: W, sample_rate: u32, channels: u16) -> Result<Self> {
+        let block_align = channels * 4;
+
+        // sizes are filled in by finish()
+        w.write_all(b"RIFF\0\0\0\0WAVEfmt \x10\0\0\0")?;
+        w.write_all(&3u16.to_le_bytes())?;
+        w.write_all(&channels.to_le_bytes())?;
+        w.write_all(&sample_rate.to_le_bytes())?;
+        w.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+        w.write_all(&block_align.to_le_bytes())?;
+        w.write_all(&32u16.to_le_bytes())?;
+        w.write_all(b"data\0\0\0\0")?;
+
+        Ok(WavWriter { w, data_len: 0 })
+    }
+
+    fn write(&mut self, samples: &[f32]) -> Result<()> {
+        for s in samples {
+            self.w.write_all(&s.to_le_bytes())?;
+        }
+        self.data_len = self.data_len.saturating_add(samples.len() as u32 * 4);
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<W> {
+        self.w.seek(SeekFrom::Start(4))?;
+        self.w
+            .write_all(&(self.data_len.saturating_add(36)).to_le_bytes())?;
+        self.w.seek(SeekFrom::Start(40))?;
+        self.w.write_all(&self.data_len.to_le_bytes())?;
+        self.w.flush()?;
+        Ok(self.w)
+    }
+}
+
+impl NullTest {
+    /// Aligns two audio files and subtracts them, writing the difference to `difference_file` if it's given.
+    pub fn run(a: &Path, b: &Path, difference_file: Option<&Path>) -> Result<Self> {
+        let (a, b) = (PcmReader::open(a)?, PcmReader::open(b)?);
+
+        let writer = match difference_file {
+            Some(path) => Some(WavWriter::new(
+                BufWriter::new(fs::File::create(path).map_err(|e| {
+                    TempoError::Audio(format!(
+                        "Failed to create difference file {}: {e}",
+                        path_to_str(path)
+                    ))
+                })?),
+                a.sample_rate,
+                a.channels,
+            )?),
+            None => None,
+        };
+
+        let mut result = Self::compare(a, b, writer)?;
+        result.difference_file = difference_file.map(|p| p.to_path_buf());
+        Ok(result)
+    }
+
+    fn compare<R: Read + Seek, W: Write + Seek>(
+        mut a: PcmReader<R>,
+        mut b: PcmReader<R>,
+        mut writer: Option<WavWriter<W>>,
+    ) -> Result<Self> {
+        if a.sample_rate != b.sample_rate {
+            return Err(TempoError::Audio(format!(
+                "Can't null test files with different sample rates ({} Hz and {} Hz)",
+                a.sample_rate, b.sample_rate
+            )));
+        }
+        if a.channels != b.channels {
+            return Err(TempoError::Audio(format!(
+                "Can't null test files with different channel counts ({} and {})",
+                a.channels, b.channels
+            )));
+        }
+
+        let sample_rate = a.sample_rate;
+        let channels = a.channels as usize;
+
+        let (a_head, a_mono) = read_head(&mut a, (sample_rate * ALIGN_SECS) as usize)?;
+        let (b_head, b_mono) = read_head(&mut b, (sample_rate * ALIGN_SECS) as usize)?;
+        let offset = find_offset(&a_mono, &b_mono, (sample_rate * MAX_OFFSET_SECS) as usize);
+
+        let source = |reader, head| Source {
+            reader,
+            head,
+            head_pos: 0,
+            block: vec![],
+        };
+        let (mut a, mut b) = (source(a, a_head), source(b, b_head));
+        if offset > 0 {
+            a.skip(offset as usize)?;
+        } else {
+            b.skip(offset.unsigned_abs() as usize)?;
+        }
+        // times are in file a
+        let start_frame = offset.max(0) as u64;
+
+        let mut windows = Windows {
+            sample_rate,
+            len: (sample_rate / WINDOWS_PER_SEC).max(1) as usize,
+            channels,
+            frame: start_frame,
+            pos: 0,
+            sq: 0.0,
+            regions: vec![],
+        };
+
+        let mut total_sq = 0.0;
+        let mut total_samples = 0u64;
+        let mut peak: f64 = 0.0;
+
+        let (mut a_block, mut b_block, mut diff) = (vec![], vec![], vec![]);
+        loop {
+            let frames = a
+                .read(&mut a_block, BLOCK_FRAMES)?
+                .max(b.read(&mut b_block, BLOCK_FRAMES)?);
+            if frames == 0 {
+                break;
+            }
+            a_block.resize(frames * channels, 0.0);
+            b_block.resize(frames * channels, 0.0);
+
+            diff.clear();
+            diff.extend(a_block.iter().zip(b_block.iter()).map(|(a, b)| a - b));
+            if let Some(w) = writer.as_mut() {
+                w.write(&diff)?;
+            }
+
+            for f in diff.chunks_exact(channels) {
+                let sq: f64 = f.iter().map(|d| (*d as f64).powi(2)).sum();
+                peak = f.iter().fold(peak, |p, d| p.max(d.abs() as f64));
+                total_sq += sq;
+                total_samples += channels as u64;
+                windows.push(sq);
+            }
+        }
+        windows.flush();
+
+        if let Some(w) = writer {
+            w.finish()?;
+        }
+
+        Ok(NullTest {
+            offset_frames: offset,
+            offset_secs: offset as f64 / sample_rate as f64,
+            difference_rms_dbfs: (total_samples > 0)
+                .then(|| to_db((total_sq / total_samples as f64).sqrt()))
+                .flatten(),
+            difference_peak_dbfs: to_db(peak),
+            regions: windows.regions,
+            difference_file: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::get_temp_dir;
+    use std::f64::consts::PI;
+
+    const RATE: u32 = 8000;
+
+    fn write_wav(path: &Path, samples: &[f32]) {
+        let mut w =
+            WavWriter::new(BufWriter::new(fs::File::create(path).unwrap()), RATE, 2).unwrap();
+        w.write(samples).unwrap();
+        w.finish().unwrap();
+    }
+
+    #[test]
+    fn test_null_test() {
+        let dir = get_temp_dir("null_test");
+
+        // 2 seconds of stereo noise
+        let mut seed = 7u32;
+        let a: Vec<f32> = (0..RATE as usize * 4)
+            .map(|_| {
+                seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+                (seed >> 8) as f32 / (1 << 24) as f32 - 0.5
+            })
+            .collect();
+
+        // the same, starting 37 frames later with a sine added from 1.0 to 1.3 seconds
+        let mut b = vec![0.0; 37 * 2];
+        b.extend(a.iter().enumerate().map(|(i, s)| {
+            let frame = i / 2;
+            if (8000..10400).contains(&frame) {
+                s + (0.1 * (2.0 * PI * 440.0 * frame as f64 / RATE as f64).sin()) as f32
+            } else {
+                *s
+            }
+        }));
+
+        let (a_path, b_path, diff_path) =
+            (dir.join("a.wav"), dir.join("b.wav"), dir.join("diff.wav"));
+        write_wav(&a_path, &a);
+        write_wav(&b_path, &b);
+
+        let res = NullTest::run(&a_path, &b_path, Some(&diff_path)).unwrap();
+        assert_eq!(res.offset_frames, -37);
+        assert_eq!(res.regions.len(), 1);
+        assert_eq!(
+            (res.regions[0].start_secs, res.regions[0].end_secs),
+            (1.0, 1.3)
+        );
+        // a 0.1 sine is -23 dBFS RMS, over 15% of the file
+        assert!((res.difference_rms_dbfs.unwrap() + 31.3).abs() < 0.5);
+        assert!((res.difference_peak_dbfs.unwrap() + 20.0).abs() < 0.1);
+
+        let mut diff = PcmReader::open(&diff_path).unwrap();
+        assert_eq!(
+            (diff.sample_rate, diff.channels, diff.frames()),
+            (RATE, 2, 16000)
+        );
+        let mut samples = vec![];
+        diff.read_all(|b| samples.extend_from_slice(b)).unwrap();
+        assert!(samples[..16000].iter().all(|s| *s == 0.0));
+
+        let res = NullTest::run(&a_path, &a_path, None).unwrap();
+        assert_eq!(res.offset_frames, 0);
+        assert_eq!(res.difference_rms_dbfs, None);
+        assert!(res.regions.is_empty());
+        assert!(res.difference_file.is_none());
+    }
+}
@@ -0,0 +1,530 @@
+// decoding audio files into samples
+
+/*
+this is only used for analysis (waveforms etc.), so everything is converted to f32 between -1 and 1.
+the chunk layouts are described in header.rs, here we also need where the samples start:
+
+WAV     samples are in the "data" chunk, little endian. 8 bit samples are unsigned, everything else is signed.
+        format tag 1 is integer PCM, 3 is IEEE float, WAVE_FORMAT_EXTENSIBLE (0xfffe) has the tag in its subformat.
+AIFF    samples are in the "SSND" chunk after an offset and block size (both u32), big endian and signed.
+        AIFC can also store "sowt" (little endian), "in24"/"in32", "fl32"/"fl64" and "raw " (unsigned 8 bit).
+
+samples are interleaved, a frame is one sample for every channel.
+compressed formats (MP3, FLAC, Ogg, µ-law etc.) are decoded with symphonia, see decode.rs.
+uncompressed files are still read here, it's a lot faster and they're what daws render.
+ */
+
+use std::{
+    fs,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use super::{
+    decode::Decoded,
+    header::{be_u16, be_u32, extended_to_f64, le_u16, le_u32, le_u64, read_vec},
+};
+use crate::misc::{path_to_str, Result, TempoError};
+
+// frames decoded per call to `read()` by `read_all()`
+const BLOCK_FRAMES: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Encoding {
+    Int { bytes: usize, big_endian: bool },
+    // 8 bit WAV and "raw " AIFC
+    UnsignedByte,
+    Float { bytes: usize, big_endian: bool },
+}
+
+impl Encoding {
+    fn bytes(&self) -> usize {
+        match self {
+            Encoding::Int { bytes, .. } | Encoding::Float { bytes, .. } => *bytes,
+            Encoding::UnsignedByte => 1,
+        }
+    }
+
+    fn decode(&self, b: &[u8]) -> f32 {
+        match *self {
+            Encoding::UnsignedByte => (b[0] as f32 - 128.0) / 128.0,
+            Encoding::Int { bytes, big_endian } => {
+                // widen into the top bytes of an i32 so the sign is kept
+                let mut v = [0u8; 4];
+                for (i, v) in v.iter_mut().take(bytes).enumerate() {
+                    *v = if big_endian { b[i] } else { b[bytes - 1 - i] };
+                }
+                i32::from_be_bytes(v) as f32 / 2_147_483_648.0
+            }
+            Encoding::Float {
+                bytes: 4,
+                big_endian,
+            } => {
+                let v = [b[0], b[1], b[2], b[3]];
+                if big_endian {
+                    f32::from_be_bytes(v)
+                } else {
+                    f32::from_le_bytes(v)
+                }
+            }
+            Encoding::Float { big_endian, .. } => {
+                let v: [u8; 8] = b[..8].try_into().unwrap();
+                (if big_endian {
+                    f64::from_be_bytes(v)
+                } else {
+                    f64::from_le_bytes(v)
+                }) as f32
+            }
+        }
+    }
+}
+
+/// Reads the samples of an audio file.
+pub struct PcmReader<R> {
+    pub sample_rate: u32,
+    pub channels: u16,
+    frames: u64,
+    source: Source<R>,
+}
+
+enum Source<R> {
+    // uncompressed WAV or AIFF
+    Pcm {
+        r: R,
+        encoding: Encoding,
+        // bytes of sample data left to read
+        remaining: u64,
+        buf: Vec<u8>,
+    },
+    Decoded(Box<Decoded>),
+}
+
+/// Where the samples of a file are and how they're stored.
+struct Layout {
+    sample_rate: u32,
+    channels: u16,
+    encoding: Encoding,
+    data_start: u64,
+    data_len: u64,
+}
+
+fn unsupported(what: &str) -> TempoError {
+    TempoError::Audio(format!(
+        "Can't decode {what}, only uncompressed WAV and AIFF files are supported"
+    ))
+}
+
+fn skip<R: Seek>(r: &mut R, size: u64) -> Option<()> {
+    r.seek(SeekFrom::Current((size + (size & 1)) as i64)).ok()?;
+    Some(())
+}
+
+fn wav_layout<R: Read + Seek>(r: &mut R) -> Result<Layout> {
+    let invalid = || TempoError::Audio("Invalid WAV file".into());
+
+    let riff = read_vec(r, 12).ok_or_else(invalid)?;
+    let is_rf64 = &riff[0..4] != b"RIFF";
+
+    let mut fmt: Option<Vec<u8>> = None;
+    let mut ds64_data_size: Option<u64> = None;
+
+    let (data_start, data_len) = loop {
+        let chunk = read_vec(r, 8).ok_or_else(invalid)?;
+        let size = le_u32(&chunk, 4).ok_or_else(invalid)? as u64;
+
+        match &chunk[0..4] {
+            b"fmt " => {
+                fmt = Some(read_vec(r, size.min(64) as usize).ok_or_else(invalid)?);
+                skip(r, size - size.min(64)).ok_or_else(invalid)?;
+            }
+            b"ds64" => {
+                let body = read_vec(r, size.min(28) as usize).ok_or_else(invalid)?;
+                ds64_data_size = le_u64(&body, 8);
+                skip(r, size - size.min(28)).ok_or_else(invalid)?;
+            }
+            b"data" => {
+                let len = match (is_rf64, ds64_data_size) {
+                    (true, Some(s)) if size == 0xffff_ffff => s,
+                    _ => size,
+                };
+                break (r.stream_position()?, len);
+            }
+            _ => skip(r, size).ok_or_else(invalid)?,
+        }
+    };
+
+    // the fmt chunk always comes before the data
+    let fmt = fmt.ok_or_else(invalid)?;
+    let mut tag = le_u16(&fmt, 0).ok_or_else(invalid)?;
+    let channels = le_u16(&fmt, 2).ok_or_else(invalid)?;
+    let sample_rate = le_u32(&fmt, 4).ok_or_else(invalid)?;
+    let bits = le_u16(&fmt, 14).ok_or_else(invalid)?;
+
+    if tag == 0xfffe {
+        tag = le_u16(&fmt, 24).ok_or_else(invalid)?;
+    }
+
+    let bytes = (bits as usize).div_ceil(8);
+    let encoding = match (tag, bytes) {
+        (1, 1) => Encoding::UnsignedByte,
+        (1, 2..=4) => Encoding::Int {
+            bytes,
+            big_endian: false,
+        },
+        (3, 4 | 8) => Encoding::Float {
+            bytes,
+            big_endian: false,
+        },
+        _ => {
+            return Err(unsupported(&format!(
+                "WAV format {tag:#06x} with {bits} bit samples"
+            )))
+        }
+    };
+
+    Ok(Layout {
+        sample_rate,
+        channels,
+        encoding,
+        data_start,
+        data_len,
+    })
+}
+
+fn aiff_layout<R: Read + Seek>(r: &mut R) -> Result<Layout> {
+    let invalid = || TempoError::Audio("Invalid AIFF file".into());
+
+    let form = read_vec(r, 12).ok_or_else(invalid)?;
+    let is_aifc = &form[8..12] == b"AIFC";
+
+    let mut comm: Option<Vec<u8>> = None;
+    let mut ssnd: Option<(u64, u64)> = None;
+
+    // COMM can come after SSND
+    while comm.is_none() || ssnd.is_none() {
+        let Some(chunk) = read_vec(r, 8) else {
+            break;
+        };
+        let size = be_u32(&chunk, 4).ok_or_else(invalid)? as u64;
+
+        match &chunk[0..4] {
+            b"COMM" => {
+                comm = Some(read_vec(r, size.min(64) as usize).ok_or_else(invalid)?);
+                skip(r, size - size.min(64)).ok_or_else(invalid)?;
+            }
+            b"SSND" => {
+                let header = read_vec(r, 8).ok_or_else(invalid)?;
+                let offset = be_u32(&header, 0).ok_or_else(invalid)? as u64;
+                let start = r.stream_position()? + offset;
+                ssnd = Some((start, size.saturating_sub(8 + offset)));
+                skip(r, size.saturating_sub(8)).ok_or_else(invalid)?;
+            }
+            _ => skip(r, size).ok_or_else(invalid)?,
+        }
+    }
+
+    let comm = comm.ok_or_else(invalid)?;
+    let (data_start, data_len) = ssnd.ok_or_else(invalid)?;
+
+    let channels = be_u16(&comm, 0).ok_or_else(invalid)?;
+    let bits = be_u16(&comm, 6).ok_or_else(invalid)?;
+    let sample_rate = extended_to_f64(comm.get(8..18).ok_or_else(invalid)?)
+        .ok_or_else(invalid)?
+        .round() as u32;
+
+    let compression: &[u8] = if is_aifc {
+        comm.get(18..22).ok_or_else(invalid)?
+    } else {
+        b"NONE"
+    };
+
+    let bytes = (bits as usize).div_ceil(8);
+    let encoding = match compression {
+        b"NONE" | b"twos" if (1..=4).contains(&bytes) => Encoding::Int {
+            bytes,
+            big_endian: true,
+        },
+        b"sowt" if (1..=4).contains(&bytes) => Encoding::Int {
+            bytes,
+            big_endian: false,
+        },
+        b"in24" => Encoding::Int {
+            bytes: 3,
+            big_endian: true,
+        },
+        b"in32" => Encoding::Int {
+            bytes: 4,
+            big_endian: true,
+        },
+        b"raw " => Encoding::UnsignedByte,
+        b"fl32" | b"FL32" => Encoding::Float {
+            bytes: 4,
+            big_endian: true,
+        },
+        b"fl64" | b"FL64" => Encoding::Float {
+            bytes: 8,
+            big_endian: true,
+        },
+        c => {
+            return Err(unsupported(&format!(
+                "AIFC compression \"{}\"",
+                String::from_utf8_lossy(c)
+            )))
+        }
+    };
+
+    Ok(Layout {
+        sample_rate,
+        channels,
+        encoding,
+        data_start,
+        data_len,
+    })
+}
+
+/// Whether an uncompressed file can be read without symphonia.
+fn is_pcm(magic: &[u8; 12]) -> bool {
+    matches!(
+        (&magic[0..4], &magic[8..12]),
+        (b"RIFF" | b"RF64" | b"BW64", b"WAVE") | (b"FORM", b"AIFF" | b"AIFC")
+    )
+}
+
+impl PcmReader<BufReader<fs::File>> {
+    /// Opens any audio file, see the notes at the top for which formats are supported.
+    pub fn open(path: &Path) -> Result<Self> {
+        let err = |e| {
+            TempoError::Audio(format!(
+                "Failed to read audio from {}: {e}",
+                path_to_str(path)
+            ))
+        };
+
+        let mut r = BufReader::new(fs::File::open(path)?);
+
+        let mut magic = [0u8; 12];
+        let is_pcm = r.read_exact(&mut magic).is_ok() && is_pcm(&magic);
+        r.seek(SeekFrom::Start(0))?;
+
+        // compressed files and WAV or AIFF encodings we can't read, eg µ-law, are left to symphonia
+        let pcm_err = match Self::new(r) {
+            Ok(reader) => return Ok(reader),
+            Err(e) => e,
+        };
+
+        let decoded = match Decoded::open(path) {
+            Ok(d) => d,
+            // the error about the WAV or AIFF file is more useful
+            Err(e) => return Err(err(if is_pcm { pcm_err } else { e })),
+        };
+        if decoded.channels == 0 || decoded.sample_rate == 0 {
+            return Err(err(TempoError::Audio(
+                "Audio file has no channels or no sample rate".into(),
+            )));
+        }
+
+        Ok(PcmReader {
+            sample_rate: decoded.sample_rate,
+            channels: decoded.channels,
+            frames: decoded.frames,
+            source: Source::Decoded(Box::new(decoded)),
+        })
+    }
+}
+
+impl<R: Read + Seek> PcmReader<R> {
+    /// Reads an uncompressed WAV or AIFF file.
+    pub fn new(mut r: R) -> Result<Self> {
+        let mut magic = [0u8; 12];
+        r.read_exact(&mut magic)
+            .map_err(|_| unsupported("this file"))?;
+        r.seek(SeekFrom::Start(0))?;
+
+        let layout = match (&magic[0..4], &magic[8..12]) {
+            (b"RIFF" | b"RF64" | b"BW64", b"WAVE") => wav_layout(&mut r)?,
+            (b"FORM", b"AIFF" | b"AIFC") => aiff_layout(&mut r)?,
+            _ => return Err(unsupported("this file")),
+        };
+
+        if layout.channels == 0 || layout.sample_rate == 0 {
+            return Err(TempoError::Audio(
+                "Audio file has no channels or no sample rate".into(),
+            ));
+        }
+
+        r.seek(SeekFrom::Start(layout.data_start))?;
+
+        let frame_len = layout.encoding.bytes() * layout.channels as usize;
+
+        Ok(PcmReader {
+            sample_rate: layout.sample_rate,
+            channels: layout.channels,
+            frames: layout.data_len / frame_len as u64,
+            source: Source::Pcm {
+                r,
+                encoding: layout.encoding,
+                remaining: layout.data_len,
+                buf: vec![],
+            },
+        })
+    }
+
+    /// Number of frames in the file, according to its header.
+    /// 0 for compressed files whose container doesn't store it.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Reads up to `max_frames` frames, replacing the contents of `out` with interleaved samples.
+    /// Returns the number of frames read, 0 at the end of the file.
+    pub fn read(&mut self, out: &mut Vec<f32>, max_frames: usize) -> Result<usize> {
+        out.clear();
+
+        let (r, encoding, remaining, buf) = match &mut self.source {
+            Source::Pcm {
+                r,
+                encoding,
+                remaining,
+                buf,
+            } => (r, encoding, remaining, buf),
+            Source::Decoded(d) => return d.read(out, max_frames),
+        };
+
+        let sample_len = encoding.bytes();
+        let frame_len = sample_len * self.channels as usize;
+        let len = (max_frames * frame_len).min(*remaining as usize);
+        let len = len - len % frame_len;
+
+        buf.resize(len, 0);
+
+        // files are sometimes shorter than their header says
+        let mut read = 0;
+        while read < len {
+            match r.read(&mut buf[read..])? {
+                0 => break,
+                n => read += n,
+            }
+        }
+        let read = read - read % frame_len;
+        *remaining = if read < len {
+            0
+        } else {
+            *remaining - read as u64
+        };
+
+        out.extend(
+            buf[..read]
+                .chunks_exact(sample_len)
+                .map(|b| encoding.decode(b)),
+        );
+
+        Ok(read / frame_len)
+    }
+
+    /// Calls `f` with blocks of interleaved samples until the end of the file.
+    pub fn read_all(&mut self, mut f: impl FnMut(&[f32])) -> Result<()> {
+        let mut block = vec![];
+        while self.read(&mut block, BLOCK_FRAMES)? > 0 {
+            f(&block);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_read_pcm() {
+        // 16 bit stereo WAV, 3 frames
+        let samples: [i16; 6] = [0, 0, 16384, -16384, i16::MAX, i16::MIN];
+        let mut wav = b"RIFF\0\0\0\0WAVE".to_vec();
+        wav.extend_from_slice(b"fmt \x10\0\0\0");
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&44100u32.to_le_bytes());
+        wav.extend_from_slice(&(44100u32 * 4).to_le_bytes());
+        wav.extend_from_slice(&4u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data\x0c\0\0\0");
+        for s in samples {
+            wav.extend_from_slice(&s.to_le_bytes());
+        }
+
+        let mut r = PcmReader::new(Cursor::new(wav)).unwrap();
+        assert_eq!((r.sample_rate, r.channels, r.frames()), (44100, 2, 3));
+
+        let mut out = vec![];
+        assert_eq!(r.read(&mut out, 2).unwrap(), 2);
+        assert_eq!(out, vec![0.0, 0.0, 0.5, -0.5]);
+        assert_eq!(r.read(&mut out, 2).unwrap(), 1);
+        assert_eq!(out[1], -1.0);
+        assert!(out[0] > 0.999);
+        assert_eq!(r.read(&mut out, 2).unwrap(), 0);
+
+        // 24 bit mono AIFF with the SSND chunk before COMM, 2 frames
+        let mut aiff = b"FORM\0\0\0\0AIFF".to_vec();
+        aiff.extend_from_slice(b"SSND\0\0\0\x0e\0\0\0\0\0\0\0\0");
+        aiff.extend_from_slice(&[0x40, 0, 0, 0xc0, 0, 0]);
+        aiff.extend_from_slice(b"COMM\0\0\0\x12");
+        aiff.extend_from_slice(&1u16.to_be_bytes());
+        aiff.extend_from_slice(&2u32.to_be_bytes());
+        aiff.extend_from_slice(&24u16.to_be_bytes());
+        aiff.extend_from_slice(&[0x40, 0x0e, 0xbb, 0x80, 0, 0, 0, 0, 0, 0]);
+
+        let mut r = PcmReader::new(Cursor::new(aiff)).unwrap();
+        assert_eq!((r.sample_rate, r.channels, r.frames()), (48000, 1, 2));
+
+        let mut all = vec![];
+        r.read_all(|b| all.extend_from_slice(b)).unwrap();
+        assert_eq!(all, vec![0.5, -0.5]);
+
+        assert!(PcmReader::new(Cursor::new(b"fLaC\0\0\0\x22".repeat(4))).is_err());
+    }
+
+    #[test]
+    fn test_open_decoded() {
+        let dir = crate::tests::get_temp_dir("pcm_decoded");
+
+        // 8 bit µ-law mono WAV, which is left to symphonia
+        let mut wav = b"RIFF\0\0\0\0WAVE".to_vec();
+        wav.extend_from_slice(b"fmt \x12\0\0\0");
+        wav.extend_from_slice(&7u16.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&8000u32.to_le_bytes());
+        wav.extend_from_slice(&8000u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&8u16.to_le_bytes());
+        wav.extend_from_slice(&0u16.to_le_bytes());
+        wav.extend_from_slice(b"data\x03\0\0\0\xff\x80\x00\0");
+        let riff_len = (wav.len() - 8) as u32;
+        wav[4..8].copy_from_slice(&riff_len.to_le_bytes());
+        let path = dir.join("ulaw.wav");
+        fs::write(&path, &wav).unwrap();
+
+        assert!(PcmReader::new(Cursor::new(wav)).is_err());
+
+        let mut r = PcmReader::open(&path).unwrap();
+        assert_eq!((r.sample_rate, r.channels), (8000, 1));
+
+        let mut all = vec![];
+        r.read_all(|b| all.extend_from_slice(b)).unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0], 0.0);
+        assert!(all[1] > 0.9);
+        assert!(all[2] < -0.9);
+    }
+
+    #[test]
+    fn test_open_unsupported() {
+        let dir = crate::tests::get_temp_dir("pcm_unsupported");
+
+        let path = dir.join("take.opus");
+        fs::write(&path, b"OggS not really an opus file".repeat(8)).unwrap();
+
+        let err = PcmReader::open(&path).err().unwrap();
+        assert!(err.to_string().starts_with("Failed to read audio from"));
+    }
+}
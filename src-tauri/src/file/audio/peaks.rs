@@ -0,0 +1,267 @@
+// waveform peaks for drawing audio files without decoding them in the frontend
+
+/*
+peaks are the minimum and maximum sample of every `frames_per_peak` frames, over all channels.
+we keep a few resolutions so the frontend can pick whichever is closest to the width it's drawing at,
+each level is 4 times coarser than the one before it.
+
+files in a folder never change (they're stored under their sha256), so peaks are cached in the data dir under
+the hash and never invalidated. the cache file is little endian:
+
+"TPKS"  u8 version  u32 sample rate  u64 frames  u8 level count
+then for every level:
+u32 frames per peak  u32 peak count  (i16 min, i16 max) * peak count
+
+samples are quantized to i16, that's far more precision than a waveform needs.
+ */
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use log::warn;
+
+use super::pcm::PcmReader;
+use crate::{
+    misc::{path_to_str, Result, TempoError},
+    shared::{PeakLevel, WaveformPeaks},
+};
+
+const MAGIC: &[u8] = b"TPKS";
+const VERSION: u8 = 1;
+
+// frames per peak of the finest level
+const FINEST: u32 = 256;
+const LEVELS: usize = 4;
+const LEVEL_FACTOR: usize = 4;
+
+/// Path of the cached peaks of a file in `cache_dir`.
+fn peaks_path(cache_dir: &Path, file_sha256: &str) -> PathBuf {
+    cache_dir.join("peaks").join(format!("{file_sha256}.peaks"))
+}
+
+fn quantize(v: f32) -> i16 {
+    (v.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16
+}
+
+fn dequantize(v: i16) -> f32 {
+    v as f32 / i16::MAX as f32
+}
+
+impl WaveformPeaks {
+    /// Gets the peaks of an audio file, generating and caching them if they aren't cached yet.
+    pub fn get(cache_dir: &Path, file_sha256: &str, file: &Path) -> Result<Self> {
+        let path = peaks_path(cache_dir, file_sha256);
+
+        if path.exists() {
+            match fs::read(&path).map(|d| Self::decode(&d)) {
+                Ok(Some(peaks)) => return Ok(peaks),
+                Ok(None) => warn!(
+                    "WaveformPeaks::get(): invalid peak file {}, regenerating",
+                    path_to_str(&path)
+                ),
+                Err(e) => warn!(
+                    "WaveformPeaks::get(): failed to read {}: {e}",
+                    path_to_str(&path)
+                ),
+            }
+        }
+
+        let peaks = Self::generate(file)?;
+
+        // a failed write just means we generate the peaks again next time
+        if let Err(e) = peaks.save(&path) {
+            warn!(
+                "WaveformPeaks::get(): failed to cache peaks at {}: {e}",
+                path_to_str(&path)
+            );
+        }
+
+        Ok(peaks)
+    }
+
+    /// Decodes an audio file and finds its peaks.
+    pub fn generate(file: &Path) -> Result<Self> {
+        let mut reader = PcmReader::open(file)?;
+        let channels = reader.channels as usize;
+
+        let mut min: Vec<f32> = vec![];
+        let mut max: Vec<f32> = vec![];
+        let mut frames = 0u64;
+
+        reader.read_all(|block| {
+            for frame in block.chunks_exact(channels) {
+                if frames.is_multiple_of(FINEST as u64) {
+                    min.push(f32::MAX);
+                    max.push(f32::MIN);
+                }
+                let (lo, hi) = (min.last_mut().unwrap(), max.last_mut().unwrap());
+                for s in frame {
+                    *lo = lo.min(*s);
+                    *hi = hi.max(*s);
+                }
+                frames += 1;
+            }
+        })?;
+
+        let mut levels = vec![PeakLevel {
+            frames_per_peak: FINEST,
+            min,
+            max,
+        }];
+
+        while levels.len() < LEVELS {
+            let prev = levels.last().unwrap();
+            levels.push(PeakLevel {
+                frames_per_peak: prev.frames_per_peak * LEVEL_FACTOR as u32,
+                min: prev
+                    .min
+                    .chunks(LEVEL_FACTOR)
+                    .map(|c| c.iter().copied().fold(f32::MAX, f32::min))
+                    .collect(),
+                max: prev
+                    .max
+                    .chunks(LEVEL_FACTOR)
+                    .map(|c| c.iter().copied().fold(f32::MIN, f32::max))
+                    .collect(),
+            });
+        }
+
+        Ok(WaveformPeaks {
+            sample_rate: reader.sample_rate,
+            frames,
+            levels,
+        })
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.push(VERSION);
+        out.extend_from_slice(&self.sample_rate.to_le_bytes());
+        out.extend_from_slice(&self.frames.to_le_bytes());
+        out.push(self.levels.len() as u8);
+
+        for level in self.levels.iter() {
+            out.extend_from_slice(&level.frames_per_peak.to_le_bytes());
+            out.extend_from_slice(&(level.min.len() as u32).to_le_bytes());
+            for (lo, hi) in level.min.iter().zip(level.max.iter()) {
+                out.extend_from_slice(&quantize(*lo).to_le_bytes());
+                out.extend_from_slice(&quantize(*hi).to_le_bytes());
+            }
+        }
+
+        out
+    }
+
+    fn decode(data: &[u8]) -> Option<Self> {
+        if !data.starts_with(MAGIC) || *data.get(4)? != VERSION {
+            return None;
+        }
+
+        let u32_at = |pos: usize| {
+            data.get(pos..pos + 4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        };
+
+        let sample_rate = u32_at(5)?;
+        let frames = u64::from_le_bytes(data.get(9..17)?.try_into().ok()?);
+        let level_count = *data.get(17)?;
+
+        let mut pos = 18;
+        let mut levels = vec![];
+        for _ in 0..level_count {
+            let frames_per_peak = u32_at(pos)?;
+            let count = u32_at(pos + 4)? as usize;
+            let peaks = data.get(pos + 8..pos + 8 + count * 4)?;
+            pos += 8 + count * 4;
+
+            let (min, max) = peaks
+                .chunks_exact(4)
+                .map(|p| {
+                    (
+                        dequantize(i16::from_le_bytes([p[0], p[1]])),
+                        dequantize(i16::from_le_bytes([p[2], p[3]])),
+                    )
+                })
+                .unzip();
+
+            levels.push(PeakLevel {
+                frames_per_peak,
+                min,
+                max,
+            });
+        }
+
+        Some(WaveformPeaks {
+            sample_rate,
+            frames,
+            levels,
+        })
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        let dir = path.parent().ok_or(TempoError::File(format!(
+            "Invalid peak file path {}",
+            path_to_str(path)
+        )))?;
+        fs::create_dir_all(dir)?;
+
+        // written next to the real file first so a crash can't leave a truncated cache
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, self.encode())?;
+        fs::rename(&tmp, path)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::get_temp_dir;
+
+    #[test]
+    fn test_peaks() {
+        let dir = get_temp_dir("peaks");
+
+        // mono 16 bit WAV, a ramp from -1 up over 1024 frames
+        let samples: Vec<i16> = (0..1024).map(|i| (i * 64 - 32768) as i16).collect();
+        let mut wav = b"RIFF\0\0\0\0WAVE".to_vec();
+        wav.extend_from_slice(b"fmt \x10\0\0\0");
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&8000u32.to_le_bytes());
+        wav.extend_from_slice(&16000u32.to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&(samples.len() as u32 * 2).to_le_bytes());
+        for s in samples {
+            wav.extend_from_slice(&s.to_le_bytes());
+        }
+        let file = dir.join("ramp.wav");
+        fs::write(&file, wav).unwrap();
+
+        let peaks = WaveformPeaks::get(&dir, "abc", &file).unwrap();
+        assert_eq!(peaks.sample_rate, 8000);
+        assert_eq!(peaks.frames, 1024);
+        assert_eq!(peaks.levels.len(), LEVELS);
+        assert_eq!(peaks.levels[0].frames_per_peak, 256);
+        assert_eq!(peaks.levels[0].min.len(), 4);
+        assert_eq!(peaks.levels[0].min[0], -1.0);
+        assert_eq!(peaks.levels[1].min.len(), 1);
+        assert!(peaks.levels[1].max[0] > 0.99);
+
+        // cached, and the same after quantization
+        fs::remove_file(&file).unwrap();
+        let cached = WaveformPeaks::get(&dir, "abc", &file).unwrap();
+        assert_eq!(cached.frames, peaks.frames);
+        for (a, b) in cached.levels.iter().zip(peaks.levels.iter()) {
+            assert_eq!(a.frames_per_peak, b.frames_per_peak);
+            for (x, y) in a.max.iter().zip(b.max.iter()) {
+                assert!((x - y).abs() < 1e-4);
+            }
+        }
+    }
+}
@@ -0,0 +1,486 @@
+// searching sample libraries for missing files
+
+/*
+users can configure sample library roots, eg ~/Music/Samples or an external drive.
+when a project references a file that can't be found, we search these roots for candidate files to relink to.
+
+a candidate has to have the same filename as the missing file (case insensitive).
+if the project knows the size of the missing file, the candidate must have the same size.
+if the sha256 of the missing file is known, the candidate must have the same hash.
+
+projects don't store hashes of their files, but the missing file might have been shared in the folder before.
+if exactly one file shared in the folder has the same filename (and size, if known) we use its hash.
+
+hashes are only calculated for candidates that passed the other checks, hashing a whole library would be slow.
+the same file can live in a few places inside of a library, candidates with identical contents are deduplicated when we hash them.
+
+walking a large library is slow too, so the index is cached in the cache directory:
+- the modification time of every directory is stored, the library is walked again if any of them changed
+  - adding, removing or renaming a file changes the modification time of its directory
+- hashes are stored along with the size and modification time of the file, they're reused while those stay the same
+ */
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    misc::{hash_file, path_to_str, Result},
+    shared::{ProjectFileRefScan, RelinkCandidate},
+    structure::{get_file_path, iter_files},
+    types::FileInfo,
+};
+
+// bump when the format of the cached index changes
+const VERSION: u32 = 1;
+
+fn index_path(cache_dir: &Path) -> PathBuf {
+    cache_dir.join("library.json")
+}
+
+fn shared_files_path(cache_dir: &Path) -> PathBuf {
+    cache_dir.join("shared_files.json")
+}
+
+/// Index of all files inside of the configured library roots.
+#[derive(Serialize, Deserialize)]
+pub struct LibraryIndex {
+    version: u32,
+    roots: Vec<PathBuf>,
+
+    // { directory : modification time when it was read }
+    dirs: HashMap<PathBuf, SystemTime>,
+
+    // { lowercase filename : paths }
+    files: HashMap<String, Vec<PathBuf>>,
+
+    // { path : hash of file }
+    hashes: HashMap<PathBuf, HashedFile>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct HashedFile {
+    size: u64,
+    modified: SystemTime,
+    sha256: String,
+}
+
+impl LibraryIndex {
+    /// Loads the cached index of `roots`.
+    /// The library is walked again if it changed since it was cached, hashes of unchanged files are kept.
+    pub fn load(cache_dir: &Path, roots: &[PathBuf]) -> Self {
+        let cached = fs::read(index_path(cache_dir))
+            .ok()
+            .and_then(|data| serde_json::from_slice::<Self>(&data).ok())
+            .filter(|index| index.version == VERSION);
+
+        match cached {
+            Some(index) if index.roots == roots && !index.changed() => index,
+            Some(index) => {
+                info!("LibraryIndex::load(): library changed, scanning again");
+                Self {
+                    hashes: index.hashes,
+                    ..Self::scan(roots)
+                }
+            }
+            None => Self::scan(roots),
+        }
+    }
+
+    /// Walks all library roots. Unreadable directories are skipped.
+    pub fn scan(roots: &[PathBuf]) -> Self {
+        let mut dirs: HashMap<PathBuf, SystemTime> = HashMap::new();
+        let mut files: HashMap<String, Vec<PathBuf>> = HashMap::new();
+        let mut to_read: Vec<PathBuf> = roots.to_vec();
+
+        while let Some(dir) = to_read.pop() {
+            let read = fs::metadata(&dir)
+                .and_then(|m| m.modified())
+                .and_then(|modified| Ok((modified, fs::read_dir(&dir)?)));
+            let (modified, ents) = match read {
+                Ok(r) => r,
+                Err(e) => {
+                    error!(
+                        "LibraryIndex::scan(): failed to read {}: {e}",
+                        path_to_str(&dir)
+                    );
+                    continue;
+                }
+            };
+
+            for ent in ents.filter_map(|ent| ent.ok()) {
+                let name = ent.file_name().to_string_lossy().to_string();
+                if name.starts_with('.') {
+                    continue;
+                }
+
+                let path = ent.path();
+                if path.is_dir() {
+                    to_read.push(path);
+                } else {
+                    files.entry(name.to_lowercase()).or_default().push(path);
+                }
+            }
+
+            dirs.insert(dir, modified);
+        }
+
+        info!("LibraryIndex::scan(): indexed {} filenames", files.len());
+
+        Self {
+            version: VERSION,
+            roots: roots.to_vec(),
+            dirs,
+            files,
+            hashes: HashMap::new(),
+        }
+    }
+
+    /// Whether any directory in the library changed since it was read.
+    /// Roots which couldn't be read count as changed, eg a drive that wasn't plugged in.
+    fn changed(&self) -> bool {
+        self.roots.iter().any(|root| !self.dirs.contains_key(root))
+            || self.dirs.iter().any(|(dir, modified)| {
+                fs::metadata(dir).and_then(|m| m.modified()).ok() != Some(*modified)
+            })
+    }
+
+    /// Caches this index, hashes of files that aren't in the library anymore are dropped.
+    pub fn save(&mut self, cache_dir: &Path) -> Result<()> {
+        let indexed: HashSet<&PathBuf> = self.files.values().flatten().collect();
+        self.hashes.retain(|path, _| indexed.contains(path));
+
+        fs::create_dir_all(cache_dir)?;
+
+        // written next to the real file first so a crash can't leave a truncated cache
+        let path = index_path(cache_dir);
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec(self)?)?;
+        fs::rename(&tmp, &path)?;
+
+        Ok(())
+    }
+
+    /// Finds candidate files for a missing file.
+    pub fn find(
+        &mut self,
+        filename: &str,
+        size: Option<u64>,
+        sha256: Option<&str>,
+    ) -> Vec<RelinkCandidate> {
+        let Some(paths) = self.files.get(&filename.to_lowercase()).cloned() else {
+            return vec![];
+        };
+
+        let mut candidates = vec![];
+        let mut seen: HashSet<String> = HashSet::new();
+
+        for path in paths {
+            let (candidate_size, modified) =
+                match fs::metadata(&path).and_then(|m| Ok((m.len(), m.modified()?))) {
+                    Ok(m) => m,
+                    Err(e) => {
+                        error!(
+                            "LibraryIndex::find(): failed to read metadata of {}: {e}",
+                            path_to_str(&path)
+                        );
+                        continue;
+                    }
+                };
+
+            if size.is_some_and(|s| s != candidate_size) {
+                continue;
+            }
+
+            let hash = match self.hash(&path, candidate_size, modified) {
+                Ok(h) => h,
+                Err(e) => {
+                    error!("LibraryIndex::find(): {e}");
+                    continue;
+                }
+            };
+
+            if sha256.is_some_and(|h| h != hash) || !seen.insert(hash) {
+                continue;
+            }
+
+            candidates.push(RelinkCandidate {
+                path,
+                size: candidate_size,
+            });
+        }
+
+        candidates
+    }
+
+    /// Hashes a file in the library, cached hashes are used if the file is unchanged.
+    fn hash(&mut self, path: &Path, size: u64, modified: SystemTime) -> Result<String> {
+        if let Some(h) = self.hashes.get(path) {
+            if h.size == size && h.modified == modified {
+                return Ok(h.sha256.clone());
+            }
+        }
+
+        let sha256 = hash_file(path)?;
+        self.hashes.insert(
+            path.to_path_buf(),
+            HashedFile {
+                size,
+                modified,
+                sha256: sha256.clone(),
+            },
+        );
+        Ok(sha256)
+    }
+}
+
+/// Filenames and sizes of files that have been shared in a folder.
+/// Used to find the hashes of missing files.
+#[derive(Serialize, Deserialize, Default)]
+pub struct SharedFiles {
+    // { sha256 : (lowercase filename, size) }
+    files: HashMap<String, (String, u64)>,
+}
+
+impl SharedFiles {
+    /// Reads the files shared in a folder.
+    /// Files in a folder never change, so only info of files that aren't cached yet is read.
+    pub fn load(folder: &Path, cache_dir: &Path) -> Result<Self> {
+        // { folder : files }
+        let mut cache: HashMap<PathBuf, SharedFiles> = fs::read(shared_files_path(cache_dir))
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+
+        let cached = cache.remove(folder).unwrap_or_default();
+        let mut files: HashMap<String, (String, u64)> = HashMap::new();
+
+        for sha256 in iter_files(folder)? {
+            if let Some(f) = cached.files.get(&sha256) {
+                files.insert(sha256, f.clone());
+                continue;
+            }
+
+            let info = FileInfo::load(folder, &sha256)
+                .and_then(|info| Ok((info, fs::metadata(get_file_path(folder, &sha256))?)));
+            match info {
+                Ok((info, meta)) => {
+                    files.insert(sha256, (info.filename.to_lowercase(), meta.len()));
+                }
+                // might still be syncing
+                Err(e) => warn!("SharedFiles::load(): skipping file {sha256}: {e}"),
+            }
+        }
+
+        let shared = Self { files };
+
+        cache.insert(
+            folder.to_path_buf(),
+            Self {
+                files: shared.files.clone(),
+            },
+        );
+        let res = fs::create_dir_all(cache_dir)
+            .map_err(|e| e.to_string())
+            .and_then(|_| serde_json::to_vec(&cache).map_err(|e| e.to_string()))
+            .and_then(|data| {
+                fs::write(shared_files_path(cache_dir), data).map_err(|e| e.to_string())
+            });
+        if let Err(e) = res {
+            warn!("SharedFiles::load(): failed to cache shared files: {e}");
+        }
+
+        Ok(shared)
+    }
+
+    /// Hash of the shared file with this filename and size.
+    /// `None` unless there's exactly one such file.
+    pub fn hash(&self, filename: &str, size: Option<u64>) -> Option<&str> {
+        let filename = filename.to_lowercase();
+        let mut matches = self
+            .files
+            .iter()
+            .filter(|(_, (f, s))| *f == filename && size.is_none_or(|size| size == *s))
+            .map(|(h, _)| h.as_str());
+
+        match (matches.next(), matches.next()) {
+            (Some(h), None) => Some(h),
+            _ => None,
+        }
+    }
+}
+
+impl ProjectFileRefScan {
+    /// Searches sample libraries for files that could replace missing files.
+    pub fn find_relinks(self, index: &mut LibraryIndex, shared: &SharedFiles) -> Self {
+        let missing = self
+            .missing
+            .into_iter()
+            .map(|mut m| {
+                let path = if m.file.abs.is_empty() {
+                    &m.file.rel
+                } else {
+                    &m.file.abs
+                };
+                let filename = get_ref_filename(path);
+                m.candidates =
+                    index.find(filename, m.file.size, shared.hash(filename, m.file.size));
+                m
+            })
+            .collect();
+
+        Self {
+            ok: self.ok,
+            missing,
+        }
+    }
+}
+
+/// Gets the filename of a path found in a project file.
+/// Project files may have been saved on another os, so both separators are handled.
+pub fn get_ref_filename(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        file::add_referenced_file,
+        structure::{create_tempo_folder, get_file_meta_path},
+        tests::get_temp_dir,
+    };
+
+    #[test]
+    fn test_find_candidates() {
+        let library = get_temp_dir("library");
+
+        fs::create_dir_all(library.join("Drums/Kicks")).unwrap();
+        fs::create_dir_all(library.join("Backup")).unwrap();
+
+        fs::write(library.join("Drums/Kicks/Kick.wav"), b"kick").unwrap();
+        fs::write(library.join("Backup/kick.wav"), b"kick").unwrap();
+        fs::write(library.join("Drums/kick.wav"), b"other kick").unwrap();
+
+        let mut index = LibraryIndex::scan(std::slice::from_ref(&library));
+
+        // identical files are deduplicated
+        assert_eq!(index.find("kick.wav", None, None).len(), 2);
+
+        let by_size = index.find("KICK.WAV", Some(10), None);
+        assert_eq!(by_size.len(), 1);
+        assert_eq!(by_size[0].path, library.join("Drums/kick.wav"));
+
+        let by_hash = index.find(
+            "kick.wav",
+            None,
+            Some(&hash_file(&library.join("Backup/kick.wav")).unwrap()),
+        );
+        assert_eq!(by_hash.len(), 1);
+        assert_eq!(by_hash[0].size, 4);
+
+        assert!(index.find("snare.wav", None, None).is_empty());
+    }
+
+    #[test]
+    fn test_cached_index() {
+        let library = get_temp_dir("library");
+        let cache_dir = get_temp_dir("library_cache");
+        let roots = vec![library.clone()];
+
+        fs::create_dir_all(library.join("Drums")).unwrap();
+        fs::write(library.join("Drums/kick.wav"), b"kick").unwrap();
+
+        let mut index = LibraryIndex::load(&cache_dir, &roots);
+        assert_eq!(index.find("kick.wav", None, None).len(), 1);
+        index.save(&cache_dir).unwrap();
+
+        // unchanged library is read from the cache, along with its hashes
+        let mut cached = LibraryIndex::load(&cache_dir, &roots);
+        assert!(!cached.changed());
+        assert_eq!(cached.hashes.len(), 1);
+        assert_eq!(cached.find("kick.wav", None, None).len(), 1);
+
+        // adding a file changes the modification time of its directory
+        // sleep so the new modification time can't be equal to the old one on coarse filesystems
+        std::thread::sleep(std::time::Duration::from_millis(1100));
+        fs::write(library.join("Drums/snare.wav"), b"snare").unwrap();
+        assert!(cached.changed());
+
+        let mut rescanned = LibraryIndex::load(&cache_dir, &roots);
+        assert_eq!(rescanned.hashes.len(), 1);
+        assert_eq!(rescanned.find("snare.wav", None, None).len(), 1);
+        rescanned.save(&cache_dir).unwrap();
+
+        // other roots aren't read from the cache
+        let other = get_temp_dir("library");
+        let mut other_index = LibraryIndex::load(&cache_dir, std::slice::from_ref(&other));
+        assert!(other_index.find("kick.wav", None, None).is_empty());
+
+        // roots that couldn't be read are tried again
+        let missing = LibraryIndex::scan(&[library.join("Unplugged Drive")]);
+        assert!(missing.changed());
+    }
+
+    #[test]
+    fn test_find_relinks_by_shared_hash() {
+        let folder = get_temp_dir("library_folder");
+        create_tempo_folder(&folder).unwrap();
+        let cache_dir = get_temp_dir("library_cache");
+
+        let library = get_temp_dir("library");
+        fs::create_dir_all(library.join("A")).unwrap();
+        fs::create_dir_all(library.join("B")).unwrap();
+        fs::write(library.join("A/kick.wav"), b"kick one").unwrap();
+        fs::write(library.join("B/kick.wav"), b"kick two").unwrap();
+
+        let mut index = LibraryIndex::scan(std::slice::from_ref(&library));
+        let empty = SharedFiles::default();
+        assert_eq!(
+            index
+                .find("kick.wav", Some(8), empty.hash("kick.wav", Some(8)))
+                .len(),
+            2
+        );
+
+        // the second kick was shared in the folder before, so only it can be the missing file
+        let shared_kick = get_temp_dir("library_src").join("Kick.wav");
+        fs::write(&shared_kick, b"kick two").unwrap();
+        let hash = add_referenced_file(&folder, "user", &shared_kick).unwrap();
+
+        let shared = SharedFiles::load(&folder, &cache_dir).unwrap();
+        assert_eq!(shared.hash("KICK.wav", Some(8)), Some(hash.as_str()));
+        assert_eq!(shared.hash("kick.wav", None), Some(hash.as_str()));
+        assert_eq!(shared.hash("kick.wav", Some(9)), None);
+        assert_eq!(shared.hash("snare.wav", None), None);
+
+        let candidates = index.find("kick.wav", Some(8), shared.hash("kick.wav", Some(8)));
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].path, library.join("B/kick.wav"));
+
+        // cached info is used for files that were already read
+        fs::remove_file(get_file_meta_path(&folder, &hash)).unwrap();
+        let cached = SharedFiles::load(&folder, &cache_dir).unwrap();
+        assert_eq!(cached.hash("kick.wav", None), Some(hash.as_str()));
+
+        // the hash is unknown if two shared files could be the missing file
+        let other_kick = get_temp_dir("library_src").join("kick.wav");
+        fs::write(&other_kick, b"kick 3!!").unwrap();
+        add_referenced_file(&folder, "user", &other_kick).unwrap();
+        let ambiguous = SharedFiles::load(&folder, &cache_dir).unwrap();
+        assert_eq!(ambiguous.hash("kick.wav", Some(8)), None);
+    }
+
+    #[test]
+    fn test_get_ref_filename() {
+        assert_eq!(get_ref_filename("/Users/a/Samples/kick.wav"), "kick.wav");
+        assert_eq!(get_ref_filename("C:\\Samples\\kick.wav"), "kick.wav");
+        assert_eq!(get_ref_filename("kick.wav"), "kick.wav");
+    }
+}
@@ -0,0 +1,287 @@
+// reading metadata from standard MIDI files
+
+/*
+a standard MIDI file is a list of chunks, each an ascii type, a u32 big endian length and data:
+
+MThd  6  u16 format  u16 track count  u16 division
+MTrk  n  events...
+MTrk  n  events...
+
+division is ticks per quarter note, or if the high bit is set, SMPTE frames per second (negated) and ticks per frame.
+
+each event in a track is a variable length delta time in ticks followed by a message:
+
+0x80..=0xef   channel messages, 0x9n with a nonzero velocity is a note on
+0xf0, 0xf7    sysex, variable length
+0xff          meta event: type, variable length, data
+              0x03 track name, 0x2f end of track, 0x51 tempo (u24 µs per quarter note), 0x58 time signature
+
+channel messages can leave out their status byte if it's the same as the previous message's (running status).
+
+see https://midi.org/standard-midi-files-specification
+ */
+
+use std::{fs, path::Path};
+
+use crate::{
+    misc::{path_to_str, Result, TempoError},
+    types::{MidiData, MidiTempo, MidiTimeSignature},
+};
+
+const HEADER_MAGIC: &[u8] = b"MThd";
+const TRACK_MAGIC: &[u8] = b"MTrk";
+
+const DEFAULT_TEMPO: u32 = 500_000;
+
+/// Whether the first bytes of a file are a MIDI file header.
+pub fn is_midi_header(header: &[u8]) -> bool {
+    header.starts_with(HEADER_MAGIC)
+}
+
+/// Whether a file is a standard MIDI file.
+pub fn is_midi_file(path: &Path) -> Result<bool> {
+    let mut magic = [0u8; 4];
+    let mut f = fs::File::open(path)?;
+
+    Ok(std::io::Read::read_exact(&mut f, &mut magic).is_ok() && is_midi_header(&magic))
+}
+
+impl MidiData {
+    pub fn read(path: &Path) -> Result<Self> {
+        Self::parse(&fs::read(path)?).ok_or(TempoError::File(format!(
+            "{} is not a valid MIDI file",
+            path_to_str(path)
+        )))
+    }
+
+    fn parse(data: &[u8]) -> Option<Self> {
+        if !is_midi_header(data) || u32_at(data, 4)? < 6 {
+            return None;
+        }
+
+        let format = u16_at(data, 8)?;
+        let division = u16_at(data, 12)?;
+
+        let mut midi = MidiData {
+            format,
+            ppq: (division & 0x8000 == 0).then_some(division),
+            track_names: vec![],
+            tempos: vec![],
+            time_signatures: vec![],
+            length_ticks: 0,
+            length_secs: 0.0,
+            note_count: 0,
+            lowest_note: None,
+            highest_note: None,
+        };
+
+        let mut pos = 8 + u32_at(data, 4)? as usize;
+        while pos + 8 <= data.len() {
+            let len = u32_at(data, pos + 4)? as usize;
+            let chunk = data.get(pos + 8..pos + 8 + len)?;
+
+            if &data[pos..pos + 4] == TRACK_MAGIC {
+                read_track(chunk, &mut midi)?;
+            }
+
+            pos += 8 + len;
+        }
+
+        midi.tempos.sort_by_key(|t| t.tick);
+        midi.time_signatures.sort_by_key(|t| t.tick);
+        midi.length_secs = ticks_to_secs(midi.length_ticks, division, &midi.tempos);
+
+        Some(midi)
+    }
+}
+
+fn u16_at(data: &[u8], pos: usize) -> Option<u16> {
+    data.get(pos..pos + 2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+}
+
+fn u32_at(data: &[u8], pos: usize) -> Option<u32> {
+    data.get(pos..pos + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// Reads a variable length quantity, returns the value and the position after it.
+fn read_vlq(data: &[u8], mut pos: usize) -> Option<(u64, usize)> {
+    let mut value = 0u64;
+
+    for _ in 0..4 {
+        let b = *data.get(pos)?;
+        pos += 1;
+        value = (value << 7) | (b & 0x7f) as u64;
+        if b & 0x80 == 0 {
+            return Some((value, pos));
+        }
+    }
+
+    None
+}
+
+fn read_track(data: &[u8], midi: &mut MidiData) -> Option<()> {
+    let mut pos = 0;
+    let mut tick = 0u64;
+    let mut running_status: Option<u8> = None;
+    let mut named = false;
+
+    while pos < data.len() {
+        let (delta, p) = read_vlq(data, pos)?;
+        pos = p;
+        tick += delta;
+
+        let mut status = *data.get(pos)?;
+        if status & 0x80 == 0 {
+            // running status, this byte is the first data byte
+            status = running_status?;
+        } else {
+            pos += 1;
+        }
+
+        match status {
+            0xff => {
+                let meta_type = *data.get(pos)?;
+                let (len, p) = read_vlq(data, pos + 1)?;
+                let value = data.get(p..p + len as usize)?;
+                pos = p + len as usize;
+
+                match meta_type {
+                    0x03 if !named => {
+                        named = true;
+                        let name = String::from_utf8_lossy(value).trim().to_string();
+                        if !name.is_empty() {
+                            midi.track_names.push(name);
+                        }
+                    }
+                    0x2f => break,
+                    0x51 if len == 3 => midi.tempos.push(MidiTempo {
+                        tick,
+                        bpm: 60_000_000.0
+                            / u32::from_be_bytes([0, value[0], value[1], value[2]]) as f64,
+                    }),
+                    0x58 if len >= 2 => midi.time_signatures.push(MidiTimeSignature {
+                        tick,
+                        numerator: value[0],
+                        denominator: 1u16.checked_shl(value[1] as u32)?,
+                    }),
+                    _ => (),
+                }
+            }
+            0xf0 | 0xf7 => {
+                let (len, p) = read_vlq(data, pos)?;
+                pos = p + len as usize;
+            }
+            0x80..=0xef => {
+                running_status = Some(status);
+
+                let len = match status & 0xf0 {
+                    0xc0 | 0xd0 => 1,
+                    _ => 2,
+                };
+                let value = data.get(pos..pos + len)?;
+                pos += len;
+
+                if status & 0xf0 == 0x90 && value[1] > 0 {
+                    let note = value[0];
+                    midi.note_count += 1;
+                    midi.lowest_note = Some(midi.lowest_note.map_or(note, |n| n.min(note)));
+                    midi.highest_note = Some(midi.highest_note.map_or(note, |n| n.max(note)));
+                }
+            }
+            // system messages other than sysex aren't allowed in files
+            _ => return None,
+        }
+    }
+
+    midi.length_ticks = midi.length_ticks.max(tick);
+
+    Some(())
+}
+
+/// Converts a tick position into seconds using a sorted tempo map.
+fn ticks_to_secs(ticks: u64, division: u16, tempos: &[MidiTempo]) -> f64 {
+    if division & 0x8000 != 0 {
+        let fps = -((division >> 8) as u8 as i8) as f64;
+        let ticks_per_frame = (division & 0xff) as f64;
+        return ticks as f64 / (fps * ticks_per_frame);
+    }
+
+    let ppq = division.max(1) as f64;
+    let mut secs = 0.0;
+    let mut last_tick = 0u64;
+    let mut us_per_quarter = DEFAULT_TEMPO as f64;
+
+    for t in tempos.iter().take_while(|t| t.tick < ticks) {
+        secs += (t.tick - last_tick) as f64 / ppq * us_per_quarter / 1_000_000.0;
+        last_tick = t.tick;
+        us_per_quarter = 60_000_000.0 / t.bpm;
+    }
+
+    secs + (ticks - last_tick) as f64 / ppq * us_per_quarter / 1_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(events: &[u8]) -> Vec<u8> {
+        let mut t = TRACK_MAGIC.to_vec();
+        t.extend_from_slice(&(events.len() as u32).to_be_bytes());
+        t.extend_from_slice(events);
+        t
+    }
+
+    #[test]
+    fn test_parse_midi() {
+        let mut data = HEADER_MAGIC.to_vec();
+        data.extend_from_slice(&[0, 0, 0, 6, 0, 1, 0, 2, 0x01, 0xe0]);
+
+        // tempo track: 120 bpm, 4/4, 60 bpm at tick 960
+        data.extend(track(&[
+            0x00, 0xff, 0x03, 0x04, b'S', b'o', b'n', b'g', //
+            0x00, 0xff, 0x51, 0x03, 0x07, 0xa1, 0x20, //
+            0x00, 0xff, 0x58, 0x04, 0x04, 0x02, 0x18, 0x08, //
+            0x87, 0x40, 0xff, 0x51, 0x03, 0x0f, 0x42, 0x40, //
+            0x00, 0xff, 0x2f, 0x00,
+        ]));
+
+        // two notes using running status, a note on with velocity 0 and a program change
+        data.extend(track(&[
+            0x00, 0xff, 0x03, 0x04, b'L', b'e', b'a', b'd', //
+            0x00, 0xc0, 0x05, //
+            0x00, 0x90, 0x3c, 0x64, //
+            0x83, 0x60, 0x48, 0x64, //
+            0x83, 0x60, 0x3c, 0x00, //
+            0x87, 0x40, 0x80, 0x48, 0x40, //
+            0x00, 0xff, 0x2f, 0x00,
+        ]));
+
+        let midi = MidiData::parse(&data).unwrap();
+
+        assert_eq!(midi.format, 1);
+        assert_eq!(midi.ppq, Some(480));
+        assert_eq!(midi.track_names, vec!["Song", "Lead"]);
+        assert_eq!(midi.tempos.len(), 2);
+        assert_eq!(midi.tempos[0].bpm, 120.0);
+        assert_eq!(midi.tempos[1].tick, 960);
+        assert_eq!(midi.tempos[1].bpm, 60.0);
+        assert_eq!(
+            midi.time_signatures,
+            vec![MidiTimeSignature {
+                tick: 0,
+                numerator: 4,
+                denominator: 4
+            }]
+        );
+        assert_eq!(midi.note_count, 2);
+        assert_eq!(midi.lowest_note, Some(0x3c));
+        assert_eq!(midi.highest_note, Some(0x48));
+        assert_eq!(midi.length_ticks, 1920);
+        // 2 beats at 120 bpm then 2 beats at 60 bpm
+        assert_eq!(midi.length_secs, 3.0);
+
+        assert!(MidiData::parse(b"RIFF").is_none());
+    }
+}
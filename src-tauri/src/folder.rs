@@ -9,13 +9,16 @@ use log::error;
 
 use crate::{
     channel::Channel,
-    daw::plugin::ProjectPluginScan,
+    daw::reader::get_path_reader,
     db::{iter_shared_db, SharedDb},
     misc::{path_to_str, Result, TempoError},
-    shared::{ChannelData, FolderData, PluginScan, SharedNote, TempoResult},
-    structure::{get_client_shared_db_path, iter_channels, iter_notes},
+    shared::{
+        ChannelData, FolderData, Loudness, MusicalAnalysis, NullTest, PluginScan,
+        ProjectFileRefScan, SharedNote, TempoResult, WaveformPeaks,
+    },
+    structure::{get_client_shared_db_path, get_file_path, iter_channels, iter_notes},
     tempo::{RuntimeFolder, Tempo},
-    types::{ChannelDoc, FileInfo},
+    types::{ChannelDoc, FileInfo, FileMeta},
 };
 
 pub struct Folder {
@@ -121,29 +124,102 @@ impl Folder {
     pub fn scan_project_plugins(&self, project: &Path) -> Result<PluginScan> {
         let folder = self.inner.path()?;
 
-        match ProjectPluginScan::new(project) {
-            Err(e) => Err(e),
-            Ok(s) => {
-                // TODO create trait for project plugin scanners
-                // and maybe for filerefs as well
-                match s {
-                    ProjectPluginScan::Ableton(mut s) => {
-                        for (username, db) in iter_shared_db(&folder)? {
-                            if let Err(e) = s.scan_db(&db, &username) {
-                                error!("scan_project_plugins(): failed to scan db for {username} in {}: {e}", path_to_str(&folder));
-                            }
-                        }
-                        Ok(s.done())
-                    }
-                }
+        let mut s = get_path_reader(project)?.scan_plugins(project)?;
+
+        for (username, db) in iter_shared_db(&folder)? {
+            if let Err(e) = s.scan_db(&db, &username) {
+                error!(
+                    "scan_project_plugins(): failed to scan db for {username} in {}: {e}",
+                    path_to_str(&folder)
+                );
             }
         }
+
+        Ok(s.done())
+    }
+
+    /// Scans the file refs of a project that's about to be added to this folder.
+    pub fn scan_project_file_refs(&self, project: &Path) -> Result<ProjectFileRefScan> {
+        ProjectFileRefScan::new(
+            project,
+            &self.inner.path()?,
+            &self.tempo.get_library_roots(),
+            &self.tempo.get_cache_dir(),
+        )
     }
 
     pub fn file_info(&self, file_sha256: &str) -> Result<FileInfo> {
         FileInfo::load(&self.inner.path()?, file_sha256)
     }
 
+    /// Gets the waveform peaks of an audio file in this folder.
+    pub fn waveform_peaks(&self, file_sha256: &str) -> Result<WaveformPeaks> {
+        let folder = self.inner.path()?;
+
+        if !matches!(self.file_info(file_sha256)?.meta, FileMeta::Audio(_)) {
+            return Err(TempoError::Audio(format!(
+                "Can't get waveform of {file_sha256}, it isn't an audio file"
+            )));
+        }
+
+        WaveformPeaks::get(
+            &self.tempo.get_cache_dir(),
+            file_sha256,
+            &get_file_path(&folder, file_sha256),
+        )
+    }
+
+    /// Measures the loudness of an audio file in this folder, saved for every client after the first time.
+    pub fn loudness(&self, file_sha256: &str) -> Result<Loudness> {
+        let folder = self.inner.path()?;
+
+        if !matches!(self.file_info(file_sha256)?.meta, FileMeta::Audio(_)) {
+            return Err(TempoError::Audio(format!(
+                "Can't measure loudness of {file_sha256}, it isn't an audio file"
+            )));
+        }
+
+        Loudness::get(&folder, file_sha256)
+    }
+
+    /// Estimates the tempo and key of an audio file in this folder, saved for every client after the first time.
+    pub fn musical_analysis(&self, file_sha256: &str) -> Result<MusicalAnalysis> {
+        let folder = self.inner.path()?;
+
+        if !matches!(self.file_info(file_sha256)?.meta, FileMeta::Audio(_)) {
+            return Err(TempoError::Audio(format!(
+                "Can't analyze tempo and key of {file_sha256}, it isn't an audio file"
+            )));
+        }
+
+        MusicalAnalysis::get(&folder, file_sha256)
+    }
+
+    /// Null tests two audio files in this folder against each other,
+    /// writing the difference to `difference_file` if it's given.
+    pub fn null_test(
+        &self,
+        a_sha256: &str,
+        b_sha256: &str,
+        difference_file: Option<&Path>,
+    ) -> Result<NullTest> {
+        let folder = self.inner.path()?;
+
+        for sha in [a_sha256, b_sha256] {
+            if !matches!(self.file_info(sha)?.meta, FileMeta::Audio(_)) {
+                return Err(TempoError::Audio(format!(
+                    "Can't null test {sha}, it isn't an audio file"
+                )));
+            }
+        }
+
+        NullTest::run(
+            &get_file_path(&folder, a_sha256),
+            &get_file_path(&folder, b_sha256),
+            difference_file,
+        )
+    }
+
     /// Copies a `shared.sqlite` db into this folder if it's valid
     pub fn copy_db(&self, db: &Path) -> Result<()> {
         self.inner.expect_valid()?;
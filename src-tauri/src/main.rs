@@ -11,6 +11,7 @@ mod file;
 mod folder;
 mod misc;
 mod note;
+mod pool;
 mod shared;
 mod state;
 mod structure;
@@ -134,12 +135,23 @@ fn main() {
             create_channel,
             create_note,
             add_comment,
+            get_comments_by_position,
             need_full_disk,
             open_full_disk,
             restart,
             fatal,
             copy_project,
+            copy_midi,
+            copy_stems,
+            copy_file_attachment,
+            copy_audio,
+            copy_render,
+            export_lineage_renders,
             get_file_info,
+            get_waveform_peaks,
+            analyze_loudness,
+            analyze_bpm_key,
+            null_test_audio,
             verify_user_has_ableton,
             scan_folder,
             scan_folders,
@@ -148,7 +160,10 @@ fn main() {
             remove_folder,
             scan_project_file_refs,
             scan_project_plugins,
-            get_last_plugin_scan_time
+            find_live_project_sets,
+            get_last_plugin_scan_time,
+            get_library_roots,
+            set_library_roots
         ])
         .run(tauri::generate_context!());
 
@@ -1,26 +1,35 @@
 use std::{
-    collections::HashMap,
-    path::Path,
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
 };
 
-use log::error;
+use log::warn;
 
 use crate::{
     channel::ChannelInner,
+    daw::{ableton::stage_ableton_live_project, project::ProjectTempo, reader::get_data_reader},
     db::SharedDb,
-    file::add_file,
+    file::{
+        copy_files_into, get_mime_type, get_unique_dir, get_unique_ref_filename, stage_audio_file,
+        stage_new_file, stage_project_file, stage_referenced_file, Staged,
+    },
     folder::FolderInner,
     misc::{new_ulid, path_to_str, Result, TempoError},
+    pool::map_bounded,
     shared::{
-        AudioFileInfo, NewAttachment, NewAudioAttachment, NewComment, NewNote,
-        NewProjectAttachment, PluginRef, ProjectInfo, SharedAttachment, SharedNote,
-        SharedProjectData, TempoResult,
+        AudioFileInfo, DuplicateAttachment, FileAttachmentInfo, FileErr, Loudness, MidiFileInfo,
+        MusicalAnalysis, NewAttachment, NewAudioAttachment, NewComment, NewCommentPosition,
+        NewFileAttachment, NewLiveProjectAttachment, NewMidiAttachment, NewNote,
+        NewProjectAttachment, NewStemsAttachment, PositionedComment, ProjectInfo, SharedAttachment,
+        SharedNote, SharedProjectData, TempoResult,
     },
-    structure::{file_exists, get_file_path},
+    structure::{file_exists, get_file_dir_path, get_file_path, iter_channels, iter_notes},
     tempo::Tempo,
     types::{
-        Attachment, AudioAttachment, Comment, FileInfo, FileMeta, NoteDoc, ProjectAttachment,
-        ProjectData, RepliableComment,
+        Attachment, AudioAttachment, AudioInfo, AudioMeta, ChannelDoc, Comment, CommentPosition,
+        FileAttachment, FileInfo, FileMeta, LiveProjectAttachment, LiveProjectSet, MidiAttachment,
+        NoteDoc, ProjectAttachment, ProjectData, RepliableComment, Stem, StemsAttachment,
     },
 };
 
@@ -64,21 +73,21 @@ impl Note {
         folder: FolderInner,
         channel: ChannelInner,
         note: NewNote,
-    ) -> Result<Self> {
-        let (note_ulid, doc) = NoteDoc::create(
+    ) -> Result<Created<Self>> {
+        let created = NoteDoc::create(
             &folder.path()?,
             &folder.username()?,
             channel.ulid().as_deref(),
             note,
         )?;
 
-        Ok(Self {
+        Ok(created.map(|(note_ulid, doc)| Self {
             tempo,
             folder,
             channel,
-            note_ulid: note_ulid.to_owned(),
+            note_ulid,
             doc,
-        })
+        }))
     }
 
     pub fn add_comment(mut self, comment: NewComment) -> Result<Self> {
@@ -88,15 +97,21 @@ impl Note {
             ));
         }
 
-        if self.doc.attachment.is_none() {
+        if self.doc.attachments.is_empty() {
             return Err(TempoError::Note(
-                "Cannot reply to a note without an attachment".into(),
+                "Cannot reply to a note without attachments".into(),
             ));
         }
 
+        let position = match comment.position {
+            Some(p) => Some(self.comment_position(p)?),
+            None => None,
+        };
+
         let new_comment = Comment {
             sender: self.folder.username()?,
             body: comment.body,
+            position,
         };
 
         if let Some(reply_ulid) = comment.reply_ulid {
@@ -134,8 +149,194 @@ impl Note {
         })
     }
 
-    pub fn attachment(&self) -> Option<&Attachment> {
-        self.doc.attachment.as_ref()
+    pub fn attachments(&self) -> &[Attachment] {
+        &self.doc.attachments
+    }
+
+    pub fn attachment(&self, index: usize) -> Result<&Attachment> {
+        self.doc
+            .attachments
+            .get(index)
+            .ok_or(TempoError::Note(format!(
+                "Note {} has no attachment at index {index}",
+                self.note_ulid
+            )))
+    }
+
+    /// This note and every note it's a reply to, oldest first.
+    pub fn lineage(&self) -> Result<Vec<Note>> {
+        let mut lineage = vec![];
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut next = Some(self.note_ulid.clone());
+
+        while let Some(ulid) = next {
+            if !seen.insert(ulid.clone()) {
+                return Err(TempoError::Note(format!(
+                    "Found a reply cycle at note {ulid}"
+                )));
+            }
+
+            let note = Note::load(
+                self.tempo.clone(),
+                self.folder.clone(),
+                self.channel.clone(),
+                &ulid,
+            )?;
+            next = note.doc.reply_ulid.clone();
+            lineage.push(note);
+        }
+
+        lineage.reverse();
+        Ok(lineage)
+    }
+
+    /// Copies every render in this note's lineage into a new directory inside of `dest_dir`.
+    /// Renders are prefixed with the number of the version they belong to.
+    pub fn export_lineage_renders(&self, dest_dir: &Path) -> Result<(PathBuf, Vec<FileErr>)> {
+        let folder = self.folder.path()?;
+        let lineage = self.lineage()?;
+
+        // { hash : filename in renders dir }
+        let mut files: HashMap<String, String> = HashMap::new();
+        let mut used: HashSet<String> = HashSet::new();
+        let mut errs = vec![];
+
+        for (i, note) in lineage.iter().enumerate() {
+            for hash in note.attachments().iter().filter_map(|a| a.render_hash()) {
+                if files.contains_key(hash) {
+                    continue;
+                }
+                match FileInfo::load(&folder, hash) {
+                    Ok(info) => {
+                        let filename = get_unique_ref_filename(
+                            &used,
+                            format!("{:02} {}", i + 1, info.filename),
+                        );
+                        used.insert(filename.clone());
+                        files.insert(hash.clone(), filename);
+                    }
+                    Err(e) => errs.push(FileErr {
+                        filename: format!("render of version {}", i + 1),
+                        err: e.to_string(),
+                    }),
+                }
+            }
+        }
+
+        if files.is_empty() && errs.is_empty() {
+            return Err(TempoError::Note(
+                "There are no renders in this note's history".into(),
+            ));
+        }
+
+        let renders_dir = get_unique_dir(dest_dir, "[tempo] renders")?;
+        errs.extend(copy_files_into(&folder, &files, &renders_dir)?);
+
+        Ok((renders_dir, errs))
+    }
+
+    /// Checks the position of a new comment and finds its bar and beat.
+    fn comment_position(&self, position: NewCommentPosition) -> Result<CommentPosition> {
+        let folder = self.folder.path()?;
+
+        let attachment = self
+            .doc
+            .attachments
+            .iter()
+            .find(|a| a.audio_hashes().contains(&&position.file_hash))
+            .ok_or(TempoError::Note(
+                "Comments can only be positioned on audio attached to the note".into(),
+            ))?;
+
+        if !position.start_secs.is_finite() || position.start_secs < 0.0 {
+            return Err(TempoError::Note(
+                "Comment position must be a time in the audio file".into(),
+            ));
+        }
+        if let Some(end) = position.end_secs {
+            if !end.is_finite() || end <= position.start_secs {
+                return Err(TempoError::Note(
+                    "The end of a comment's time range must be after its start".into(),
+                ));
+            }
+        }
+
+        // header durations can be slightly off, so only clearly wrong positions are rejected
+        if let FileMeta::Audio(AudioMeta {
+            info:
+                Some(AudioInfo {
+                    duration_secs: Some(duration),
+                    ..
+                }),
+            ..
+        }) = FileInfo::load(&folder, &position.file_hash)?.meta
+        {
+            if position.end_secs.unwrap_or(position.start_secs) > duration + 1.0 {
+                return Err(TempoError::Note(
+                    "Comment position is past the end of the audio file".into(),
+                ));
+            }
+        }
+
+        let bar_beat = match attachment {
+            Attachment::Project(p) if p.render_hash.as_ref() == Some(&position.file_hash) => {
+                match project_tempo(&folder, &p.hash) {
+                    Ok(t) => t.map(|t| t.bar_beat(position.start_secs)),
+                    Err(e) => {
+                        warn!("Note::comment_position(): failed to read project tempo: {e}");
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+
+        Ok(CommentPosition {
+            file_hash: position.file_hash,
+            start_secs: position.start_secs,
+            end_secs: position.end_secs,
+            bar_beat,
+        })
+    }
+
+    /// Comments and replies with positions, sorted by position.
+    /// Only comments on `file_hash` are returned if it's given.
+    pub fn positioned_comments(&self, file_hash: Option<&str>) -> Vec<PositionedComment> {
+        let mut comments: Vec<PositionedComment> = vec![];
+
+        for (ulid, c) in self.doc.comments.iter() {
+            comments.push(PositionedComment {
+                ulid: ulid.clone(),
+                reply_ulid: None,
+                comment: c.comment.clone(),
+            });
+            comments.extend(c.replies.iter().map(|(reply, r)| PositionedComment {
+                ulid: reply.clone(),
+                reply_ulid: Some(ulid.clone()),
+                comment: r.clone(),
+            }));
+        }
+
+        comments.retain(|c| {
+            c.comment
+                .position
+                .as_ref()
+                .is_some_and(|p| file_hash.is_none_or(|h| p.file_hash == h))
+        });
+
+        // ulids sort by time, so comments at the same position are in the order they were made
+        comments.sort_by(|a, b| {
+            let (pa, pb) = (a.comment.position.as_ref(), b.comment.position.as_ref());
+            let key = |p: Option<&CommentPosition>| {
+                p.map(|p| (p.start_secs, p.end_secs.unwrap_or(p.start_secs)))
+            };
+            key(pa)
+                .partial_cmp(&key(pb))
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.ulid.cmp(&b.ulid))
+        });
+
+        comments
     }
 
     pub fn get(&self) -> Result<TempoResult<SharedNote>> {
@@ -144,10 +345,9 @@ impl Note {
             &self.folder.username()?,
             self.channel.ulid().as_deref(),
             &self.note_ulid,
-            &self
-                .tempo
-                .get_data_dir_db()?
-                .ok_or(TempoError::Db("Please scan your plugins. Missing plugin database".into()))?,
+            &self.tempo.get_data_dir_db()?.ok_or(TempoError::Db(
+                "Please scan your plugins. Missing plugin database".into(),
+            ))?,
         ))
     }
 
@@ -164,24 +364,204 @@ impl Note {
 }
 
 impl NewAttachment {
-    pub fn create(self, folder: &Path, username: &str) -> Result<Attachment> {
-        Ok(match self {
+    /// Stages the files of this attachment, nothing is added to the folder until it's committed.
+    pub fn stage(self, folder: &Path, username: &str) -> Result<StagedAttachment> {
+        let mut files: Vec<Staged> = vec![];
+        let mut add = |staged: Staged| {
+            let hash = staged.sha256().clone();
+            files.push(staged);
+            hash
+        };
+
+        let attachment = match self {
             NewAttachment::Project(NewProjectAttachment {
                 title,
                 path,
                 render,
+                relinks,
             }) => Attachment::Project(ProjectAttachment {
                 title,
-                hash: add_file(folder, username, &path)?,
-                render_hash: render.map(|r| add_file(folder, username, &r)).transpose()?,
+                hash: add(Staged::Project(stage_project_file(
+                    folder, username, &path, &relinks,
+                )?)),
+                render_hash: render
+                    .map(|r| stage_new_file(folder, username, &r))
+                    .transpose()?
+                    .map(&mut add),
             }),
             NewAttachment::Audio(NewAudioAttachment { title, path }) => {
                 Attachment::Audio(AudioAttachment {
                     title,
-                    hash: add_file(folder, username, &path)?,
+                    hash: add(stage_new_file(folder, username, &path)?),
                 })
             }
-        })
+            NewAttachment::Midi(NewMidiAttachment { title, path }) => {
+                Attachment::Midi(MidiAttachment {
+                    title,
+                    hash: add(stage_new_file(folder, username, &path)?),
+                })
+            }
+            NewAttachment::File(NewFileAttachment { title, path }) => {
+                Attachment::File(FileAttachment {
+                    title,
+                    mime: get_mime_type(&path)?,
+                    size: fs::metadata(&path)?.len(),
+                    hash: add(stage_referenced_file(folder, username, &path)?),
+                })
+            }
+            NewAttachment::Stems(NewStemsAttachment { title, stems, mix }) => {
+                let staged = map_bounded(&stems, |s| stage_audio_file(folder, username, &s.path));
+
+                Attachment::Stems(StemsAttachment {
+                    title,
+                    stems: stems
+                        .into_iter()
+                        .zip(staged)
+                        .map(|(s, staged)| {
+                            Ok(Stem {
+                                name: s.name,
+                                hash: add(staged?),
+                            })
+                        })
+                        .collect::<Result<Vec<Stem>>>()?,
+                    mix_hash: mix
+                        .map(|m| stage_audio_file(folder, username, &m))
+                        .transpose()?
+                        .map(&mut add),
+                })
+            }
+            NewAttachment::LiveProject(NewLiveProjectAttachment {
+                title,
+                path,
+                render,
+                relinks,
+            }) => Attachment::LiveProject(LiveProjectAttachment {
+                title,
+                sets: stage_ableton_live_project(folder, username, &path, &relinks)?
+                    .into_iter()
+                    .map(|(path, set)| LiveProjectSet {
+                        path,
+                        hash: add(Staged::Project(set)),
+                    })
+                    .collect(),
+                render_hash: render
+                    .map(|r| stage_new_file(folder, username, &r))
+                    .transpose()?
+                    .map(&mut add),
+            }),
+        };
+
+        Ok(StagedAttachment { attachment, files })
+    }
+}
+
+/// An attachment whose files have been staged, see `NewAttachment::stage()`.
+/// The staged files are removed if it's dropped without being committed.
+pub struct StagedAttachment {
+    // hashes the files will have once they're added
+    pub attachment: Attachment,
+    files: Vec<Staged>,
+}
+
+impl StagedAttachment {
+    /// Adds the staged files to the folder.
+    pub fn commit(self, folder: &Path, username: &str) -> Result<Attachment> {
+        for staged in self.files {
+            staged.commit(folder, username)?;
+        }
+        Ok(self.attachment)
+    }
+}
+
+/// A created note, or the attachments that were already sent if it wasn't created.
+#[derive(Debug)]
+pub enum Created<T> {
+    Note(T),
+    Duplicates(Vec<DuplicateAttachment>),
+}
+
+impl<T> Created<T> {
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Created<U> {
+        match self {
+            Created::Note(n) => Created::Note(f(n)),
+            Created::Duplicates(d) => Created::Duplicates(d),
+        }
+    }
+}
+
+/// Finds attachments that are already on notes in a folder, by the hashes of their projects and audio.
+/// Only hashes of files already in the folder are looked for, so new files don't cost a scan of the folder.
+pub fn find_duplicate_attachments(
+    folder: &Path,
+    username: &str,
+    attachments: &[&Attachment],
+) -> Result<Vec<DuplicateAttachment>> {
+    // hash : index of attachment in the new note
+    let hashes: HashMap<&String, usize> = attachments
+        .iter()
+        .enumerate()
+        .flat_map(|(i, a)| a.version_hashes().into_iter().map(move |h| (h, i)))
+        .filter(|(h, _)| get_file_dir_path(folder, h).exists())
+        .collect();
+    if hashes.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut channels: Vec<(Option<String>, Option<String>)> = vec![(None, None)];
+    for (_, channel_ulid) in iter_channels(folder)? {
+        match ChannelDoc::load(folder, username, &channel_ulid) {
+            Ok(doc) => channels.push((Some(channel_ulid), Some(doc.name))),
+            Err(e) => {
+                warn!("find_duplicate_attachments(): failed to load channel {channel_ulid}: {e}")
+            }
+        }
+    }
+
+    // hash : duplicate on the oldest note
+    let mut found: HashMap<&String, DuplicateAttachment> = HashMap::new();
+
+    for (channel_ulid, channel_name) in channels {
+        for (_, note_ulid) in iter_notes(folder, channel_ulid.as_deref())? {
+            let doc = match NoteDoc::load(folder, username, channel_ulid.as_deref(), &note_ulid) {
+                Ok(d) => d,
+                Err(e) => {
+                    warn!("find_duplicate_attachments(): failed to load note {note_ulid}: {e}");
+                    continue;
+                }
+            };
+
+            for hash in doc.attachments.iter().flat_map(|a| a.version_hashes()) {
+                let Some((hash, index)) = hashes.get_key_value(hash) else {
+                    continue;
+                };
+                if found.get(hash).is_some_and(|d| d.note_ulid < note_ulid) {
+                    continue;
+                }
+                found.insert(
+                    hash,
+                    DuplicateAttachment {
+                        attachment_index: *index,
+                        hash: hash.to_string(),
+                        channel_ulid: channel_ulid.clone(),
+                        channel_name: channel_name.clone(),
+                        note_ulid: note_ulid.clone(),
+                        sender: doc.sender.clone(),
+                    },
+                );
+            }
+        }
+    }
+
+    let mut duplicates: Vec<DuplicateAttachment> = found.into_values().collect();
+    duplicates.sort_by(|a, b| (a.attachment_index, &a.hash).cmp(&(b.attachment_index, &b.hash)));
+    Ok(duplicates)
+}
+
+/// Reads the tempo of a project stored in a folder.
+fn project_tempo(folder: &Path, hash: &str) -> Result<Option<ProjectTempo>> {
+    match FileInfo::load(folder, hash)?.meta {
+        FileMeta::Project(data) => get_data_reader(&data).read_tempo(&get_file_path(folder, hash)),
+        _ => Ok(None),
     }
 }
 
@@ -202,7 +582,11 @@ impl SharedNote {
             sender: doc.sender,
             body: doc.body,
             reply_ulid: doc.reply_ulid,
-            attachment: doc.attachment.map(|a| SharedAttachment::new(folder, a, db)),
+            attachments: doc
+                .attachments
+                .into_iter()
+                .map(|a| SharedAttachment::new(folder, a, db))
+                .collect(),
             comments: doc.comments,
         })
     }
@@ -232,7 +616,10 @@ impl ProjectInfo {
             }
         };
         let data = match file_info.meta {
-            FileMeta::Project(data) => SharedProjectData::new(folder, data, db),
+            FileMeta::Project(data) => match SharedProjectData::new(folder, data, db) {
+                Ok(d) => d,
+                Err(e) => return TempoResult::Err(format!("Failed to read project data: {e}")),
+            },
             o => {
                 return TempoResult::Err(format!(
                     "Corrupt file metadata: expected Project, found {:#?}",
@@ -250,40 +637,8 @@ impl ProjectInfo {
 impl SharedProjectData {
     /// Scans to see if database contains all plugins.
     /// Checks for presence of all referenced files.
-    pub fn new(folder: &Path, data: ProjectData, db: &SharedDb) -> Self {
-        match data {
-            ProjectData::Ableton { refs, plugins } => {
-                let mut missing_files = vec![];
-                for (hash, filename) in refs {
-                    let exists = match file_exists(folder, &hash) {
-                        Ok(e) => e,
-                        Err(e) => {
-                            error!("SharedProjectData::new(): error while trying to read file {hash}, treating as missing: {e}");
-                            false
-                        }
-                    };
-                    if !exists {
-                        missing_files.push(filename)
-                    }
-                }
-
-                let mut missing_plugins = vec![];
-                for plugin in plugins {
-                    match db.get_ableton_plugin(&plugin) {
-                        Ok(None) => missing_plugins.push(PluginRef::from(plugin)),
-                        Ok(Some(_)) => (),
-                        Err(e) => {
-                            error!("SharedProjectData::new(): error while reading plugin: {:#?}, error: {e}, treating as missing", &plugin);
-                            missing_plugins.push(PluginRef::from(plugin))
-                        }
-                    }
-                }
-                Self::Ableton {
-                    missing_files,
-                    missing_plugins,
-                }
-            }
-        }
+    pub fn new(folder: &Path, data: ProjectData, db: &SharedDb) -> Result<Self> {
+        get_data_reader(&data).project_info(folder, data, db)
     }
 }
 
@@ -295,6 +650,10 @@ impl AudioFileInfo {
         };
 
         let filename = file_info.filename;
+        let meta = match file_info.meta {
+            FileMeta::Audio(m) => Some(m),
+            _ => None,
+        };
 
         let exists = match file_exists(folder, hash) {
             Ok(e) => e,
@@ -308,8 +667,178 @@ impl AudioFileInfo {
         }
 
         TempoResult::Ok(AudioFileInfo {
+            path: get_file_path(folder, hash),
+            sha256: hash.to_string(),
+            filename,
+            meta,
+            loudness: Loudness::load(folder, hash),
+            musical: MusicalAnalysis::load(folder, hash),
+        })
+    }
+}
+
+impl MidiFileInfo {
+    pub fn new(folder: &Path, hash: &str) -> TempoResult<MidiFileInfo> {
+        let file_info = match FileInfo::load(folder, hash) {
+            Ok(i) => i,
+            Err(e) => return TempoResult::Err(format!("Failed to load MIDI file metadata: {e}")),
+        };
+
+        let midi = match file_info.meta {
+            FileMeta::Midi(m) => m,
+            o => {
+                return TempoResult::Err(format!(
+                    "Corrupt file metadata: expected Midi, found {:#?}",
+                    o
+                ))
+            }
+        };
+
+        let filename = file_info.filename;
+
+        let exists = match file_exists(folder, hash) {
+            Ok(e) => e,
+            Err(e) => return TempoResult::from(e),
+        };
+
+        if !exists {
+            return TempoResult::Err(format!(
+                "Missing local copy of {filename}, it might still be syncing"
+            ));
+        }
+
+        TempoResult::Ok(MidiFileInfo {
+            path: get_file_path(folder, hash),
+            filename,
+            midi,
+        })
+    }
+}
+
+impl FileAttachmentInfo {
+    pub fn new(folder: &Path, hash: &str) -> TempoResult<FileAttachmentInfo> {
+        let file_info = match FileInfo::load(folder, hash) {
+            Ok(i) => i,
+            Err(e) => return TempoResult::Err(format!("Failed to load file metadata: {e}")),
+        };
+
+        let filename = file_info.filename;
+
+        let exists = match file_exists(folder, hash) {
+            Ok(e) => e,
+            Err(e) => return TempoResult::from(e),
+        };
+
+        if !exists {
+            return TempoResult::Err(format!(
+                "Missing local copy of {filename}, it might still be syncing"
+            ));
+        }
+
+        TempoResult::Ok(FileAttachmentInfo {
             path: get_file_path(folder, hash),
             filename,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        structure::{create_tempo_folder, get_client_staging_path},
+        tests::get_temp_dir,
+    };
+
+    fn write_wav(path: &Path, sample: u8) {
+        let mut data = b"RIFF".to_vec();
+        data.extend_from_slice(&40u32.to_le_bytes());
+        data.extend_from_slice(b"WAVEfmt ");
+        data.extend_from_slice(&16u32.to_le_bytes());
+        // PCM, mono, 44100 Hz, 8 bit
+        data.extend_from_slice(&[1, 0, 1, 0]);
+        data.extend_from_slice(&44100u32.to_le_bytes());
+        data.extend_from_slice(&44100u32.to_le_bytes());
+        data.extend_from_slice(&[1, 0, 8, 0]);
+        data.extend_from_slice(b"data");
+        data.extend_from_slice(&4u32.to_le_bytes());
+        data.extend_from_slice(&[sample; 4]);
+        fs::write(path, data).unwrap();
+    }
+
+    fn audio(path: &Path) -> NewAttachment {
+        NewAttachment::Audio(NewAudioAttachment {
+            title: None,
+            path: path.to_path_buf(),
+        })
+    }
+
+    fn send(
+        folder: &Path,
+        attachments: Vec<NewAttachment>,
+        allow_duplicates: bool,
+    ) -> Created<String> {
+        NoteDoc::create(
+            folder,
+            "user",
+            None,
+            NewNote {
+                body: "new mix".into(),
+                reply_ulid: None,
+                attachments,
+                allow_duplicates,
+            },
+        )
+        .unwrap()
+        .map(|(note_ulid, _)| note_ulid)
+    }
+
+    #[test]
+    fn test_find_duplicate_attachments() {
+        let folder = get_temp_dir("duplicates_folder");
+        create_tempo_folder(&folder).unwrap();
+
+        let dir = get_temp_dir("duplicates_src");
+        let mix = dir.join("mix.wav");
+        let other = dir.join("other mix.wav");
+        write_wav(&mix, 1);
+        write_wav(&other, 2);
+
+        let Created::Note(first) = send(&folder, vec![audio(&mix)], false) else {
+            panic!("first note reported duplicates");
+        };
+
+        // ulids are only ordered across milliseconds
+        std::thread::sleep(std::time::Duration::from_millis(2));
+
+        // sent again on purpose
+        let Created::Note(second) = send(&folder, vec![audio(&mix)], true) else {
+            panic!("note allowing duplicates reported duplicates");
+        };
+        assert!(first < second);
+
+        let Created::Duplicates(duplicates) =
+            send(&folder, vec![audio(&other), audio(&mix)], false)
+        else {
+            panic!("note with a render that was already sent was created");
+        };
+
+        // only the render that was already sent is reported, on the oldest note it's on
+        assert_eq!(duplicates.len(), 1);
+        let d = &duplicates[0];
+        assert_eq!(d.attachment_index, 1);
+        assert_eq!(d.hash, crate::misc::hash_file(&mix).unwrap());
+        assert_eq!(d.note_ulid, first);
+        assert_eq!(d.sender, "user");
+        assert_eq!(d.channel_ulid, None);
+        assert_eq!(d.channel_name, None);
+
+        assert_eq!(iter_notes(&folder, None).unwrap().count(), 2);
+
+        // nothing was added for the note that wasn't created, and its staged copies were removed
+        let other_hash = crate::misc::hash_file(&other).unwrap();
+        assert!(!get_file_dir_path(&folder, &other_hash).exists());
+        let staging = get_client_staging_path(&folder, "user");
+        assert_eq!(fs::read_dir(staging).unwrap().count(), 0);
+    }
+}
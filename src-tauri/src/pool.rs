@@ -0,0 +1,86 @@
+// running file work (hashing, copying) on a bounded number of threads
+
+/*
+projects can reference hundreds of files, hashing and copying them one at a time is slow on big projects.
+we spread this work over a small number of threads.
+
+the number of threads is capped, folders usually live on a single disk (or a synced folder) and too many
+threads just thrash the disk.
+
+results are always returned in the same order as the input, so errors are reported deterministically
+and callers can assign filenames etc. in a stable order.
+ */
+
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+/// Maximum number of threads used for file work.
+const MAX_WORKERS: usize = 8;
+
+fn worker_count(jobs: usize) -> usize {
+    thread::available_parallelism()
+        .map(NonZeroUsize::get)
+        .unwrap_or(1)
+        .min(MAX_WORKERS)
+        .min(jobs)
+        .max(1)
+}
+
+/// Runs `f` on every item using a bounded number of threads.
+/// Results are returned in the same order as `items`.
+pub fn map_bounded<T, R, F>(items: &[T], f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let workers = worker_count(items.len());
+
+    if workers == 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new(items.iter().map(|_| None).collect());
+
+    thread::scope(|s| {
+        for _ in 0..workers {
+            s.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(i) else {
+                    break;
+                };
+
+                let r = f(item);
+                results.lock().unwrap()[i] = Some(r);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|r| r.expect("map_bounded(): missing result"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_bounded_order() {
+        let items: Vec<usize> = (0..100).collect();
+        let results = map_bounded(&items, |i| i * 2);
+        assert_eq!(results, items.iter().map(|i| i * 2).collect::<Vec<_>>());
+
+        assert!(map_bounded(&Vec::<usize>::new(), |i| *i).is_empty());
+    }
+}
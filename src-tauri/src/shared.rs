@@ -10,9 +10,12 @@ use thiserror::Error;
 use ts_rs::TS;
 
 use crate::{
-    daw::{plugin::PluginType, project::ProjectType},
-    misc::TempoError,
-    types::{AudioType, ChannelDoc, FileInfo, FileMeta, FileType, ProjectData, RepliableComment},
+    daw::{plugin::PluginType, project::ProjectType, reader::get_data_reader},
+    misc::{extract_file_extension, TempoError},
+    types::{
+        AudioMeta, AudioType, ChannelDoc, Comment, FileInfo, FileMeta, FileType, MidiData,
+        RepliableComment,
+    },
 };
 
 /// Error enum that's directly shared with the frontend.
@@ -85,7 +88,7 @@ pub struct ChannelData {
     pub notes: HashMap<String, TempoResult<SharedNote>>,
 }
 
-/// Similar to `NoteDoc`, but contains extra information validating note's attachment (if any)
+/// Similar to `NoteDoc`, but contains extra information validating note's attachments
 #[derive(Serialize, TS)]
 #[ts(export)]
 pub struct SharedNote {
@@ -93,7 +96,7 @@ pub struct SharedNote {
     pub body: String,
     pub reply_ulid: Option<String>,
 
-    pub attachment: Option<SharedAttachment>,
+    pub attachments: Vec<SharedAttachment>,
 
     pub comments: HashMap<String, RepliableComment>,
 }
@@ -103,6 +106,10 @@ pub struct SharedNote {
 pub enum SharedAttachment {
     Project(SharedProjectAttachment),
     Audio(SharedAudioAttachment),
+    LiveProject(SharedLiveProjectAttachment),
+    Midi(SharedMidiAttachment),
+    Stems(SharedStemsAttachment),
+    File(SharedFileAttachment),
 }
 
 #[derive(Serialize, TS)]
@@ -113,6 +120,22 @@ pub struct SharedProjectAttachment {
     pub render: Option<TempoResult<AudioFileInfo>>, // err if render file or its FileInfo is missing
 }
 
+#[derive(Serialize, TS)]
+#[ts(export)]
+pub struct SharedLiveProjectAttachment {
+    pub title: String,
+    pub sets: Vec<SharedLiveProjectSet>,
+    pub render: Option<TempoResult<AudioFileInfo>>, // err if render file or its FileInfo is missing
+}
+
+#[derive(Serialize, TS)]
+#[ts(export)]
+pub struct SharedLiveProjectSet {
+    // path of set relative to the Live Project directory
+    pub path: String,
+    pub project: TempoResult<ProjectInfo>, // err if we can't load FileInfo
+}
+
 #[derive(Serialize, TS)]
 #[ts(export)]
 pub struct SharedAudioAttachment {
@@ -120,12 +143,150 @@ pub struct SharedAudioAttachment {
     pub file: TempoResult<AudioFileInfo>,
 }
 
+#[derive(Serialize, TS)]
+#[ts(export)]
+pub struct SharedStemsAttachment {
+    pub title: String,
+    pub stems: Vec<SharedStem>,
+    pub mix: Option<TempoResult<AudioFileInfo>>, // err if mix file or its FileInfo is missing
+}
+
+#[derive(Serialize, TS)]
+#[ts(export)]
+pub struct SharedStem {
+    pub name: String,
+    pub file: TempoResult<AudioFileInfo>,
+}
+
 #[derive(Serialize, TS)]
 #[ts(export)]
 pub struct AudioFileInfo {
     // path here for playback
     pub path: PathBuf,
     pub filename: String,
+    // used to get waveform peaks and loudness
+    pub sha256: String,
+    // None if the file's metadata isn't audio metadata
+    pub meta: Option<AudioMeta>,
+    // None if the file hasn't been measured yet
+    pub loudness: Option<Loudness>,
+    // None if the file hasn't been analyzed yet
+    pub musical: Option<MusicalAnalysis>,
+}
+
+/// Loudness and peaks of an audio file, measured per ITU-R BS.1770 and EBU Tech 3342.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct Loudness {
+    // None if the file is silent or too short to measure
+    pub integrated_lufs: Option<f64>,
+    pub loudness_range_lu: Option<f64>,
+    // None for digital silence
+    pub sample_peak_dbfs: Option<f64>,
+    pub true_peak_dbtp: Option<f64>,
+}
+
+/// Estimated tempo and key of an audio file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct MusicalAnalysis {
+    // None if the file is too short or has no clear beat
+    pub bpm: Option<f64>,
+    // None if the file has no pitched content
+    pub key: Option<MusicalKey>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct MusicalKey {
+    // pitch class of the tonic, C is 0
+    pub tonic: u8,
+    pub mode: KeyMode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub enum KeyMode {
+    Major,
+    Minor,
+}
+
+/// Result of null testing one audio file against another.
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct NullTest {
+    // frames the first file is delayed relative to the second, negative if the second is delayed
+    #[ts(type = "number")]
+    pub offset_frames: i64,
+    pub offset_secs: f64,
+    // None if the files null perfectly
+    pub difference_rms_dbfs: Option<f64>,
+    pub difference_peak_dbfs: Option<f64>,
+    // times in the first file
+    pub regions: Vec<DivergentRegion>,
+    // where the difference was written, if it was requested
+    pub difference_file: Option<PathBuf>,
+}
+
+/// A stretch of time where two null tested files differ.
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct DivergentRegion {
+    pub start_secs: f64,
+    pub end_secs: f64,
+    // RMS of the difference in the loudest 100 ms of the region
+    pub rms_dbfs: f64,
+}
+
+/// Min/max peaks of an audio file at a few resolutions, used to draw waveforms.
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct WaveformPeaks {
+    pub sample_rate: u32,
+    #[ts(type = "number")]
+    pub frames: u64,
+    // finest first
+    pub levels: Vec<PeakLevel>,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct PeakLevel {
+    pub frames_per_peak: u32,
+    pub min: Vec<f32>,
+    pub max: Vec<f32>,
+}
+
+#[derive(Serialize, TS)]
+#[ts(export)]
+pub struct SharedMidiAttachment {
+    pub title: Option<String>,
+    pub file: TempoResult<MidiFileInfo>,
+}
+
+#[derive(Serialize, TS)]
+#[ts(export)]
+pub struct MidiFileInfo {
+    pub path: PathBuf,
+    pub filename: String,
+    pub midi: MidiData,
+}
+
+#[derive(Serialize, TS)]
+#[ts(export)]
+pub struct SharedFileAttachment {
+    pub title: Option<String>,
+    pub mime: Option<String>,
+    #[ts(type = "number")]
+    pub size: u64,
+    pub file: TempoResult<FileAttachmentInfo>,
+}
+
+#[derive(Serialize, TS)]
+#[ts(export)]
+pub struct FileAttachmentInfo {
+    pub path: PathBuf,
+    pub filename: String,
 }
 
 /// Info about a project that's been added to a Tempo folder.
@@ -145,6 +306,45 @@ pub enum SharedProjectData {
 
         // plugins user doesn't have installed
         missing_plugins: Vec<PluginRef>,
+
+        // version of Live that saved this project, if known
+        version: Option<String>,
+
+        // users who don't have a version of Live that can open this project
+        cannot_open: Vec<String>,
+    },
+    Reaper {
+        missing_files: Vec<String>,
+
+        // plugins user doesn't have installed
+        missing_plugins: Vec<PluginRef>,
+
+        // version of REAPER that saved this project, if known
+        version: Option<String>,
+    },
+    Bitwig {
+        missing_files: Vec<String>,
+
+        // plugins user doesn't have installed
+        missing_plugins: Vec<PluginRef>,
+
+        // files Bitwig will ask the user to locate when a copy of the project is opened
+        relink_files: Vec<String>,
+    },
+    StudioOne {
+        missing_files: Vec<String>,
+
+        // plugins user doesn't have installed
+        missing_plugins: Vec<PluginRef>,
+    },
+    FlStudio {
+        missing_files: Vec<String>,
+
+        // plugins user doesn't have installed
+        missing_plugins: Vec<PluginRef>,
+
+        // version of FL Studio that saved this project, if known
+        version: Option<String>,
     },
 }
 
@@ -154,7 +354,36 @@ pub enum SharedProjectData {
 pub struct NewNote {
     pub body: String,
     pub reply_ulid: Option<String>,
-    pub attachment: Option<NewAttachment>,
+    pub attachments: Vec<NewAttachment>,
+    // send even if attachments are already on other notes in the folder
+    #[serde(default)]
+    pub allow_duplicates: bool,
+}
+
+/// Result of sending a note.
+#[derive(Serialize, TS)]
+#[ts(export)]
+pub enum CreatedNote {
+    Created(TempoResult<SharedNote>),
+    // the note wasn't sent since some of its attachments are already on other notes,
+    // sending it again with `allow_duplicates` sends it anyway
+    Duplicates(Vec<DuplicateAttachment>),
+}
+
+/// An attachment of a new note that's identical to one already on a note in the folder.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct DuplicateAttachment {
+    // index of the attachment in the new note
+    pub attachment_index: usize,
+    pub hash: String,
+
+    // the note it's already on, the oldest one if it's on several
+    pub channel_ulid: Option<String>,
+    // None for the global channel
+    pub channel_name: Option<String>,
+    pub note_ulid: String,
+    pub sender: String,
 }
 
 #[derive(Debug, Deserialize, TS)]
@@ -162,6 +391,10 @@ pub struct NewNote {
 pub enum NewAttachment {
     Project(NewProjectAttachment),
     Audio(NewAudioAttachment),
+    LiveProject(NewLiveProjectAttachment),
+    Midi(NewMidiAttachment),
+    Stems(NewStemsAttachment),
+    File(NewFileAttachment),
 }
 
 #[derive(Debug, Deserialize, TS)]
@@ -170,6 +403,23 @@ pub struct NewProjectAttachment {
     pub title: String,
     pub path: PathBuf,
     pub render: Option<PathBuf>,
+
+    // accepted relinks for missing file refs, see `ProjectFileRefScan`
+    #[serde(default)]
+    pub relinks: Vec<Relink>,
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[ts(export)]
+pub struct NewLiveProjectAttachment {
+    pub title: String,
+    // path to Live Project directory
+    pub path: PathBuf,
+    pub render: Option<PathBuf>,
+
+    // accepted relinks for missing file refs, see `ProjectFileRefScan`
+    #[serde(default)]
+    pub relinks: Vec<Relink>,
 }
 
 #[derive(Debug, Deserialize, TS)]
@@ -179,6 +429,35 @@ pub struct NewAudioAttachment {
     pub path: PathBuf,
 }
 
+#[derive(Debug, Deserialize, TS)]
+#[ts(export)]
+pub struct NewMidiAttachment {
+    pub title: Option<String>,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[ts(export)]
+pub struct NewFileAttachment {
+    pub title: Option<String>,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[ts(export)]
+pub struct NewStemsAttachment {
+    pub title: String,
+    pub stems: Vec<NewStem>,
+    pub mix: Option<PathBuf>,
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[ts(export)]
+pub struct NewStem {
+    pub name: String,
+    pub path: PathBuf,
+}
+
 /// A request from the frontend to create a comment on a note.
 #[derive(Debug, Deserialize, TS)]
 #[ts(export)]
@@ -187,6 +466,28 @@ pub struct NewComment {
     pub reply_ulid: Option<String>,
 
     pub body: String,
+
+    // bar and beat are filled in by the backend
+    pub position: Option<NewCommentPosition>,
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[ts(export)]
+pub struct NewCommentPosition {
+    // hash of an audio file or render attached to the note
+    pub file_hash: String,
+    pub start_secs: f64,
+    pub end_secs: Option<f64>,
+}
+
+/// A comment with a position, returned by position.
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct PositionedComment {
+    pub ulid: String,
+    // ulid of the comment this replies to
+    pub reply_ulid: Option<String>,
+    pub comment: Comment,
 }
 
 #[derive(Debug, Serialize, TS)]
@@ -194,6 +495,11 @@ pub struct NewComment {
 pub enum AttachmentType {
     Audio(AudioType),
     Project(ProjectType),
+    // directory containing Ableton Project Info
+    LiveProject,
+    Midi,
+    // any other file, attached as is
+    File,
 }
 
 /// Used when adding a project to a folder.
@@ -213,9 +519,14 @@ pub struct PluginScan {
     pub plugins: Vec<PluginRef>,
     // { username: idxs of missing plugins }
     pub missing: HashMap<String, Vec<usize>>,
+
+    // version of the DAW that saved the project, if known
+    pub project_version: Option<String>,
+    // users who don't have a version of the DAW that can open the project
+    pub cannot_open: Vec<String>,
 }
 
-#[derive(Eq, PartialEq, Hash, Debug, Serialize, TS)]
+#[derive(Eq, PartialEq, Hash, Debug, Serialize, Deserialize, TS)]
 #[ts(export)]
 pub struct FileRef {
     // TODO: this is based on Ableton's filerefs
@@ -223,6 +534,13 @@ pub struct FileRef {
     // or actually send AbletonFileRefs instead
     pub rel: String,
     pub abs: String,
+
+    // name of Live Pack or library this file is part of, if any
+    pub pack: Option<String>,
+
+    // size of the file in bytes, if the project file stores it
+    #[ts(type = "number | null")]
+    pub size: Option<u64>,
 }
 
 #[derive(Eq, PartialEq, Hash, Debug, Serialize, TS)]
@@ -230,6 +548,27 @@ pub struct FileRef {
 pub struct MissingFileRef {
     pub file: FileRef,
     pub err: String,
+
+    // files found in the user's sample libraries which could replace this file
+    pub candidates: Vec<RelinkCandidate>,
+}
+
+/// A file in a sample library which might be a missing file.
+#[derive(Eq, PartialEq, Hash, Debug, Serialize, TS)]
+#[ts(export)]
+pub struct RelinkCandidate {
+    pub path: PathBuf,
+    #[ts(type = "number")]
+    pub size: u64,
+}
+
+/// A missing file ref that should point at `path` instead.
+/// Sent by the frontend after a user accepts a `RelinkCandidate`.
+#[derive(Debug, Deserialize, TS)]
+#[ts(export)]
+pub struct Relink {
+    pub file: FileRef,
+    pub path: PathBuf,
 }
 
 #[derive(Eq, PartialEq, Hash, Debug, Serialize, TS)]
@@ -266,21 +605,24 @@ impl From<FileInfo> for SharedFileInfo {
             meta,
         } = value;
 
+        let file_type = match meta {
+            FileMeta::Audio(m) => FileType::Audio(m.audio_type),
+            FileMeta::Project(d) => FileType::Project(
+                extract_file_extension(&filename)
+                    .1
+                    .and_then(|e| ProjectType::from_extension(&e))
+                    .unwrap_or_else(|| get_data_reader(&d).default_type()),
+            ),
+            FileMeta::Midi(_) => FileType::Midi,
+            FileMeta::MaxForLive => FileType::MaxForLive,
+            FileMeta::Other => FileType::Other,
+        };
+
         Self {
             filename,
             added_by,
             timestamp,
-            file_type: match meta {
-                FileMeta::Audio(t) => FileType::Audio(t),
-                FileMeta::Project(d) => match d {
-                    ProjectData::Ableton {
-                        refs: _,
-                        plugins: _,
-                    } => FileType::Project(ProjectType::Ableton),
-                },
-                FileMeta::MaxForLive => FileType::MaxForLive,
-                FileMeta::Other => FileType::Other,
-            },
+            file_type,
         }
     }
 }
@@ -146,6 +146,16 @@ pub fn get_file_path(folder: &Path, file_sha256: &str) -> PathBuf {
     get_file_dir_path(folder, file_sha256).join("file")
 }
 
+/// Loudness of an audio file, saved by the first client to measure it.
+pub fn get_file_loudness_path(folder: &Path, file_sha256: &str) -> PathBuf {
+    get_file_dir_path(folder, file_sha256).join("loudness")
+}
+
+/// Tempo and key of an audio file, saved by the first client to estimate them.
+pub fn get_file_musical_path(folder: &Path, file_sha256: &str) -> PathBuf {
+    get_file_dir_path(folder, file_sha256).join("musical")
+}
+
 pub fn get_clients_path(folder: &Path) -> PathBuf {
     folder.join("tempo").join("clients")
 }
@@ -158,6 +168,12 @@ pub fn get_client_shared_db_path(folder: &Path, username: &str) -> PathBuf {
     get_client_dir_path(folder, username).join("shared.sqlite")
 }
 
+/// Files are copied here before being moved into the files directory.
+/// Every client has their own staging directory so clients never write to the same staging file.
+pub fn get_client_staging_path(folder: &Path, username: &str) -> PathBuf {
+    get_client_dir_path(folder, username).join("staging")
+}
+
 fn exists_with_nice_error(folder: &Path, res: std::io::Result<bool>) -> Result<bool> {
     match res {
         Ok(e) => Ok(e),
@@ -271,6 +287,33 @@ pub fn iter_notes(
     iter_ulid(channel_dir)
 }
 
+/// Iterates over the files in a folder.
+/// Returns an iterator of file hashes.
+pub fn iter_files(folder: &Path) -> Result<impl Iterator<Item = String>> {
+    expect_valid_folder(folder)?;
+
+    let files_dir = folder.join("tempo").join("files");
+
+    Ok(WalkDir::new(&files_dir)
+        .min_depth(2)
+        .max_depth(2)
+        .into_iter()
+        .filter_map(move |entry| match entry {
+            Ok(entry) => entry
+                .file_type()
+                .is_dir()
+                .then(|| entry.file_name().to_string_lossy().to_string()),
+            Err(e) => {
+                warn!(
+                    "error while reading folder entry in {}, {}",
+                    path_to_str(&files_dir),
+                    e
+                );
+                None
+            }
+        }))
+}
+
 pub fn iter_clients(folder: &Path) -> Result<impl Iterator<Item = (PathBuf, String)>> {
     expect_valid_folder(folder)?;
 
@@ -30,6 +30,9 @@ struct TempoInner {
 
     pub client_ulid: String,
     pub folders: HashMap<PathBuf, Arc<RwLock<RuntimeFolder>>>,
+
+    // sample library directories, searched for missing files
+    pub library_roots: Vec<PathBuf>,
 }
 
 impl TempoInner {
@@ -94,6 +97,7 @@ impl TempoInner {
                         (p.to_path_buf(), folder.username.clone())
                     })
                     .collect(),
+                library_roots: self.library_roots.clone(),
             },
         )?)
     }
@@ -119,6 +123,7 @@ impl Tempo {
             let data = DataFile {
                 ulid: new_ulid(),
                 folders: HashMap::new(),
+                library_roots: vec![],
             };
             serde_json::to_writer_pretty(fs::File::create(&data_file)?, &data)?;
             data
@@ -146,6 +151,7 @@ impl Tempo {
             data_dir: data_dir.to_path_buf(),
             data_file,
             folders,
+            library_roots: data.library_roots,
             // watcher,
             // watched_folder,
         }));
@@ -255,6 +261,11 @@ impl Tempo {
         self.inner.read().unwrap().data_dir.join("tempo.json")
     }
 
+    /// Directory for data derived from files (waveform peaks etc.), which can always be regenerated.
+    pub fn get_cache_dir(&self) -> PathBuf {
+        self.inner.read().unwrap().data_dir.join("cache")
+    }
+
     pub fn scan_folder(&self, folder: &Path) -> Result<FolderInfo> {
         let mut tempo = self.inner.write().unwrap();
         tempo.scan_folder(folder)
@@ -337,6 +348,25 @@ impl Tempo {
     pub fn get_data_dir_db(&self) -> Result<Option<SharedDb>> {
         self.inner.read().unwrap().get_data_dir_db()
     }
+
+    pub fn get_library_roots(&self) -> Vec<PathBuf> {
+        self.inner.read().unwrap().library_roots.clone()
+    }
+
+    pub fn set_library_roots(&self, roots: Vec<PathBuf>) -> Result<()> {
+        for root in roots.iter() {
+            if !root.is_dir() {
+                return Err(TempoError::Config(format!(
+                    "Sample library {} is not a directory",
+                    path_to_str(root)
+                )));
+            }
+        }
+
+        let mut tempo = self.inner.write().unwrap();
+        tempo.library_roots = roots;
+        tempo.save()
+    }
 }
 
 impl Drop for Tempo {
@@ -353,6 +383,8 @@ struct DataFile {
     pub ulid: String,
     // { folder : username to use }
     pub folders: HashMap<PathBuf, String>,
+    #[serde(default)]
+    pub library_roots: Vec<PathBuf>,
 }
 
 #[derive(Debug)]
@@ -172,7 +172,7 @@ fn test_channel() {
 
 #[test]
 fn test_note() {
-    use crate::shared::NewNote;
+    use crate::{note::Created, shared::NewNote};
 
     let (
         Testpo {
@@ -189,19 +189,24 @@ fn test_note() {
         .channel(None)
         .expect("failed to retrieve global channel");
 
-    let first_note = global
+    let Created::Note(first_note) = global
         .create_note(NewNote {
             body: "hi".into(),
             reply_ulid: None,
-            attachment: None,
+            attachments: vec![],
+            allow_duplicates: false,
         })
-        .expect("failed to create note in global with no reply, no attachment");
+        .expect("failed to create note in global with no reply, no attachment")
+    else {
+        panic!("note without attachments reported duplicates");
+    };
 
     global
         .create_note(NewNote {
             body: "hi there".into(),
             reply_ulid: Some(crate::misc::new_ulid()),
-            attachment: None,
+            attachments: vec![],
+            allow_duplicates: false,
         })
         .expect_err("shouldn't be able to reply to nonexistent note in global");
 
@@ -209,7 +214,8 @@ fn test_note() {
         .create_note(NewNote {
             body: "how are you".into(),
             reply_ulid: Some(first_note.ulid().to_string()),
-            attachment: None,
+            attachments: vec![],
+            allow_duplicates: false,
         })
         .expect("failed to create reply note in global with no attachment");
 }
\ No newline at end of file
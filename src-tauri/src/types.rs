@@ -6,7 +6,14 @@ use std::collections::HashMap;
 use autosurgeon::{Hydrate, Reconcile};
 use serde::{Deserialize, Serialize};
 
-use crate::daw::{ableton::AbletonPluginRef, project::ProjectType};
+use crate::daw::{
+    ableton::{AbletonPackFileRef, AbletonPluginRef, AbletonVersion},
+    bitwig::BitwigPluginRef,
+    fl_studio::FlPluginRef,
+    project::ProjectType,
+    reaper::ReaperPluginRef,
+    studio_one::StudioOnePluginRef,
+};
 
 // TODO figure out how to disable ts_rs generated tests when running `cargo test`
 // not too big of a deal though
@@ -27,10 +34,17 @@ pub struct NoteDoc {
     pub body: String,
     pub reply_ulid: Option<String>, // ulid of note being replied to, has to be in same channel
 
-    // a note can only have one attachment
-    // this helps simplify Tempo's version management
+    // attachments are ordered and addressed by their index
     // a user replies to a message with a project to represent a new version of a project
     // TODO maybe it should be possible to reply to multiple messages for 'merging' of projects
+    #[autosurgeon(missing = "Default::default")]
+    pub attachments: Vec<Attachment>,
+
+    // the first attachment, for clients made before notes could have several
+    // notes sent by those clients only have this, it's used as `attachments` when the doc is loaded
+    #[autosurgeon(missing = "Default::default")]
+    #[serde(skip)]
+    #[ts(skip)]
     pub attachment: Option<Attachment>,
 
     // { ulid : comment }
@@ -44,7 +58,11 @@ pub struct NoteDoc {
 #[derive(Debug, Clone, Reconcile, Hydrate, Serialize, Deserialize, ts_rs::TS)]
 pub enum Attachment {
     Project(ProjectAttachment),
-    Audio(AudioAttachment)
+    Audio(AudioAttachment),
+    LiveProject(LiveProjectAttachment),
+    Midi(MidiAttachment),
+    Stems(StemsAttachment),
+    File(FileAttachment),
 }
 
 #[derive(Debug, Clone, Reconcile, Hydrate, Serialize, Deserialize, ts_rs::TS)]
@@ -58,10 +76,65 @@ pub struct ProjectAttachment {
     pub render_hash: Option<String>,
 }
 
+/// A whole Live Project directory containing one or more sets.
+#[derive(Debug, Clone, Reconcile, Hydrate, Serialize, Deserialize, ts_rs::TS)]
+pub struct LiveProjectAttachment {
+    pub title: String,
+
+    // sets in the Live Project, sorted by path
+    // files referenced by the sets are only stored once in the folder
+    pub sets: Vec<LiveProjectSet>,
+
+    // hash of render of project
+    pub render_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, Reconcile, Hydrate, Serialize, Deserialize, ts_rs::TS)]
+pub struct LiveProjectSet {
+    // path of set relative to the Live Project directory, / separated
+    pub path: String,
+    pub hash: String,
+}
+
 #[derive(Debug, Clone, Reconcile, Hydrate, Serialize, Deserialize, ts_rs::TS)]
 pub struct AudioAttachment {
     pub title: Option<String>,
-    pub hash: String
+    pub hash: String,
+}
+
+#[derive(Debug, Clone, Reconcile, Hydrate, Serialize, Deserialize, ts_rs::TS)]
+pub struct MidiAttachment {
+    pub title: Option<String>,
+    pub hash: String,
+}
+
+/// Any other kind of file, eg a lyric sheet, artwork or a zip archive.
+#[derive(Debug, Clone, Reconcile, Hydrate, Serialize, Deserialize, ts_rs::TS)]
+pub struct FileAttachment {
+    pub title: Option<String>,
+    pub hash: String,
+    // from the file's header, None if it couldn't be recognized
+    pub mime: Option<String>,
+    #[ts(type = "number")]
+    pub size: u64,
+}
+
+/// A set of audio files sent together, eg the stems of a mix.
+#[derive(Debug, Clone, Reconcile, Hydrate, Serialize, Deserialize, ts_rs::TS)]
+pub struct StemsAttachment {
+    pub title: String,
+
+    // in the order they were added, names are unique
+    pub stems: Vec<Stem>,
+
+    // hash of reference mix
+    pub mix_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, Reconcile, Hydrate, Serialize, Deserialize, ts_rs::TS)]
+pub struct Stem {
+    pub name: String,
+    pub hash: String,
 }
 
 /// Repliable comment on a note.
@@ -77,11 +150,38 @@ pub struct RepliableComment {
 pub struct Comment {
     pub sender: String,
     pub body: String,
+
+    // where in an attached audio file or render the comment is about
+    // None for comments on the whole note, and comments made before comments had positions
+    #[autosurgeon(missing = "Default::default")]
+    #[serde(default)]
+    pub position: Option<CommentPosition>,
+}
+
+/// A time or time range in an audio file attached to a note.
+#[derive(Debug, Clone, PartialEq, Reconcile, Hydrate, Serialize, Deserialize, ts_rs::TS)]
+pub struct CommentPosition {
+    // hash of the audio file or render
+    pub file_hash: String,
+    pub start_secs: f64,
+    // None for a single point in time
+    pub end_secs: Option<f64>,
+    // of `start_secs`, from the project's tempo if the file is a render
+    pub bar_beat: Option<BarBeat>,
+}
+
+/// A position in bars and beats, both starting at 1.
+#[derive(Debug, Clone, PartialEq, Reconcile, Hydrate, Serialize, Deserialize, ts_rs::TS)]
+pub struct BarBeat {
+    pub bar: u32,
+    // fractional, eg 2.5 is halfway through the second beat
+    pub beat: f64,
 }
 
 /// Contains metadata about a file.
 /// This is immutable and created when a file is initially added to a folder.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Deserialize)]
+#[serde(from = "StoredFileInfo")]
 pub struct FileInfo {
     pub filename: String,
     pub added_by: String,
@@ -89,16 +189,90 @@ pub struct FileInfo {
     pub meta: FileMeta,
 }
 
+// how a `FileInfo` is stored
+// older versions of Tempo expect `FileMeta::Audio` to hold an `AudioType` they know,
+// so audio files store one of those in `meta` and the full `AudioMeta` in `audio`
+#[derive(Deserialize)]
+struct StoredFileInfo {
+    filename: String,
+    added_by: String,
+    timestamp: u64,
+    meta: FileMeta,
+    #[serde(default)]
+    audio: Option<AudioMeta>,
+}
+
+impl From<StoredFileInfo> for FileInfo {
+    fn from(stored: StoredFileInfo) -> Self {
+        let meta = match (stored.meta, stored.audio) {
+            (FileMeta::Audio(_), Some(audio)) => FileMeta::Audio(audio),
+            (meta, _) => meta,
+        };
+
+        FileInfo {
+            filename: stored.filename,
+            added_by: stored.added_by,
+            timestamp: stored.timestamp,
+            meta,
+        }
+    }
+}
+
+impl Serialize for FileInfo {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> std::result::Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        enum LegacyFileMeta {
+            Audio(AudioType),
+        }
+
+        #[derive(Serialize)]
+        #[serde(untagged)]
+        enum Meta<'a> {
+            Legacy(LegacyFileMeta),
+            Meta(&'a FileMeta),
+        }
+
+        #[derive(Serialize)]
+        struct Stored<'a> {
+            filename: &'a str,
+            added_by: &'a str,
+            timestamp: u64,
+            meta: Meta<'a>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            audio: Option<&'a AudioMeta>,
+        }
+
+        let (meta, audio) = match &self.meta {
+            FileMeta::Audio(m) => (
+                Meta::Legacy(LegacyFileMeta::Audio(m.audio_type.legacy())),
+                Some(m),
+            ),
+            m => (Meta::Meta(m), None),
+        };
+
+        Stored {
+            filename: &self.filename,
+            added_by: &self.added_by,
+            timestamp: self.timestamp,
+            meta,
+            audio,
+        }
+        .serialize(s)
+    }
+}
+
 /// File's type and any extra type-specific info
 #[derive(Debug, Serialize, Deserialize)]
 pub enum FileMeta {
-    Audio(AudioType),
+    Audio(AudioMeta),
 
     Project(ProjectData),
 
+    Midi(MidiData),
+
     MaxForLive,
 
-    Other
+    Other,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -114,8 +288,65 @@ pub enum ProjectData {
         // { file hash : filename to use in Files folder }
         refs: HashMap<String, String>,
 
+        // files referenced relative to a Live Pack or the Core Library
+        // these files are also in refs, they're only copied into the Files folder if the pack is missing
+        // a file can be referenced from more than one pack, or from more than one place in a pack
+        // { file hash : pack file refs }
+        #[serde(default)]
+        pack_refs: HashMap<String, Vec<AbletonPackFileRef>>,
+
         // plugins used in this project
         plugins: Vec<AbletonPluginRef>,
+
+        // version of Live that saved this project, read from the project file
+        // None for projects added by older versions of Tempo
+        #[serde(default)]
+        version: Option<AbletonVersion>,
+    },
+    Reaper {
+        // { file hash : filename to use in Media folder }
+        refs: HashMap<String, String>,
+
+        // plugins used in this project
+        plugins: Vec<ReaperPluginRef>,
+
+        // version of REAPER that saved this project, eg "7.14"
+        #[serde(default)]
+        version: Option<String>,
+    },
+    Bitwig {
+        // { file hash : paths relative to the project directory }
+        refs: HashMap<String, Vec<String>>,
+
+        // media from outside of the project directory, collected into the samples directory
+        // the project file isn't modified, so the user has to point Bitwig at these files
+        // paths relative to the project directory
+        #[serde(default)]
+        relink: Vec<String>,
+
+        // plugins used in this project
+        plugins: Vec<BitwigPluginRef>,
+    },
+    StudioOne {
+        // { file hash : filename to use in Media folder }
+        refs: HashMap<String, String>,
+
+        // plugins used in this song
+        plugins: Vec<StudioOnePluginRef>,
+    },
+    FlStudio {
+        // { file hash : filename to use in data folder }
+        refs: HashMap<String, String>,
+
+        // { sample path in project : file hash }
+        // the stored project keeps its original sample paths, they're rewritten on copy-out
+        samples: HashMap<String, String>,
+
+        // third party plugins used in this project
+        plugins: Vec<FlPluginRef>,
+
+        // version of FL Studio that saved this project, eg "21.2.3.4004"
+        version: Option<String>,
     },
 }
 
@@ -123,14 +354,14 @@ pub enum ProjectData {
 #[derive(Debug, Serialize, ts_rs::TS)]
 #[ts(export)]
 pub enum FileType {
-    // always AudioType::Other for now
-    // tauri supports loading mime type
     Audio(AudioType),
 
     // project file
     Project(ProjectType),
+    // standard MIDI file
+    Midi,
     MaxForLive,
-    Other
+    Other,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, ts_rs::TS)]
@@ -138,5 +369,112 @@ pub enum AudioType {
     Wav,
     Mp3,
     Flac,
+    Aiff,
+    // Vorbis, or FLAC in an Ogg container
+    Ogg,
+    Opus,
     Other,
 }
+
+impl AudioType {
+    /// The type older versions of Tempo know this as, they only know wav, mp3 and flac.
+    fn legacy(&self) -> AudioType {
+        match self {
+            AudioType::Wav => AudioType::Wav,
+            AudioType::Mp3 => AudioType::Mp3,
+            AudioType::Flac => AudioType::Flac,
+            _ => AudioType::Other,
+        }
+    }
+}
+
+/// Metadata stored for audio files.
+#[derive(Debug, Clone, Serialize, PartialEq, ts_rs::TS)]
+#[ts(export)]
+pub struct AudioMeta {
+    pub audio_type: AudioType,
+    // None if the header couldn't be read
+    pub info: Option<AudioInfo>,
+}
+
+// older versions of Tempo only stored the `AudioType`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AudioMetaRepr {
+    Meta {
+        audio_type: AudioType,
+        info: Option<AudioInfo>,
+    },
+    Type(AudioType),
+}
+
+impl<'de> Deserialize<'de> for AudioMeta {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> std::result::Result<Self, D::Error> {
+        Ok(match AudioMetaRepr::deserialize(d)? {
+            AudioMetaRepr::Meta { audio_type, info } => AudioMeta { audio_type, info },
+            AudioMetaRepr::Type(audio_type) => AudioMeta {
+                audio_type,
+                info: None,
+            },
+        })
+    }
+}
+
+/// Technical details read from the header of an audio file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ts_rs::TS)]
+#[ts(export)]
+pub struct AudioInfo {
+    // eg "PCM", "IEEE float", "MPEG-1 Layer III", "FLAC", "Vorbis", "Opus"
+    pub codec: String,
+    pub sample_rate: u32,
+    // only for lossless formats
+    pub bit_depth: Option<u16>,
+    pub channels: u16,
+    // None if the length couldn't be found
+    pub duration_secs: Option<f64>,
+}
+
+/// Metadata read from a standard MIDI file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ts_rs::TS)]
+#[ts(export)]
+pub struct MidiData {
+    // 0: single track, 1: multiple tracks played together, 2: independent patterns
+    pub format: u16,
+
+    // ticks per quarter note, None for files using SMPTE timing
+    pub ppq: Option<u16>,
+
+    // first name of each track that has one
+    pub track_names: Vec<String>,
+
+    // sorted by tick, files without tempo events play at 120 bpm
+    pub tempos: Vec<MidiTempo>,
+
+    // sorted by tick
+    pub time_signatures: Vec<MidiTimeSignature>,
+
+    // position of the last event
+    pub length_ticks: u64,
+    pub length_secs: f64,
+
+    pub note_count: u64,
+
+    // MIDI note numbers, None if the file has no notes
+    pub lowest_note: Option<u8>,
+    pub highest_note: Option<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ts_rs::TS)]
+#[ts(export)]
+pub struct MidiTempo {
+    pub tick: u64,
+    pub bpm: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ts_rs::TS)]
+#[ts(export)]
+pub struct MidiTimeSignature {
+    pub tick: u64,
+    pub numerator: u8,
+    pub denominator: u16,
+}
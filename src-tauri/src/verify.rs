@@ -1,8 +1,13 @@
-use std::path::Path;
+use std::{collections::HashSet, fs, path::Path};
 
 use crate::{
-    misc::{Result, TempoError},
-    shared::{NewAttachment, NewAudioAttachment, NewNote, NewProjectAttachment},
+    daw::ableton::is_live_project,
+    file::MAX_FILE_ATTACHMENT_SIZE,
+    misc::{path_to_str, Result, TempoError},
+    shared::{
+        NewAttachment, NewAudioAttachment, NewFileAttachment, NewLiveProjectAttachment,
+        NewMidiAttachment, NewNote, NewProjectAttachment, NewStem, NewStemsAttachment, Relink,
+    },
     structure::note_exists,
     types::FileType,
 };
@@ -25,7 +30,7 @@ pub trait VerifiableWithInfo {
 
 impl VerifiableWithInfo for NewNote {
     fn verify(&self, folder: &Path, channel_ulid: Option<&str>) -> Result<()> {
-        if self.body.is_empty() && self.attachment.is_none() {
+        if self.body.is_empty() && self.attachments.is_empty() {
             return Err(TempoError::Note(
                 "Notes can only have an empty body with an attachment".into(),
             ));
@@ -35,7 +40,7 @@ impl VerifiableWithInfo for NewNote {
                 return Err(TempoError::Note("Tried to reply to unknown note".into()));
             }
         }
-        if let Some(a) = self.attachment.as_ref() {
+        for a in &self.attachments {
             a.verify()?;
         }
         Ok(())
@@ -49,7 +54,9 @@ impl Verifiable for NewAttachment {
                 title,
                 path,
                 render,
+                relinks,
             }) => {
+                verify_relinks(relinks)?;
                 if title.is_empty() {
                     return Err(TempoError::Note(
                         "A project cannot have an empty title".into(),
@@ -94,7 +101,148 @@ impl Verifiable for NewAttachment {
                     }
                 }
             }
+            NewAttachment::Midi(NewMidiAttachment { title, path }) => {
+                if let Some(title) = title.as_ref() {
+                    if title.is_empty() {
+                        return Err(TempoError::Note(
+                            "A MIDI attachment cannot have an empty string as a title".into(),
+                        ));
+                    }
+                }
+                match FileType::get(path)? {
+                    FileType::Midi => (),
+                    t => {
+                        return Err(TempoError::Note(format!(
+                            "Expected a MIDI file as an attachment, found {:#?} instead",
+                            t
+                        )))
+                    }
+                }
+            }
+            NewAttachment::File(NewFileAttachment { title, path }) => {
+                if let Some(title) = title.as_ref() {
+                    if title.is_empty() {
+                        return Err(TempoError::Note(
+                            "A file attachment cannot have an empty string as a title".into(),
+                        ));
+                    }
+                }
+                if !path.is_file() {
+                    return Err(TempoError::Note(format!(
+                        "{} is not a file",
+                        path_to_str(path)
+                    )));
+                }
+                if let FileType::Project(_) = FileType::get(path)? {
+                    return Err(TempoError::Note(
+                        "Projects have to be attached as projects".into(),
+                    ));
+                }
+                let size = fs::metadata(path)?.len();
+                if size > MAX_FILE_ATTACHMENT_SIZE {
+                    return Err(TempoError::Note(format!(
+                        "{} is too large to attach, files can be at most {} MB",
+                        path_to_str(path),
+                        MAX_FILE_ATTACHMENT_SIZE / (1024 * 1024)
+                    )));
+                }
+            }
+            NewAttachment::Stems(NewStemsAttachment { title, stems, mix }) => {
+                if title.is_empty() {
+                    return Err(TempoError::Note(
+                        "A stem pack cannot have an empty title".into(),
+                    ));
+                }
+                if stems.is_empty() {
+                    return Err(TempoError::Note(
+                        "A stem pack needs at least one stem".into(),
+                    ));
+                }
+
+                let mut names: HashSet<&str> = HashSet::new();
+                let mut paths: HashSet<&Path> = HashSet::new();
+                for NewStem { name, path } in stems {
+                    if name.is_empty() {
+                        return Err(TempoError::Note("Stems cannot have an empty name".into()));
+                    }
+                    if !names.insert(name) {
+                        return Err(TempoError::Note(format!(
+                            "A stem pack cannot contain two stems named {name}"
+                        )));
+                    }
+                    if !paths.insert(path) {
+                        return Err(TempoError::Note(format!(
+                            "{} was added to the stem pack more than once",
+                            path_to_str(path)
+                        )));
+                    }
+                    match FileType::get(path)? {
+                        FileType::Audio(_) => (),
+                        t => {
+                            return Err(TempoError::Note(format!(
+                                "Expected an audio file for stem {name}, found {:#?} instead",
+                                t
+                            )))
+                        }
+                    }
+                }
+
+                if let Some(mix) = mix.as_ref() {
+                    match FileType::get(mix)? {
+                        FileType::Audio(_) => (),
+                        t => {
+                            return Err(TempoError::Note(format!(
+                                "Expected an audio file as a reference mix, found {:#?} instead",
+                                t
+                            )))
+                        }
+                    }
+                }
+            }
+            NewAttachment::LiveProject(NewLiveProjectAttachment {
+                title,
+                path,
+                render,
+                relinks,
+            }) => {
+                verify_relinks(relinks)?;
+                if title.is_empty() {
+                    return Err(TempoError::Note(
+                        "A project cannot have an empty title".into(),
+                    ));
+                }
+                if !is_live_project(path) {
+                    return Err(TempoError::Note(format!(
+                        "Expected a Live Project directory as an attachment, {} is not a Live Project",
+                        path_to_str(path)
+                    )));
+                }
+                if let Some(render) = render.as_ref() {
+                    match FileType::get(render)? {
+                        FileType::Audio(_) => (),
+                        t => {
+                            return Err(TempoError::Note(format!(
+                                "Expected an audio file as a render, found {:#?} instead",
+                                t
+                            )))
+                        }
+                    }
+                }
+            }
         }
         Ok(())
     }
 }
+
+fn verify_relinks(relinks: &[Relink]) -> Result<()> {
+    for r in relinks {
+        if !r.path.is_file() {
+            return Err(TempoError::Note(format!(
+                "Cannot relink {} to {}, it is not a file",
+                r.file.abs,
+                path_to_str(&r.path)
+            )));
+        }
+    }
+    Ok(())
+}
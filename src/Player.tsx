@@ -5,9 +5,10 @@ import AudioPlayer from "react-h5-audio-player";
 import "react-h5-audio-player/lib/styles.css";
 
 export function Player() {
-  const [playingInfo, playing] = useStore((state) => [
+  const [playingInfo, playing, setPlayerTime] = useStore((state) => [
     state.playingInfo,
     state.playing,
+    state.setPlayerTime,
   ]);
 
   const [src, setSrc] = useState<string | null>(null);
@@ -20,6 +21,7 @@ export function Player() {
     } else {
       setSrc(null);
     }
+    setPlayerTime(0);
   }, [playingInfo]);
 
   useEffect(() => {
@@ -33,6 +35,12 @@ export function Player() {
       className="min-w-max min-h-max"
       layout="stacked-reverse"
       ref={audioRef}
+      onListen={(e) =>
+        setPlayerTime((e.target as HTMLAudioElement).currentTime)
+      }
+      onSeeked={(e) =>
+        setPlayerTime((e.target as HTMLAudioElement).currentTime)
+      }
       {...(src
         ? { customAdditionalControls: [<div>{playingInfo?.filename}</div>] }
         : { customAdditionalControls: [] })}
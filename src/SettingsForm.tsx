@@ -4,8 +4,9 @@ import { Input } from "@/components/ui/input";
 import { Button } from "@/components/ui/button";
 import { useStore } from "./Store";
 import { Checkbox } from "@/components/ui/checkbox";
-import { Folder, X } from "lucide-react";
+import { Folder, Plus, X } from "lucide-react";
 import { open } from "@tauri-apps/plugin-dialog";
+import { getLibraryRoots, setLibraryRoots } from "./commands";
 
 export function SettingsForm<T>({
   doOnce,
@@ -17,16 +18,20 @@ export function SettingsForm<T>({
     setDefaultUsername,
     setRequireRender,
     setDefaultProjectCopyPath,
+    invokeWithError,
   ] = useStore((state) => [
     state.setSettingsOpen,
     state.setDefaultUsername,
     state.setRequireRender,
     state.setDefaultProjectCopyPath,
+    state.invokeWithError,
   ]);
 
   const [username, setUsername] = useState<string>("");
   const [render, setRender] = useState<boolean>(false);
   const [defaultCopy, setDefaultCopy] = useState<string | null>(null);
+  // sample libraries searched for files missing from projects
+  const [libraries, setLibraries] = useState<string[]>([]);
 
   const [usernameError, setUsernameError] = useState<string | null>(null);
 
@@ -44,6 +49,8 @@ export function SettingsForm<T>({
 
     setRender(useStore.getState().requireRender);
     setDefaultCopy(dc);
+
+    invokeWithError(getLibraryRoots()).then(setLibraries);
   }, []);
 
   function validateUsername(value: string) {
@@ -88,7 +95,9 @@ export function SettingsForm<T>({
     setRequireRender(render);
     setDefaultProjectCopyPath(defaultCopy);
 
-    setSettingsOpen(false);
+    invokeWithError(setLibraryRoots(libraries)).then(() =>
+      setSettingsOpen(false)
+    );
   }
 
   return (
@@ -160,6 +169,52 @@ export function SettingsForm<T>({
               </Button>
             </div>
           </div>
+
+          <Label className="text-right col-span-3">
+            Sample libraries searched for missing files (optional)
+          </Label>
+          <div className="col-span-4">
+            <div className="flex flex-col px-3 py-1 border border-gray-200 rounded-md text-sm">
+              {libraries.map((library) => (
+                <div className="flex flex-row items-center" key={library}>
+                  <p className="select-none cursor-default">{library}</p>
+                  <Button
+                    variant="ghost"
+                    className="ml-auto"
+                    onClick={() =>
+                      setLibraries(libraries.filter((l) => l != library))
+                    }
+                  >
+                    <X size={20} />
+                  </Button>
+                </div>
+              ))}
+              <div className="flex flex-row items-center">
+                {libraries.length == 0 && (
+                  <p className="select-none cursor-default">
+                    <i>No sample libraries selected</i>
+                  </p>
+                )}
+                <Button
+                  variant="secondary"
+                  className="ml-auto"
+                  onClick={() => {
+                    open({
+                      directory: true,
+                      multiple: false,
+                      title: "Select a sample library directory",
+                    }).then((path) => {
+                      if (path != null && !libraries.includes(path)) {
+                        setLibraries([...libraries, path]);
+                      }
+                    });
+                  }}
+                >
+                  <Plus size={20} />
+                </Button>
+              </div>
+            </div>
+          </div>
         </div>
       </div>
       <Button
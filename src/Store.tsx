@@ -153,6 +153,10 @@ interface TempoStore {
   playing: boolean;
   setPlaying: (playing: boolean) => void;
 
+  // current position of the player in seconds
+  playerTime: number;
+  setPlayerTime: (time: number) => void;
+
   focused: boolean;
   setFocused: (focused: boolean) => void;
 
@@ -318,6 +322,12 @@ export const useStore = create<TempoStore>()(
             state.playing = playing;
           }),
 
+        playerTime: 0,
+        setPlayerTime: (time: number) =>
+          set((state: TempoStore) => {
+            state.playerTime = time;
+          }),
+
         focused: true,
         setFocused: (focused: boolean) =>
           set((state: TempoStore) => {
@@ -1,12 +1,39 @@
 import { extractFilename, truncateAttachmentThing } from "../misc";
 import { Music } from "lucide-react";
 import { ReactNode } from "react";
+import { AudioInfo } from "@bindings/AudioInfo";
+
+function formatDuration(secs: number): string {
+  const s = Math.round(secs);
+  return `${Math.floor(s / 60)}:${(s % 60).toString().padStart(2, "0")}`;
+}
+
+function formatChannels(channels: number): string {
+  if (channels == 1) return "mono";
+  if (channels == 2) return "stereo";
+  return `${channels} channels`;
+}
+
+// e.g. "WAV · 48 kHz · 24-bit · stereo · 3:12"
+export function formatAudioInfo(info: AudioInfo): string {
+  return [
+    info.codec,
+    `${info.sample_rate / 1000} kHz`,
+    info.bit_depth != null ? `${info.bit_depth}-bit` : null,
+    formatChannels(info.channels),
+    info.duration_secs != null ? formatDuration(info.duration_secs) : null,
+  ]
+    .filter((d) => d)
+    .join(" · ");
+}
 
 export function AudioAttachment({
   filename,
+  info,
   children,
 }: {
   filename: string;
+  info?: AudioInfo | null;
   children?: ReactNode;
 }) {
   return (
@@ -18,6 +45,9 @@ export function AudioAttachment({
         <b className="text-nowrap">
           {truncateAttachmentThing(extractFilename(filename))}
         </b>
+        {info && (
+          <span className="text-sm text-nowrap">{formatAudioInfo(info)}</span>
+        )}
       </div>
       <div className="flex flex-col h-full w-full align-center">
         <div className="flex items-center h-full">{children}</div>
@@ -0,0 +1,32 @@
+import { CircleAlert } from "lucide-react";
+import { useStore } from "../Store";
+
+// warning about users who don't have a version of Live that can open a project
+export function CannotOpenWarning({
+  version,
+  cannotOpen,
+}: {
+  version: string | null;
+  cannotOpen: string[];
+}) {
+  const username = useStore((state) => state.folderData!.username);
+
+  if (cannotOpen.length == 0) return null;
+
+  const names = cannotOpen.map((u) => (u == username ? "you" : u));
+  const who =
+    names.length == 1
+      ? names[0]
+      : `${names.slice(0, -1).join(", ")} and ${names[names.length - 1]}`;
+
+  return (
+    <div className="flex items-center text-red-500 text-sm">
+      <CircleAlert size={16} className="mr-2 shrink-0" />
+      <span>
+        Saved in Live {version ?? "(unknown version)"}, {who}{" "}
+        {names.length == 1 && who != "you" ? "doesn't" : "don't"} have a version
+        of Live that can open it
+      </span>
+    </div>
+  );
+}
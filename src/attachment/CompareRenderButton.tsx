@@ -0,0 +1,132 @@
+import { GitCompare } from "lucide-react";
+import { useMemo, useState } from "react";
+import { toast } from "sonner";
+import { Button } from "@/components/ui/button";
+import { useStore } from "../Store";
+import { LoadingSpinnerWhite } from "../misc";
+import { nullTestAudio } from "../commands";
+import { SharedNote } from "@bindings/SharedNote";
+import { NullTest } from "@bindings/NullTest";
+
+// hash of the first render in a note
+function renderHash(note: SharedNote): string | null {
+  for (const a of note.attachments) {
+    const render =
+      "Project" in a
+        ? a.Project.render
+        : "LiveProject" in a
+        ? a.LiveProject.render
+        : null;
+    if (render && "Ok" in render) return render.Ok.sha256;
+  }
+  return null;
+}
+
+// m:ss
+function formatTime(secs: number): string {
+  const s = Math.floor(secs);
+  return `${Math.floor(s / 60)}:${(s % 60).toString().padStart(2, "0")}`;
+}
+
+function formatNullTest(res: NullTest): [string, string | undefined] {
+  if (res.difference_rms_dbfs == null)
+    return ["The render is the same as the previous one", undefined];
+
+  const shifted =
+    res.offset_frames != 0
+      ? ` It starts ${Math.abs(res.offset_secs).toFixed(3)}s ${
+          // negative when the new render's audio is delayed
+          res.offset_secs < 0 ? "later" : "earlier"
+        }.`
+      : "";
+  return [
+    `The render differs from the previous one by ${res.difference_rms_dbfs.toFixed(
+      1
+    )} dBFS RMS.${shifted}`,
+    res.regions.length > 0
+      ? "Differs at " +
+        res.regions
+          .map((r) => `${formatTime(r.start_secs)} - ${formatTime(r.end_secs)}`)
+          .join(", ")
+      : undefined,
+  ];
+}
+
+// null tests a render against the render of the note it's a reply to (or that note's replied note and so on)
+export function CompareRenderButton({
+  channelUlid,
+  noteUlid,
+  renderSha256,
+}: {
+  channelUlid: string | null;
+  noteUlid: string;
+  renderSha256: string;
+}) {
+  const [folder, folderData, invokeWithError] = useStore((state) => [
+    state.folder!,
+    state.folderData,
+    state.invokeWithError,
+  ]);
+
+  const [comparing, setComparing] = useState(false);
+
+  const previous = useMemo(() => {
+    if (folderData == null) return null;
+    const notes = channelUlid
+      ? folderData.channels[channelUlid]?.notes
+      : folderData.global;
+
+    // guards against reply cycles
+    const seen = new Set<string>([noteUlid]);
+    let note = notes?.[noteUlid];
+    while (note && "Ok" in note && note.Ok.reply_ulid) {
+      const ulid = note.Ok.reply_ulid;
+      if (seen.has(ulid)) return null;
+      seen.add(ulid);
+
+      note = notes?.[ulid];
+      if (note && "Ok" in note) {
+        const hash = renderHash(note.Ok);
+        if (hash) return hash;
+      }
+    }
+    return null;
+  }, [folderData, channelUlid, noteUlid]);
+
+  if (previous == null) return null;
+
+  function compare() {
+    setComparing(true);
+    invokeWithError(nullTestAudio(folder, previous!, renderSha256, null))
+      .then((res: NullTest) => {
+        const [message, description] = formatNullTest(res);
+        // stays until dismissed so the regions can be checked while listening
+        toast.info(message, {
+          description,
+          duration: Infinity,
+          closeButton: true,
+        });
+      })
+      .finally(() => setComparing(false));
+  }
+
+  return (
+    <Button
+      onClick={compare}
+      disabled={comparing}
+      className="rounded-2xl bg-gray-500 hover:bg-gray-500/90"
+    >
+      {comparing ? (
+        <div className="flex items-center">
+          <LoadingSpinnerWhite className="mr-2" />
+          <span>Comparing...</span>
+        </div>
+      ) : (
+        <div className="flex items-center">
+          <GitCompare className="mr-2" />
+          <span>Compare with previous render</span>
+        </div>
+      )}
+    </Button>
+  );
+}
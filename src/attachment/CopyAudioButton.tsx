@@ -0,0 +1,68 @@
+import { Download } from "lucide-react";
+import { useState } from "react";
+import { open as openDialog } from "@tauri-apps/plugin-dialog";
+import { Button } from "@/components/ui/button";
+import { useStore } from "../Store";
+import { LoadingSpinnerWhite } from "../misc";
+import { copyAudio, copyRender } from "../commands";
+
+export function CopyAudioButton({
+  channelUlid,
+  noteUlid,
+  attachmentIndex,
+  render = false,
+}: {
+  channelUlid: string | null;
+  noteUlid: string;
+  attachmentIndex: number;
+  // copy the render of a project attachment instead
+  render?: boolean;
+}) {
+  const [folder, invokeWithError] = useStore((state) => [
+    state.folder!,
+    state.invokeWithError,
+  ]);
+
+  const [copying, setCopying] = useState(false);
+  const [copyPath, setCopyPath] = useState<string | null>(null);
+
+  function saveCopy() {
+    setCopying(true);
+
+    openDialog({
+      multiple: false,
+      directory: true,
+      title: "Select a directory to copy into",
+    }).then((dir) => {
+      if (dir == null) {
+        setCopying(false);
+        return;
+      }
+
+      const copy = render ? copyRender : copyAudio;
+      invokeWithError(copy(folder, channelUlid, noteUlid, attachmentIndex, dir))
+        .then((path) => setCopyPath(path))
+        .finally(() => setCopying(false));
+    });
+  }
+
+  return (
+    <Button
+      onClick={saveCopy}
+      disabled={copying}
+      className="rounded-2xl bg-gray-500 hover:bg-gray-500/90"
+    >
+      {copying ? (
+        <div className="flex items-center">
+          <LoadingSpinnerWhite className="mr-2" />
+          <span>Copying...</span>
+        </div>
+      ) : (
+        <div className="flex items-center">
+          <Download className="mr-2" />
+          <span>{copyPath ? "Copied" : "Save copy"}</span>
+        </div>
+      )}
+    </Button>
+  );
+}
@@ -18,19 +18,23 @@ import {
 } from "@/components/ui/dialog";
 import { Button } from "@/components/ui/button";
 import { SharedProjectData } from "@bindings/SharedProjectData";
+import { PluginRef } from "@bindings/PluginRef";
 import { PluginTable } from "@/tables/PluginTable";
 import { ScrollArea } from "@/components/ui/scroll-area";
 import { open as openDialog } from "@tauri-apps/plugin-dialog";
 import { copyProject } from "../commands";
+import { toast } from "sonner";
 
 export function CopyProjectButton({
   channelUlid,
   noteUlid,
+  attachmentIndex,
   title,
   projectData,
 }: {
   channelUlid: string | null;
   noteUlid: string;
+  attachmentIndex: number;
   title: string;
   projectData: SharedProjectData;
 }) {
@@ -52,14 +56,18 @@ export function CopyProjectButton({
   const [copyPath, setCopyPath] = useState<string | null>(null);
   const [checkModalOpen, setCheckModalOpen] = useState(false);
 
+  // every daw's project data has missing_files and missing_plugins
+  const data: { missing_files: string[]; missing_plugins: PluginRef[] } =
+    useMemo(() => Object.values(projectData)[0], [projectData]);
+
   const missingFiles = useMemo(
-    () => projectData.Ableton.missing_files.length > 0,
-    [projectData]
+    () => data.missing_files.length > 0,
+    [data]
   );
 
   const missingPlugins = useMemo(
-    () => projectData.Ableton.missing_plugins.length > 0,
-    [projectData]
+    () => data.missing_plugins.length > 0,
+    [data]
   );
 
   const [bgColor, hover] = useMemo(
@@ -96,11 +104,21 @@ export function CopyProjectButton({
   }
 
   function actuallyCopy(dir: string) {
-    invokeWithError(copyProject(folder, channelUlid, noteUlid, dir))
+    invokeWithError(copyProject(folder, channelUlid, noteUlid, attachmentIndex, dir))
       .then(([path, errs]) => {
         console.log("copied to ", path);
         console.warn("missing files:", errs);
         setCopyPath(path);
+        if ("Bitwig" in projectData && projectData.Bitwig.relink_files.length > 0)
+          toast.info(
+            `When opening ${title}, Bitwig will ask you to locate some files. They were copied into the samples folder of the project.`,
+            // stays until dismissed, the user needs the list when Bitwig asks for the files
+            {
+              description: projectData.Bitwig.relink_files.join(", "),
+              duration: Infinity,
+              closeButton: true,
+            }
+          );
         if (askUserAboutCopyPath && defaultProjectCopyPath == null)
           setPossibleDefaultCopyPath(dir);
       })
@@ -259,7 +277,7 @@ export function CopyProjectButton({
             {missingFiles && missingPlugins && (
               <p className="text-2xl font-semibold pb-2">Files</p>
             )}
-            {projectData.Ableton.missing_files.map((file) => (
+            {data.missing_files.map((file) => (
               <div className="mb-2" key={file}>
                 {file}
               </div>
@@ -267,7 +285,7 @@ export function CopyProjectButton({
             {missingFiles && missingPlugins && (
               <p className="text-2xl font-semibold pb-2">Plugins</p>
             )}
-            <PluginTable plugins={projectData.Ableton.missing_plugins} />
+            <PluginTable plugins={data.missing_plugins} />
           </ScrollArea>
           <DialogFooter>
             <Button
@@ -0,0 +1,72 @@
+import { Download } from "lucide-react";
+import { useState } from "react";
+import { open as openDialog } from "@tauri-apps/plugin-dialog";
+import { Button } from "@/components/ui/button";
+import { useStore } from "../Store";
+import { LoadingSpinnerWhite } from "../misc";
+import { copyStems } from "../commands";
+
+export function CopyStemsButton({
+  channelUlid,
+  noteUlid,
+  attachmentIndex,
+}: {
+  channelUlid: string | null;
+  noteUlid: string;
+  attachmentIndex: number;
+}) {
+  const [folder, invokeWithError, addError] = useStore((state) => [
+    state.folder!,
+    state.invokeWithError,
+    state.addError,
+  ]);
+
+  const [copying, setCopying] = useState(false);
+  const [copyPath, setCopyPath] = useState<string | null>(null);
+
+  function saveCopy() {
+    setCopying(true);
+
+    openDialog({
+      multiple: false,
+      directory: true,
+      title: "Select a directory to copy into",
+    }).then((dir) => {
+      if (dir == null) {
+        setCopying(false);
+        return;
+      }
+
+      invokeWithError(copyStems(folder, channelUlid, noteUlid, attachmentIndex, dir))
+        .then(([path, errs]) => {
+          if (errs.length > 0) {
+            addError(
+              `Couldn't copy ${errs.map((e) => e.filename).join(", ")}, your sync service might still be syncing`
+            );
+          }
+          setCopyPath(path);
+        })
+        .finally(() => setCopying(false));
+    });
+  }
+
+  return (
+    <Button
+      onClick={saveCopy}
+      disabled={copying}
+      className="rounded-2xl bg-gray-500 hover:bg-gray-500/90"
+    >
+      {copying ? (
+        <div className="flex items-center">
+          <LoadingSpinnerWhite className="mr-2" />
+          <span>Copying...</span>
+        </div>
+      ) : (
+        <div className="flex items-center">
+          <Download className="mr-2" />
+          <span>{copyPath ? "Copied" : "Save copy"}</span>
+        </div>
+      )}
+    </Button>
+  );
+}
@@ -0,0 +1,44 @@
+import { extractFilename, truncateAttachmentThing } from "../misc";
+import { File as FileIcon } from "lucide-react";
+import { ReactNode } from "react";
+
+function formatSize(bytes: number): string {
+  if (bytes < 1024) return `${bytes} B`;
+  if (bytes < 1024 * 1024) return `${Math.round(bytes / 1024)} KB`;
+  return `${Math.round((bytes / (1024 * 1024)) * 10) / 10} MB`;
+}
+
+export function FileAttachment({
+  filename,
+  mime,
+  size,
+  children,
+}: {
+  filename: string;
+  mime?: string | null;
+  size?: number;
+  children?: ReactNode;
+}) {
+  const details = [mime, size != undefined ? formatSize(size) : null].filter(
+    (d) => d
+  );
+
+  return (
+    <div className="flex p-4 m-2">
+      <div>
+        <FileIcon size="40" className="items-center justify-center h-full" />
+      </div>
+      <div className="flex flex-col mx-4 justify-center">
+        <b className="text-nowrap">
+          {truncateAttachmentThing(extractFilename(filename))}
+        </b>
+        {details.length > 0 && (
+          <span className="text-sm text-nowrap">{details.join(" · ")}</span>
+        )}
+      </div>
+      <div className="flex flex-col h-full w-full align-center">
+        <div className="flex items-center h-full justify-end">{children}</div>
+      </div>
+    </div>
+  );
+}
@@ -0,0 +1,44 @@
+import { useEffect, useState } from "react";
+import { useStore } from "@/Store";
+import { analyzeLoudness } from "@/commands";
+import { AudioFileInfo } from "@bindings/AudioFileInfo";
+import { Loudness } from "@bindings/Loudness";
+
+function formatDb(value: number | null, unit: string): string | null {
+  return value != null ? `${value.toFixed(1)} ${unit}` : null;
+}
+
+// e.g. "-9.8 LUFS · LRA 5.2 LU · -0.3 dBTP"
+export function formatLoudness(loudness: Loudness): string {
+  return [
+    formatDb(loudness.integrated_lufs, "LUFS"),
+    loudness.loudness_range_lu != null
+      ? `LRA ${loudness.loudness_range_lu.toFixed(1)} LU`
+      : null,
+    formatDb(loudness.true_peak_dbtp, "dBTP"),
+  ]
+    .filter((d) => d)
+    .join(" · ");
+}
+
+// loudness of an audio file, measured by the backend the first time it's shown
+export function LoudnessInfo({ audio }: { audio: AudioFileInfo }) {
+  const folder = useStore((state) => state.folder!);
+  const [loudness, setLoudness] = useState<Loudness | null>(audio.loudness);
+
+  useEffect(() => {
+    if (audio.loudness) {
+      setLoudness(audio.loudness);
+      return;
+    }
+
+    // formats the backend can't decode just don't show loudness
+    analyzeLoudness(folder, audio.sha256)
+      .then(setLoudness)
+      .catch(() => setLoudness(null));
+  }, [folder, audio]);
+
+  return loudness ? (
+    <span className="text-sm text-nowrap">{formatLoudness(loudness)}</span>
+  ) : null;
+}
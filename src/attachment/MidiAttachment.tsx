@@ -0,0 +1,71 @@
+import { extractFilename, truncateAttachmentThing } from "../misc";
+import { Piano } from "lucide-react";
+import { ReactNode } from "react";
+import { MidiData } from "@bindings/MidiData";
+
+const NOTE_NAMES = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+
+function noteName(note: number): string {
+  return `${NOTE_NAMES[note % 12]}${Math.floor(note / 12) - 1}`;
+}
+
+function formatLength(secs: number): string {
+  const m = Math.floor(secs / 60);
+  const s = Math.round(secs % 60);
+  return `${m}:${s.toString().padStart(2, "0")}`;
+}
+
+function describeMidi(midi: MidiData): string[] {
+  const lines = [];
+
+  const tempo = midi.tempos.length > 0 ? midi.tempos[0].bpm : 120;
+  const timeSig = midi.time_signatures.length > 0 ? midi.time_signatures[0] : null;
+  lines.push(
+    `${formatLength(midi.length_secs)} · ${Math.round(tempo * 100) / 100} bpm` +
+      (midi.tempos.length > 1 ? " (tempo changes)" : "") +
+      (timeSig ? ` · ${timeSig.numerator}/${timeSig.denominator}` : "")
+  );
+
+  lines.push(
+    `${midi.note_count} notes` +
+      (midi.lowest_note != null && midi.highest_note != null
+        ? ` · ${noteName(midi.lowest_note)}–${noteName(midi.highest_note)}`
+        : "")
+  );
+
+  if (midi.track_names.length > 0) lines.push(midi.track_names.join(", "));
+
+  return lines;
+}
+
+export function MidiAttachment({
+  filename,
+  midi,
+  children,
+}: {
+  filename: string;
+  midi?: MidiData;
+  children?: ReactNode;
+}) {
+  return (
+    <div className="flex p-4 m-2">
+      <div>
+        <Piano size="40" className="items-center justify-center h-full" />
+      </div>
+      <div className="flex flex-col mx-4 justify-center">
+        <b className="text-nowrap">
+          {truncateAttachmentThing(extractFilename(filename))}
+        </b>
+        {midi &&
+          describeMidi(midi).map((line, i) => (
+            <span key={i} className="text-sm text-nowrap">
+              {line}
+            </span>
+          ))}
+      </div>
+      <div className="flex flex-col h-full w-full align-center">
+        <div className="flex items-center h-full justify-end">{children}</div>
+      </div>
+    </div>
+  );
+}
@@ -0,0 +1,59 @@
+import { useEffect, useState } from "react";
+import { useStore } from "@/Store";
+import { analyzeBpmKey } from "@/commands";
+import { AudioFileInfo } from "@bindings/AudioFileInfo";
+import { MusicalAnalysis } from "@bindings/MusicalAnalysis";
+import { MusicalKey } from "@bindings/MusicalKey";
+
+const PITCH_CLASSES = [
+  "C",
+  "C#",
+  "D",
+  "Eb",
+  "E",
+  "F",
+  "F#",
+  "G",
+  "Ab",
+  "A",
+  "Bb",
+  "B",
+];
+
+export function formatKey(key: MusicalKey): string {
+  return `${PITCH_CLASSES[key.tonic]} ${key.mode == "Major" ? "major" : "minor"}`;
+}
+
+// e.g. "128 BPM · A minor"
+export function formatMusical(analysis: MusicalAnalysis): string {
+  return [
+    analysis.bpm != null ? `${Math.round(analysis.bpm * 10) / 10} BPM` : null,
+    analysis.key ? formatKey(analysis.key) : null,
+  ]
+    .filter((d) => d)
+    .join(" · ");
+}
+
+// estimated tempo and key of an audio file, analyzed by the backend the first time it's shown
+export function MusicalInfo({ audio }: { audio: AudioFileInfo }) {
+  const folder = useStore((state) => state.folder!);
+  const [analysis, setAnalysis] = useState<MusicalAnalysis | null>(
+    audio.musical
+  );
+
+  useEffect(() => {
+    if (audio.musical) {
+      setAnalysis(audio.musical);
+      return;
+    }
+
+    // formats the backend can't decode just don't show tempo and key
+    analyzeBpmKey(folder, audio.sha256)
+      .then(setAnalysis)
+      .catch(() => setAnalysis(null));
+  }, [folder, audio]);
+
+  return analysis ? (
+    <span className="text-sm text-nowrap">{formatMusical(analysis)}</span>
+  ) : null;
+}
@@ -1,10 +1,17 @@
 import { NewAttachment } from "@bindings/NewAttachment";
+import { Relink } from "@bindings/Relink";
 import { AudioAttachment } from "./AudioAttachment";
+import { MidiAttachment } from "./MidiAttachment";
+import { FileAttachment } from "./FileAttachment";
 import { ProjectAttachment } from "./ProjectAttachment";
+import { StemsAttachment } from "./StemsAttachment";
 import { produce } from "immer";
 import { NewProjectAttachmentScan } from "./NewProjectAttachmentScan";
 import { MutableAttachment } from "./Attachment";
 import { NewProjectRender } from "./NewProjectRender";
+import { useEffect, useState } from "react";
+import { useStore } from "../Store";
+import { findLiveProjectSets } from "../commands";
 
 export function NewNoteAttachment({
   attachment,
@@ -44,6 +51,84 @@ export function NewNoteAttachment({
         </MutableAttachment>
       );
     }
+    if ("Midi" in attachment) {
+      return (
+        <MutableAttachment
+          title={attachment.Midi.title ? attachment.Midi.title : ""}
+          setTitle={(title: string) => {
+            setAttachment(
+              produce(attachment, (draft) => {
+                draft.Midi.title = title.length == 0 ? null : title;
+              })
+            );
+          }}
+          titlePlaceholder="Add an optional title..."
+          onXButton={() => setAttachment(null)}
+        >
+          <MidiAttachment filename={attachment.Midi.path} />
+        </MutableAttachment>
+      );
+    }
+    if ("File" in attachment) {
+      return (
+        <MutableAttachment
+          title={attachment.File.title ? attachment.File.title : ""}
+          setTitle={(title: string) => {
+            setAttachment(
+              produce(attachment, (draft) => {
+                draft.File.title = title.length == 0 ? null : title;
+              })
+            );
+          }}
+          titlePlaceholder="Add an optional title..."
+          onXButton={() => setAttachment(null)}
+        >
+          <FileAttachment filename={attachment.File.path} />
+        </MutableAttachment>
+      );
+    }
+    if ("Stems" in attachment) {
+      return (
+        <MutableAttachment
+          title={attachment.Stems.title}
+          setTitle={(title: string) => {
+            setAttachment(
+              produce(attachment, (draft) => {
+                draft.Stems.title = title;
+              })
+            );
+          }}
+          titlePlaceholder="Add a title..."
+          onXButton={() => setAttachment(null)}
+        >
+          <StemsAttachment
+            stems={attachment.Stems.stems.map((s) => ({
+              name: s.name,
+              filename: s.path,
+            }))}
+            setStemName={(i: number, name: string) => {
+              setAttachment(
+                produce(attachment, (draft) => {
+                  draft.Stems.stems[i].name = name;
+                })
+              );
+            }}
+          >
+            <NewProjectRender
+              label="reference mix"
+              renderPath={attachment.Stems.mix}
+              setRenderPath={(path: string | null) => {
+                setAttachment(
+                  produce(attachment, (draft) => {
+                    draft.Stems.mix = path;
+                  })
+                );
+              }}
+            />
+          </StemsAttachment>
+        </MutableAttachment>
+      );
+    }
     if ("Project" in attachment) {
       return (
         <MutableAttachment
@@ -79,6 +164,14 @@ export function NewNoteAttachment({
                 <NewProjectAttachmentScan
                   projectPath={attachment.Project.path}
                   setScanningProject={setScanning}
+                  relinks={attachment.Project.relinks}
+                  setRelinks={(relinks: Relink[]) => {
+                    setAttachment(
+                      produce(attachment, (draft) => {
+                        draft.Project.relinks = relinks;
+                      })
+                    );
+                  }}
                 />
               </div>
             </div>
@@ -86,7 +179,78 @@ export function NewNoteAttachment({
         </MutableAttachment>
       );
     }
+    if ("LiveProject" in attachment) {
+      return (
+        <MutableAttachment
+          title={attachment.LiveProject.title}
+          setTitle={(title: string) => {
+            setAttachment(
+              produce(attachment, (draft) => {
+                draft.LiveProject.title = title;
+              })
+            );
+          }}
+          titlePlaceholder="Add a title..."
+          onXButton={() => setAttachment(null)}
+        >
+          <ProjectAttachment
+            projectPath={attachment.LiveProject.path}
+            projectType="Live Project"
+          >
+            <div className="flex flex-row min-w-full items-center align-middle">
+              <div className="mr-auto">
+                <NewProjectRender
+                  renderPath={attachment.LiveProject.render}
+                  setRenderPath={(path: string | null) => {
+                    setAttachment(
+                      produce(attachment, (draft) => {
+                        draft.LiveProject.render = path;
+                      })
+                    );
+                  }}
+                />
+              </div>
+              <div className="p-2">
+                <NewProjectAttachmentScan
+                  projectPath={attachment.LiveProject.path}
+                  setScanningProject={setScanning}
+                  relinks={attachment.LiveProject.relinks}
+                  setRelinks={(relinks: Relink[]) => {
+                    setAttachment(
+                      produce(attachment, (draft) => {
+                        draft.LiveProject.relinks = relinks;
+                      })
+                    );
+                  }}
+                />
+              </div>
+            </div>
+          </ProjectAttachment>
+          <LiveProjectSets liveProject={attachment.LiveProject.path} />
+        </MutableAttachment>
+      );
+    }
   }
 
   return render();
 }
+
+// sets that will be sent along with a Live Project
+function LiveProjectSets({ liveProject }: { liveProject: string }) {
+  const invokeWithError = useStore((state) => state.invokeWithError);
+  const [sets, setSets] = useState<string[]>([]);
+
+  useEffect(() => {
+    invokeWithError(findLiveProjectSets(liveProject)).then(setSets);
+  }, [liveProject]);
+
+  return (
+    <div className="flex flex-col px-4 pb-4">
+      {sets.map((set) => (
+        <i className="text-nowrap text-sm" key={set}>
+          {set.slice(liveProject.length).replace(/^[/\\]/, "")}
+        </i>
+      ))}
+    </div>
+  );
+}
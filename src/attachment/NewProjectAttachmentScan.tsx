@@ -19,13 +19,23 @@ import { ProjectFileRefScan } from "@bindings/ProjectFileRefScan";
 import { PluginScan } from "@bindings/PluginScan";
 import { PluginTable } from "@/tables/PluginTable";
 import { ScrollArea } from "@/components/ui/scroll-area";
+import { Checkbox } from "@/components/ui/checkbox";
+import { Relink } from "@bindings/Relink";
+import { MissingFileRef } from "@bindings/MissingFileRef";
+import { RelinkCandidate } from "@bindings/RelinkCandidate";
+import { CannotOpenWarning } from "./CannotOpenWarning";
 
 export function NewProjectAttachmentScan({
   projectPath,
   setScanningProject,
+  relinks,
+  setRelinks,
 }: {
   projectPath: string;
   setScanningProject: (b: boolean) => void;
+  // missing files the user chose replacements for
+  relinks: Relink[];
+  setRelinks: (relinks: Relink[]) => void;
 }) {
   const [invokeWithError, folder, folderData] = useStore((state) => [
     state.invokeWithError,
@@ -57,9 +67,17 @@ export function NewProjectAttachmentScan({
     setPluginScanErr(null);
 
     (async () => {
-      const files = invokeWithError(scanProjectFileRefs(projectPath)).then(
-        (scan) => {
+      const files = invokeWithError(
+        scanProjectFileRefs(folder, projectPath)
+      ).then(
+        (scan: ProjectFileRefScan) => {
           setFileRefScan(scan);
+          // drop replacements for files that aren't missing anymore
+          setRelinks(
+            relinks.filter((r) =>
+              scan.missing.some((m) => sameFileRef(m, r))
+            )
+          );
           console.log(scan);
         },
         (err) => setFileScanErr(err)
@@ -86,6 +104,23 @@ export function NewProjectAttachmentScan({
     scan();
   }, [projectPath]);
 
+  function getRelink(missing: MissingFileRef): Relink | undefined {
+    return relinks.find((r) => sameFileRef(missing, r));
+  }
+
+  function toggleRelink(missing: MissingFileRef, candidate: RelinkCandidate) {
+    const others = relinks.filter((r) => !sameFileRef(missing, r));
+    if (getRelink(missing)?.path == candidate.path) {
+      setRelinks(others);
+    } else {
+      setRelinks([...others, { file: missing.file, path: candidate.path }]);
+    }
+  }
+
+  const unresolved = fileRefScan
+    ? fileRefScan.missing.filter((m) => getRelink(m) == undefined).length
+    : 0;
+
   function renderFiles() {
     if (fileRefScan == null && fileScanErr == null) {
       return (
@@ -104,17 +139,23 @@ export function NewProjectAttachmentScan({
       return (
         <div
           className={`rounded-md p-3 font-bold select-none cursor-pointer text-lg text-white ${
-            fileRefScan.missing.length > 0 ? "bg-red-400" : "bg-green-400"
+            unresolved > 0 ? "bg-red-400" : "bg-green-400"
           }`}
           onClick={() => {
             setDialogContent("files");
             setDialogOpen(true);
           }}
         >
-          {fileRefScan.missing.length > 0 ? (
+          {unresolved > 0 ? (
             <>
               <CircleAlert color="white" size={25} />
               Missing files
+              {relinks.length > 0 && ` (${relinks.length} replaced)`}
+            </>
+          ) : fileRefScan.missing.length > 0 ? (
+            <>
+              <CheckCircle color="white" size={30} />
+              Replaced all missing files
             </>
           ) : (
             <>
@@ -209,11 +250,42 @@ export function NewProjectAttachmentScan({
               Files missing
             </AccordionTrigger>
             <AccordionContent>
+              {fileRefScan.missing.every((ref) => ref.candidates.length == 0) && (
+                <p className="pb-3">
+                  No replacements were found in your sample libraries. You can
+                  add sample libraries to search in the settings.
+                </p>
+              )}
               {fileRefScan.missing.map((ref, idx) => {
                 return (
                   <div className="mb-2" key={"missing-" + idx}>
                     <p>{ref.file.abs}</p>
                     <p className="text-red-400">{ref.err}</p>
+                    {ref.candidates.length > 0 && (
+                      <div className="pt-2 pl-4">
+                        <p className="pb-1">
+                          Replace with a file from your sample libraries:
+                        </p>
+                        {ref.candidates.map((candidate) => (
+                          <div
+                            className="flex items-center pb-1"
+                            key={candidate.path}
+                          >
+                            <Checkbox
+                              id={`relink-${idx}-${candidate.path}`}
+                              checked={getRelink(ref)?.path == candidate.path}
+                              onClick={() => toggleRelink(ref, candidate)}
+                            />
+                            <label
+                              htmlFor={`relink-${idx}-${candidate.path}`}
+                              className="ml-2"
+                            >
+                              {candidate.path}
+                            </label>
+                          </div>
+                        ))}
+                      </div>
+                    )}
                     <br />
                   </div>
                 );
@@ -305,7 +377,19 @@ export function NewProjectAttachmentScan({
       {renderRefresh()}
       {renderFiles()}
       {renderPlugins()}
+      {pluginScan && (
+        <CannotOpenWarning
+          version={pluginScan.project_version}
+          cannotOpen={pluginScan.cannot_open}
+        />
+      )}
       {renderDialog()}
     </div>
   );
 }
+
+function sameFileRef(missing: MissingFileRef, relink: Relink): boolean {
+  return (
+    missing.file.rel == relink.file.rel && missing.file.abs == relink.file.abs
+  );
+}
@@ -13,9 +13,11 @@ import { XButton } from "@/XButton";
 export function NewProjectRender({
   renderPath,
   setRenderPath,
+  label = "project render",
 }: {
   renderPath: string | null;
   setRenderPath: (path: string | null) => void;
+  label?: string;
 }) {
   const [addError] = useStore((state) => [state.addError]);
 
@@ -63,7 +65,7 @@ export function NewProjectRender({
         lastDropTime.current = Date.now();
         setIsHoveringRender(false);
         if (event.paths.length != 1) {
-          addError("Please drag and drop only one render at most.");
+          addError(`Please drag and drop only one ${label} at most.`);
         } else {
           trySetRenderPath(event.paths[0]);
         }
@@ -73,7 +75,7 @@ export function NewProjectRender({
   return renderPath ? (
     <div className="flex-col items-center p-6 shadow-sm rounded border border-gray-300 cursor-default relative">
       <XButton onClick={() => setRenderPath(null)} />
-      <b>Attached render</b>
+      <b>Attached {label}</b>
       <br />
       {extractFilename(renderPath)}
     </div>
@@ -88,7 +90,7 @@ export function NewProjectRender({
         (async () => {
           const path = await open({
             multiple: false,
-            title: "Select a render to attach",
+            title: `Select a ${label} to attach`,
           });
           if (path) {
             trySetRenderPath(path);
@@ -101,7 +103,7 @@ export function NewProjectRender({
         className="text-gray-500 transition-all duration-300 hover:text-gray-700"
       />
       <span className="text-sm text-gray-500 transition-all duration-300 hover:text-gray-700">
-        Click or drag a <b>project render</b> here
+        Click or drag a <b>{label}</b> here
       </span>
     </div>
   );
@@ -10,22 +10,60 @@ import { TempoResult } from "@bindings/TempoResult";
 import { CopyProjectButton } from "@/attachment/CopyProjectButton";
 import { PlayAudioButton } from "./PlayAudioButton";
 import { AudioAttachment } from "./AudioAttachment";
+import { SharedMidiAttachment } from "@bindings/SharedMidiAttachment";
+import { MidiFileInfo } from "@bindings/MidiFileInfo";
+import { MidiAttachment } from "./MidiAttachment";
+import { CopyMidiButton } from "./CopyMidiButton";
+import { SharedStemsAttachment } from "@bindings/SharedStemsAttachment";
+import { StemsAttachment } from "./StemsAttachment";
+import { CopyStemsButton } from "./CopyStemsButton";
+import { SharedFileAttachment } from "@bindings/SharedFileAttachment";
+import { FileAttachmentInfo } from "@bindings/FileAttachmentInfo";
+import { FileAttachment } from "./FileAttachment";
+import { CopyFileButton } from "./CopyFileButton";
+import { CopyAudioButton } from "./CopyAudioButton";
+import { ExportRendersButton } from "./ExportRendersButton";
+import { CompareRenderButton } from "./CompareRenderButton";
+import { Waveform } from "./Waveform";
+import { LoudnessInfo } from "./LoudnessInfo";
+import { MusicalInfo } from "./MusicalInfo";
+import { SharedLiveProjectAttachment } from "@bindings/SharedLiveProjectAttachment";
+import { SharedProjectData } from "@bindings/SharedProjectData";
+import { PluginRef } from "@bindings/PluginRef";
+import { CannotOpenWarning } from "./CannotOpenWarning";
+import { RelinkWarning } from "./RelinkWarning";
+import { PositionedComment } from "@bindings/PositionedComment";
 
 // attachment on a previously sent note
 export function NoteAttachment({
   channelUlid,
   noteUlid,
+  attachmentIndex,
   attachment,
+  comments,
 }: {
   channelUlid: string | null;
   noteUlid: string;
+  attachmentIndex: number;
   attachment: SharedAttachment;
+  // comments on the note with a position, marked on waveforms
+  comments: PositionedComment[];
 }) {
   const [ty, att]: [
-    "Project" | "Audio",
-    SharedProjectAttachment | SharedAudioAttachment
+    "Project" | "LiveProject" | "Audio" | "Midi" | "Stems" | "File",
+    | SharedProjectAttachment
+    | SharedLiveProjectAttachment
+    | SharedAudioAttachment
+    | SharedMidiAttachment
+    | SharedStemsAttachment
+    | SharedFileAttachment
   ] = useMemo(() => {
     if ("Project" in attachment) return ["Project", attachment.Project];
+    if ("LiveProject" in attachment)
+      return ["LiveProject", attachment.LiveProject];
+    if ("Midi" in attachment) return ["Midi", attachment.Midi];
+    if ("Stems" in attachment) return ["Stems", attachment.Stems];
+    if ("File" in attachment) return ["File", attachment.File];
     return ["Audio", attachment.Audio];
   }, [attachment]);
 
@@ -33,21 +71,63 @@ export function NoteAttachment({
     <NoteProjectAttachment
       channelUlid={channelUlid}
       noteUlid={noteUlid}
+      attachmentIndex={attachmentIndex}
       attachment={att as SharedProjectAttachment}
+      comments={comments}
+    />
+  ) : ty == "LiveProject" ? (
+    <NoteLiveProjectAttachment
+      channelUlid={channelUlid}
+      noteUlid={noteUlid}
+      attachmentIndex={attachmentIndex}
+      attachment={att as SharedLiveProjectAttachment}
+      comments={comments}
+    />
+  ) : ty == "Stems" ? (
+    <NoteStemsAttachment
+      channelUlid={channelUlid}
+      noteUlid={noteUlid}
+      attachmentIndex={attachmentIndex}
+      attachment={att as SharedStemsAttachment}
+      comments={comments}
+    />
+  ) : ty == "File" ? (
+    <NoteFileAttachment
+      channelUlid={channelUlid}
+      noteUlid={noteUlid}
+      attachmentIndex={attachmentIndex}
+      attachment={att as SharedFileAttachment}
+    />
+  ) : ty == "Midi" ? (
+    <NoteMidiAttachment
+      channelUlid={channelUlid}
+      noteUlid={noteUlid}
+      attachmentIndex={attachmentIndex}
+      attachment={att as SharedMidiAttachment}
     />
   ) : (
-    <NoteAudioAttachment attachment={att as SharedAudioAttachment} />
+    <NoteAudioAttachment
+      channelUlid={channelUlid}
+      noteUlid={noteUlid}
+      attachmentIndex={attachmentIndex}
+      attachment={att as SharedAudioAttachment}
+      comments={comments}
+    />
   );
 }
 
 function NoteProjectAttachment({
   channelUlid,
   noteUlid,
+  attachmentIndex,
   attachment,
+  comments,
 }: {
   channelUlid: string | null;
   noteUlid: string;
+  attachmentIndex: number;
   attachment: SharedProjectAttachment;
+  comments: PositionedComment[];
 }) {
   const [projectOk, projectErrOrInfo] = useMemo(() => {
     if ("Err" in attachment.project) return [false, attachment.project.Err];
@@ -61,15 +141,42 @@ function NoteProjectAttachment({
         projectType="Ableton project"
       >
         <div className="flex flex-row justify-end w-full items-center align-middle">
-          <div className="mr-auto">
+          <div className="mr-auto flex items-center">
             {attachment.render && (
-              <NoteProjectRender render={attachment.render} />
+              <NoteProjectRender
+                render={attachment.render}
+                comments={comments}
+              />
+            )}
+            {attachment.render && "Ok" in attachment.render && (
+              <div className="flex ml-2">
+                <CopyAudioButton
+                  channelUlid={channelUlid}
+                  noteUlid={noteUlid}
+                  attachmentIndex={attachmentIndex}
+                  render
+                />
+                <div className="ml-2">
+                  <ExportRendersButton
+                    channelUlid={channelUlid}
+                    noteUlid={noteUlid}
+                  />
+                </div>
+                <div className="ml-2">
+                  <CompareRenderButton
+                    channelUlid={channelUlid}
+                    noteUlid={noteUlid}
+                    renderSha256={attachment.render.Ok.sha256}
+                  />
+                </div>
+              </div>
             )}
           </div>
           {projectOk ? (
             <CopyProjectButton
               channelUlid={channelUlid}
               noteUlid={noteUlid}
+              attachmentIndex={attachmentIndex}
               title={attachment.title}
               projectData={(projectErrOrInfo as ProjectInfo).data}
             />
@@ -80,6 +187,22 @@ function NoteProjectAttachment({
           )}
         </div>
       </ProjectAttachment>
+      {"Ableton" in (projectErrOrInfo as ProjectInfo).data && (
+        <div className="px-4 pb-4">
+          <CannotOpenWarning
+            version={(projectErrOrInfo as ProjectInfo).data.Ableton.version}
+            cannotOpen={(projectErrOrInfo as ProjectInfo).data.Ableton.cannot_open}
+          />
+        </div>
+      )}
+      {"Bitwig" in (projectErrOrInfo as ProjectInfo).data && (
+        <div className="px-4 pb-4">
+          <RelinkWarning
+            daw="Bitwig"
+            files={(projectErrOrInfo as ProjectInfo).data.Bitwig.relink_files}
+          />
+        </div>
+      )}
     </Attachment>
   ) : (
     <p className="text-red-500 m-4">
@@ -92,14 +215,164 @@ function NoteProjectAttachment({
   );
 }
 
-function NoteProjectRender({ render }: { render: TempoResult<AudioFileInfo> }) {
+function NoteLiveProjectAttachment({
+  channelUlid,
+  noteUlid,
+  attachmentIndex,
+  attachment,
+  comments,
+}: {
+  channelUlid: string | null;
+  noteUlid: string;
+  attachmentIndex: number;
+  attachment: SharedLiveProjectAttachment;
+  comments: PositionedComment[];
+}) {
+  // the whole Live Project is copied at once, so missing files and plugins of all sets are shown together
+  const [projectData, errs]: [SharedProjectData, string[]] = useMemo(() => {
+    const missingFiles = new Set<string>();
+    const missingPlugins = new Map<string, PluginRef>();
+    const cannotOpen = new Set<string>();
+    // sets which some users can't open, the version they were saved in
+    const versions = new Set<string>();
+    const errs: string[] = [];
+
+    for (const set of attachment.sets) {
+      if ("Err" in set.project) {
+        errs.push(`${set.path}: ${set.project.Err}`);
+        continue;
+      }
+      // every daw's project data has missing_files and missing_plugins
+      const data: {
+        missing_files: string[];
+        missing_plugins: PluginRef[];
+        cannot_open?: string[];
+      } = Object.values(set.project.Ok.data)[0];
+      data.missing_files.forEach((f) => missingFiles.add(f));
+      data.missing_plugins.forEach((p) =>
+        missingPlugins.set(JSON.stringify(p), p)
+      );
+      data.cannot_open?.forEach((u) => cannotOpen.add(u));
+      if ("Ableton" in set.project.Ok.data) {
+        const { version, cannot_open } = set.project.Ok.data.Ableton;
+        if (version && cannot_open.length > 0) versions.add(version);
+      }
+    }
+
+    return [
+      {
+        Ableton: {
+          missing_files: [...missingFiles],
+          missing_plugins: [...missingPlugins.values()],
+          version: versions.size > 0 ? [...versions].join(", ") : null,
+          cannot_open: [...cannotOpen],
+        },
+      },
+      errs,
+    ];
+  }, [attachment]);
+
+  return (
+    <Attachment title={attachment.title}>
+      <ProjectAttachment
+        projectPath={attachment.title}
+        projectType={`Live Project, ${attachment.sets.length} ${
+          attachment.sets.length == 1 ? "set" : "sets"
+        }`}
+      >
+        <div className="flex flex-row justify-end w-full items-center align-middle">
+          <div className="mr-auto flex items-center">
+            {attachment.render && (
+              <NoteProjectRender
+                render={attachment.render}
+                comments={comments}
+              />
+            )}
+            {attachment.render && "Ok" in attachment.render && (
+              <div className="flex ml-2">
+                <CopyAudioButton
+                  channelUlid={channelUlid}
+                  noteUlid={noteUlid}
+                  attachmentIndex={attachmentIndex}
+                  render
+                />
+                <div className="ml-2">
+                  <ExportRendersButton
+                    channelUlid={channelUlid}
+                    noteUlid={noteUlid}
+                  />
+                </div>
+                <div className="ml-2">
+                  <CompareRenderButton
+                    channelUlid={channelUlid}
+                    noteUlid={noteUlid}
+                    renderSha256={attachment.render.Ok.sha256}
+                  />
+                </div>
+              </div>
+            )}
+          </div>
+          {errs.length == 0 ? (
+            <CopyProjectButton
+              channelUlid={channelUlid}
+              noteUlid={noteUlid}
+              attachmentIndex={attachmentIndex}
+              title={attachment.title}
+              projectData={projectData}
+            />
+          ) : (
+            <p className="text-red-500 m-4">
+              <b>Error, your sync service might be syncing still</b>
+            </p>
+          )}
+        </div>
+      </ProjectAttachment>
+      <div className="flex flex-col px-4 pb-4">
+        {attachment.sets.map((set) => (
+          <i className="text-nowrap text-sm" key={set.path}>
+            {set.path}
+          </i>
+        ))}
+        {errs.map((err) => (
+          <p className="text-red-500 text-sm" key={err}>
+            {err}
+          </p>
+        ))}
+        {"Ableton" in projectData && (
+          <CannotOpenWarning
+            version={projectData.Ableton.version}
+            cannotOpen={projectData.Ableton.cannot_open}
+          />
+        )}
+      </div>
+    </Attachment>
+  );
+}
+
+function NoteProjectRender({
+  render,
+  comments,
+}: {
+  render: TempoResult<AudioFileInfo>;
+  comments: PositionedComment[];
+}) {
   const [ok, errOrInfo] = useMemo(() => {
     if ("Ok" in render) return [true, render.Ok];
     return [false, render.Err];
   }, [render]);
 
   return ok ? (
-    <PlayAudioButton audio={errOrInfo as AudioFileInfo} />
+    <div className="flex items-center">
+      <PlayAudioButton audio={errOrInfo as AudioFileInfo} />
+      <div className="flex flex-col items-center">
+        <Waveform
+          sha256={(errOrInfo as AudioFileInfo).sha256}
+          comments={comments}
+        />
+        <LoudnessInfo audio={errOrInfo as AudioFileInfo} />
+        <MusicalInfo audio={errOrInfo as AudioFileInfo} />
+      </div>
+    </div>
   ) : (
     <p>
       <b>Error with render, your sync service might be syncing still: </b>
@@ -109,9 +382,17 @@ function NoteProjectRender({ render }: { render: TempoResult<AudioFileInfo> }) {
 }
 
 function NoteAudioAttachment({
+  channelUlid,
+  noteUlid,
+  attachmentIndex,
   attachment,
+  comments,
 }: {
+  channelUlid: string | null;
+  noteUlid: string;
+  attachmentIndex: number;
   attachment: SharedAudioAttachment;
+  comments: PositionedComment[];
 }) {
   const [ok, errOrInfo] = useMemo(() => {
     if ("Ok" in attachment.file) return [true, attachment.file.Ok];
@@ -120,8 +401,26 @@ function NoteAudioAttachment({
   return (
     <Attachment title={attachment.title}>
       {ok ? (
-        <AudioAttachment filename={(errOrInfo as AudioFileInfo).filename}>
+        <AudioAttachment
+          filename={(errOrInfo as AudioFileInfo).filename}
+          info={(errOrInfo as AudioFileInfo).meta?.info}
+        >
           <PlayAudioButton audio={errOrInfo as AudioFileInfo} />
+          <div className="flex flex-col items-center">
+            <Waveform
+              sha256={(errOrInfo as AudioFileInfo).sha256}
+              comments={comments}
+            />
+            <LoudnessInfo audio={errOrInfo as AudioFileInfo} />
+            <MusicalInfo audio={errOrInfo as AudioFileInfo} />
+          </div>
+          <div className="ml-auto">
+            <CopyAudioButton
+              channelUlid={channelUlid}
+              noteUlid={noteUlid}
+              attachmentIndex={attachmentIndex}
+            />
+          </div>
         </AudioAttachment>
       ) : (
         <p>Error finding audio file: {errOrInfo as string}</p>
@@ -129,3 +428,115 @@ function NoteAudioAttachment({
     </Attachment>
   );
 }
+
+function NoteMidiAttachment({
+  channelUlid,
+  noteUlid,
+  attachmentIndex,
+  attachment,
+}: {
+  channelUlid: string | null;
+  noteUlid: string;
+  attachmentIndex: number;
+  attachment: SharedMidiAttachment;
+}) {
+  const [ok, errOrInfo] = useMemo(() => {
+    if ("Ok" in attachment.file) return [true, attachment.file.Ok];
+    return [false, attachment.file.Err];
+  }, [attachment]);
+  return (
+    <Attachment title={attachment.title}>
+      {ok ? (
+        <MidiAttachment
+          filename={(errOrInfo as MidiFileInfo).filename}
+          midi={(errOrInfo as MidiFileInfo).midi}
+        >
+          <CopyMidiButton channelUlid={channelUlid} noteUlid={noteUlid} attachmentIndex={attachmentIndex} />
+        </MidiAttachment>
+      ) : (
+        <p>Error finding MIDI file: {errOrInfo as string}</p>
+      )}
+    </Attachment>
+  );
+}
+
+function NoteStemsAttachment({
+  channelUlid,
+  noteUlid,
+  attachmentIndex,
+  attachment,
+  comments,
+}: {
+  channelUlid: string | null;
+  noteUlid: string;
+  attachmentIndex: number;
+  attachment: SharedStemsAttachment;
+  comments: PositionedComment[];
+}) {
+  const stems = useMemo(
+    () =>
+      attachment.stems.map((stem) =>
+        "Ok" in stem.file
+          ? {
+              name: stem.name,
+              filename: stem.file.Ok.filename,
+              action: <PlayAudioButton audio={stem.file.Ok} />,
+            }
+          : {
+              name: stem.name,
+              filename: null,
+              action: <p className="text-red-500">Error finding stem: {stem.file.Err}</p>,
+            }
+      ),
+    [attachment]
+  );
+
+  return (
+    <Attachment title={attachment.title}>
+      <StemsAttachment stems={stems}>
+        <div className="mr-auto">
+          {attachment.mix && (
+            <NoteProjectRender render={attachment.mix} comments={comments} />
+          )}
+        </div>
+        <CopyStemsButton channelUlid={channelUlid} noteUlid={noteUlid} attachmentIndex={attachmentIndex} />
+      </StemsAttachment>
+    </Attachment>
+  );
+}
+
+function NoteFileAttachment({
+  channelUlid,
+  noteUlid,
+  attachmentIndex,
+  attachment,
+}: {
+  channelUlid: string | null;
+  noteUlid: string;
+  attachmentIndex: number;
+  attachment: SharedFileAttachment;
+}) {
+  const [ok, errOrInfo] = useMemo(() => {
+    if ("Ok" in attachment.file) return [true, attachment.file.Ok];
+    return [false, attachment.file.Err];
+  }, [attachment]);
+  return (
+    <Attachment title={attachment.title}>
+      {ok ? (
+        <FileAttachment
+          filename={(errOrInfo as FileAttachmentInfo).filename}
+          mime={attachment.mime}
+          size={attachment.size}
+        >
+          <CopyFileButton
+            channelUlid={channelUlid}
+            noteUlid={noteUlid}
+            attachmentIndex={attachmentIndex}
+          />
+        </FileAttachment>
+      ) : (
+        <p>Error finding file: {errOrInfo as string}</p>
+      )}
+    </Attachment>
+  );
+}
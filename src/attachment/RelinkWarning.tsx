@@ -0,0 +1,31 @@
+import { TriangleAlert } from "lucide-react";
+
+// warning about files a daw will ask the user to locate after copying a project
+export function RelinkWarning({
+  daw,
+  files,
+}: {
+  daw: string;
+  files: string[];
+}) {
+  if (files.length == 0) return null;
+
+  return (
+    <div className="flex items-start text-amber-600 text-sm">
+      <TriangleAlert size={16} className="mr-2 mt-0.5 shrink-0" />
+      <div className="flex flex-col">
+        <span>
+          {daw} will ask you to locate{" "}
+          {files.length == 1 ? "a file" : `${files.length} files`} when opening
+          a copy of this project, pick {files.length == 1 ? "it" : "them"} from
+          the copied project:
+        </span>
+        {files.map((f) => (
+          <i className="text-nowrap" key={f}>
+            {f}
+          </i>
+        ))}
+      </div>
+    </div>
+  );
+}
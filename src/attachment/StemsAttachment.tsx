@@ -0,0 +1,56 @@
+import { extractFilename, truncateAttachmentThing } from "../misc";
+import { Layers } from "lucide-react";
+import { ReactNode } from "react";
+
+export interface StemRow {
+  name: string;
+  filename: string | null;
+  // shown at the end of the row, e.g. a play button
+  action?: ReactNode;
+}
+
+// list of stems, names are editable if setStemName is given
+export function StemsAttachment({
+  stems,
+  setStemName,
+  children,
+}: {
+  stems: StemRow[];
+  setStemName?: (i: number, name: string) => void;
+  children?: ReactNode;
+}) {
+  return (
+    <div className="flex p-4 m-2">
+      <div>
+        <Layers size="40" className="items-center justify-center h-full" />
+      </div>
+      <div className="flex flex-col mx-4 justify-center w-full">
+        {stems.map((stem, i) => (
+          <div key={i} className="flex items-center py-1">
+            {setStemName ? (
+              <input
+                value={stem.name}
+                onChange={(e) => setStemName(i, e.target.value)}
+                placeholder="Stem name..."
+                className={`font-bold outline-none border-b ${
+                  stem.name.length == 0 ? "border-red-400" : "border-gray-200"
+                }`}
+              />
+            ) : (
+              <b className="text-nowrap">{stem.name}</b>
+            )}
+            {stem.filename && (
+              <span className="text-sm text-gray-500 text-nowrap mx-2">
+                {truncateAttachmentThing(extractFilename(stem.filename))}
+              </span>
+            )}
+            <div className="ml-auto">{stem.action}</div>
+          </div>
+        ))}
+        {children && (
+          <div className="flex items-center pt-2 justify-end">{children}</div>
+        )}
+      </div>
+    </div>
+  );
+}
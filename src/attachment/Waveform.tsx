@@ -0,0 +1,89 @@
+import { useEffect, useRef, useState } from "react";
+import { useStore } from "@/Store";
+import { getWaveformPeaks } from "@/commands";
+import { WaveformPeaks } from "@bindings/WaveformPeaks";
+import { PositionedComment } from "@bindings/PositionedComment";
+import { formatCommentPosition } from "@/note/Comments";
+
+// waveform of an audio file drawn from peaks generated by the backend
+// comments on this file are marked at their position
+export function Waveform({
+  sha256,
+  comments = [],
+  width = 300,
+  height = 48,
+}: {
+  sha256: string;
+  comments?: PositionedComment[];
+  width?: number;
+  height?: number;
+}) {
+  const folder = useStore((state) => state.folder!);
+  const [peaks, setPeaks] = useState<WaveformPeaks | null>(null);
+  const canvasRef = useRef<HTMLCanvasElement | null>(null);
+
+  useEffect(() => {
+    // formats the backend can't decode just don't get a waveform
+    getWaveformPeaks(folder, sha256)
+      .then(setPeaks)
+      .catch(() => setPeaks(null));
+  }, [folder, sha256]);
+
+  useEffect(() => {
+    const ctx = canvasRef.current?.getContext("2d");
+    if (!ctx || !peaks || peaks.levels.length == 0) return;
+
+    // coarsest level that still has a peak for every pixel
+    const level =
+      [...peaks.levels].reverse().find((l) => l.min.length >= width) ??
+      peaks.levels[0];
+
+    ctx.clearRect(0, 0, width, height);
+    ctx.fillStyle = "rgb(107 114 128)";
+
+    const mid = height / 2;
+    const perPixel = level.min.length / width;
+    for (let x = 0; x < width; x++) {
+      const start = Math.floor(x * perPixel);
+      const end = Math.max(start + 1, Math.floor((x + 1) * perPixel));
+      let lo = 0;
+      let hi = 0;
+      for (let i = start; i < end && i < level.min.length; i++) {
+        lo = Math.min(lo, level.min[i]);
+        hi = Math.max(hi, level.max[i]);
+      }
+      ctx.fillRect(x, mid - hi * mid, 1, Math.max(1, (hi - lo) * mid));
+    }
+  }, [peaks, width, height]);
+
+  if (!peaks) return null;
+
+  const duration = peaks.frames / peaks.sample_rate;
+
+  return (
+    <div className="relative mx-2" style={{ width, height }}>
+      <canvas ref={canvasRef} width={width} height={height} />
+      {duration > 0 &&
+        comments
+          .filter(({ comment }) => comment.position?.file_hash == sha256)
+          .map(({ ulid, comment }) => {
+            const position = comment.position!;
+            const left = Math.min(1, position.start_secs / duration) * width;
+            const right =
+              position.end_secs != null
+                ? Math.min(1, position.end_secs / duration) * width
+                : left;
+            return (
+              <div
+                key={ulid}
+                className="absolute top-0 h-full border-l-2 border-orange-500 bg-orange-500/20"
+                style={{ left, width: Math.max(2, right - left) }}
+                title={`${formatCommentPosition(position)}\n${comment.sender}: ${
+                  comment.body
+                }`}
+              />
+            );
+          })}
+    </div>
+  );
+}
@@ -1,6 +1,7 @@
 import { invoke } from "@tauri-apps/api/core";
 import { NewNote } from "@bindings/NewNote";
 import { NewComment } from "@bindings/NewComment";
+import { CreatedNote } from "@bindings/CreatedNote";
 import { FolderInfo } from "@bindings/FolderInfo";
 import { FolderData } from "@bindings/FolderData";
 import { ChannelDoc } from "@bindings/ChannelDoc";
@@ -11,6 +12,11 @@ import { ProjectFileRefScan } from "@bindings/ProjectFileRefScan";
 import { PluginScan } from "@bindings/PluginScan";
 import { FileErr } from "@bindings/FileErr";
 import { AttachmentType } from "@bindings/AttachmentType";
+import { WaveformPeaks } from "@bindings/WaveformPeaks";
+import { Loudness } from "@bindings/Loudness";
+import { MusicalAnalysis } from "@bindings/MusicalAnalysis";
+import { NullTest } from "@bindings/NullTest";
+import { PositionedComment } from "@bindings/PositionedComment";
 import { useStore } from "./Store";
 
 export type InvokePromise<T> = Promise<T>;
@@ -96,26 +102,82 @@ export async function createNote(
   folder: string,
   channelUlid: string | null,
   note: NewNote
-): InvokePromise<TempoResult<SharedNote>> {
-  return invoke<TempoResult<SharedNote>>("create_note", { folder, channelUlid, note }).then((d) => { pollFolderDataOnce(); return d; });
+): InvokePromise<CreatedNote> {
+  return invoke<CreatedNote>("create_note", { folder, channelUlid, note }).then((d) => { pollFolderDataOnce(); return d; });
 }
 
 export async function addComment(folder: string, channelUlid: string | null, noteUlid: string, comment: NewComment): InvokePromise<TempoResult<SharedNote>> {
   return invoke<TempoResult<SharedNote>>("add_comment", { folder, channelUlid, noteUlid, comment }).then((d) => { pollFolderDataOnce(); return d; });
 }
 
-export async function copyProject(folder: string, channelUlid: string | null, noteUlid: string, destDir: string): InvokePromise<[String, FileErr[]]> {
-  return invoke<[String, FileErr[]]>("copy_project", { folder, channelUlid, noteUlid, destDir });
+export async function getCommentsByPosition(folder: string, channelUlid: string | null, noteUlid: string, fileSha256: string | null): InvokePromise<PositionedComment[]> {
+  return invoke<PositionedComment[]>("get_comments_by_position", { folder, channelUlid, noteUlid, fileSha256 });
+}
+
+export async function copyProject(folder: string, channelUlid: string | null, noteUlid: string, attachmentIndex: number, destDir: string): InvokePromise<[String, FileErr[]]> {
+  return invoke<[String, FileErr[]]>("copy_project", { folder, channelUlid, noteUlid, attachmentIndex, destDir });
+}
+
+export async function copyMidi(folder: string, channelUlid: string | null, noteUlid: string, attachmentIndex: number, destDir: string): InvokePromise<string> {
+  return invoke<string>("copy_midi", { folder, channelUlid, noteUlid, attachmentIndex, destDir });
+}
+
+export async function copyStems(folder: string, channelUlid: string | null, noteUlid: string, attachmentIndex: number, destDir: string): InvokePromise<[string, FileErr[]]> {
+  return invoke<[string, FileErr[]]>("copy_stems", { folder, channelUlid, noteUlid, attachmentIndex, destDir });
+}
+
+export async function copyFileAttachment(folder: string, channelUlid: string | null, noteUlid: string, attachmentIndex: number, destDir: string): InvokePromise<string> {
+  return invoke<string>("copy_file_attachment", { folder, channelUlid, noteUlid, attachmentIndex, destDir });
+}
+
+export async function copyAudio(folder: string, channelUlid: string | null, noteUlid: string, attachmentIndex: number, destDir: string): InvokePromise<string> {
+  return invoke<string>("copy_audio", { folder, channelUlid, noteUlid, attachmentIndex, destDir });
+}
+
+export async function copyRender(folder: string, channelUlid: string | null, noteUlid: string, attachmentIndex: number, destDir: string): InvokePromise<string> {
+  return invoke<string>("copy_render", { folder, channelUlid, noteUlid, attachmentIndex, destDir });
+}
+
+export async function exportLineageRenders(folder: string, channelUlid: string | null, noteUlid: string, destDir: string): InvokePromise<[string, FileErr[]]> {
+  return invoke<[string, FileErr[]]>("export_lineage_renders", { folder, channelUlid, noteUlid, destDir });
 }
 
 export async function getFileInfo(folder: string, fileSha256: string): InvokePromise<SharedFileInfo> {
   return invoke<SharedFileInfo>("get_file_info", { folder, fileSha256 });
 }
 
-export async function scanProjectFileRefs(project: String): InvokePromise<ProjectFileRefScan> {
-  return invoke<ProjectFileRefScan>("scan_project_file_refs", { project });
+export async function getWaveformPeaks(folder: string, fileSha256: string): InvokePromise<WaveformPeaks> {
+  return invoke<WaveformPeaks>("get_waveform_peaks", { folder, fileSha256 });
+}
+
+export async function analyzeLoudness(folder: string, fileSha256: string): InvokePromise<Loudness> {
+  return invoke<Loudness>("analyze_loudness", { folder, fileSha256 });
+}
+
+export async function analyzeBpmKey(folder: string, fileSha256: string): InvokePromise<MusicalAnalysis> {
+  return invoke<MusicalAnalysis>("analyze_bpm_key", { folder, fileSha256 });
+}
+
+export async function nullTestAudio(folder: string, aSha256: string, bSha256: string, differenceFile: string | null): InvokePromise<NullTest> {
+  return invoke<NullTest>("null_test_audio", { folder, aSha256, bSha256, differenceFile });
+}
+
+export async function scanProjectFileRefs(folder: string, project: String): InvokePromise<ProjectFileRefScan> {
+  return invoke<ProjectFileRefScan>("scan_project_file_refs", { folder, project });
 }
 
 export async function scanProjectPlugins(folder: string, project: String): InvokePromise<PluginScan> {
   return invoke<PluginScan>("scan_project_plugins", { folder, project });
 }
+
+export async function findLiveProjectSets(liveProject: string): InvokePromise<string[]> {
+  return invoke<string[]>("find_live_project_sets", { liveProject });
+}
+
+export async function getLibraryRoots(): InvokePromise<string[]> {
+  return invoke<string[]>("get_library_roots");
+}
+
+export async function setLibraryRoots(roots: string[]): InvokePromise<null> {
+  return invoke<null>("set_library_roots", { roots });
+}
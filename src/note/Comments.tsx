@@ -7,6 +7,7 @@ import { Button } from "@/components/ui/button";
 import {
   ChevronDown,
   ChevronUp,
+  Clock,
   MessageCircle,
   Reply,
   Send,
@@ -17,6 +18,7 @@ import { useStore } from "../Store";
 import { CommentsType } from "@/types";
 import { ScrollArea } from "@/components/ui/scroll-area";
 import { Comment } from "@bindings/Comment";
+import { CommentPosition } from "@bindings/CommentPosition";
 import { decodeTime } from "ulidx";
 
 // TODO this copies a lot of code from NoteBox and Note
@@ -33,14 +35,20 @@ export function Comments({
   noteUlid: string;
   comments: CommentsType;
 }) {
-  const [folder, invokeWithError] = useStore((state) => [
-    state.folder!,
-    state.invokeWithError,
-  ]);
+  const [folder, invokeWithError, playingInfo, playerTime] = useStore(
+    (state) => [
+      state.folder!,
+      state.invokeWithError,
+      state.playingInfo,
+      state.playerTime,
+    ]
+  );
 
   const [newComment, setNewComment] = useState<string>("");
   const [expanded, setExpanded] = useState<boolean>(false);
   const [replyUlid, setReplyUlid] = useState<string | null>(null);
+  // whether to comment at the player's current position
+  const [atTime, setAtTime] = useState<boolean>(false);
 
   const textAreaRef = useRef<HTMLTextAreaElement | null>(null);
 
@@ -52,10 +60,19 @@ export function Comments({
       addComment(folder, channelUlid, noteUlid, {
         reply_ulid: replyUlid,
         body: newComment,
+        position:
+          atTime && playingInfo
+            ? {
+                file_hash: playingInfo.sha256,
+                start_secs: playerTime,
+                end_secs: null,
+              }
+            : null,
       })
     );
     setNewComment("");
     setReplyUlid(null);
+    setAtTime(false);
     textAreaRef.current?.blur();
   }
 
@@ -107,7 +124,18 @@ export function Comments({
               }
             }}
           />
-          <div className="flex flex-col">
+          <div className="flex flex-row items-center">
+            {playingInfo && (
+              <Button
+                variant="outline"
+                size="sm"
+                className={atTime ? "border-orange-500 text-orange-600" : ""}
+                onClick={() => setAtTime(!atTime)}
+              >
+                <Clock className="h-4 w-4 mr-1" />
+                at {formatTime(playerTime)}
+              </Button>
+            )}
             <Button
               size="icon"
               className={`ml-auto ${
@@ -135,6 +163,25 @@ export function Comments({
   );
 }
 
+// m:ss
+function formatTime(secs: number): string {
+  const s = Math.floor(secs);
+  return `${Math.floor(s / 60)}:${(s % 60).toString().padStart(2, "0")}`;
+}
+
+// e.g. "1:05 - 1:12 (bar 33, beat 2)"
+export function formatCommentPosition(position: CommentPosition): string {
+  return [
+    formatTime(position.start_secs),
+    position.end_secs != null ? ` - ${formatTime(position.end_secs)}` : "",
+    position.bar_beat
+      ? ` (bar ${position.bar_beat.bar}, beat ${Math.floor(
+          position.bar_beat.beat
+        )})`
+      : "",
+  ].join("");
+}
+
 function CommentReply({
   sender,
   text,
@@ -242,6 +289,12 @@ function AComment({
           </div>
         )}
       </div>
+      {comment.position && (
+        <p className="px-3 text-xs text-gray-500">
+          <Clock className="inline h-3 w-3 mr-1" />
+          {formatCommentPosition(comment.position)}
+        </p>
+      )}
       <p className="p-3">{comment.body}</p>
     </div>
   );
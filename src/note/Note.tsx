@@ -1,11 +1,14 @@
 import { Reply } from "lucide-react";
-import { useMemo, useState } from "react";
+import { useEffect, useMemo, useState } from "react";
 import { decodeTime } from "ulidx";
 import { ReplyText } from "./ReplyText";
 import { SharedNote } from "@bindings/SharedNote";
 import { TempoResult } from "@bindings/TempoResult";
 import { NoteAttachment } from "@/attachment/NoteAttachment";
 import { Comments } from "./Comments";
+import { useStore } from "@/Store";
+import { getCommentsByPosition } from "@/commands";
+import { PositionedComment } from "@bindings/PositionedComment";
 
 export type NoteBottom = "comments" | "jump";
 
@@ -36,6 +39,20 @@ export function Note({
     return [false, note.Err];
   }, [note]);
 
+  const folder = useStore((state) => state.folder!);
+  const [positionedComments, setPositionedComments] = useState<
+    PositionedComment[]
+  >([]);
+  const comments = ok ? (noteOrErr as SharedNote).comments : null;
+
+  // comments with a position are marked on the waveforms of the note's audio
+  useEffect(() => {
+    if (!comments) return;
+    getCommentsByPosition(folder, channelUlid, noteUlid, null)
+      .then(setPositionedComments)
+      .catch(() => setPositionedComments([]));
+  }, [folder, channelUlid, noteUlid, comments]);
+
   function render() {
     if (ok) {
       return renderOk(noteOrErr as SharedNote);
@@ -102,14 +119,17 @@ export function Note({
           )}
         </div>
         {note.body.length > 0 && <p className="p-3">{note.body}</p>}
-        {note.attachment && (
+        {note.attachments.map((attachment, i) => (
           <NoteAttachment
+            key={i}
             channelUlid={channelUlid}
             noteUlid={noteUlid}
-            attachment={note.attachment}
+            attachmentIndex={i}
+            attachment={attachment}
+            comments={positionedComments}
           />
-        )}
-        {note.attachment && noteBottom == "comments" && (
+        ))}
+        {note.attachments.length > 0 && noteBottom == "comments" && (
           <Comments
             channelUlid={channelUlid}
             noteUlid={noteUlid}
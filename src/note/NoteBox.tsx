@@ -7,22 +7,33 @@ import {
 } from "react";
 import TextareaAutosize from "react-textarea-autosize";
 import { Button } from "@/components/ui/button";
-import { Paperclip, Plus, Send, X } from "lucide-react";
-import { checkWithin, LoadingSpinnerBlack, useHandleTauriDrag } from "../misc";
+import { FolderOpen, Paperclip, Plus, Send, X } from "lucide-react";
+import {
+  checkWithin,
+  extractFilename,
+  LoadingSpinnerBlack,
+  useHandleTauriDrag,
+} from "../misc";
 import { open } from "@tauri-apps/plugin-dialog";
 import { useStore } from "../Store";
 import { ReplyText } from "./ReplyText";
 import { NewNote } from "@bindings/NewNote";
-import { createNote, getAttachmentType } from "../commands";
+import {
+  createNote,
+  findLiveProjectSets,
+  getAttachmentType,
+} from "../commands";
 import { NewAttachment } from "@bindings/NewAttachment";
+import { DuplicateAttachment } from "@bindings/DuplicateAttachment";
+import { toast } from "sonner";
 import { isValidNewAttachment } from "../types";
 import { NewNoteAttachment } from "../attachment/NewNoteAttachment";
 
 export interface NoteBoxProps {
   channelUlid: string | null;
 
-  attachment: NewAttachment | null;
-  setAttachment: (attachment: NewAttachment | null) => void;
+  attachments: NewAttachment[];
+  setAttachments: (attachments: NewAttachment[]) => void;
 
   replyUlid: string | null;
   setReplyUlid?: (replyUlid: string | null) => void;
@@ -33,9 +44,10 @@ export interface NoteBoxProps {
 // textarea for writing new notes
 export const NoteBox = forwardRef<HTMLDivElement, NoteBoxProps>(
   (props, ref) => {
-    const [invokeWithError, requireRender] = useStore((state) => [
+    const [invokeWithError, requireRender, addError] = useStore((state) => [
       state.invokeWithError,
       state.requireRender,
+      state.addError,
     ]);
 
     useImperativeHandle(ref, () => parentRef.current!, []);
@@ -60,35 +72,85 @@ export const NoteBox = forwardRef<HTMLDivElement, NoteBoxProps>(
     useEffect(() => {
       // this just determines whether to gray out send button
       const haveNote = body.length > 0;
-      const attachmentOk = isValidNewAttachment(props.attachment, requireRender);
+      const attachmentsOk = props.attachments.every((a) =>
+        isValidNewAttachment(a, requireRender)
+      );
       setCanSend(
-        !scanning &&
-          ((haveNote && attachmentOk) ||
-            (!haveNote && props.attachment != null && attachmentOk) ||
-            (haveNote && props.attachment == null))
+        !scanning && attachmentsOk && (haveNote || props.attachments.length > 0)
       );
-    }, [body, props.attachment]);
+    }, [body, props.attachments]);
+
+    function addAttachment(attachment: NewAttachment) {
+      props.setAttachments([...props.attachments, attachment]);
+    }
 
     async function trySetAttachmentPath(file: string) {
       if (scanning) return;
-      if (!props.setAttachment) return;
+      if (!props.setAttachments) return;
 
       invokeWithError(
         getAttachmentType(file).then((t) => {
           console.log(file);
           console.log(t);
-          if ("Audio" in t) {
-            props.setAttachment({ Audio: { title: null, path: file } });
+          // unit variants like "Midi" are plain strings
+          if (t == "Midi") {
+            addAttachment({ Midi: { title: null, path: file } });
+          } else if (t == "LiveProject") {
+            return findLiveProjectSets(file).then((sets) => {
+              if (sets.length == 0) {
+                addError(
+                  `${extractFilename(file)} doesn't contain any Live Sets.`
+                );
+                return;
+              }
+              addAttachment({
+                LiveProject: {
+                  title: extractFilename(file),
+                  path: file,
+                  render: null,
+                  relinks: [],
+                },
+              });
+            });
+          } else if (t == "File") {
+            addAttachment({ File: { title: null, path: file } });
+          } else if (typeof t != "object") {
+            return;
+          } else if ("Audio" in t) {
+            addAttachment({ Audio: { title: null, path: file } });
           } else if ("Project" in t) {
-            props.setAttachment({
-              Project: { title: "", path: file, render: null },
+            addAttachment({
+              Project: { title: "", path: file, render: null, relinks: [] },
             });
           }
         })
       );
     }
 
-    useNoteBoxDragHandler(dragElems, setDragHover, trySetAttachmentPath);
+    // several audio files at once become a stem pack
+    async function trySetAttachmentPaths(files: string[]) {
+      if (files.length == 1) return trySetAttachmentPath(files[0]);
+      if (scanning) return;
+      if (!props.setAttachments) return;
+
+      invokeWithError(
+        Promise.all(files.map((file) => getAttachmentType(file))).then((types) => {
+          if (!types.every((t) => typeof t == "object" && "Audio" in t)) {
+            addError("Multiple files can only be attached as stems, please only select audio files.");
+            return;
+          }
+          addAttachment({
+            Stems: {
+              title: "",
+              stems: files.map((path) => ({ name: stemName(path), path })),
+              mix: null,
+            },
+          });
+        })
+      );
+    }
+
+    useNoteBoxDragHandler(dragElems, setDragHover, trySetAttachmentPaths);
 
     function addElemToDragSet(node: HTMLElement) {
       if (!dragElems.current.includes(node)) dragElems.current.push(node);
@@ -96,9 +158,9 @@ export const NoteBox = forwardRef<HTMLDivElement, NoteBoxProps>(
 
     function onSend() {
       if (canSend) {
-        createNote(props.channelUlid, body, props.replyUlid, props.attachment);
+        createNote(props.channelUlid, body, props.replyUlid, props.attachments);
         setBody("");
-        if (props.setAttachment) props.setAttachment(null);
+        if (props.setAttachments) props.setAttachments([]);
         unfocusNoteBox();
         if (props.setReplyUlid) {
           props.setReplyUlid(null);
@@ -175,7 +237,7 @@ export const NoteBox = forwardRef<HTMLDivElement, NoteBoxProps>(
           body={body}
           setBody={setBody}
           onEnter={onSend}
-          haveAttachment={props.attachment != null}
+          haveAttachment={props.attachments.length > 0}
         />
       );
     }
@@ -194,24 +256,29 @@ export const NoteBox = forwardRef<HTMLDivElement, NoteBoxProps>(
       }
     }
 
-    function renderAttachment() {
-      if (props.attachment) {
-        return (
-          <div
-            className="m-3"
-            ref={(node) => {
-              if (!node) return;
-              node.onmousedown = (e) => focusTextareaOnClick(e, node);
+    function renderAttachments() {
+      return props.attachments.map((attachment, i) => (
+        <div
+          key={i}
+          className="m-3"
+          ref={(node) => {
+            if (!node) return;
+            node.onmousedown = (e) => focusTextareaOnClick(e, node);
+          }}
+        >
+          <NewNoteAttachment
+            attachment={attachment}
+            setAttachment={(a: NewAttachment | null) => {
+              props.setAttachments(
+                a == null
+                  ? props.attachments.filter((_, j) => j != i)
+                  : props.attachments.map((old, j) => (j == i ? a : old))
+              );
             }}
-          >
-            <NewNoteAttachment
-              attachment={props.attachment}
-              setAttachment={props.setAttachment}
-              setScanning={setScanning}
-            />
-          </div>
-        );
-      }
+            setScanning={setScanning}
+          />
+        </div>
+      ));
     }
 
     function renderSending() {
@@ -245,27 +312,66 @@ export const NoteBox = forwardRef<HTMLDivElement, NoteBoxProps>(
             }}
             onAttach={() => {
               (async () => {
-                const path = await open({
-                  multiple: false,
-                  title: "Select a file to attach",
+                const paths = await open({
+                  multiple: true,
+                  title: "Select a file to attach, or several audio files as stems",
                   filters: [
                     {
                       extensions: ["als"],
                       name: "Ableton Live Set",
                     },
                     {
-                      extensions: ["wav", "mp3", "flac", "ogg", "aif", "aiff"],
+                      extensions: ["alc", "adg", "adv"],
+                      name: "Ableton clips, racks and presets",
+                    },
+                    {
+                      extensions: ["rpp"],
+                      name: "REAPER project",
+                    },
+                    {
+                      extensions: ["bwproject"],
+                      name: "Bitwig Studio project",
+                    },
+                    {
+                      extensions: ["song"],
+                      name: "Studio One song",
+                    },
+                    {
+                      extensions: ["flp"],
+                      name: "FL Studio project",
+                    },
+                    {
+                      extensions: ["wav", "mp3", "flac", "ogg", "opus", "aif", "aiff"],
                       name: "Audio files",
                     },
+                    {
+                      extensions: ["mid", "midi"],
+                      name: "MIDI files",
+                    },
+                    {
+                      extensions: ["*"],
+                      name: "All files",
+                    },
                   ],
                 });
-                if (path) {
-                  trySetAttachmentPath(path);
+                if (paths && paths.length > 0) {
+                  trySetAttachmentPaths(paths);
+                  focusTextarea();
+                }
+              })();
+            }}
+            onAttachLiveProject={() => {
+              (async () => {
+                const dir = await open({
+                  directory: true,
+                  title: "Select a Live Project to attach",
+                });
+                if (dir) {
+                  trySetAttachmentPath(dir);
                   focusTextarea();
                 }
               })();
             }}
-            haveAttachment={props.attachment != null}
             onSend={onSend}
             canSend={canSend}
           />
@@ -289,7 +395,7 @@ export const NoteBox = forwardRef<HTMLDivElement, NoteBoxProps>(
         {renderHover()}
         {renderTextarea()}
         {renderReply()}
-        {renderAttachment()}
+        {renderAttachments()}
         {sending ? renderSending() : renderButtons()}
       </div>
     );
@@ -299,7 +405,7 @@ export const NoteBox = forwardRef<HTMLDivElement, NoteBoxProps>(
 function useNoteBoxDragHandler(
   elems: React.RefObject<HTMLElement[]>,
   setDragHover: (b: boolean) => void,
-  trySetAttachmentPath: (path: string) => void
+  trySetAttachmentPaths: (paths: string[]) => void
 ) {
   const addError = useStore((state) => state.addError);
   const lastDropTime = useRef<number | null>();
@@ -329,10 +435,8 @@ function useNoteBoxDragHandler(
           return;
         lastDropTime.current = Date.now();
         setDragHover(false);
-        if (e.paths.length != 1) {
-          addError("Please drag and drop only one file at most.");
-        } else {
-          trySetAttachmentPath(e.paths[0]);
+        if (e.paths.length != 0) {
+          trySetAttachmentPaths(e.paths);
         }
         break;
     }
@@ -345,37 +449,74 @@ function useCreateNote(
   channelUlid: string | null,
   body: string,
   replyUlid: string | null,
-  attachment: NewAttachment | null
+  attachments: NewAttachment[]
 ) => void {
   const [folder, invokeWithError] = useStore((state) => [
     state.folder,
     state.invokeWithError,
   ]);
 
+  function send(channelUlid: string | null, msg: NewNote) {
+    if (!folder) return;
+
+    if (msg.attachments.some((a) => "Project" in a || "Stems" in a)) {
+      setSending("Adding note, this might take a second...");
+    } else {
+      setSending("Adding note...");
+    }
+    invokeWithError(createNote(folder, channelUlid, msg))
+      .then((created) => {
+        if ("Duplicates" in created)
+          warnDuplicates(channelUlid, msg, created.Duplicates);
+      })
+      .finally(() => {
+        setSending(null);
+      });
+  }
+
+  // nothing was sent, let the user send anyway or reply to the note that already has the attachment
+  function warnDuplicates(
+    channelUlid: string | null,
+    msg: NewNote,
+    duplicates: DuplicateAttachment[]
+  ) {
+    const dup = duplicates[0]!;
+    const where = dup.channel_name
+      ? `in ${dup.channel_name}`
+      : "in the global channel";
+
+    toast.warning(`This was already sent by ${dup.sender} ${where}`, {
+      duration: Infinity,
+      action: {
+        label: "Send anyway",
+        onClick: () => send(channelUlid, { ...msg, allow_duplicates: true }),
+      },
+      cancel: {
+        label: "Reply instead",
+        onClick: () =>
+          send(dup.channel_ulid, {
+            ...msg,
+            reply_ulid: dup.note_ulid,
+            attachments: msg.attachments.filter(
+              (_, i) => !duplicates.some((d) => d.attachment_index == i)
+            ),
+          }),
+      },
+    });
+  }
+
   return (
     channelUlid: string | null,
     body: string,
     replyUlid: string | null,
-    attachment: NewAttachment | null
+    attachments: NewAttachment[]
   ) => {
-    (async () => {
-      const msg: NewNote = {
-        body,
-        reply_ulid: replyUlid,
-        attachment,
-      };
-
-      if (folder) {
-        if (attachment && "Project" in attachment) {
-          setSending("Adding note, this might take a second...");
-        } else {
-          setSending("Adding note...");
-        }
-        invokeWithError(createNote(folder, channelUlid, msg)).finally(() => {
-          setSending(null);
-        });
-      }
-    })();
+    send(channelUlid, {
+      body,
+      reply_ulid: replyUlid,
+      attachments,
+      allow_duplicates: false,
+    });
   };
 }
 
@@ -452,7 +593,7 @@ export function NoteBoxReply({
 
 interface NoteBoxButtonsProps {
   onAttach?: () => void;
-  haveAttachment: boolean;
+  onAttachLiveProject?: () => void;
 
   onSend: () => void;
   canSend: boolean;
@@ -469,7 +610,7 @@ const NoteBoxButtons = forwardRef<HTMLDivElement, NoteBoxButtonsProps>(
           e.preventDefault();
         }}
       >
-        {props.onAttach && !props.haveAttachment && (
+        {props.onAttach && (
           <Button
             variant="ghost"
             size="icon"
@@ -479,6 +620,16 @@ const NoteBoxButtons = forwardRef<HTMLDivElement, NoteBoxButtonsProps>(
             <Paperclip className="h-5 w-5" />
           </Button>
         )}
+        {props.onAttachLiveProject && (
+          <Button
+            variant="ghost"
+            size="icon"
+            className="text-gray-400 hover:text-gray-600 transition-colors duration-200"
+            onClick={props.onAttachLiveProject}
+          >
+            <FolderOpen className="h-5 w-5" />
+          </Button>
+        )}
         <Button
           size="icon"
           className={`${
@@ -494,3 +645,10 @@ const NoteBoxButtons = forwardRef<HTMLDivElement, NoteBoxButtonsProps>(
     );
   }
 );
+
+// stem names default to the filename without its extension
+function stemName(path: string): string {
+  const filename = extractFilename(path);
+  const dot = filename.lastIndexOf(".");
+  return dot > 0 ? filename.slice(0, dot) : filename;
+}
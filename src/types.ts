@@ -32,6 +32,22 @@ export function isValidNewAttachment(a: NewAttachment | null, requireRender: boo
     return a.Audio.title == null || a.Audio.title.length != 0;
   }
 
+  if ("Midi" in a) {
+    return a.Midi.title == null || a.Midi.title.length != 0;
+  }
+
+  if ("File" in a) {
+    return a.File.title == null || a.File.title.length != 0;
+  }
+
+  if ("Stems" in a) {
+    return (
+      a.Stems.title.length != 0 &&
+      a.Stems.stems.length != 0 &&
+      a.Stems.stems.every((s) => s.name.length != 0)
+    );
+  }
+
   if ("Project" in a) {
     const titleOk = a.Project.title.length != 0;
     if (requireRender) {
@@ -40,5 +56,13 @@ export function isValidNewAttachment(a: NewAttachment | null, requireRender: boo
     return titleOk;
   }
 
+  if ("LiveProject" in a) {
+    const titleOk = a.LiveProject.title.length != 0;
+    if (requireRender) {
+      return titleOk && a.LiveProject.render != null;
+    }
+    return titleOk;
+  }
+
   throw `isValidNewAttachment: found unexpected NewAttachment type ${a}`;
 }
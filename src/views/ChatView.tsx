@@ -16,7 +16,7 @@ export function ChatView() {
   ]);
 
   const [replyUlid, setReplyUlid] = useState<string | null>(null);
-  const [attachment, setAttachment] = useState<NewAttachment | null>(null);
+  const [attachments, setAttachments] = useState<NewAttachment[]>([]);
   const [noteBoxHeight, setNoteBoxHeight] = useState(0);
 
   const noteBoxRef = useRef<HTMLDivElement | null>(null);
@@ -82,8 +82,8 @@ export function ChatView() {
         <NoteBox
           channelUlid={channelUlid}
 
-          attachment={attachment}
-          setAttachment={setAttachment}
+          attachments={attachments}
+          setAttachments={setAttachments}
 
           replyUlid={replyUlid}
           setReplyUlid={setReplyUlid}
@@ -77,7 +77,7 @@ function Flow() {
     let edgeCount = 0;
 
     Object.entries(notes).forEach(([ulid, doc]) => {
-      if (!doc || "Err" in doc! || ("Ok" in doc! && !doc.Ok.attachment)) return;
+      if (!doc || "Err" in doc! || ("Ok" in doc! && doc.Ok.attachments.length == 0)) return;
 
       // not sure why typescript thinks doc could be undefined here
       // maybe im missing something?